@@ -19,7 +19,7 @@
 
 use async_trait::async_trait;
 use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Universe};
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, QueueCapacity, Universe};
 
 #[derive(Default)]
 struct DoNothingActor<const YIELD_AFTER_EACH_MESSAGE: bool>(u64);
@@ -85,9 +85,8 @@ async fn flume_bench_code(num_messages: usize) {
     assert_eq!(total, num_messages as u64);
 }
 
-async fn chan_with_priority_bench_code(num_messages: usize) {
-    let (tx, rx) =
-        quickwit_actors::channel_with_priority::channel(quickwit_actors::QueueCapacity::Unbounded);
+async fn chan_with_priority_bench_code(num_messages: usize, queue_capacity: QueueCapacity) {
+    let (tx, rx) = quickwit_actors::channel_with_priority::channel(queue_capacity);
     for _ in 0..num_messages {
         tx.send_low_priority(AddMessage(1)).await.unwrap();
     }
@@ -164,7 +163,23 @@ fn message_throughput(c: &mut Criterion) {
                     .build()
                     .unwrap();
                 b.to_async(runtime)
-                    .iter(|| chan_with_priority_bench_code(num_messages));
+                    .iter(|| chan_with_priority_bench_code(num_messages, QueueCapacity::Unbounded));
+            },
+        );
+        c.bench_with_input(
+            BenchmarkId::new(
+                "unlimited_capacity_lock_free_chan_with_priority",
+                num_messages,
+            ),
+            &num_messages,
+            |b, &num_messages| {
+                let runtime = tokio::runtime::Builder::new_multi_thread()
+                    .enable_all()
+                    .build()
+                    .unwrap();
+                b.to_async(runtime).iter(|| {
+                    chan_with_priority_bench_code(num_messages, QueueCapacity::UnboundedLockFree)
+                });
             },
         );
     }
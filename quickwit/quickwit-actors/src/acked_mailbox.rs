@@ -0,0 +1,282 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::{Arc, Mutex, Weak};
+
+use async_trait::async_trait;
+
+use crate::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, SendError};
+
+struct RetransmitBuffer<M> {
+    next_seq_no: u64,
+    // Messages sent and not acknowledged yet.
+    unacked_messages: BTreeMap<u64, M>,
+    // Messages dropped by the receiver without being acknowledged, e.g. because it failed
+    // while processing them.
+    lost_seq_nos: BTreeSet<u64>,
+}
+
+impl<M> Default for RetransmitBuffer<M> {
+    fn default() -> Self {
+        RetransmitBuffer {
+            next_seq_no: 0,
+            unacked_messages: BTreeMap::new(),
+            lost_seq_nos: BTreeSet::new(),
+        }
+    }
+}
+
+/// A mailbox wrapper providing at-least-once delivery of messages of type `M`.
+///
+/// A copy of every message is kept in a retransmit buffer until the receiver acknowledges it,
+/// which happens automatically once its handler returns successfully. Messages dequeued by
+/// the receiver but not handled, because the receiver failed or panicked, are sent again
+/// to the mailbox with the next message, or with `retransmit_lost_messages`. Paired with a
+/// supervised receiver, which keeps its mailbox across restarts, no message is lost.
+///
+/// Messages can be delivered more than once, and retransmitted messages are delivered out
+/// of order.
+pub struct AckedMailbox<A: Actor, M> {
+    mailbox: Mailbox<A>,
+    retransmit_buffer: Arc<Mutex<RetransmitBuffer<M>>>,
+}
+
+impl<A: Actor, M> Clone for AckedMailbox<A, M> {
+    fn clone(&self) -> Self {
+        AckedMailbox {
+            mailbox: self.mailbox.clone(),
+            retransmit_buffer: self.retransmit_buffer.clone(),
+        }
+    }
+}
+
+impl<A, M> AckedMailbox<A, M>
+where
+    A: Handler<M>,
+    M: Clone + fmt::Debug + Send + 'static,
+{
+    pub fn new(mailbox: Mailbox<A>) -> Self {
+        AckedMailbox {
+            mailbox,
+            retransmit_buffer: Arc::default(),
+        }
+    }
+
+    pub fn mailbox(&self) -> &Mailbox<A> {
+        &self.mailbox
+    }
+
+    /// Returns the number of messages sent and not acknowledged yet.
+    pub fn num_unacked_messages(&self) -> usize {
+        self.retransmit_buffer
+            .lock()
+            .unwrap()
+            .unacked_messages
+            .len()
+    }
+
+    /// Retransmits the lost messages, if any, then sends `message`.
+    pub async fn send_message(&self, message: M) -> Result<(), SendError> {
+        self.retransmit_lost_messages().await?;
+        let seq_no = {
+            let mut retransmit_buffer = self.retransmit_buffer.lock().unwrap();
+            let seq_no = retransmit_buffer.next_seq_no;
+            retransmit_buffer.next_seq_no += 1;
+            retransmit_buffer
+                .unacked_messages
+                .insert(seq_no, message.clone());
+            seq_no
+        };
+        self.send_acked(seq_no, message).await
+    }
+
+    /// Sends again the messages dropped by the receiver without being acknowledged.
+    ///
+    /// Returns the number of retransmitted messages.
+    pub async fn retransmit_lost_messages(&self) -> Result<usize, SendError> {
+        let lost_messages: Vec<(u64, M)> = {
+            let mut retransmit_buffer = self.retransmit_buffer.lock().unwrap();
+            let lost_seq_nos = std::mem::take(&mut retransmit_buffer.lost_seq_nos);
+            lost_seq_nos
+                .into_iter()
+                .filter_map(|seq_no| {
+                    let message = retransmit_buffer.unacked_messages.get(&seq_no)?;
+                    Some((seq_no, message.clone()))
+                })
+                .collect()
+        };
+        let num_lost_messages = lost_messages.len();
+        for (seq_no, message) in lost_messages {
+            self.send_acked(seq_no, message).await?;
+        }
+        Ok(num_lost_messages)
+    }
+
+    async fn send_acked(&self, seq_no: u64, message: M) -> Result<(), SendError> {
+        let acked_message = Acked {
+            message,
+            ack_handle: AckHandle {
+                seq_no,
+                retransmit_buffer: Arc::downgrade(&self.retransmit_buffer),
+                acked: false,
+            },
+        };
+        self.mailbox.send_message(acked_message).await?;
+        Ok(())
+    }
+}
+
+/// Message sent through an [`AckedMailbox`].
+///
+/// Actors handling messages of type `M` can handle `Acked<M>` messages out of the box.
+pub struct Acked<M> {
+    message: M,
+    ack_handle: AckHandle<M>,
+}
+
+impl<M: fmt::Debug> fmt::Debug for Acked<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Acked")
+            .field("seq_no", &self.ack_handle.seq_no)
+            .field("message", &self.message)
+            .finish()
+    }
+}
+
+struct AckHandle<M> {
+    seq_no: u64,
+    retransmit_buffer: Weak<Mutex<RetransmitBuffer<M>>>,
+    acked: bool,
+}
+
+impl<M> AckHandle<M> {
+    fn ack(mut self) {
+        self.acked = true;
+        if let Some(retransmit_buffer) = self.retransmit_buffer.upgrade() {
+            retransmit_buffer
+                .lock()
+                .unwrap()
+                .unacked_messages
+                .remove(&self.seq_no);
+        }
+    }
+}
+
+impl<M> Drop for AckHandle<M> {
+    fn drop(&mut self) {
+        if self.acked {
+            return;
+        }
+        if let Some(retransmit_buffer) = self.retransmit_buffer.upgrade() {
+            retransmit_buffer
+                .lock()
+                .unwrap()
+                .lost_seq_nos
+                .insert(self.seq_no);
+        }
+    }
+}
+
+#[async_trait]
+impl<A, M> Handler<Acked<M>> for A
+where
+    A: Handler<M>,
+    M: fmt::Debug + Send + 'static,
+{
+    type Reply = A::Reply;
+
+    async fn handle(
+        &mut self,
+        acked_message: Acked<M>,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let Acked {
+            message,
+            ack_handle,
+        } = acked_message;
+        let reply = <Self as Handler<M>>::handle(self, message, ctx).await?;
+        ack_handle.ack();
+        Ok(reply)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::Universe;
+
+    #[derive(Clone, Debug)]
+    struct Batch(u64);
+
+    #[derive(Clone, Default)]
+    struct FailOnceActor {
+        has_failed: Arc<AtomicBool>,
+        processed_batches: Arc<Mutex<Vec<u64>>>,
+    }
+
+    impl Actor for FailOnceActor {
+        type ObservableState = ();
+
+        fn observable_state(&self) -> Self::ObservableState {}
+    }
+
+    #[async_trait]
+    impl Handler<Batch> for FailOnceActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            batch: Batch,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            if batch.0 == 2 && !self.has_failed.swap(true, Ordering::Relaxed) {
+                return Err(ActorExitStatus::from(anyhow::anyhow!("failed once")));
+            }
+            self.processed_batches.lock().unwrap().push(batch.0);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_acked_mailbox_retransmits_messages_lost_by_supervised_actor() {
+        let universe = Universe::with_accelerated_time();
+        let actor = FailOnceActor::default();
+        let processed_batches = actor.processed_batches.clone();
+        let (mailbox, supervisor_handle) = universe.spawn_builder().supervise(actor);
+        let acked_mailbox = AckedMailbox::new(mailbox);
+        for batch_id in 1..=3 {
+            acked_mailbox.send_message(Batch(batch_id)).await.unwrap();
+        }
+        for _ in 0..10 {
+            if acked_mailbox.num_unacked_messages() == 0 {
+                break;
+            }
+            universe.sleep(*crate::HEARTBEAT).await;
+            acked_mailbox.retransmit_lost_messages().await.unwrap();
+        }
+        assert_eq!(acked_mailbox.num_unacked_messages(), 0);
+        assert_eq!(*processed_batches.lock().unwrap(), vec![1, 3, 2]);
+        assert_eq!(supervisor_handle.observe().await.num_errors, 1);
+        universe.assert_quit().await;
+    }
+}
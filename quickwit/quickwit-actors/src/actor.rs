@@ -18,14 +18,16 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::any::type_name;
+use std::backtrace::Backtrace;
 use std::fmt;
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_trait::async_trait;
 use thiserror::Error;
-use tracing::error;
+use tracing::{error, warn};
 
-use crate::{ActorContext, QueueCapacity, SendError};
+use crate::{ActorContext, CheckpointBarrier, CustomCommand, Mailbox, QueueCapacity, SendError};
 
 /// The actor exit status represents the outcome of the execution of an actor,
 /// after the end of the execution.
@@ -72,17 +74,160 @@ pub enum ActorExitStatus {
     Killed,
 
     /// An unexpected error happened while processing a message.
-    #[error("Failure(cause={0:?})")]
-    Failure(Arc<anyhow::Error>),
+    #[error("Failure(cause={0})")]
+    Failure(Arc<ActorTermination>),
 
     /// The thread or the task executing the actor loop panicked.
     #[error("Panicked")]
     Panicked,
 }
 
+/// Message sent to the downstream actors registered with
+/// `ActorContext::notify_termination_to` when an actor terminates.
+///
+/// It makes it possible for a downstream actor to distinguish an upstream actor that
+/// finished cleanly from one that failed.
+#[derive(Clone, Debug)]
+pub struct UpstreamTerminated {
+    pub upstream_actor_id: String,
+    pub exit_status: ActorExitStatus,
+}
+
+impl UpstreamTerminated {
+    /// Returns a failure caused by the failure of the upstream actor, so that a downstream
+    /// actor exiting because of it keeps track of the root cause.
+    ///
+    /// If the upstream actor did not fail, the returned failure has no upstream termination.
+    pub fn into_cascading_failure(self, error: anyhow::Error) -> ActorExitStatus {
+        let termination = ActorTermination::new(error);
+        let termination = if let ActorExitStatus::Failure(upstream_termination) = self.exit_status {
+            termination.caused_by(upstream_termination)
+        } else {
+            termination
+        };
+        ActorExitStatus::from(termination)
+    }
+}
+
+/// Structured description of an actor failure.
+///
+/// On top of the error itself, it carries the actor that failed, the type of the message
+/// it was processing, and the termination of the upstream actor that caused this failure,
+/// if any. This makes it possible to find the root cause of a cascade of failures without
+/// correlating log lines.
+#[derive(Debug)]
+pub struct ActorTermination {
+    error: anyhow::Error,
+    // Captured when the termination is created, typically where the error is converted
+    // into an `ActorExitStatus`.
+    backtrace: Arc<Backtrace>,
+    actor_instance_id_opt: Option<String>,
+    message_type_opt: Option<&'static str>,
+    upstream_termination_opt: Option<Arc<ActorTermination>>,
+}
+
+impl ActorTermination {
+    pub fn new(error: anyhow::Error) -> Self {
+        ActorTermination {
+            error,
+            backtrace: Arc::new(Backtrace::capture()),
+            actor_instance_id_opt: None,
+            message_type_opt: None,
+            upstream_termination_opt: None,
+        }
+    }
+
+    /// Records the termination of the upstream actor that caused this failure.
+    pub fn caused_by(mut self, upstream_termination: Arc<ActorTermination>) -> Self {
+        self.upstream_termination_opt = Some(upstream_termination);
+        self
+    }
+
+    pub fn error(&self) -> &anyhow::Error {
+        &self.error
+    }
+
+    pub fn backtrace(&self) -> &Arc<Backtrace> {
+        &self.backtrace
+    }
+
+    /// Returns the instance id of the actor that failed.
+    ///
+    /// It is set by the framework when the failure is returned by the actor.
+    pub fn actor_instance_id(&self) -> Option<&str> {
+        self.actor_instance_id_opt.as_deref()
+    }
+
+    /// Returns the type of the message being processed when the actor failed,
+    /// or `None` if the failure did not happen in a message handler.
+    pub fn message_type(&self) -> Option<&'static str> {
+        self.message_type_opt
+    }
+
+    pub fn upstream_termination(&self) -> Option<&Arc<ActorTermination>> {
+        self.upstream_termination_opt.as_ref()
+    }
+
+    /// Returns the first termination of the chain of upstream terminations.
+    pub fn root_cause(&self) -> &ActorTermination {
+        let mut termination = self;
+        while let Some(upstream_termination) = termination.upstream_termination_opt.as_deref() {
+            termination = upstream_termination;
+        }
+        termination
+    }
+}
+
+impl fmt::Display for ActorTermination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "actor `{}` failed",
+            self.actor_instance_id().unwrap_or("<unknown>")
+        )?;
+        if let Some(message_type) = self.message_type_opt {
+            write!(f, " while processing `{message_type}`")?;
+        }
+        write!(f, ": {:?}", self.error)?;
+        if let Some(upstream_termination) = &self.upstream_termination_opt {
+            write!(f, ", caused by: {upstream_termination}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Details about the termination of an actor that failed or panicked, retrievable via
+/// `ActorHandle::termination_details()`.
+#[derive(Clone, Debug)]
+pub struct TerminationDetails {
+    pub exit_status: ActorExitStatus,
+    /// Message of the panic, if the actor panicked.
+    pub panic_message_opt: Option<String>,
+    /// Backtrace of the failure or of the panic. It is only captured if backtraces are
+    /// enabled, and, for panics, if the panic hook was installed with `install_panic_hook`.
+    /// It is not part of the `Display` rendering.
+    pub backtrace: Arc<Backtrace>,
+}
+
+impl fmt::Display for TerminationDetails {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.exit_status)?;
+        if let Some(panic_message) = &self.panic_message_opt {
+            write!(f, ": {panic_message}")?;
+        }
+        Ok(())
+    }
+}
+
 impl From<anyhow::Error> for ActorExitStatus {
     fn from(err: anyhow::Error) -> Self {
-        ActorExitStatus::Failure(Arc::new(err))
+        ActorExitStatus::Failure(Arc::new(ActorTermination::new(err)))
+    }
+}
+
+impl From<ActorTermination> for ActorExitStatus {
+    fn from(termination: ActorTermination) -> Self {
+        ActorExitStatus::Failure(Arc::new(termination))
     }
 }
 
@@ -90,6 +235,39 @@ impl ActorExitStatus {
     pub fn is_success(&self) -> bool {
         matches!(self, ActorExitStatus::Success)
     }
+
+    /// Returns the structured description of the failure if the actor failed.
+    pub fn termination(&self) -> Option<&Arc<ActorTermination>> {
+        if let ActorExitStatus::Failure(termination) = self {
+            Some(termination)
+        } else {
+            None
+        }
+    }
+
+    /// Attaches the failing actor and the type of the message being processed
+    /// to a failure, unless they were already set.
+    pub(crate) fn with_failure_context(
+        self,
+        actor_instance_id: &str,
+        message_type_opt: Option<&'static str>,
+    ) -> ActorExitStatus {
+        let ActorExitStatus::Failure(termination_arc) = self else {
+            return self;
+        };
+        if termination_arc.actor_instance_id_opt.is_some() {
+            return ActorExitStatus::Failure(termination_arc);
+        }
+        match Arc::try_unwrap(termination_arc) {
+            Ok(mut termination) => {
+                termination.actor_instance_id_opt = Some(actor_instance_id.to_string());
+                termination.message_type_opt = message_type_opt;
+                ActorExitStatus::Failure(Arc::new(termination))
+            }
+            // The termination is shared, we cannot amend it.
+            Err(termination_arc) => ActorExitStatus::Failure(termination_arc),
+        }
+    }
 }
 
 impl From<SendError> for ActorExitStatus {
@@ -98,6 +276,20 @@ impl From<SendError> for ActorExitStatus {
     }
 }
 
+/// Defines what happens to the messages still queued in the mailbox of an actor
+/// when it gracefully quits (i.e. exits with `ActorExitStatus::Quit`).
+pub enum DrainPolicy<A: Actor> {
+    /// The queued messages are dropped, and counted in the
+    /// `quickwit_actors_dropped_messages_total` metric.
+    Drop,
+    /// The actor processes all of the messages queued at the time it quits before
+    /// calling `finalize`.
+    Process,
+    /// The queued messages are transferred to the mailbox of a successor actor, along
+    /// with their reply channels. Messages that cannot be transferred are dropped.
+    TransferTo(Mailbox<A>),
+}
+
 /// An actor has an internal state and processes a stream of messages.
 /// Each actor has a mailbox where the messages are enqueued before being processed.
 ///
@@ -122,7 +314,9 @@ pub trait Actor: Send + Sized + 'static {
     /// of execution of the Actor.
     ///
     /// Actor with a handler that may block for more than 50 microseconds
-    /// should use the `ActorRunner::DedicatedThread`.
+    /// should run on the `RuntimeType::Blocking` runtime, while latency-critical actors
+    /// (e.g. serving ingest requests) should run on the `RuntimeType::LatencyCritical` runtime so
+    /// that they never wait behind throughput-oriented actors for a worker thread.
     fn runtime_handle(&self) -> tokio::runtime::Handle {
         tokio::runtime::Handle::current()
     }
@@ -136,11 +330,82 @@ pub trait Actor: Send + Sized + 'static {
         true
     }
 
+    /// Maximum number of available messages processed in a row before yielding, when
+    /// `yield_after_each_message` returns true.
+    ///
+    /// Actors processing a large number of small messages on a saturated pipeline can
+    /// raise it to amortize the cost of yielding and of being woken up again over a batch
+    /// of messages. The kill switch is still checked before every message.
+    fn max_batch_size(&self) -> usize {
+        1
+    }
+
+    /// Maximum number of times a message sent with `Mailbox::send_redeliverable_message` is
+    /// processed, when its processing keeps failing across supervised restarts of the actor.
+    ///
+    /// Past this number of attempts, the message is moved to the dead letter queue of the
+    /// universe instead.
+    fn max_message_attempts(&self) -> usize {
+        3
+    }
+
+    /// Interval within which the actor is expected to record some progress.
+    ///
+    /// If no progress is observed within that interval, its supervisor will consider it as
+    /// blocked and will proceed to kill it.
+    ///
+    /// Actors that are slow by design (e.g. running large merges) can override this
+    /// method instead of forcing the global `HEARTBEAT` up for every actor.
+    fn heartbeat(&self) -> Duration {
+        *crate::HEARTBEAT
+    }
+
+    /// Expected processing time of a single message.
+    ///
+    /// If set, the supervisor of the actor periodically checks the 99th percentile of the
+    /// processing time of the last messages, and reports the actor as slow when it exceeds
+    /// this budget. Unlike the heartbeat, exceeding the budget does not kill the actor.
+    ///
+    /// In addition, every single message exceeding the budget is logged along with its
+    /// Debug rendering and counted in the
+    /// `quickwit_actors_processing_time_budget_violations_total` metric. Since the message
+    /// is consumed by its handler, it is rendered before being processed, which has a cost
+    /// for large messages.
+    fn processing_time_budget(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Duration of silence after which `on_receive_timeout` is called.
+    ///
+    /// If set, `on_receive_timeout` is called whenever no message has been received
+    /// for this duration, and then again after every subsequent period of silence
+    /// of the same duration. Paused actors do not receive timeouts.
+    fn receive_timeout(&self) -> Option<Duration> {
+        None
+    }
+
     /// The Actor's incoming mailbox queue capacity. It is set when the actor is spawned.
     fn queue_capacity(&self) -> QueueCapacity {
         QueueCapacity::Unbounded
     }
 
+    /// Defines what happens to the messages still queued in the mailbox of the actor
+    /// when it gracefully quits.
+    ///
+    /// By default, they are dropped.
+    fn drain_policy(&self) -> DrainPolicy<Self> {
+        DrainPolicy::Drop
+    }
+
+    /// Whether the actor is asked to quit when its kill switch is soft killed.
+    ///
+    /// Actors downstream of a source typically return `false`: they exit on their own once
+    /// their upstream actors have, after processing everything they were sent. The soft kill
+    /// still waits for them before killing the kill switch.
+    fn quit_on_soft_kill(&self) -> bool {
+        true
+    }
+
     /// Extracts an observable state. Useful for unit tests, and admin UI.
     ///
     /// This function should return quickly.
@@ -160,6 +425,16 @@ pub trait Actor: Send + Sized + 'static {
         Ok(())
     }
 
+    /// Checks the dependencies of the actor (e.g. metastore connection, storage
+    /// reachability) before it starts processing messages.
+    ///
+    /// It is only called, right after `initialize`, for actors spawned with a
+    /// [`crate::ReadinessGate`]. Returning an error fails the whole group of actors
+    /// sharing the gate.
+    async fn check_readiness(&mut self, _ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// This function is called after a series of one, or several messages have been processed and
     /// no more message is available.
     ///
@@ -175,6 +450,75 @@ pub trait Actor: Send + Sized + 'static {
         Ok(())
     }
 
+    /// This function is called once all of the mailboxes of the actor have been dropped and
+    /// all of its messages have been processed, i.e. when no more messages can ever be
+    /// received.
+    ///
+    /// Stream-style actors can use it to flush their buffered work. The actor then exits
+    /// with `ActorExitStatus::Success`.
+    async fn on_eof(&mut self, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        Ok(())
+    }
+
+    /// This function is called upon reception of a `Command::Flush`.
+    ///
+    /// Actors buffering work should complete it here. Quickwit's Indexer actor for
+    /// instance commits its in-progress split.
+    async fn flush(&mut self, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        Ok(())
+    }
+
+    /// This function is called upon reception of a `Command::Custom`, sent for instance
+    /// with `ActorHandle::send_command`.
+    ///
+    /// Custom commands go through the high priority channel, so they are processed before
+    /// the messages queued in the mailbox.
+    async fn process_command(
+        &mut self,
+        command: CustomCommand,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        warn!(actor = %self.name(), command = command.name(), "unhandled-custom-command");
+        Ok(())
+    }
+
+    /// Number of copies of each [`CheckpointBarrier`] the actor receives, i.e. the number of
+    /// upstream actors forwarding barriers to it, or of sources the barrier is injected into.
+    ///
+    /// The barrier is aligned: the actor waits for all of the copies before flushing and
+    /// forwarding the barrier, once.
+    fn num_checkpoint_barrier_upstreams(&self) -> usize {
+        1
+    }
+
+    /// This function is called once all of the copies of a [`CheckpointBarrier`] have been
+    /// received, right after `flush`.
+    ///
+    /// Actors with downstream actors should send them a clone of the barrier. By default,
+    /// the actor is considered a sink of the pipeline, and the barrier stops there.
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        _checkpoint_barrier: &CheckpointBarrier,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        Ok(())
+    }
+
+    /// This function is called when no message has been received for the duration
+    /// returned by `receive_timeout`.
+    ///
+    /// A source can for instance use it to force the commit of a partially filled
+    /// split after some time of inactivity without plumbing its own timer.
+    ///
+    /// Returning an ActorExitStatus will have the same effect as if it was returned
+    /// by a message handler.
+    async fn on_receive_timeout(
+        &mut self,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        Ok(())
+    }
+
     /// Hook  that can be set up to define what should happen upon actor exit.
     /// This hook is called only once.
     ///
@@ -208,6 +552,35 @@ pub trait DeferableReplyHandler<M>: Actor {
         M: Send + 'static;
 }
 
+/// Reply callback of a message handled by a [`DeferableReplyHandler`], boxed so that it
+/// can be stored and invoked later, e.g. from the handler of another message once a
+/// downstream upload completes.
+///
+/// If the handle is dropped without responding, the sender of the message gets an
+/// `AskError::ProcessMessageError`.
+pub struct ResponseHandle<R> {
+    reply_fn: Box<dyn FnOnce(R) + Send + Sync>,
+}
+
+impl<R> ResponseHandle<R> {
+    pub fn new(reply_fn: impl FnOnce(R) + Send + Sync + 'static) -> Self {
+        ResponseHandle {
+            reply_fn: Box::new(reply_fn),
+        }
+    }
+
+    /// Sends the reply to the sender of the message.
+    pub fn respond(self, reply: R) {
+        (self.reply_fn)(reply)
+    }
+}
+
+impl<R> fmt::Debug for ResponseHandle<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ResponseHandle").finish()
+    }
+}
+
 /// Message handler that requires actor to provide immediate response
 #[async_trait::async_trait]
 pub trait Handler<M>: Actor {
@@ -17,28 +17,57 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::any::{type_name, Any};
+use std::backtrace::Backtrace;
+use std::collections::{BTreeMap, HashMap};
 use std::convert::Infallible;
 use std::fmt;
 use std::future::Future;
 use std::ops::Deref;
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant, SystemTime};
 
+use futures::future::BoxFuture;
 use quickwit_common::metrics::IntCounter;
 use quickwit_common::{KillSwitch, Progress, ProtectedZoneGuard};
-use tokio::sync::{oneshot, watch};
-use tracing::{debug, error};
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::{broadcast, oneshot, watch};
+use tracing::{debug, error, warn};
 
 use crate::actor_state::AtomicState;
+use crate::config_watcher::{ConfigUpdated, ConfigWatcher};
+use crate::cpu_time::{CpuUsage, CpuUsageCounters};
+use crate::envelope::CorrelationId;
+use crate::flight_recorder::{FlightRecord, FlightRecorder};
+use crate::lifecycle_events::ActorLifecycleEventKind;
+use crate::mailbox::Priority;
+use crate::memory_budget::MemoryPermit;
+use crate::message_counters::MessageCounters;
+use crate::message_tracing::{should_trace_message, TruncatedDebug};
+use crate::panic_backtrace::{panic_message, take_last_panic_backtrace};
+use crate::panic_policy::PanicPolicy;
+use crate::processing_time::ProcessingTimeTracker;
 use crate::registry::ActorRegistry;
+use crate::reported_error::{ReportedError, REPORTED_ERRORS_CHANNEL_CAPACITY};
+use crate::retry::{FailedAttempt, RetryError, RetryPolicy};
 use crate::spawn_builder::{SpawnBuilder, SpawnContext};
 #[cfg(any(test, feature = "testsuite"))]
 use crate::Universe;
 use crate::{
-    Actor, ActorExitStatus, ActorState, AskError, Command, DeferableReplyHandler, Mailbox,
-    SendError, TrySendError,
+    Actor, ActorExitStatus, ActorHandle, ActorState, Addr, AskError, CheckpointBarrier, Command,
+    DeferableReplyHandler, DeferredMailbox, Mailbox, MessageSize, SendError, TerminationDetails,
+    TrySendError, UpstreamTerminated, Watermark,
 };
 
+type TerminationNotifier = Box<dyn FnOnce(UpstreamTerminated) -> BoxFuture<'static, ()> + Send>;
+
+// Number of downstream mailboxes per actor for which sending a message does not take a lock to
+// record the wiring. Actors rarely have more.
+const NUM_WIRED_MAILBOX_SLOTS: usize = 8;
+
 // TODO hide all of this public stuff
 pub struct ActorContext<A: Actor> {
     inner: Arc<ActorContextInner<A>>,
@@ -52,6 +81,18 @@ impl<A: Actor> Clone for ActorContext<A> {
     }
 }
 
+/// Weak reference to an `ActorContext`, which does not keep the actor mailbox alive.
+pub(crate) struct WeakActorContext<A: Actor> {
+    inner: Weak<ActorContextInner<A>>,
+}
+
+impl<A: Actor> WeakActorContext<A> {
+    pub fn upgrade(&self) -> Option<ActorContext<A>> {
+        let inner = self.inner.upgrade()?;
+        Some(ActorContext { inner })
+    }
+}
+
 impl<A: Actor> Deref for ActorContext<A> {
     type Target = ActorContextInner<A>;
 
@@ -63,10 +104,44 @@ impl<A: Actor> Deref for ActorContext<A> {
 pub struct ActorContextInner<A: Actor> {
     spawn_ctx: SpawnContext,
     self_mailbox: Mailbox<A>,
+    // Incarnation of the actor behind the mailbox, starting at 1 and incremented each time
+    // the actor is respawned.
+    generation: u64,
     progress: Progress,
     actor_state: AtomicState,
     backpressure_micros_counter_opt: Option<IntCounter>,
     observable_state_tx: watch::Sender<A::ObservableState>,
+    heartbeat: Duration,
+    processing_time_budget_opt: Option<Duration>,
+    processing_time_tracker: ProcessingTimeTracker,
+    flight_recorder: FlightRecorder,
+    message_counters: MessageCounters,
+    cpu_usage_counters: CpuUsageCounters,
+    // Correlation id of the message being processed. 0 means no message is being processed.
+    current_correlation_id: AtomicU64,
+    // Set while processing a high priority message.
+    current_message_high_priority: AtomicBool,
+    // Set when the queued messages are meant to be handed over to a respawned actor.
+    keep_queue_on_quit: AtomicBool,
+    // Set once all of the mailboxes of the actor have been dropped and its queue drained.
+    reached_eof: AtomicBool,
+    termination_notifiers: Mutex<Vec<TerminationNotifier>>,
+    deferred_cleanups: Mutex<Vec<BoxFuture<'static, ()>>>,
+    // Output of an actor that finished with `ActorContext::finish_with`.
+    output_opt: Mutex<Option<Box<dyn Any + Send>>>,
+    termination_details_opt: Mutex<Option<TerminationDetails>>,
+    // Instance ids of the actors this actor has sent messages to.
+    downstream_actor_ids: Mutex<Vec<String>>,
+    // Ids of the first mailboxes this actor has sent messages to, 0 marking a free slot. Sends
+    // to these mailboxes skip recording the wiring altogether.
+    wired_mailbox_ids: [AtomicU64; NUM_WIRED_MAILBOX_SLOTS],
+    reported_errors_tx: broadcast::Sender<ReportedError>,
+    num_reported_errors: AtomicU64,
+    // Dedup key -> generation of the last self message scheduled with that key.
+    self_msg_dedup_generations: Arc<Mutex<HashMap<String, u64>>>,
+    // Checkpoint id -> copies of the barrier received so far, waiting for the copies of the
+    // other upstream actors.
+    pending_checkpoint_barriers: Mutex<HashMap<u64, Vec<CheckpointBarrier>>>,
 }
 
 impl<A: Actor> ActorContext<A> {
@@ -75,35 +150,95 @@ impl<A: Actor> ActorContext<A> {
         spawn_ctx: SpawnContext,
         observable_state_tx: watch::Sender<A::ObservableState>,
         backpressure_micros_counter_opt: Option<IntCounter>,
+        heartbeat: Duration,
+        processing_time_budget_opt: Option<Duration>,
+        generation: u64,
     ) -> Self {
         ActorContext {
             inner: ActorContextInner {
                 self_mailbox,
+                generation,
                 spawn_ctx,
                 progress: Progress::default(),
                 actor_state: AtomicState::default(),
                 observable_state_tx,
                 backpressure_micros_counter_opt,
+                heartbeat,
+                processing_time_budget_opt,
+                processing_time_tracker: ProcessingTimeTracker::default(),
+                flight_recorder: FlightRecorder::default(),
+                message_counters: MessageCounters::default(),
+                cpu_usage_counters: CpuUsageCounters::default(),
+                current_correlation_id: AtomicU64::new(0),
+                current_message_high_priority: AtomicBool::new(false),
+                keep_queue_on_quit: AtomicBool::new(false),
+                reached_eof: AtomicBool::new(false),
+                termination_notifiers: Mutex::default(),
+                deferred_cleanups: Mutex::default(),
+                output_opt: Mutex::default(),
+                termination_details_opt: Mutex::default(),
+                downstream_actor_ids: Mutex::default(),
+                wired_mailbox_ids: Default::default(),
+                self_msg_dedup_generations: Arc::default(),
+                pending_checkpoint_barriers: Mutex::default(),
+                reported_errors_tx: broadcast::channel(REPORTED_ERRORS_CHANNEL_CAPACITY).0,
+                num_reported_errors: AtomicU64::new(0),
             }
             .into(),
         }
     }
 
+    pub(crate) fn downgrade(&self) -> WeakActorContext<A> {
+        WeakActorContext {
+            inner: Arc::downgrade(&self.inner),
+        }
+    }
+
     pub fn spawn_ctx(&self) -> &SpawnContext {
         &self.spawn_ctx
     }
 
+    /// Consults the failpoint `name` (e.g. `uploader.before_put`) of the universe
+    /// `FailpointRegistry`, and fails, panics or sleeps if it is enabled.
+    ///
+    /// Without the `failpoints` feature, this is a no-op.
+    pub async fn failpoint(&self, name: &str) -> Result<(), ActorExitStatus> {
+        #[cfg(feature = "failpoints")]
+        match self.spawn_ctx.failpoint_registry.trigger(name) {
+            None => {}
+            Some(crate::FailpointAction::ReturnError) => {
+                return Err(anyhow::anyhow!("Failpoint `{name}` triggered.").into());
+            }
+            Some(crate::FailpointAction::Panic) => panic!("Failpoint `{name}` triggered."),
+            Some(crate::FailpointAction::Sleep(duration)) => self.sleep(duration).await,
+        }
+        #[cfg(not(feature = "failpoints"))]
+        let _ = name;
+        Ok(())
+    }
+
     /// Sleeps for a given amount of time.
     ///
     /// That sleep is measured by the universe scheduler, which means that it can be
     /// shortened if `Universe::simulate_sleep(..)` is used.
     ///
-    /// While sleeping, an actor is NOT protected from its supervisor.
-    /// It is up to the user to call `ActorContext::protect_future(..)`.
+    /// The sleep is split into increments shorter than the heartbeat, and progress is
+    /// recorded between them, so that a long sleep (e.g. a retry backoff) does not get
+    /// the actor identified as blocked by its supervisor.
     pub async fn sleep(&self, duration: Duration) {
         let scheduler_client = &self.spawn_ctx().scheduler_client;
+        let max_increment = self.heartbeat() / 2;
+        let mut remaining = duration;
         scheduler_client.dec_no_advance_time();
-        scheduler_client.sleep(duration).await;
+        loop {
+            let increment = remaining.min(max_increment);
+            scheduler_client.sleep(increment).await;
+            self.record_progress();
+            remaining -= increment;
+            if remaining.is_zero() {
+                break;
+            }
+        }
         scheduler_client.inc_no_advance_time();
     }
 
@@ -118,6 +253,9 @@ impl<A: Actor> ActorContext<A> {
             universe.spawn_ctx.clone(),
             observable_state_tx,
             None,
+            *crate::HEARTBEAT,
+            None,
+            1,
         )
     }
 
@@ -125,17 +263,274 @@ impl<A: Actor> ActorContext<A> {
         &self.self_mailbox
     }
 
+    /// Returns the address of the actor.
+    pub fn addr(&self) -> Addr<A> {
+        Addr::new(
+            self.self_mailbox.clone(),
+            self.kill_switch().clone(),
+            self.generation,
+        )
+    }
+
+    /// Returns the incarnation of the actor: 1 for the actor spawned initially, incremented
+    /// each time it is respawned, e.g. by its supervisor.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns the actor instance id, suffixed with the generation of the actor,
+    /// e.g. `Indexer-winter-moon-gen3`.
+    pub fn actor_instance_name(&self) -> String {
+        format!("{}-gen{}", self.actor_instance_id(), self.generation)
+    }
+
     pub(crate) fn registry(&self) -> &ActorRegistry {
         &self.spawn_ctx.registry
     }
 
+    pub(crate) fn subscribe_observable_state(&self) -> watch::Receiver<A::ObservableState> {
+        self.observable_state_tx.subscribe()
+    }
+
+    /// Returns a handle of a live actor of type `B` of the universe, looked up by name
+    /// (see [`Actor::name`]) or by instance id.
+    ///
+    /// This makes it possible to observe a peer actor or to send it commands without
+    /// threading its handle through constructors. If several actors match, any of them is
+    /// returned.
+    pub fn handle_of<B: Actor>(&self, name: &str) -> Option<ActorHandle<B>> {
+        self.registry().get_handle::<B>(name)
+    }
+
     pub fn actor_instance_id(&self) -> &str {
         self.mailbox().actor_instance_id()
     }
 
+    /// Returns the heartbeat of the actor, as defined by `Actor::heartbeat` at spawn time.
+    pub fn heartbeat(&self) -> Duration {
+        self.heartbeat
+    }
+
+    /// Returns the processing time budget of the actor, as defined by
+    /// `Actor::processing_time_budget` at spawn time.
+    pub fn processing_time_budget(&self) -> Option<Duration> {
+        self.processing_time_budget_opt
+    }
+
+    /// Returns the correlation id of the message currently being processed, if any.
+    ///
+    /// Messages sent using the context while processing a message inherit its correlation
+    /// id.
+    pub fn current_message_id(&self) -> Option<CorrelationId> {
+        CorrelationId::from_u64(self.current_correlation_id.load(Ordering::Relaxed))
+    }
+
+    pub(crate) fn set_current_message_id(&self, correlation_id_opt: Option<CorrelationId>) {
+        let correlation_id_u64 = correlation_id_opt
+            .map(|correlation_id| correlation_id.as_u64())
+            .unwrap_or(0);
+        self.current_correlation_id
+            .store(correlation_id_u64, Ordering::Relaxed);
+    }
+
+    /// Returns the priority inherited by the messages sent using the context, i.e. the
+    /// priority of the message currently being processed.
+    fn current_message_priority(&self) -> Priority {
+        if self.current_message_high_priority.load(Ordering::Relaxed) {
+            Priority::High
+        } else {
+            Priority::Low
+        }
+    }
+
+    pub(crate) fn set_current_message_priority(&self, priority: Priority) {
+        self.current_message_high_priority
+            .store(priority == Priority::High, Ordering::Relaxed);
+    }
+
+    /// Prevents the drain policy of the actor from being applied when it quits, so that
+    /// the messages still queued in its inbox can be consumed by a respawned actor.
+    pub(crate) fn keep_queue_on_quit(&self) {
+        self.keep_queue_on_quit.store(true, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_queue_kept_on_quit(&self) -> bool {
+        self.keep_queue_on_quit.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_eof(&self) {
+        self.reached_eof.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns true if all of the mailboxes of the actor have been dropped and all of its
+    /// messages processed. See `Actor::on_eof`.
+    pub fn reached_eof(&self) -> bool {
+        self.reached_eof.load(Ordering::Relaxed)
+    }
+
+    /// Registers a downstream actor that will receive an [`UpstreamTerminated`] message
+    /// when this actor terminates, after its `finalize` hook has been called.
+    ///
+    /// The message is sent as a regular message, so that the downstream actor
+    /// processes it after the messages previously sent by this actor.
+    pub fn notify_termination_to<D>(&self, downstream_mailbox: Mailbox<D>)
+    where D: DeferableReplyHandler<UpstreamTerminated> {
+        let termination_notifier: TerminationNotifier =
+            Box::new(move |upstream_terminated: UpstreamTerminated| {
+                Box::pin(async move {
+                    let _ = downstream_mailbox.send_message(upstream_terminated).await;
+                })
+            });
+        self.termination_notifiers
+            .lock()
+            .unwrap()
+            .push(termination_notifier);
+    }
+
+    pub(crate) async fn notify_termination(&self, exit_status: &ActorExitStatus) {
+        let termination_notifiers: Vec<TerminationNotifier> =
+            std::mem::take(&mut *self.termination_notifiers.lock().unwrap());
+        for termination_notifier in termination_notifiers {
+            let upstream_terminated = UpstreamTerminated {
+                upstream_actor_id: self.actor_instance_id().to_string(),
+                exit_status: exit_status.clone(),
+            };
+            termination_notifier(upstream_terminated).await;
+        }
+    }
+
+    /// Registers a future releasing a resource owned by the actor (e.g. aborting a multipart
+    /// upload), as an async equivalent of `Drop`.
+    ///
+    /// The framework awaits the deferred futures once the actor has been finalized, whatever
+    /// its exit status, including when it was killed or panicked. They are awaited in the
+    /// reverse order of their registration.
+    pub fn defer_async(&self, cleanup_fut: impl Future<Output = ()> + Send + 'static) {
+        self.deferred_cleanups
+            .lock()
+            .unwrap()
+            .push(Box::pin(cleanup_fut));
+    }
+
+    pub(crate) async fn run_deferred_cleanups(&self) {
+        let deferred_cleanups: Vec<BoxFuture<'static, ()>> =
+            std::mem::take(&mut *self.deferred_cleanups.lock().unwrap());
+        for deferred_cleanup in deferred_cleanups.into_iter().rev() {
+            deferred_cleanup.await;
+        }
+    }
+
+    /// Records the output of an actor implementing a batch job (e.g. a merge task), and
+    /// returns the exit status with which the actor should exit:
+    ///
+    /// ```ignore
+    /// return Err(ctx.finish_with(merged_split));
+    /// ```
+    ///
+    /// The output can then be retrieved with `ActorHandle::join_with_output`.
+    pub fn finish_with<T: Any + Send>(&self, output: T) -> ActorExitStatus {
+        *self.output_opt.lock().unwrap() = Some(Box::new(output));
+        ActorExitStatus::Success
+    }
+
+    /// Takes the output recorded with `finish_with`, if it is of type `T`.
+    pub(crate) fn take_output<T: Any>(&self) -> Option<T> {
+        let mut output_opt_guard = self.output_opt.lock().unwrap();
+        if !output_opt_guard.as_ref()?.is::<T>() {
+            return None;
+        }
+        let output = output_opt_guard.take()?.downcast::<T>().ok()?;
+        Some(*output)
+    }
+
+    pub(crate) fn record_flight(&self, flight_record: FlightRecord) {
+        if self.spawn_ctx.record_flights {
+            self.flight_recorder.record(flight_record);
+        }
+    }
+
+    /// Returns the records of the last messages processed by the actor, from the oldest to
+    /// the most recent one.
+    ///
+    /// The records are only kept if the flight recorder is enabled with
+    /// `Universe::with_flight_recorder`.
+    pub fn flight_records(&self) -> Vec<FlightRecord> {
+        self.flight_recorder.records()
+    }
+
+    fn dump_flight_records(&self) {
+        if !self.spawn_ctx.record_flights {
+            return;
+        }
+        error!(actor = %self.actor_instance_id(), flight_records = ?self.flight_records(), "flight-recorder-dump");
+    }
+
+    pub(crate) fn record_processing_time(&self, processing_time: Duration) {
+        // The samples are only used to report actors exceeding their processing time budget.
+        if self.processing_time_budget_opt.is_some() {
+            self.processing_time_tracker.record(processing_time);
+        }
+    }
+
+    pub(crate) fn processing_time_p99(&self) -> Option<Duration> {
+        self.processing_time_tracker.p99()
+    }
+
+    pub(crate) fn record_cpu_usage(&self, cpu_usage: CpuUsage) {
+        self.cpu_usage_counters.record(cpu_usage);
+    }
+
+    /// Returns the cumulative CPU and blocking time spent by the actor processing messages.
+    ///
+    /// It stays at zero unless the universe was built with `Universe::with_cpu_usage_tracking`.
+    pub fn cpu_usage(&self) -> CpuUsage {
+        self.cpu_usage_counters.snapshot()
+    }
+
+    pub(crate) fn record_processed_message(&self, message_type_name: &'static str) {
+        self.message_counters.record(message_type_name);
+    }
+
+    pub(crate) fn num_processed_messages(&self) -> u64 {
+        self.message_counters.total()
+    }
+
+    /// Reports a non-fatal error, e.g. a single failed upload that will be retried, without
+    /// terminating the actor.
+    ///
+    /// The error is logged and published to the subscribers of
+    /// `ActorHandle::subscribe_reported_errors`.
+    pub fn report_error(&self, error: impl Into<anyhow::Error>) {
+        let error: anyhow::Error = error.into();
+        warn!(actor_id = %self.actor_instance_id(), error = ?error, "actor-reported-error");
+        self.num_reported_errors.fetch_add(1, Ordering::Relaxed);
+        let reported_error = ReportedError {
+            actor_instance_id: self.actor_instance_id().to_string(),
+            error: Arc::new(error),
+        };
+        // Sending only fails if there are no subscribers.
+        let _ = self.reported_errors_tx.send(reported_error);
+    }
+
+    pub(crate) fn subscribe_reported_errors(&self) -> broadcast::Receiver<ReportedError> {
+        self.reported_errors_tx.subscribe()
+    }
+
+    /// Returns the number of errors reported with `report_error` so far.
+    pub fn num_reported_errors(&self) -> u64 {
+        self.num_reported_errors.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of messages processed by the actor so far, broken down by
+    /// message type.
+    pub fn processed_message_counts(&self) -> BTreeMap<String, u64> {
+        self.message_counters.snapshot()
+    }
+
     /// This function returns a guard that prevents any supervisor from identifying the
     /// actor as dead.
-    /// The protection ends when the `ProtectZoneGuard` is dropped.
+    /// The protection ends when the `ProtectZoneGuard` is dropped. Protected zones can be
+    /// nested: the protection lasts until all of the guards are dropped.
     ///
     /// In an ideal world, you should never need to call this function.
     /// It is only useful in some corner cases, like calling a long blocking
@@ -151,6 +546,83 @@ impl<A: Actor> ActorContext<A> {
         future.await
     }
 
+    /// Retries a fallible operation with exponential backoff and jitter, until it succeeds
+    /// or `retry_policy.max_attempts` attempts failed.
+    ///
+    /// The backoff delays are waited with `ActorContext::sleep`, so the actor keeps
+    /// recording progress in the meantime. Once all of the attempts failed, the last error
+    /// is returned along with the history of the attempts.
+    pub async fn retry<F, Fut, T, E>(
+        &self,
+        retry_policy: &RetryPolicy,
+        mut operation: F,
+    ) -> Result<T, RetryError<E>>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: fmt::Debug,
+    {
+        let mut attempts: Vec<FailedAttempt> = Vec::new();
+        loop {
+            let error = match operation().await {
+                Ok(output) => return Ok(output),
+                Err(error) => error,
+            };
+            self.record_progress();
+            let num_attempts = attempts.len() + 1;
+            if num_attempts >= retry_policy.max_attempts {
+                attempts.push(FailedAttempt {
+                    error: format!("{error:?}"),
+                    backoff_opt: None,
+                });
+                return Err(RetryError {
+                    last_error: error,
+                    attempts,
+                });
+            }
+            let backoff = retry_policy.backoff(num_attempts, &mut rand::thread_rng());
+            warn!(
+                actor_id = %self.actor_instance_id(),
+                num_attempts = num_attempts,
+                backoff = ?backoff,
+                error = ?error,
+                "retrying-failed-operation"
+            );
+            attempts.push(FailedAttempt {
+                error: format!("{error:?}"),
+                backoff_opt: Some(backoff),
+            });
+            self.sleep(backoff).await;
+        }
+    }
+
+    /// Runs a blocking function on tokio's blocking thread pool, e.g. a call into tantivy
+    /// or zstd, without blocking the executor thread of the actor.
+    ///
+    /// The actor is kept protected until the function returns, and progress is recorded
+    /// periodically in the meantime. A panic in the function is reported as
+    /// `ActorExitStatus::Panicked`.
+    pub async fn run_blocking<F, T>(&self, blocking_fn: F) -> Result<T, ActorExitStatus>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let _protect_guard = self.protect_zone();
+        let mut join_handle = tokio::task::spawn_blocking(blocking_fn);
+        let mut progress_interval = tokio::time::interval(self.heartbeat() / 2);
+        let join_result = loop {
+            tokio::select! {
+                join_result = &mut join_handle => break join_result,
+                _ = progress_interval.tick() => self.record_progress(),
+            }
+        };
+        self.record_progress();
+        join_result.map_err(|join_error| {
+            error!(actor_id=%self.actor_instance_id(), error=%join_error, "blocking-task-failed");
+            ActorExitStatus::Panicked
+        })
+    }
+
     /// Cooperatively yields, while keeping the actor protected.
     pub async fn yield_now(&self) {
         self.protect_future(tokio::task::yield_now()).await;
@@ -176,7 +648,7 @@ impl<A: Actor> ActorContext<A> {
 
     /// Records some progress.
     /// This function is only useful when implementing actors that may take more than
-    /// `HEARTBEAT` to process a single message.
+    /// their heartbeat to process a single message.
     /// In that case, you can call this function in the middle of the process_message method
     /// to prevent the actor from being identified as blocked or dead.
     pub fn record_progress(&self) {
@@ -197,10 +669,18 @@ impl<A: Actor> ActorContext<A> {
 
     pub(crate) fn pause(&self) {
         self.actor_state.pause();
+        self.publish_lifecycle_event(ActorLifecycleEventKind::Paused);
     }
 
     pub(crate) fn resume(&self) {
         self.actor_state.resume();
+        self.publish_lifecycle_event(ActorLifecycleEventKind::Resumed);
+    }
+
+    pub(crate) fn publish_lifecycle_event(&self, kind: ActorLifecycleEventKind) {
+        self.spawn_ctx
+            .lifecycle_event_bus
+            .publish(self.actor_instance_id(), kind);
     }
 
     pub(crate) fn observe(&self, actor: &mut A) -> A::ObservableState {
@@ -209,14 +689,102 @@ impl<A: Actor> ActorContext<A> {
         obs_state
     }
 
+    /// Records a copy of a checkpoint barrier, and returns all of its copies once
+    /// `num_upstreams` of them have been received.
+    pub(crate) fn align_checkpoint_barrier(
+        &self,
+        checkpoint_barrier: CheckpointBarrier,
+        num_upstreams: usize,
+    ) -> Option<Vec<CheckpointBarrier>> {
+        let checkpoint_id = checkpoint_barrier.checkpoint_id();
+        let mut pending_checkpoint_barriers = self.pending_checkpoint_barriers.lock().unwrap();
+        let checkpoint_barriers = pending_checkpoint_barriers
+            .entry(checkpoint_id)
+            .or_default();
+        checkpoint_barriers.push(checkpoint_barrier);
+
+        if checkpoint_barriers.len() < num_upstreams {
+            return None;
+        }
+        pending_checkpoint_barriers.remove(&checkpoint_id)
+    }
+
     pub(crate) fn exit(&self, exit_status: &ActorExitStatus) {
+        if let Some(termination) = exit_status.termination() {
+            self.set_termination_details(TerminationDetails {
+                exit_status: exit_status.clone(),
+                panic_message_opt: None,
+                backtrace: termination.backtrace().clone(),
+            });
+        }
         self.actor_state.exit(exit_status.is_success());
+        // The copies of the barriers the actor was waiting on will never go through: the
+        // checkpoints fail.
+        self.pending_checkpoint_barriers.lock().unwrap().clear();
+        if matches!(
+            exit_status,
+            ActorExitStatus::Failure(_) | ActorExitStatus::Panicked
+        ) {
+            self.dump_flight_records();
+        }
+        self.publish_lifecycle_event(ActorLifecycleEventKind::Terminated(exit_status.clone()));
         if should_activate_kill_switch(exit_status) {
             error!(actor=%self.actor_instance_id(), exit_status=?exit_status, "exit activating-kill-switch");
+            self.activate_kill_switch();
+        }
+    }
+
+    fn activate_kill_switch(&self) {
+        if let Some(termination_details) = self.termination_details() {
+            let kill_reason = format!(
+                "actor `{}` exited: {termination_details}",
+                self.actor_instance_id()
+            );
+            self.kill_switch().kill_with_reason(kill_reason);
+        } else {
             self.kill_switch().kill();
         }
     }
 
+    /// Applies the panic policy of the universe, once the panic of the actor has been
+    /// recorded.
+    pub(crate) fn apply_panic_policy(&self) {
+        match self.spawn_ctx.panic_policy {
+            PanicPolicy::KillActor => {}
+            PanicPolicy::KillPipeline => {
+                error!(actor=%self.actor_instance_id(), "panic activating-kill-switch");
+                self.activate_kill_switch();
+            }
+            PanicPolicy::AbortProcess => {
+                error!(actor=%self.actor_instance_id(), termination_details=?self.termination_details(), "panic aborting-process");
+                std::process::abort();
+            }
+        }
+    }
+
+    /// Records the message and the backtrace of the panic of the actor.
+    pub(crate) fn record_panic(&self, panic_payload: &(dyn Any + Send)) {
+        let backtrace = take_last_panic_backtrace().unwrap_or_else(Backtrace::disabled);
+        self.set_termination_details(TerminationDetails {
+            exit_status: ActorExitStatus::Panicked,
+            panic_message_opt: Some(panic_message(panic_payload)),
+            backtrace: Arc::new(backtrace),
+        });
+        self.dump_flight_records();
+        self.publish_lifecycle_event(ActorLifecycleEventKind::Terminated(
+            ActorExitStatus::Panicked,
+        ));
+    }
+
+    fn set_termination_details(&self, termination_details: TerminationDetails) {
+        *self.termination_details_opt.lock().unwrap() = Some(termination_details);
+    }
+
+    /// Returns the details of the termination of the actor if it failed or panicked.
+    pub fn termination_details(&self) -> Option<TerminationDetails> {
+        self.termination_details_opt.lock().unwrap().clone()
+    }
+
     /// Posts a message in an actor's mailbox.
     ///
     /// This method does not wait for the message to be handled by the
@@ -246,13 +814,229 @@ impl<A: Actor> ActorContext<A> {
         M: fmt::Debug + Send + 'static,
     {
         let _guard = self.protect_zone();
-        debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), msg=?msg);
+        self.record_wiring(mailbox.mailbox_id(), mailbox.actor_instance_id());
+        let correlation_id_opt = self.current_message_id();
+        if should_trace_message() {
+            debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), correlation_id=?correlation_id_opt, msg=%TruncatedDebug(&msg));
+        }
+        let send_res = mailbox
+            .send_message_with_correlation_id(
+                msg,
+                correlation_id_opt,
+                self.current_message_priority(),
+                self.backpressure_micros_counter_opt.as_ref(),
+            )
+            .await;
+        send_res.map_err(|send_error| self.attach_queue_diagnostics(send_error, mailbox))
+    }
+
+    /// Logs a failure to send a message to `mailbox`, and attaches the state of its queue to
+    /// the error.
+    fn attach_queue_diagnostics<DestActor: Actor>(
+        &self,
+        send_error: SendError,
+        mailbox: &Mailbox<DestActor>,
+    ) -> SendError {
+        let send_error = mailbox.attach_queue_diagnostics(send_error);
+        warn!(
+            from=%self.self_mailbox.actor_instance_id(),
+            error=%send_error,
+            "send-message-failed"
+        );
+        send_error
+    }
+
+    /// Similar to `send_message`, except the size of the message is accounted for in the
+    /// memory budget of the universe, if any.
+    ///
+    /// This method waits while the memory budget is exhausted, which makes it the right way
+    /// for sources to push potentially large batches into a pipeline.
+    pub async fn send_sized_message<DestActor: Actor, M>(
+        &self,
+        mailbox: &Mailbox<DestActor>,
+        msg: M,
+    ) -> Result<oneshot::Receiver<DestActor::Reply>, SendError>
+    where
+        DestActor: DeferableReplyHandler<M>,
+        M: MessageSize + fmt::Debug + Send + 'static,
+    {
+        let Some(memory_budget) = &self.spawn_ctx.memory_budget_opt else {
+            return self.send_message(mailbox, msg).await;
+        };
+        let _guard = self.protect_zone();
+        let memory_permit = memory_budget.acquire(msg.size_in_bytes()).await;
+        self.send_message_with_memory_permit(mailbox, msg, memory_permit)
+            .await
+    }
+
+    /// Similar to `send_sized_message`, except this method never waits on the memory budget:
+    /// the size of the message is accounted for even if the budget is exhausted.
+    ///
+    /// This is the right way for the stages downstream of a source to forward their output.
+    /// Waiting on the budget there could deadlock, as the messages exhausting the budget may
+    /// be the ones waiting in the mailbox of the stage.
+    pub async fn forward_sized_message<DestActor: Actor, M>(
+        &self,
+        mailbox: &Mailbox<DestActor>,
+        msg: M,
+    ) -> Result<oneshot::Receiver<DestActor::Reply>, SendError>
+    where
+        DestActor: DeferableReplyHandler<M>,
+        M: MessageSize + fmt::Debug + Send + 'static,
+    {
+        let Some(memory_budget) = &self.spawn_ctx.memory_budget_opt else {
+            return self.send_message(mailbox, msg).await;
+        };
+        let _guard = self.protect_zone();
+        let memory_permit = memory_budget.reserve(msg.size_in_bytes());
+        self.send_message_with_memory_permit(mailbox, msg, memory_permit)
+            .await
+    }
+
+    async fn send_message_with_memory_permit<DestActor: Actor, M>(
+        &self,
+        mailbox: &Mailbox<DestActor>,
+        msg: M,
+        memory_permit: MemoryPermit,
+    ) -> Result<oneshot::Receiver<DestActor::Reply>, SendError>
+    where
+        DestActor: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        self.record_wiring(mailbox.mailbox_id(), mailbox.actor_instance_id());
+        if should_trace_message() {
+            debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), msg=%TruncatedDebug(&msg), "send-sized-message");
+        }
         mailbox
-            .send_message_with_backpressure_counter(
+            .send_message_with_memory_permit(
                 msg,
+                self.current_message_id(),
                 self.backpressure_micros_counter_opt.as_ref(),
+                memory_permit,
             )
             .await
+            .map_err(|send_error| self.attach_queue_diagnostics(send_error, mailbox))
+    }
+
+    /// Similar to `send_message`, except the message survives the failure of the
+    /// destination actor. See [`Mailbox::send_redeliverable_message`].
+    pub async fn send_redeliverable_message<DestActor: Actor, M>(
+        &self,
+        mailbox: &Mailbox<DestActor>,
+        msg: M,
+    ) -> Result<oneshot::Receiver<DestActor::Reply>, SendError>
+    where
+        DestActor: DeferableReplyHandler<M>,
+        M: Clone + fmt::Debug + Send + 'static,
+    {
+        let _guard = self.protect_zone();
+        self.record_wiring(mailbox.mailbox_id(), mailbox.actor_instance_id());
+        if should_trace_message() {
+            debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), msg=%TruncatedDebug(&msg), "send-redeliverable-message");
+        }
+        mailbox.send_redeliverable_message(msg).await
+    }
+
+    /// Sends a [`Watermark`] signaling that this actor has sent all of its data up to `epoch`
+    /// to the destination actor.
+    pub async fn send_watermark<DestActor: Actor>(
+        &self,
+        mailbox: &Mailbox<DestActor>,
+        epoch: u64,
+    ) -> Result<(), SendError>
+    where
+        DestActor: DeferableReplyHandler<Watermark>,
+    {
+        let watermark = Watermark {
+            upstream_actor_id: self.actor_instance_id().to_string(),
+            epoch,
+        };
+        self.send_message(mailbox, watermark).await?;
+        Ok(())
+    }
+
+    /// Records the wiring between this actor and the destination actor, so that the universe
+    /// can drain upstream actors first on shutdown.
+    fn record_wiring(&self, downstream_mailbox_id: u64, downstream_actor_id: &str) {
+        if self
+            .wired_mailbox_ids
+            .iter()
+            .any(|mailbox_id| mailbox_id.load(Ordering::Relaxed) == downstream_mailbox_id)
+        {
+            return;
+        }
+        let mut downstream_actor_ids = self.downstream_actor_ids.lock().unwrap();
+        if !downstream_actor_ids
+            .iter()
+            .any(|actor_id| actor_id == downstream_actor_id)
+        {
+            downstream_actor_ids.push(downstream_actor_id.to_string());
+            self.spawn_ctx
+                .registry
+                .record_wiring(self.actor_instance_id(), downstream_actor_id);
+        }
+        for mailbox_id in &self.wired_mailbox_ids {
+            if mailbox_id
+                .compare_exchange(
+                    0,
+                    downstream_mailbox_id,
+                    Ordering::Relaxed,
+                    Ordering::Relaxed,
+                )
+                .is_ok()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Subscribes the actor to a config section. The actor receives a [`ConfigUpdated`]
+    /// message, queued like a regular message, whenever the value of the section changes.
+    ///
+    /// The subscription is dropped on the first change following the exit of the actor.
+    pub fn subscribe_to_config<C>(&self, config_watcher: &ConfigWatcher<C>)
+    where
+        A: DeferableReplyHandler<ConfigUpdated<C>>,
+        C: Clone + PartialEq + fmt::Debug + Send + Sync + 'static,
+    {
+        let mut config_rx = config_watcher.subscribe();
+        let weak_mailbox = self.mailbox().downgrade();
+        tokio::spawn(async move {
+            // The loop ends when the config watcher is dropped.
+            while config_rx.changed().await.is_ok() {
+                let Some(mailbox) = weak_mailbox.upgrade() else {
+                    return;
+                };
+                let config = config_rx.borrow_and_update().clone();
+                if mailbox.send_message(ConfigUpdated(config)).await.is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Similar to `send_message`, except the message is sent to a [`DeferredMailbox`]. If the
+    /// deferred mailbox is not bound yet, this method waits until it is.
+    ///
+    /// The reply of the destination actor is discarded.
+    pub async fn send_message_to_deferred<M>(
+        &self,
+        deferred_mailbox: &DeferredMailbox<M>,
+        msg: M,
+    ) -> Result<(), SendError>
+    where
+        M: fmt::Debug + Send + 'static,
+    {
+        let _guard = self.protect_zone();
+        let recipient = deferred_mailbox.recipient().await;
+        self.record_wiring(recipient.mailbox_id(), recipient.actor_instance_id());
+        let correlation_id_opt = self.current_message_id();
+        if should_trace_message() {
+            debug!(from=%self.self_mailbox.actor_instance_id(), send=%recipient.actor_instance_id(), correlation_id=?correlation_id_opt, msg=%TruncatedDebug(&msg), "send-deferred-message");
+        }
+        recipient
+            .send_with_correlation_id(msg, correlation_id_opt, self.current_message_priority())
+            .await
     }
 
     pub async fn ask<DestActor: Actor, M, T>(
@@ -265,10 +1049,21 @@ impl<A: Actor> ActorContext<A> {
         M: fmt::Debug + Send + 'static,
     {
         let _guard = self.protect_zone();
-        debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), msg=?msg, "ask");
+        let correlation_id_opt = self.current_message_id();
+        if should_trace_message() {
+            debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), correlation_id=?correlation_id_opt, msg=%TruncatedDebug(&msg), "ask");
+        }
         mailbox
-            .ask_with_backpressure_counter(msg, self.backpressure_micros_counter_opt.as_ref())
+            .send_message_with_correlation_id(
+                msg,
+                correlation_id_opt,
+                self.current_message_priority(),
+                self.backpressure_micros_counter_opt.as_ref(),
+            )
+            .await
+            .map_err(|_send_error| AskError::MessageNotDelivered)?
             .await
+            .map_err(|_| AskError::ProcessMessageError)
     }
 
     /// Similar to `send_message`, except this method
@@ -284,8 +1079,22 @@ impl<A: Actor> ActorContext<A> {
         E: fmt::Debug,
     {
         let _guard = self.protect_zone();
-        debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), msg=?msg, "ask");
-        mailbox.ask_for_res(msg).await
+        let correlation_id_opt = self.current_message_id();
+        if should_trace_message() {
+            debug!(from=%self.self_mailbox.actor_instance_id(), send=%mailbox.actor_instance_id(), correlation_id=?correlation_id_opt, msg=%TruncatedDebug(&msg), "ask");
+        }
+        mailbox
+            .send_message_with_correlation_id(
+                msg,
+                correlation_id_opt,
+                self.current_message_priority(),
+                None,
+            )
+            .await
+            .map_err(|_send_error| AskError::MessageNotDelivered)?
+            .await
+            .map_err(|_| AskError::ProcessMessageError)?
+            .map_err(AskError::from)
     }
 
     /// Send the Success message to terminate the destination actor with the Success exit status.
@@ -314,8 +1123,13 @@ impl<A: Actor> ActorContext<A> {
         A: DeferableReplyHandler<M>,
         M: 'static + Sync + Send + fmt::Debug,
     {
-        debug!(self=%self.self_mailbox.actor_instance_id(), msg=?msg, "self_send");
-        self.self_mailbox.send_message(msg).await
+        let correlation_id_opt = self.current_message_id();
+        if should_trace_message() {
+            debug!(self=%self.self_mailbox.actor_instance_id(), correlation_id=?correlation_id_opt, msg=%TruncatedDebug(&msg), "self_send");
+        }
+        self.self_mailbox
+            .send_message_with_correlation_id(msg, correlation_id_opt, Priority::Low, None)
+            .await
     }
 
     /// Attempts to send a message to itself.
@@ -334,6 +1148,31 @@ impl<A: Actor> ActorContext<A> {
         self.self_mailbox.try_send_message(msg)
     }
 
+    /// Schedules `callback` to be executed once `after_duration` has elapsed.
+    ///
+    /// If the actor runs on an isolated runtime, the callback is executed on that runtime
+    /// rather than within the scheduler task, so that the actor never receives messages sent
+    /// from another runtime.
+    fn schedule_event<F>(&self, callback: F, after_duration: Duration)
+    where F: FnOnce() + Send + Sync + 'static {
+        let scheduler_client = &self.spawn_ctx.scheduler_client;
+        let Some(isolated_runtime) = &self.spawn_ctx.isolated_runtime_opt else {
+            scheduler_client.schedule_event(callback, after_duration);
+            return;
+        };
+        let runtime_handle = isolated_runtime.handle();
+        let scheduler_client_clone = scheduler_client.clone();
+        let callback_on_actor_runtime = move || {
+            // Time must not be accelerated before the callback has actually been executed.
+            let no_advance_time_guard = scheduler_client_clone.no_advance_time_guard();
+            runtime_handle.spawn(async move {
+                callback();
+                drop(no_advance_time_guard);
+            });
+        };
+        scheduler_client.schedule_event(callback_on_actor_runtime, after_duration);
+    }
+
     /// Schedules a message that will be sent to the high-priority
     /// queue of the actor Mailbox once `after_duration` has elapsed.
     pub async fn schedule_self_msg<M>(&self, after_duration: Duration, message: M)
@@ -342,14 +1181,193 @@ impl<A: Actor> ActorContext<A> {
         M: Sync + Send + std::fmt::Debug + 'static,
     {
         let self_mailbox = self.inner.self_mailbox.clone();
+        let callback = move || send_scheduled_self_msg(&self_mailbox, message);
+        self.schedule_event(callback, after_duration);
+    }
+
+    /// Schedules a message like `schedule_self_msg`, to be delivered at the wall-clock time
+    /// `deadline`, e.g. at a day boundary for a retention enforcement job.
+    ///
+    /// The deadline is converted into a delay when the message is scheduled: a message whose
+    /// deadline has already passed is delivered right away, and changes of the system clock
+    /// happening afterwards are not taken into account.
+    pub async fn schedule_self_msg_at<M>(&self, deadline: SystemTime, message: M)
+    where
+        A: DeferableReplyHandler<M>,
+        M: Sync + Send + std::fmt::Debug + 'static,
+    {
+        let after_duration = deadline
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        self.schedule_self_msg(after_duration, message).await;
+    }
+
+    /// Schedules a message like `schedule_self_msg`, to be delivered at `deadline`. A message
+    /// whose deadline has already passed is delivered right away.
+    pub async fn schedule_self_msg_at_instant<M>(&self, deadline: Instant, message: M)
+    where
+        A: DeferableReplyHandler<M>,
+        M: Sync + Send + std::fmt::Debug + 'static,
+    {
+        let after_duration = deadline.saturating_duration_since(Instant::now());
+        self.schedule_self_msg(after_duration, message).await;
+    }
+
+    /// Schedules a message like `schedule_self_msg`, replacing the message previously
+    /// scheduled with the same `dedup_key` if it has not been delivered yet.
+    ///
+    /// This is useful to postpone a logical timeout (e.g. "commit split X") without
+    /// stacking up events that would all fire later.
+    pub async fn schedule_self_msg_with_dedup_key<M>(
+        &self,
+        dedup_key: impl Into<String>,
+        after_duration: Duration,
+        message: M,
+    ) where
+        A: DeferableReplyHandler<M>,
+        M: Sync + Send + std::fmt::Debug + 'static,
+    {
+        let dedup_key: String = dedup_key.into();
+        let generation = {
+            let mut dedup_generations = self.self_msg_dedup_generations.lock().unwrap();
+            let generation = dedup_generations.entry(dedup_key.clone()).or_default();
+            *generation += 1;
+            *generation
+        };
+        let dedup_generations = Arc::downgrade(&self.self_msg_dedup_generations);
+        let self_mailbox = self.inner.self_mailbox.clone();
+        let callback = move || {
+            let Some(dedup_generations) = dedup_generations.upgrade() else {
+                return;
+            };
+            let mut dedup_generations_guard = dedup_generations.lock().unwrap();
+            if dedup_generations_guard.get(&dedup_key) != Some(&generation) {
+                // The message was replaced by a more recent one.
+                return;
+            }
+            dedup_generations_guard.remove(&dedup_key);
+            drop(dedup_generations_guard);
+            send_scheduled_self_msg(&self_mailbox, message);
+        };
+        self.schedule_event(callback, after_duration);
+    }
+
+    /// Schedules a message like `schedule_self_msg`, with a delay picked at random in
+    /// `[after_duration * (1 - jitter_fraction), after_duration * (1 + jitter_fraction)]`.
+    ///
+    /// Periodic tasks running in many actors (commit ticks, GC scans, ...) should use it to
+    /// avoid synchronizing and hitting the metastore or the storage all at once.
+    /// `jitter_fraction` is clamped to `[0, 1]`.
+    pub async fn schedule_self_msg_with_jitter<M>(
+        &self,
+        after_duration: Duration,
+        jitter_fraction: f32,
+        message: M,
+    ) where
+        A: DeferableReplyHandler<M>,
+        M: Sync + Send + std::fmt::Debug + 'static,
+    {
+        let after_duration =
+            jittered_duration(after_duration, jitter_fraction, &mut rand::thread_rng());
+        self.schedule_self_msg(after_duration, message).await;
+    }
+
+    /// Schedules a message for the actor itself, like `schedule_self_msg`, and persists it
+    /// until it is delivered if the universe has a `ScheduledMessageStore`.
+    ///
+    /// After a process restart, the actor can get the messages that were not delivered back
+    /// by calling `restore_persistent_self_msgs` with the same `persistence_key`. The key
+    /// should therefore be stable across restarts (e.g. a pipeline id).
+    ///
+    /// If the universe has no `ScheduledMessageStore`, the message is simply scheduled.
+    pub async fn schedule_persistent_self_msg<M>(
+        &self,
+        persistence_key: &str,
+        after_duration: Duration,
+        message: M,
+    ) -> anyhow::Result<()>
+    where
+        A: DeferableReplyHandler<M>,
+        M: Serialize + Sync + Send + std::fmt::Debug + 'static,
+    {
+        let Some(scheduled_message_store) = self.spawn_ctx.scheduled_message_store_opt.clone()
+        else {
+            self.schedule_self_msg(after_duration, message).await;
+            return Ok(());
+        };
+        let message_json = serde_json::to_value(&message)?;
+        let record_id = scheduled_message_store
+            .insert(
+                persistence_key,
+                type_name::<M>(),
+                after_duration,
+                message_json,
+            )
+            .await?;
+        let self_mailbox = self.inner.self_mailbox.clone();
         let callback = move || {
-            let _ = self_mailbox.send_message_with_high_priority(message);
+            // If the message cannot be delivered, we keep it in the store, so that it gets
+            // restored on the next start.
+            if self_mailbox
+                .send_message_with_high_priority(message)
+                .is_ok()
+            {
+                scheduled_message_store.remove(record_id);
+            }
         };
-        self.inner
-            .spawn_ctx
-            .scheduler_client
-            .schedule_event(callback, after_duration);
+        self.schedule_event(callback, after_duration);
+        Ok(())
     }
+
+    /// Schedules again the messages of type `M` persisted under `persistence_key` that were
+    /// not delivered before the previous process stopped. Messages whose deadline has
+    /// passed are delivered right away.
+    ///
+    /// This is typically called in `Actor::initialize`. Returns the number of restored
+    /// messages.
+    pub async fn restore_persistent_self_msgs<M>(
+        &self,
+        persistence_key: &str,
+    ) -> anyhow::Result<usize>
+    where
+        A: DeferableReplyHandler<M>,
+        M: Serialize + DeserializeOwned + Sync + Send + std::fmt::Debug + 'static,
+    {
+        let Some(scheduled_message_store) = &self.spawn_ctx.scheduled_message_store_opt else {
+            return Ok(0);
+        };
+        let messages = scheduled_message_store
+            .take(persistence_key, type_name::<M>())
+            .await?;
+        let num_messages = messages.len();
+        for (time_left, message_json) in messages {
+            let message: M = serde_json::from_value(message_json)?;
+            self.schedule_persistent_self_msg(persistence_key, time_left, message)
+                .await?;
+        }
+        Ok(num_messages)
+    }
+}
+
+fn jittered_duration(duration: Duration, jitter_fraction: f32, rng: &mut impl Rng) -> Duration {
+    let jitter_fraction = jitter_fraction.clamp(0.0, 1.0);
+    if jitter_fraction == 0.0 {
+        return duration;
+    }
+    let factor = rng.gen_range(1.0 - jitter_fraction..=1.0 + jitter_fraction);
+    duration.mul_f32(factor)
+}
+
+/// Delivers a scheduled message to the high-priority queue of the actor.
+///
+/// The high-priority queue is unbounded, so scheduled messages are never dropped. The send
+/// only fails if the actor has exited, in which case the message is moot.
+fn send_scheduled_self_msg<A, M>(self_mailbox: &Mailbox<A>, message: M)
+where
+    A: DeferableReplyHandler<M>,
+    M: Sync + Send + std::fmt::Debug + 'static,
+{
+    let _ = self_mailbox.send_message_with_high_priority(message);
 }
 
 /// If an actor exits in an unexpected manner, its kill
@@ -365,3 +1383,109 @@ fn should_activate_kill_switch(exit_status: &ActorExitStatus) -> bool {
         ActorExitStatus::Killed => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::tests::PingReceiverActor;
+    use crate::IsolatedRuntime;
+
+    #[test]
+    fn test_jittered_duration() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let duration = Duration::from_secs(100);
+        assert_eq!(jittered_duration(duration, 0.0, &mut rng), duration);
+        let mut jittered_durations = Vec::new();
+        for _ in 0..100 {
+            let jittered = jittered_duration(duration, 0.1, &mut rng);
+            assert!(jittered >= Duration::from_millis(89_999));
+            assert!(jittered <= Duration::from_millis(110_001));
+            jittered_durations.push(jittered);
+        }
+        jittered_durations.dedup();
+        assert!(jittered_durations.len() > 1);
+        // The jitter fraction is clamped.
+        assert!(jittered_duration(duration, 2.0, &mut rng) <= Duration::from_millis(200_001));
+    }
+
+    #[tokio::test]
+    async fn test_send_error_carries_queue_diagnostics() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, _inbox) = universe.create_test_mailbox::<PingReceiverActor>();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(0);
+        let ctx = ActorContext::for_test(&universe, mailbox, observable_state_tx);
+        let (dest_mailbox, dest_inbox) = universe.create_test_mailbox::<PingReceiverActor>();
+        drop(dest_inbox);
+        let send_error = ctx
+            .send_message(&dest_mailbox, crate::tests::Ping)
+            .await
+            .unwrap_err();
+        assert!(send_error.is_disconnected());
+        let queue_diagnostics = send_error.queue_diagnostics().unwrap();
+        assert_eq!(
+            queue_diagnostics.actor_instance_id,
+            dest_mailbox.actor_instance_id()
+        );
+        assert!(send_error
+            .to_string()
+            .contains(dest_mailbox.actor_instance_id()));
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_record_wiring_beyond_wired_mailbox_slots() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, _inbox) = universe.create_test_mailbox::<PingReceiverActor>();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(0);
+        let ctx = ActorContext::for_test(&universe, mailbox, observable_state_tx);
+        let dest_mailboxes: Vec<(Mailbox<PingReceiverActor>, _)> = (0..NUM_WIRED_MAILBOX_SLOTS + 2)
+            .map(|_| universe.create_test_mailbox::<PingReceiverActor>())
+            .collect();
+        for _ in 0..2 {
+            for (dest_mailbox, _dest_inbox) in &dest_mailboxes {
+                ctx.record_wiring(dest_mailbox.mailbox_id(), dest_mailbox.actor_instance_id());
+            }
+        }
+        assert!(ctx
+            .wired_mailbox_ids
+            .iter()
+            .all(|mailbox_id| mailbox_id.load(Ordering::Relaxed) != 0));
+        let downstream_actor_ids = ctx.downstream_actor_ids.lock().unwrap().clone();
+        let expected_downstream_actor_ids: Vec<String> = dest_mailboxes
+            .iter()
+            .map(|(dest_mailbox, _)| dest_mailbox.actor_instance_id().to_string())
+            .collect();
+        assert_eq!(downstream_actor_ids, expected_downstream_actor_ids);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_schedule_event_runs_on_isolated_runtime() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, _inbox) = universe.create_test_mailbox::<PingReceiverActor>();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(0);
+        let mut spawn_ctx = universe.spawn_ctx.clone();
+        spawn_ctx.isolated_runtime_opt = Some(IsolatedRuntime::new("test-scheduled", 1).unwrap());
+        let ctx = ActorContext::new(
+            mailbox,
+            spawn_ctx,
+            observable_state_tx,
+            None,
+            *crate::HEARTBEAT,
+            None,
+            1,
+        );
+        let (thread_name_tx, thread_name_rx) = oneshot::channel();
+        let callback = move || {
+            let thread_name_opt = std::thread::current().name().map(str::to_string);
+            let _ = thread_name_tx.send(thread_name_opt);
+        };
+        ctx.schedule_event(callback, Duration::from_secs(1));
+        let thread_name = thread_name_rx.await.unwrap().unwrap();
+        assert!(thread_name.starts_with("test-scheduled-"));
+        universe.assert_quit().await;
+    }
+}
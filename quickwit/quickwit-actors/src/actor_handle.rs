@@ -17,24 +17,41 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::any::Any;
+use std::collections::BTreeMap;
 use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use anyhow::Context;
 use serde::Serialize;
-use tokio::sync::{oneshot, watch};
-use tracing::error;
+use serde_json::Value as JsonValue;
+use tokio::sync::{broadcast, oneshot, watch};
+use tracing::{error, warn};
 
 use crate::actor_state::ActorState;
 use crate::command::Observe;
-use crate::mailbox::Priority;
-use crate::observation::ObservationType;
+use crate::cpu_time::CpuUsage;
+use crate::mailbox::{Priority, WeakInbox};
+use crate::observation::{ObservationDiff, ObservationType};
+use crate::processing_time::SlowActorReport;
 use crate::registry::ActorJoinHandle;
-use crate::{Actor, ActorContext, ActorExitStatus, Command, Mailbox, Observation};
+use crate::spawn_builder::SpawnBuilder;
+use crate::{
+    Actor, ActorContext, ActorExitStatus, Addr, Command, CustomCommand, FlightRecord, Mailbox,
+    Observation, ReportedError, TerminationDetails,
+};
 
 /// An Actor Handle serves as an address to communicate with an actor.
 pub struct ActorHandle<A: Actor> {
     actor_context: ActorContext<A>,
     last_state: watch::Receiver<A::ObservableState>,
     join_handle: ActorJoinHandle,
+    // Observable state as of the last call to `diff()`.
+    last_diffed_state: Mutex<JsonValue>,
+    // Used to respawn the actor with the same mailbox. It is weak so that the queue is
+    // dropped, and the senders notified, as soon as the actor exits.
+    weak_inbox: WeakInbox<A>,
 }
 
 /// Describes the health of a given actor.
@@ -64,6 +81,13 @@ impl<A: Actor> fmt::Debug for ActorHandle<A> {
 pub trait Supervisable {
     fn name(&self) -> &str;
     fn harvest_health(&self) -> Health;
+    /// Returns true if the actor is not processing any message and its mailbox is empty.
+    fn is_idle(&self) -> bool;
+    /// Returns the number of messages processed by the actor so far.
+    fn num_processed_messages(&self) -> u64;
+    /// Stops processing the messages of the low priority channel until resumed.
+    fn pause(&self);
+    fn resume(&self);
 }
 
 impl<A: Actor> Supervisable for ActorHandle<A> {
@@ -93,6 +117,28 @@ impl<A: Actor> Supervisable for ActorHandle<A> {
             Health::FailureOrUnhealthy
         }
     }
+
+    fn is_idle(&self) -> bool {
+        if self.state() == ActorState::Processing {
+            return false;
+        }
+        self.weak_inbox
+            .upgrade()
+            .map(|inbox| inbox.is_empty())
+            .unwrap_or(true)
+    }
+
+    fn num_processed_messages(&self) -> u64 {
+        self.actor_context.num_processed_messages()
+    }
+
+    fn pause(&self) {
+        ActorHandle::pause(self)
+    }
+
+    fn resume(&self) {
+        ActorHandle::resume(self)
+    }
 }
 
 impl<A: Actor> ActorHandle<A> {
@@ -100,11 +146,14 @@ impl<A: Actor> ActorHandle<A> {
         last_state: watch::Receiver<A::ObservableState>,
         join_handle: ActorJoinHandle,
         actor_context: ActorContext<A>,
+        weak_inbox: WeakInbox<A>,
     ) -> Self {
         ActorHandle {
             actor_context,
             last_state,
             join_handle,
+            last_diffed_state: Mutex::new(JsonValue::Null),
+            weak_inbox,
         }
     }
 
@@ -112,6 +161,80 @@ impl<A: Actor> ActorHandle<A> {
         self.actor_context.state()
     }
 
+    /// Subscribes to the non-fatal errors reported by the actor from now on.
+    /// See `ActorContext::report_error`.
+    pub fn subscribe_reported_errors(&self) -> broadcast::Receiver<ReportedError> {
+        self.actor_context.subscribe_reported_errors()
+    }
+
+    /// Returns true if the actor reached the end of its input, i.e. all of its mailboxes have
+    /// been dropped and all of its messages processed, as opposed to merely starving.
+    pub fn reached_eof(&self) -> bool {
+        self.actor_context.reached_eof()
+    }
+
+    /// Returns the records of the last messages processed by the actor. They are also logged
+    /// when the actor fails or panics. Empty unless the universe was built with
+    /// `Universe::with_flight_recorder`.
+    pub fn flight_records(&self) -> Vec<FlightRecord> {
+        self.actor_context.flight_records()
+    }
+
+    /// Returns the number of non-fatal errors reported by the actor so far.
+    pub fn num_reported_errors(&self) -> u64 {
+        self.actor_context.num_reported_errors()
+    }
+
+    /// Returns the incarnation of the actor. See `ActorContext::generation`.
+    pub fn generation(&self) -> u64 {
+        self.actor_context.generation()
+    }
+
+    /// Returns the interval within which the actor is expected to record some progress.
+    pub fn heartbeat(&self) -> Duration {
+        self.actor_context.heartbeat()
+    }
+
+    /// Returns the exit status, panic message, and backtrace of the actor if it failed
+    /// or panicked.
+    pub fn termination_details(&self) -> Option<TerminationDetails> {
+        self.actor_context.termination_details()
+    }
+
+    /// Returns the 99th percentile of the time spent processing the last messages.
+    ///
+    /// Processing times are only recorded for actors with a processing time budget, this
+    /// method returns `None` for the others.
+    pub fn processing_time_p99(&self) -> Option<Duration> {
+        self.actor_context.processing_time_p99()
+    }
+
+    /// Returns the number of messages processed by the actor so far, broken down by
+    /// message type (e.g. `{"Command": 1, "ProcessedDocBatch": 12}`).
+    pub fn processed_message_counts(&self) -> BTreeMap<String, u64> {
+        self.actor_context.processed_message_counts()
+    }
+
+    /// Returns the cumulative CPU and blocking time spent by the actor processing messages,
+    /// if the universe was built with `Universe::with_cpu_usage_tracking`.
+    pub fn cpu_usage(&self) -> CpuUsage {
+        self.actor_context.cpu_usage()
+    }
+
+    /// Returns a report if the 99th percentile of the time spent processing the last
+    /// messages exceeds the processing time budget of the actor.
+    pub fn slow_actor_report(&self) -> Option<SlowActorReport> {
+        let processing_time_budget = self.actor_context.processing_time_budget()?;
+        let processing_time_p99 = self.processing_time_p99()?;
+        if processing_time_p99 <= processing_time_budget {
+            return None;
+        }
+        Some(SlowActorReport {
+            processing_time_p99,
+            processing_time_budget,
+        })
+    }
+
     /// Process all of the pending messages, and returns a snapshot of
     /// the observable state of the actor after this.
     ///
@@ -176,6 +299,15 @@ impl<A: Actor> ActorHandle<A> {
             .send_message_with_high_priority(Command::Resume);
     }
 
+    /// Sends a custom command to the actor, to be handled by `Actor::process_command`
+    /// ahead of the messages queued in its mailbox.
+    pub fn send_command<C: Any + Send>(&self, command: C) {
+        let _ = self
+            .actor_context
+            .mailbox()
+            .send_message_with_high_priority(Command::Custom(CustomCommand::new(command)));
+    }
+
     /// Kills the actor. Its finalize function will still be called.
     ///
     /// This function also actionnates the actor kill switch.
@@ -206,6 +338,64 @@ impl<A: Actor> ActorHandle<A> {
         self.join().await
     }
 
+    /// Asks the actor, and the actors sharing its kill switch or one of its descendants, to
+    /// quit gracefully. The ones still alive after `grace_period` are killed.
+    ///
+    /// See `KillSwitch::soft_kill`.
+    pub async fn soft_kill(self, grace_period: Duration) -> (ActorExitStatus, A::ObservableState) {
+        self.actor_context
+            .kill_switch()
+            .soft_kill(grace_period)
+            .await;
+        let _ = self
+            .actor_context
+            .mailbox()
+            .send_message_with_high_priority(Command::Nudge);
+        self.join().await
+    }
+
+    /// Asks the actor to flush its buffered work, and waits for it to be done.
+    ///
+    /// The flush command is sent through the low priority channel, so all of the messages
+    /// queued before it are processed first.
+    pub async fn flush(&self) -> anyhow::Result<()> {
+        let (ack_tx, ack_rx) = oneshot::channel();
+        self.actor_context
+            .mailbox()
+            .send_message(Command::Flush(ack_tx))
+            .await
+            .context("Failed to send the flush command.")?;
+        ack_rx
+            .await
+            .context("Actor exited before completing the flush.")?;
+        Ok(())
+    }
+
+    /// Gracefully quits the actor and replaces it with a new instance reusing the same
+    /// mailbox.
+    ///
+    /// The messages queued and not processed yet by the current instance are processed by
+    /// the new instance, so that in-flight messages survive the restart, and the mailboxes
+    /// held by other actors remain valid.
+    ///
+    /// Returns `None` if the actor has already exited, as its queue was dropped along
+    /// with it. Failing actors that need to be restarted without losing their queue
+    /// should be supervised instead.
+    pub async fn respawn(self, new_actor: A) -> Option<(Mailbox<A>, ActorHandle<A>)> {
+        let inbox = self.weak_inbox.upgrade()?;
+        let mailbox = self.mailbox().clone();
+        let spawn_ctx = self.actor_context.spawn_ctx().clone();
+        // `quit` consumes the handle.
+        let generation = self.actor_context.generation() + 1;
+        self.actor_context.keep_queue_on_quit();
+        self.quit().await;
+        let respawned = SpawnBuilder::new(spawn_ctx)
+            .set_mailboxes(mailbox, inbox)
+            .set_generation(generation)
+            .spawn(new_actor);
+        Some(respawned)
+    }
+
     /// Waits until the actor exits by itself. This is the equivalent of `Thread::join`.
     pub async fn join(self) -> (ActorExitStatus, A::ObservableState) {
         let exit_status = self.join_handle.join().await;
@@ -213,10 +403,51 @@ impl<A: Actor> ActorHandle<A> {
         (exit_status, observation)
     }
 
+    /// Waits until the actor exits by itself, and kills it if it is still running at
+    /// `deadline`.
+    pub(crate) async fn join_or_kill_at(
+        self,
+        deadline: tokio::time::Instant,
+    ) -> (ActorExitStatus, A::ObservableState) {
+        if tokio::time::timeout_at(deadline, self.join_handle.join())
+            .await
+            .is_err()
+        {
+            warn!(
+                actor_id = %self.actor_context.actor_instance_id(),
+                "join-deadline-exceeded-killing-actor"
+            );
+            return self.kill().await;
+        }
+        self.join().await
+    }
+
+    /// Waits until the actor exits, and returns the output it recorded with
+    /// `ActorContext::finish_with`, if any and if it is of type `T`.
+    pub async fn join_with_output<T: Any>(self) -> (ActorExitStatus, Option<T>) {
+        let exit_status = self.join_handle.join().await;
+        let output_opt = self.actor_context.take_output::<T>();
+        (exit_status, output_opt)
+    }
+
     pub fn last_observation(&self) -> A::ObservableState {
         self.last_state.borrow().clone()
     }
 
+    /// Returns the fields of the last observed state that changed since the previous call
+    /// to `diff()`, or since the actor was spawned for the first call.
+    ///
+    /// This makes it possible to report meaningful deltas (e.g. the number of documents
+    /// indexed since the last tick) instead of raw observations.
+    pub fn diff(&self) -> ObservationDiff {
+        let last_state_json = serde_json::to_value(self.last_observation())
+            .expect("Observable states should be serializable to JSON.");
+        let mut last_diffed_state_guard = self.last_diffed_state.lock().unwrap();
+        let observation_diff = ObservationDiff::compute(&last_diffed_state_guard, &last_state_json);
+        *last_diffed_state_guard = last_state_json;
+        observation_diff
+    }
+
     async fn wait_for_observable_state_callback(
         &self,
         rx: oneshot::Receiver<A::ObservableState>,
@@ -249,6 +480,10 @@ impl<A: Actor> ActorHandle<A> {
     pub fn mailbox(&self) -> &Mailbox<A> {
         self.actor_context.mailbox()
     }
+
+    pub fn addr(&self) -> Addr<A> {
+        self.actor_context.addr()
+    }
 }
 
 #[cfg(test)]
@@ -256,7 +491,7 @@ mod tests {
     use async_trait::async_trait;
 
     use super::*;
-    use crate::{Handler, Universe};
+    use crate::{Handler, PanicPolicy, Universe};
 
     #[derive(Default)]
     struct PanickingActor {
@@ -286,6 +521,29 @@ mod tests {
         }
     }
 
+    struct FailingActor;
+
+    impl Actor for FailingActor {
+        type ObservableState = ();
+        fn observable_state(&self) {}
+    }
+
+    #[derive(Debug)]
+    struct Fail;
+
+    #[async_trait]
+    impl Handler<Fail> for FailingActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            _msg: Fail,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            Err(anyhow::anyhow!("failing handler").into())
+        }
+    }
+
     #[derive(Default)]
     struct ExitActor {
         count: usize,
@@ -317,12 +575,148 @@ mod tests {
 
     #[tokio::test]
     async fn test_panic_in_actor() -> anyhow::Result<()> {
+        crate::install_panic_hook();
         let universe = Universe::with_accelerated_time();
         let (mailbox, handle) = universe.spawn_builder().spawn(PanickingActor::default());
         mailbox.send_message(Panic).await?;
+        let termination_details_handle = handle.actor_context.clone();
         let (exit_status, count) = handle.join().await;
         assert!(matches!(exit_status, ActorExitStatus::Panicked));
         assert!(matches!(count, 1)); //< Upon panick we cannot get a post mortem state.
+        let termination_details = termination_details_handle.termination_details().unwrap();
+        assert!(matches!(
+            termination_details.exit_status,
+            ActorExitStatus::Panicked
+        ));
+        assert_eq!(
+            termination_details.panic_message_opt.as_deref(),
+            Some("Oops")
+        );
+        // The backtrace is not part of the rendering of the termination details.
+        assert_eq!(termination_details.to_string(), "Panicked: Oops");
+        assert!(termination_details_handle.kill_switch().is_alive());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_panic_in_actor_with_kill_pipeline_policy() -> anyhow::Result<()> {
+        let universe =
+            Universe::with_accelerated_time().with_panic_policy(PanicPolicy::KillPipeline);
+        let (mailbox, handle) = universe.spawn_builder().spawn(PanickingActor::default());
+        mailbox.send_message(Panic).await?;
+        let kill_switch = handle.actor_context.kill_switch().clone();
+        let (exit_status, _count) = handle.join().await;
+        assert!(matches!(exit_status, ActorExitStatus::Panicked));
+        assert!(kill_switch.is_dead());
+        assert!(kill_switch.kill_reason().unwrap().contains("Oops"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_diff() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe
+            .spawn_builder()
+            .spawn(crate::tests::PingReceiverActor::default());
+        let observation_diff = handle.diff();
+        assert_eq!(observation_diff.changed_fields[""].current, 0);
+
+        for _ in 0..3 {
+            mailbox.send_message(crate::tests::Ping).await?;
+        }
+        handle.process_pending_and_observe().await;
+        let observation_diff = handle.diff();
+        assert_eq!(observation_diff.changed_fields[""].delta_opt, Some(3));
+
+        handle.process_pending_and_observe().await;
+        assert!(handle.diff().is_empty());
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_termination_details_on_failure() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe.spawn_builder().spawn(FailingActor);
+        assert!(handle.termination_details().is_none());
+        mailbox.send_message(Fail).await?;
+        handle.process_pending_and_observe().await;
+        let termination_details = handle.termination_details().unwrap();
+        assert!(matches!(
+            termination_details.exit_status,
+            ActorExitStatus::Failure(_)
+        ));
+        assert!(termination_details.panic_message_opt.is_none());
+        let kill_reason = handle.actor_context.kill_switch().kill_reason().unwrap();
+        assert!(kill_reason.contains("failing handler"));
+        assert!(!kill_reason.contains('\n'));
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_respawn_preserves_queued_messages() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe
+            .spawn_builder()
+            .spawn(crate::tests::PingReceiverActor::default());
+        assert_eq!(handle.generation(), 1);
+        handle.pause();
+        for _ in 0..3 {
+            mailbox.send_message(crate::tests::Ping).await?;
+        }
+        let (respawned_mailbox, respawned_handle) = handle
+            .respawn(crate::tests::PingReceiverActor::default())
+            .await
+            .unwrap();
+        assert_eq!(
+            respawned_mailbox.actor_instance_id(),
+            mailbox.actor_instance_id()
+        );
+        assert_eq!(respawned_handle.generation(), 2);
+        mailbox.send_message(crate::tests::Ping).await?;
+        let observation = respawned_handle.process_pending_and_observe().await;
+        assert_eq!(observation.obs_type, ObservationType::Alive);
+        assert_eq!(observation.state, 4);
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_respawn_twice() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe
+            .spawn_builder()
+            .spawn(crate::tests::PingReceiverActor::default());
+        mailbox.send_message(crate::tests::Ping).await?;
+        let (_, handle) = handle
+            .respawn(crate::tests::PingReceiverActor::default())
+            .await
+            .unwrap();
+        // The queued message is processed by one of the first two instances.
+        handle.process_pending_and_observe().await;
+        let (respawned_mailbox, respawned_handle) = handle
+            .respawn(crate::tests::PingReceiverActor::default())
+            .await
+            .unwrap();
+        assert_eq!(respawned_handle.generation(), 3);
+        respawned_mailbox.send_message(crate::tests::Ping).await?;
+        let observation = respawned_handle.process_pending_and_observe().await;
+        assert_eq!(observation.obs_type, ObservationType::Alive);
+        assert_eq!(observation.state, 1);
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_respawn_exited_actor() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe.spawn_builder().spawn(ExitActor::default());
+        mailbox.send_message(Exit).await?;
+        let observation = handle.process_pending_and_observe().await;
+        assert_eq!(observation.obs_type, ObservationType::PostMortem);
+        assert!(handle.respawn(ExitActor::default()).await.is_none());
+        universe.assert_quit().await;
         Ok(())
     }
 
@@ -336,4 +730,68 @@ mod tests {
         assert!(matches!(count, 1)); //< Upon panick we cannot get a post mortem state.
         Ok(())
     }
+
+    #[derive(Default)]
+    struct BufferingActor {
+        num_buffered: usize,
+        num_flushed: usize,
+    }
+
+    #[async_trait]
+    impl Actor for BufferingActor {
+        type ObservableState = (usize, usize);
+
+        fn observable_state(&self) -> Self::ObservableState {
+            (self.num_buffered, self.num_flushed)
+        }
+
+        async fn flush(&mut self, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+            self.num_flushed += self.num_buffered;
+            self.num_buffered = 0;
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct Buffer;
+
+    #[async_trait]
+    impl Handler<Buffer> for BufferingActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            _message: Buffer,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            self.num_buffered += 1;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe.spawn_builder().spawn(BufferingActor::default());
+        for _ in 0..3 {
+            mailbox.send_message(Buffer).await?;
+        }
+        handle.flush().await?;
+        assert_eq!(handle.process_pending_and_observe().await.state, (0, 3));
+        let (exit_status, _) = handle.quit().await;
+        assert!(matches!(exit_status, ActorExitStatus::Quit));
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flush_exited_actor() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe.spawn_builder().spawn(ExitActor::default());
+        mailbox.send_message(Exit).await?;
+        handle.process_pending_and_observe().await;
+        assert!(handle.flush().await.is_err());
+        universe.assert_quit().await;
+        Ok(())
+    }
 }
@@ -17,10 +17,12 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::atomic::{AtomicU32, Ordering};
+use serde::Serialize;
+
+use crate::sync::{AtomicU32, Ordering};
 
 #[repr(u32)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
 pub enum ActorState {
     /// Processing implies that the actor has some message(s) (this includes commands) to process.
     Processing = 0,
@@ -54,7 +56,7 @@ impl From<u32> for ActorState {
 
 impl From<ActorState> for AtomicState {
     fn from(state: ActorState) -> Self {
-        AtomicState(AtomicU32::from(state as u32))
+        AtomicState(AtomicU32::new(state as u32))
     }
 }
 
@@ -230,3 +232,24 @@ mod tests {
         );
     }
 }
+
+#[cfg(loom)]
+mod loom_tests {
+    use super::*;
+
+    #[test]
+    fn loom_exit_is_never_overridden() {
+        loom::model(|| {
+            let state = loom::sync::Arc::new(AtomicState::default());
+            let state_clone = state.clone();
+            let pause_and_resume_thread = loom::thread::spawn(move || {
+                state_clone.pause();
+                state_clone.resume();
+                state_clone.idle();
+            });
+            state.exit(true);
+            pause_and_resume_thread.join().unwrap();
+            assert_eq!(state.get_state(), ActorState::Success);
+        });
+    }
+}
@@ -0,0 +1,176 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use tokio::sync::watch;
+
+use crate::{Actor, ActorContext, ActorExitStatus, Handler, Inbox, Universe};
+
+/// Harness driving a single actor from the test task, without spawning its actor loop.
+///
+/// Messages are passed directly to the handlers of the actor, one at a time, so that unit
+/// tests of the processing logic do not have to deal with the scheduling of actors:
+///
+/// ```ignore
+/// ActorTester::new(PingReceiverActor::default())
+///     .send(Ping)
+///     .await
+///     .expect_state(|num_pings| *num_pings == 1);
+/// ```
+///
+/// The messages the actor sends to itself are queued in its inbox, and can be retrieved
+/// with [`ActorTester::drain_self_messages`].
+pub struct ActorTester<A: Actor> {
+    actor: A,
+    ctx: ActorContext<A>,
+    inbox: Inbox<A>,
+    exit_status_opt: Option<ActorExitStatus>,
+    // Declared last, so that the universe is dropped after the context.
+    _universe: Universe,
+}
+
+impl<A: Actor> ActorTester<A> {
+    pub fn new(actor: A) -> Self {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe.create_test_mailbox::<A>();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(actor.observable_state());
+        let ctx = ActorContext::for_test(&universe, mailbox, observable_state_tx);
+        ActorTester {
+            actor,
+            ctx,
+            inbox,
+            exit_status_opt: None,
+            _universe: universe,
+        }
+    }
+
+    /// Calls `Actor::initialize`.
+    pub async fn initialize(mut self) -> Self {
+        self.assert_running();
+        if let Err(exit_status) = self.actor.initialize(&self.ctx).await {
+            self.exit_status_opt = Some(exit_status);
+        }
+        self
+    }
+
+    /// Has the actor handle `message`, ignoring the reply.
+    pub async fn send<M>(mut self, message: M) -> Self
+    where A: Handler<M> {
+        let _ = self.ask(message).await;
+        self
+    }
+
+    /// Has the actor handle `message`, and returns the reply.
+    ///
+    /// If the handler returns an error, the actor is considered as exited and the error is
+    /// returned.
+    pub async fn ask<M>(&mut self, message: M) -> Result<A::Reply, ActorExitStatus>
+    where A: Handler<M> {
+        self.assert_running();
+        self.ctx.process();
+        let reply_res = self.actor.handle(message, &self.ctx).await;
+        self.ctx.idle();
+        if let Err(exit_status) = &reply_res {
+            self.exit_status_opt = Some(exit_status.clone());
+        }
+        reply_res
+    }
+
+    /// Asserts that the observable state of the actor satisfies `predicate`.
+    pub fn expect_state(self, predicate: impl FnOnce(&A::ObservableState) -> bool) -> Self {
+        let observable_state = self.actor.observable_state();
+        assert!(
+            predicate(&observable_state),
+            "unexpected observable state: {observable_state:?}"
+        );
+        self
+    }
+
+    /// Asserts that a handler returned an exit status satisfying `predicate`.
+    pub fn expect_exit_status(self, predicate: impl FnOnce(&ActorExitStatus) -> bool) -> Self {
+        let exit_status = self
+            .exit_status_opt
+            .as_ref()
+            .expect("the actor should have exited");
+        assert!(
+            predicate(exit_status),
+            "unexpected exit status: {exit_status:?}"
+        );
+        self
+    }
+
+    pub fn actor(&self) -> &A {
+        &self.actor
+    }
+
+    pub fn ctx(&self) -> &ActorContext<A> {
+        &self.ctx
+    }
+
+    /// Returns the messages of type `M` the actor sent to itself so far.
+    pub fn drain_self_messages<M: 'static>(&self) -> Vec<M> {
+        self.inbox.drain_for_test_typed::<M>()
+    }
+
+    fn assert_running(&self) {
+        if let Some(exit_status) = &self.exit_status_opt {
+            panic!("the actor already exited with status `{exit_status:?}`");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::tests::{Ping, PingReceiverActor};
+
+    #[derive(Debug)]
+    struct Fail;
+
+    #[async_trait]
+    impl Handler<Fail> for PingReceiverActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            _message: Fail,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            Err(ActorExitStatus::from(anyhow::anyhow!("failed")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_actor_tester() {
+        let mut actor_tester = ActorTester::new(PingReceiverActor::default())
+            .initialize()
+            .await
+            .send(Ping)
+            .await
+            .send(Ping)
+            .await
+            .expect_state(|num_pings| *num_pings == 2);
+        actor_tester.ask(Ping).await.unwrap();
+        assert!(actor_tester.ask(Fail).await.is_err());
+        actor_tester
+            .expect_state(|num_pings| *num_pings == 3)
+            .expect_exit_status(|exit_status| matches!(exit_status, ActorExitStatus::Failure(_)));
+    }
+}
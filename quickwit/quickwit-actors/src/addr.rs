@@ -0,0 +1,173 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+use quickwit_common::KillSwitch;
+
+use crate::{Actor, Mailbox};
+
+/// Address of an actor, bundling its mailbox with its kill switch and its instance metadata.
+///
+/// APIs should take an `Addr<A>` rather than a naked `Mailbox<A>` whenever they also need
+/// to know which incarnation of the actor they are talking to, or whether it is still alive.
+/// It dereferences to the mailbox of the actor, so messages can be sent through it directly.
+///
+/// Two addresses are equal if they point to the same incarnation of the same actor.
+pub struct Addr<A: Actor> {
+    mailbox: Mailbox<A>,
+    kill_switch: KillSwitch,
+    generation: u64,
+}
+
+impl<A: Actor> Addr<A> {
+    pub(crate) fn new(mailbox: Mailbox<A>, kill_switch: KillSwitch, generation: u64) -> Self {
+        Addr {
+            mailbox,
+            kill_switch,
+            generation,
+        }
+    }
+
+    pub fn mailbox(&self) -> &Mailbox<A> {
+        &self.mailbox
+    }
+
+    pub fn into_mailbox(self) -> Mailbox<A> {
+        self.mailbox
+    }
+
+    pub fn kill_switch(&self) -> &KillSwitch {
+        &self.kill_switch
+    }
+
+    pub fn actor_instance_id(&self) -> &str {
+        self.mailbox.actor_instance_id()
+    }
+
+    /// Returns the incarnation of the actor, see `ActorContext::generation`.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Returns false if the kill switch of the actor was killed or if the actor exited.
+    pub fn is_alive(&self) -> bool {
+        self.kill_switch.is_alive() && !self.mailbox.is_disconnected()
+    }
+}
+
+impl<A: Actor> Clone for Addr<A> {
+    fn clone(&self) -> Self {
+        Addr {
+            mailbox: self.mailbox.clone(),
+            kill_switch: self.kill_switch.clone(),
+            generation: self.generation,
+        }
+    }
+}
+
+impl<A: Actor> Deref for Addr<A> {
+    type Target = Mailbox<A>;
+
+    fn deref(&self) -> &Mailbox<A> {
+        &self.mailbox
+    }
+}
+
+impl<A: Actor> PartialEq for Addr<A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.actor_instance_id() == other.actor_instance_id() && self.generation == other.generation
+    }
+}
+
+impl<A: Actor> Eq for Addr<A> {}
+
+impl<A: Actor> PartialOrd for Addr<A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<A: Actor> Ord for Addr<A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.actor_instance_id()
+            .cmp(other.actor_instance_id())
+            .then(self.generation.cmp(&other.generation))
+    }
+}
+
+impl<A: Actor> Hash for Addr<A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.actor_instance_id().hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<A: Actor> fmt::Debug for Addr<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Addr")
+            .field("actor_instance_id", &self.actor_instance_id())
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl<A: Actor> fmt::Display for Addr<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-gen{}", self.actor_instance_id(), self.generation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use crate::tests::{Ping, PingReceiverActor};
+    use crate::Universe;
+
+    #[tokio::test]
+    async fn test_addr() {
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+        let (_other_mailbox, other_handle) =
+            universe.spawn_builder().spawn(PingReceiverActor::default());
+        let addr = handle.addr();
+        assert_eq!(addr, addr.clone());
+        assert_ne!(addr, other_handle.addr());
+        assert_eq!(addr.generation(), 1);
+        assert_eq!(
+            addr.to_string(),
+            format!("{}-gen1", handle.mailbox().actor_instance_id())
+        );
+        let addrs: HashSet<_> = [addr.clone(), addr.clone(), other_handle.addr()]
+            .into_iter()
+            .collect();
+        assert_eq!(addrs.len(), 2);
+
+        assert!(addr.is_alive());
+        addr.send_message(Ping).await.unwrap();
+        assert_eq!(handle.process_pending_and_observe().await.state, 1);
+        handle.quit().await;
+        assert!(!addr.is_alive());
+        universe.assert_quit().await;
+    }
+}
@@ -17,11 +17,19 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Mutex;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use flume::TryRecvError;
+use futures::future::{self, Either, FutureExt};
 use thiserror::Error;
+use tokio::sync::{watch, Semaphore, TryAcquireError};
+
+use crate::lock_free_queue::{lock_free_channel, LockFreeReceiver, LockFreeSender};
+use crate::mailbox::QueueDiagnostics;
 
 #[derive(Default)]
 struct LockedOption<T> {
@@ -60,6 +68,91 @@ impl<T> LockedOption<T> {
         self.has_val.store(true, Ordering::Release);
         *lock = Some(val);
     }
+
+    /// Calls `f` on the value if there is one. The lock is not taken when there is none.
+    pub fn with_val(&self, f: impl FnOnce(&T)) {
+        if !self.has_val.load(Ordering::Acquire) {
+            return;
+        }
+        if let Some(val) = self.opt.lock().unwrap().as_ref() {
+            f(val);
+        }
+    }
+}
+
+/// Keeps track of the instants at which the low priority messages
+/// currently in the queue were sent.
+///
+/// Senders record the send instant while holding the lock, right as the message enters the
+/// queue, and the receiver takes the lock after dequeuing. The instants are thus recorded in
+/// the order of the queue, and a message is never dequeued before its instant was recorded.
+#[derive(Default)]
+struct SendInstants {
+    instants: VecDeque<Instant>,
+    // Debug renderings of the messages currently in the queue, in the same order.
+    #[cfg(feature = "peek-pending")]
+    renderings: VecDeque<String>,
+    #[cfg(feature = "peek-pending")]
+    num_unrendered_recvs: usize,
+}
+
+impl SendInstants {
+    fn record_send(&mut self, send_instant: Instant) {
+        self.instants.push_back(send_instant);
+    }
+
+    fn record_recv(&mut self) {
+        self.instants.pop_front();
+        #[cfg(feature = "peek-pending")]
+        if self.renderings.pop_front().is_none() {
+            self.num_unrendered_recvs += 1;
+        }
+    }
+
+    #[cfg(feature = "peek-pending")]
+    fn record_rendering(&mut self, rendering: String) {
+        if self.num_unrendered_recvs > 0 {
+            self.num_unrendered_recvs -= 1;
+            return;
+        }
+        self.renderings.push_back(rendering);
+    }
+
+    fn oldest(&self) -> Option<Instant> {
+        self.instants.front().copied()
+    }
+}
+
+/// Signals when the number of messages in a low priority queue rises to a high
+/// watermark, and again when it falls to a low watermark.
+struct Watermarks {
+    high_watermark: usize,
+    low_watermark: usize,
+    is_above_high_watermark_tx: watch::Sender<bool>,
+}
+
+impl Watermarks {
+    fn update(&self, queue_len: usize) {
+        self.is_above_high_watermark_tx
+            .send_if_modified(|is_above_high_watermark| {
+                if !*is_above_high_watermark && queue_len >= self.high_watermark {
+                    *is_above_high_watermark = true;
+                    return true;
+                }
+                if *is_above_high_watermark && queue_len <= self.low_watermark {
+                    *is_above_high_watermark = false;
+                    return true;
+                }
+                false
+            });
+    }
+}
+
+// Most queues have no watermarks: `LockedOption` lets them skip the lock on every message.
+type SharedWatermarks = Arc<LockedOption<Watermarks>>;
+
+fn update_watermarks(watermarks: &SharedWatermarks, queue_len: usize) {
+    watermarks.with_val(|watermarks| watermarks.update(queue_len));
 }
 
 #[derive(Debug, Error)]
@@ -68,6 +161,35 @@ pub enum SendError {
     Disconnected,
     #[error("The channel is full.")]
     Full,
+    /// Failure to send a message from an actor, along with the state of the queue of the
+    /// destination actor at the time.
+    #[error("{source} (destination: {queue_diagnostics:?})")]
+    WithQueueDiagnostics {
+        source: Box<SendError>,
+        queue_diagnostics: Box<QueueDiagnostics>,
+    },
+}
+
+impl SendError {
+    /// Returns the state of the queue of the destination actor when the send failed, if it
+    /// was recorded.
+    pub fn queue_diagnostics(&self) -> Option<&QueueDiagnostics> {
+        match self {
+            SendError::WithQueueDiagnostics {
+                queue_diagnostics, ..
+            } => Some(queue_diagnostics),
+            SendError::Disconnected | SendError::Full => None,
+        }
+    }
+
+    /// Returns true if the destination actor has exited.
+    pub fn is_disconnected(&self) -> bool {
+        match self {
+            SendError::Disconnected => true,
+            SendError::Full => false,
+            SendError::WithQueueDiagnostics { source, .. } => source.is_disconnected(),
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -123,6 +245,110 @@ impl<T> From<flume::TrySendError<T>> for SendError {
 pub enum QueueCapacity {
     Bounded(usize),
     Unbounded,
+    /// Unbounded lock-free queue, which only wakes up the receiver if it is waiting for
+    /// messages. This is meant for actors processing a very large number of small messages,
+    /// for which the cost of a wakeup per message dominates.
+    UnboundedLockFree,
+}
+
+/// Sending half of a low priority queue.
+enum LowPriorityTx<T> {
+    Flume(flume::Sender<T>),
+    LockFree(LockFreeSender<T>),
+}
+
+impl<T> LowPriorityTx<T> {
+    fn is_disconnected(&self) -> bool {
+        match self {
+            LowPriorityTx::Flume(tx) => tx.is_disconnected(),
+            LowPriorityTx::LockFree(tx) => tx.is_disconnected(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            LowPriorityTx::Flume(tx) => tx.len(),
+            LowPriorityTx::LockFree(tx) => tx.len(),
+        }
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        match self {
+            LowPriorityTx::Flume(tx) => tx.capacity(),
+            LowPriorityTx::LockFree(_) => None,
+        }
+    }
+
+    fn try_send(&self, msg: T) -> Result<(), TrySendError<T>> {
+        match self {
+            LowPriorityTx::Flume(tx) => tx.try_send(msg)?,
+            LowPriorityTx::LockFree(tx) => tx.send(msg).map_err(|_| TrySendError::Disconnected)?,
+        }
+        Ok(())
+    }
+
+    async fn send(&self, msg: T) -> Result<(), SendError> {
+        match self {
+            LowPriorityTx::Flume(tx) => tx.send_async(msg).await?,
+            LowPriorityTx::LockFree(tx) => tx.send(msg).map_err(|_| SendError::Disconnected)?,
+        }
+        Ok(())
+    }
+}
+
+/// Receiving half of a low priority queue.
+enum LowPriorityRx<T> {
+    Flume(flume::Receiver<T>),
+    LockFree(LockFreeReceiver<T>),
+}
+
+impl<T> LowPriorityRx<T> {
+    fn is_disconnected(&self) -> bool {
+        match self {
+            LowPriorityRx::Flume(rx) => rx.is_disconnected(),
+            LowPriorityRx::LockFree(rx) => rx.is_disconnected(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            LowPriorityRx::Flume(rx) => rx.is_empty(),
+            LowPriorityRx::LockFree(rx) => rx.is_empty(),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            LowPriorityRx::Flume(rx) => rx.len(),
+            LowPriorityRx::LockFree(rx) => rx.len(),
+        }
+    }
+
+    fn try_recv(&self) -> Result<T, TryRecvError> {
+        match self {
+            LowPriorityRx::Flume(rx) => rx.try_recv(),
+            LowPriorityRx::LockFree(rx) => rx.try_recv(),
+        }
+    }
+
+    /// Waits for the next message. Resolves to `None` once the queue is disconnected and
+    /// empty.
+    fn recv_async(&self) -> impl Future<Output = Option<T>> + '_ {
+        match self {
+            LowPriorityRx::Flume(rx) => Either::Left(rx.recv_async().map(Result::ok)),
+            LowPriorityRx::LockFree(rx) => Either::Right(rx.recv()),
+        }
+    }
+
+    /// Drops all of the pending messages.
+    fn clear(&self) {
+        match self {
+            LowPriorityRx::Flume(rx) => {
+                rx.drain();
+            }
+            LowPriorityRx::LockFree(rx) => rx.clear(),
+        }
+    }
 }
 
 /// Creates a channel with the ability to send high priority messages.
@@ -130,28 +356,124 @@ pub enum QueueCapacity {
 /// A high priority message is guaranteed to be consumed before any
 /// low priority message sent after it.
 pub fn channel<T>(queue_capacity: QueueCapacity) -> (Sender<T>, Receiver<T>) {
+    let (mut senders, receiver) = multi_source_channel(&[queue_capacity], false);
+    let sender = senders
+        .pop()
+        .expect("The channel should have exactly one source.");
+    (sender, receiver)
+}
+
+/// Creates a channel with several low priority sources, each with its own capacity,
+/// and the ability to send high priority messages.
+///
+/// The receiver consumes the low priority sources in a round-robin fashion, so
+/// that a saturated source cannot starve the others, while each source
+/// applies its own backpressure.
+///
+/// A high priority message is guaranteed to be consumed before any
+/// low priority message sent after it.
+///
+/// If `track_message_ages` is true, the instants at which the queued messages were sent are
+/// kept track of, so that the age of the oldest one can be reported. It costs a lock per
+/// message sent and received.
+pub fn multi_source_channel<T>(
+    queue_capacities: &[QueueCapacity],
+    track_message_ages: bool,
+) -> (Vec<Sender<T>>, Receiver<T>) {
+    assert!(
+        !queue_capacities.is_empty(),
+        "A channel requires at least one source."
+    );
     let (high_priority_tx, high_priority_rx) = flume::unbounded();
-    let (low_priority_tx, low_priority_rx) = match queue_capacity {
-        QueueCapacity::Bounded(cap) => flume::bounded(cap),
-        QueueCapacity::Unbounded => flume::unbounded(),
-    };
+    let mut senders = Vec::with_capacity(queue_capacities.len());
+    let mut low_priority_sources = Vec::with_capacity(queue_capacities.len());
+
+    for queue_capacity in queue_capacities {
+        let (low_priority_tx, low_priority_rx) = match *queue_capacity {
+            QueueCapacity::Bounded(cap) => {
+                let (tx, rx) = flume::bounded(cap);
+                (LowPriorityTx::Flume(tx), LowPriorityRx::Flume(rx))
+            }
+            QueueCapacity::Unbounded => {
+                let (tx, rx) = flume::unbounded();
+                (LowPriorityTx::Flume(tx), LowPriorityRx::Flume(rx))
+            }
+            QueueCapacity::UnboundedLockFree => {
+                let (tx, rx) = lock_free_channel();
+                (LowPriorityTx::LockFree(tx), LowPriorityRx::LockFree(rx))
+            }
+        };
+        let free_slots_opt = match *queue_capacity {
+            QueueCapacity::Bounded(cap) if cap > 0 => Some(Arc::new(Semaphore::new(cap))),
+            _ => None,
+        };
+        // Messages are never queued in a channel with a capacity of 0.
+        let is_rendezvous = matches!(queue_capacity, QueueCapacity::Bounded(0));
+        let send_instants_opt: Option<Arc<Mutex<SendInstants>>> =
+            if (track_message_ages || cfg!(feature = "peek-pending")) && !is_rendezvous {
+                Some(Arc::default())
+            } else {
+                None
+            };
+        let watermarks: SharedWatermarks = Arc::new(LockedOption::none());
+        senders.push(Sender {
+            low_priority_tx,
+            high_priority_tx: high_priority_tx.clone(),
+            free_slots_opt: free_slots_opt.clone(),
+            send_instants_opt: send_instants_opt.clone(),
+            watermarks: watermarks.clone(),
+        });
+        low_priority_sources.push(LowPrioritySource {
+            rx: low_priority_rx,
+            free_slots_opt,
+            send_instants_opt,
+            watermarks,
+        });
+    }
     let receiver = Receiver {
-        low_priority_rx,
+        low_priority_sources,
+        next_low_priority_source: AtomicUsize::new(0),
         high_priority_rx,
-        _high_priority_tx: high_priority_tx.clone(),
+        _high_priority_tx: high_priority_tx,
         pending_low_priority_message: LockedOption::none(),
         _clone_is_forbidden: CloneIsForbidden,
     };
-    let sender = Sender {
-        low_priority_tx,
-        high_priority_tx,
-    };
-    (sender, receiver)
+    (senders, receiver)
+}
+
+/// Slots of a low priority queue reserved with `Sender::reserve_low_priority`.
+///
+/// The slots that were not used are released when the reservation is dropped.
+pub struct LowPriorityReservation {
+    free_slots_opt: Option<Arc<Semaphore>>,
+    num_reserved_slots: usize,
+}
+
+impl LowPriorityReservation {
+    /// Returns the number of reserved slots that have not been used yet.
+    pub fn num_reserved_slots(&self) -> usize {
+        self.num_reserved_slots
+    }
+}
+
+impl Drop for LowPriorityReservation {
+    fn drop(&mut self) {
+        if let Some(free_slots) = &self.free_slots_opt {
+            free_slots.add_permits(self.num_reserved_slots);
+        }
+    }
 }
 
 pub struct Sender<T> {
-    low_priority_tx: flume::Sender<T>,
+    low_priority_tx: LowPriorityTx<T>,
     high_priority_tx: flume::Sender<T>,
+    // Free slots of a bounded low priority queue. A slot is taken before sending a message,
+    // and released once the message is received, so that slots can be reserved ahead of
+    // time. `None` if the queue is unbounded or has a capacity of 0.
+    free_slots_opt: Option<Arc<Semaphore>>,
+    // `None` if the ages of the messages are not tracked.
+    send_instants_opt: Option<Arc<Mutex<SendInstants>>>,
+    watermarks: SharedWatermarks,
 }
 
 impl<T> Sender<T> {
@@ -159,16 +481,180 @@ impl<T> Sender<T> {
         self.low_priority_tx.is_disconnected()
     }
 
+    /// Returns the number of messages in the low priority queue.
+    pub fn low_priority_len(&self) -> usize {
+        self.low_priority_tx.len()
+    }
+
+    /// Returns the capacity of the low priority queue, or `None` if it is unbounded.
+    pub fn low_priority_capacity(&self) -> Option<usize> {
+        self.low_priority_tx.capacity()
+    }
+
+    /// Returns the amount of time the oldest message of the low priority queue
+    /// has been waiting for, if the ages of the messages are tracked.
+    pub fn oldest_low_priority_message_age(&self) -> Option<Duration> {
+        let oldest_send_instant = self.send_instants_opt.as_ref()?.lock().unwrap().oldest()?;
+        Some(oldest_send_instant.elapsed())
+    }
+
+    /// Returns a watch channel signaling `true` once the number of messages in the low
+    /// priority queue reaches `high_watermark`, and `false` again once it falls to
+    /// `low_watermark`.
+    ///
+    /// The hysteresis between the two watermarks lets upstream producers pause and resume
+    /// smoothly instead of oscillating on every message. Calling this method again
+    /// replaces the watermarks, and closes the previously returned watch channels.
+    pub fn watermark_signal(
+        &self,
+        high_watermark: usize,
+        low_watermark: usize,
+    ) -> watch::Receiver<bool> {
+        assert!(
+            low_watermark < high_watermark,
+            "The low watermark must be lower than the high watermark."
+        );
+        let is_above_high_watermark = self.low_priority_tx.len() >= high_watermark;
+        let (is_above_high_watermark_tx, is_above_high_watermark_rx) =
+            watch::channel(is_above_high_watermark);
+        self.watermarks.place(Watermarks {
+            high_watermark,
+            low_watermark,
+            is_above_high_watermark_tx,
+        });
+        is_above_high_watermark_rx
+    }
+
+    /// Records the Debug rendering of a message that was just sent to the low priority
+    /// queue.
+    #[cfg(feature = "peek-pending")]
+    pub fn record_rendering(&self, rendering: String) {
+        if let Some(send_instants) = &self.send_instants_opt {
+            send_instants.lock().unwrap().record_rendering(rendering);
+        }
+    }
+
+    /// Returns the Debug renderings of the next `num_messages` messages of the low priority
+    /// queue, without consuming them.
+    #[cfg(feature = "peek-pending")]
+    pub fn peek_renderings(&self, num_messages: usize) -> Vec<String> {
+        let Some(send_instants) = &self.send_instants_opt else {
+            return Vec::new();
+        };
+        send_instants
+            .lock()
+            .unwrap()
+            .renderings
+            .iter()
+            .take(num_messages)
+            .cloned()
+            .collect()
+    }
+
     pub fn try_send_low_priority(&self, msg: T) -> Result<(), TrySendError<T>> {
-        self.low_priority_tx.try_send(msg)?;
-        Ok(())
+        if let Some(free_slots) = &self.free_slots_opt {
+            match free_slots.try_acquire() {
+                Ok(free_slot) => free_slot.forget(),
+                Err(TryAcquireError::NoPermits) => return Err(TrySendError::Full(msg)),
+                Err(TryAcquireError::Closed) => return Err(TrySendError::Disconnected),
+            }
+        }
+        self.try_send_low_priority_in_taken_slot(msg)
     }
 
     pub async fn send_low_priority(&self, msg: T) -> Result<(), SendError> {
-        self.low_priority_tx.send_async(msg).await?;
+        if let Some(free_slots) = &self.free_slots_opt {
+            free_slots
+                .acquire()
+                .await
+                .map_err(|_| SendError::Disconnected)?
+                .forget();
+        } else if self.low_priority_tx.capacity() == Some(0) {
+            // The message is handed over to the receiver directly, it is never queued.
+            self.low_priority_tx.send(msg).await?;
+            return Ok(());
+        }
+        // A slot was taken, or the queue is unbounded: the message is queued right away.
+        match self.try_send_low_priority_in_taken_slot(msg) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => Err(SendError::Full),
+            Err(TrySendError::Disconnected) => Err(SendError::Disconnected),
+        }
+    }
+
+    /// Waits until `num_slots` slots of the low priority queue are free, and reserves them.
+    /// Messages sent with `send_reserved_low_priority` then never block.
+    ///
+    /// For unbounded queues, the reservation is granted right away. Queues with a capacity
+    /// of 0 cannot hold messages, so their reservations do not guarantee anything.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_slots` exceeds the capacity of the queue.
+    pub async fn reserve_low_priority(
+        &self,
+        num_slots: usize,
+    ) -> Result<LowPriorityReservation, SendError> {
+        if self.is_disconnected() {
+            return Err(SendError::Disconnected);
+        }
+        if let Some(free_slots) = &self.free_slots_opt {
+            let capacity = self.low_priority_capacity().unwrap_or(usize::MAX);
+            assert!(
+                num_slots <= capacity,
+                "Cannot reserve {num_slots} slots in a queue with a capacity of {capacity}."
+            );
+            free_slots
+                .acquire_many(num_slots as u32)
+                .await
+                .map_err(|_| SendError::Disconnected)?
+                .forget();
+        }
+        Ok(LowPriorityReservation {
+            free_slots_opt: self.free_slots_opt.clone(),
+            num_reserved_slots: num_slots,
+        })
+    }
+
+    /// Sends a message in one of the slots of `reservation`, which must have been obtained
+    /// from this sender. Returns `TrySendError::Full` once all of the slots have been used.
+    pub fn send_reserved_low_priority(
+        &self,
+        reservation: &mut LowPriorityReservation,
+        msg: T,
+    ) -> Result<(), TrySendError<T>> {
+        if reservation.num_reserved_slots == 0 {
+            return Err(TrySendError::Full(msg));
+        }
+        reservation.num_reserved_slots -= 1;
+        self.try_send_low_priority_in_taken_slot(msg)
+    }
+
+    fn try_send_low_priority_in_taken_slot(&self, msg: T) -> Result<(), TrySendError<T>> {
+        // The lock is held while the message is queued, so that the send instants are
+        // recorded in the order of the queue.
+        let send_instants_guard_opt = self
+            .send_instants_opt
+            .as_ref()
+            .map(|send_instants| send_instants.lock().unwrap());
+        if let Err(try_send_error) = self.low_priority_tx.try_send(msg) {
+            self.release_slot();
+            return Err(try_send_error);
+        }
+        if let Some(mut send_instants_guard) = send_instants_guard_opt {
+            send_instants_guard.record_send(Instant::now());
+        }
+        update_watermarks(&self.watermarks, self.low_priority_tx.len());
         Ok(())
     }
 
+    fn release_slot(&self) {
+        if let Some(free_slots) = &self.free_slots_opt {
+            free_slots.add_permits(1);
+        }
+    }
+
+    /// Sends a message to the high priority queue. The high priority queue is unbounded.
     pub fn send_high_priority(&self, msg: T) -> Result<(), SendError> {
         self.high_priority_tx.send(msg)?;
         Ok(())
@@ -180,8 +666,35 @@ impl<T> Sender<T> {
 // The drop implementation drains the elements in the channel.
 struct CloneIsForbidden;
 
+struct LowPrioritySource<T> {
+    rx: LowPriorityRx<T>,
+    free_slots_opt: Option<Arc<Semaphore>>,
+    send_instants_opt: Option<Arc<Mutex<SendInstants>>>,
+    watermarks: SharedWatermarks,
+}
+
+impl<T> LowPrioritySource<T> {
+    /// Returns true if no message will ever be received from this source.
+    fn is_exhausted(&self) -> bool {
+        self.rx.is_disconnected() && self.rx.is_empty()
+    }
+
+    fn record_recv(&self) {
+        if let Some(free_slots) = &self.free_slots_opt {
+            free_slots.add_permits(1);
+        }
+        if let Some(send_instants) = &self.send_instants_opt {
+            send_instants.lock().unwrap().record_recv();
+        }
+        update_watermarks(&self.watermarks, self.rx.len());
+    }
+}
+
 pub struct Receiver<T> {
-    low_priority_rx: flume::Receiver<T>,
+    low_priority_sources: Vec<LowPrioritySource<T>>,
+    // Ordinal of the low priority source that should be polled first.
+    // It is used to consume the sources in a round-robin fashion.
+    next_low_priority_source: AtomicUsize,
     high_priority_rx: flume::Receiver<T>,
     _high_priority_tx: flume::Sender<T>,
     pending_low_priority_message: LockedOption<T>,
@@ -196,17 +709,31 @@ impl<T> Drop for Receiver<T> {
         // They are only dropped when both the receivers AND the sender are dropped.
         // We fix this behavior by drainng the channel upon drop.
         self.high_priority_rx.drain();
-        self.low_priority_rx.drain();
+        for low_priority_source in &self.low_priority_sources {
+            // Wakes up the senders waiting for a free slot.
+            if let Some(free_slots) = &low_priority_source.free_slots_opt {
+                free_slots.close();
+            }
+            low_priority_source.rx.clear();
+        }
     }
 }
 
 impl<T> Receiver<T> {
     pub fn is_empty(&self) -> bool {
-        self.low_priority_rx.is_empty()
+        self.low_priority_sources
+            .iter()
+            .all(|low_priority_source| low_priority_source.rx.is_empty())
             && self.pending_low_priority_message.is_none()
             && self.high_priority_rx.is_empty()
     }
 
+    fn is_low_priority_disconnected(&self) -> bool {
+        self.low_priority_sources
+            .iter()
+            .all(|low_priority_source| low_priority_source.rx.is_disconnected())
+    }
+
     pub fn try_recv_high_priority_message(&self) -> Result<T, RecvError> {
         match self.high_priority_rx.try_recv() {
             Ok(msg) => Ok(msg),
@@ -216,7 +743,7 @@ impl<T> Receiver<T> {
                 );
             }
             Err(TryRecvError::Empty) => {
-                if self.low_priority_rx.is_disconnected() {
+                if self.is_low_priority_disconnected() {
                     // We check that no new high priority message were sent
                     // in between.
                     if let Ok(msg) = self.high_priority_rx.try_recv() {
@@ -231,6 +758,45 @@ impl<T> Receiver<T> {
         }
     }
 
+    /// Attempts to receive a message from the low priority sources,
+    /// polling them in a round-robin fashion.
+    fn try_recv_low_priority(&self) -> Result<T, RecvError> {
+        let num_sources = self.low_priority_sources.len();
+        let first_source_ord = self.next_low_priority_source.load(Ordering::Relaxed);
+        let mut all_sources_disconnected = true;
+
+        for offset in 0..num_sources {
+            let source_ord = (first_source_ord + offset) % num_sources;
+            match self.low_priority_sources[source_ord].rx.try_recv() {
+                Ok(low_priority_msg) => {
+                    self.record_low_priority_recv(source_ord);
+                    return Ok(low_priority_msg);
+                }
+                Err(TryRecvError::Empty) => {
+                    all_sources_disconnected = false;
+                }
+                Err(TryRecvError::Disconnected) => {}
+            }
+        }
+        if all_sources_disconnected {
+            Err(RecvError::Disconnected)
+        } else {
+            Err(RecvError::NoMessageAvailable)
+        }
+    }
+
+    /// Returns the next high priority message if there is one, in which case the low
+    /// priority message is placed in the pending slot. Returns the low priority message
+    /// otherwise.
+    fn prioritize_high_priority_message(&self, low_priority_msg: T) -> T {
+        if let Ok(high_priority_msg) = self.high_priority_rx.try_recv() {
+            self.pending_low_priority_message.place(low_priority_msg);
+            high_priority_msg
+        } else {
+            low_priority_msg
+        }
+    }
+
     pub fn try_recv(&self) -> Result<T, RecvError> {
         if let Ok(msg) = self.high_priority_rx.try_recv() {
             return Ok(msg);
@@ -238,23 +804,16 @@ impl<T> Receiver<T> {
         if let Some(pending_msg) = self.pending_low_priority_message.take() {
             return Ok(pending_msg);
         }
-        match self.low_priority_rx.try_recv() {
-            Ok(low_msg) => {
-                if let Ok(high_msg) = self.high_priority_rx.try_recv() {
-                    self.pending_low_priority_message.place(low_msg);
-                    Ok(high_msg)
-                } else {
-                    Ok(low_msg)
-                }
-            }
-            Err(TryRecvError::Disconnected) => {
+        match self.try_recv_low_priority() {
+            Ok(low_msg) => Ok(self.prioritize_high_priority_message(low_msg)),
+            Err(RecvError::Disconnected) => {
                 if let Ok(high_msg) = self.high_priority_rx.try_recv() {
                     Ok(high_msg)
                 } else {
                     Err(RecvError::Disconnected)
                 }
             }
-            Err(TryRecvError::Empty) => Err(RecvError::NoMessageAvailable),
+            Err(RecvError::NoMessageAvailable) => Err(RecvError::NoMessageAvailable),
         }
     }
 
@@ -265,57 +824,106 @@ impl<T> Receiver<T> {
             .expect("The Receiver owns the high priority Sender to avoid any disconnection.")
     }
 
-    pub async fn recv(&self) -> Result<T, RecvError> {
+    pub async fn recv(&self) -> Result<T, RecvError>
+    where T: Send {
         if let Ok(msg) = self.try_recv_high_priority_message() {
             return Ok(msg);
         }
         if let Some(pending_msg) = self.pending_low_priority_message.take() {
             return Ok(pending_msg);
         }
-        tokio::select! {
-            // We don't really care about fairness here.
-            // We will double check if there is a command or not anyway.
-            biased;
-            high_priority_msg_res = self.high_priority_rx.recv_async() => {
-                match high_priority_msg_res {
-                    Ok(high_priority_msg) => {
-                        Ok(high_priority_msg)
-                    },
-                    Err(_) => {
-                        unreachable!("The Receiver owns the high priority Sender to avoid any disconnection.")
-                    },
+        loop {
+            match self.try_recv_low_priority() {
+                Ok(low_priority_msg) => {
+                    return Ok(self.prioritize_high_priority_message(low_priority_msg));
                 }
+                Err(RecvError::Disconnected) => {
+                    return self
+                        .try_recv_high_priority_message()
+                        .map_err(|_| RecvError::Disconnected);
+                }
+                Err(RecvError::NoMessageAvailable) => {}
             }
-            low_priority_msg_res = self.low_priority_rx.recv_async() => {
-                match low_priority_msg_res {
-                    Ok(low_priority_msg) => {
-                        if let Ok(high_priority_msg) = self.try_recv_high_priority_message() {
-                            self.pending_low_priority_message.place(low_priority_msg);
-                            Ok(high_priority_msg)
-                        } else {
-                            Ok(low_priority_msg)
-                        }
-                    },
-                    Err(flume::RecvError::Disconnected) => {
-                        if let Ok(high_priority_msg) = self.try_recv_high_priority_message() {
-                            Ok(high_priority_msg)
-                        } else {
-                            Err(RecvError::Disconnected)
-                        }
+            let low_priority_recv_future =
+                if let [low_priority_source] = self.low_priority_sources.as_slice() {
+                    // Most mailboxes have a single source: we await it directly rather than
+                    // boxing its future for `select_all`.
+                    if low_priority_source.is_exhausted() {
+                        return self
+                            .try_recv_high_priority_message()
+                            .map_err(|_| RecvError::Disconnected);
+                    }
+                    Either::Left(
+                        low_priority_source
+                            .rx
+                            .recv_async()
+                            .map(|low_priority_msg_opt| (0, low_priority_msg_opt)),
+                    )
+                } else {
+                    let low_priority_recv_futures: Vec<_> = self
+                        .low_priority_sources
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, low_priority_source)| !low_priority_source.is_exhausted())
+                        .map(|(source_ord, low_priority_source)| {
+                            low_priority_source
+                                .rx
+                                .recv_async()
+                                .map(move |low_priority_msg_opt| (source_ord, low_priority_msg_opt))
+                                .boxed()
+                        })
+                        .collect();
+                    if low_priority_recv_futures.is_empty() {
+                        return self
+                            .try_recv_high_priority_message()
+                            .map_err(|_| RecvError::Disconnected);
+                    }
+                    Either::Right(
+                        future::select_all(low_priority_recv_futures)
+                            .map(|(source_msg_opt, _, _)| source_msg_opt),
+                    )
+                };
+            tokio::select! {
+                // We don't really care about fairness here.
+                // We will double check if there is a command or not anyway.
+                biased;
+                high_priority_msg_res = self.high_priority_rx.recv_async() => {
+                    let high_priority_msg = high_priority_msg_res
+                        .expect("The Receiver owns the high priority Sender to avoid any disconnection.");
+                    return Ok(high_priority_msg);
+                }
+                (source_ord, low_priority_msg_opt) = low_priority_recv_future => {
+                    if let Some(low_priority_msg) = low_priority_msg_opt {
+                        self.record_low_priority_recv(source_ord);
+                        return Ok(self.prioritize_high_priority_message(low_priority_msg));
                     }
+                    // The source got disconnected in the meantime, we just
+                    // loop to check the other sources.
                 }
-           }
+            }
         }
     }
 
     /// Drain all of the pending low priority messages and return them.
     pub fn drain_low_priority(&self) -> Vec<T> {
         let mut messages = Vec::new();
-        while let Ok(msg) = self.low_priority_rx.try_recv() {
-            messages.push(msg);
+        for (source_ord, low_priority_source) in self.low_priority_sources.iter().enumerate() {
+            while let Ok(msg) = low_priority_source.rx.try_recv() {
+                self.record_low_priority_recv(source_ord);
+                messages.push(msg);
+            }
         }
         messages
     }
+
+    /// Records the reception of a message from the given source, and moves
+    /// the round-robin cursor to the next source.
+    fn record_low_priority_recv(&self, source_ord: usize) {
+        self.low_priority_sources[source_ord].record_recv();
+        let next_source_ord = (source_ord + 1) % self.low_priority_sources.len();
+        self.next_low_priority_source
+            .store(next_source_ord, Ordering::Relaxed);
+    }
 }
 
 #[cfg(test)]
@@ -368,6 +976,76 @@ mod tests {
         assert_eq!(locked_option.take(), None);
     }
 
+    #[test]
+    fn test_locked_option_with_val() {
+        let locked_option = LockedOption::none();
+        let mut num_calls = 0;
+        locked_option.with_val(|_: &usize| num_calls += 1);
+        assert_eq!(num_calls, 0);
+        locked_option.place(1);
+        locked_option.with_val(|val| {
+            assert_eq!(*val, 1);
+            num_calls += 1;
+        });
+        assert_eq!(num_calls, 1);
+        assert_eq!(locked_option.take(), Some(1));
+    }
+
+    #[cfg(feature = "peek-pending")]
+    #[tokio::test]
+    async fn test_peek_renderings() -> anyhow::Result<()> {
+        let (sender, receiver) = super::channel::<usize>(QueueCapacity::Unbounded);
+        for i in 0..3 {
+            sender.send_low_priority(i).await?;
+            sender.record_rendering(i.to_string());
+        }
+        assert_eq!(sender.peek_renderings(2), vec!["0", "1"]);
+        assert_eq!(receiver.recv().await, Ok(0));
+        assert_eq!(sender.peek_renderings(5), vec!["1", "2"]);
+        // A message can be received before its rendering is recorded.
+        sender.send_low_priority(3).await?;
+        receiver.drain_low_priority();
+        sender.record_rendering("3".to_string());
+        assert!(sender.peek_renderings(5).is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watermark_signal() -> anyhow::Result<()> {
+        let (sender, receiver) = super::channel::<usize>(QueueCapacity::Unbounded);
+        sender.send_low_priority(0).await?;
+        let watermark_rx = sender.watermark_signal(3, 1);
+        assert!(!*watermark_rx.borrow());
+        sender.send_low_priority(1).await?;
+        assert!(!*watermark_rx.borrow());
+        sender.try_send_low_priority(2).unwrap();
+        assert!(*watermark_rx.borrow());
+        assert_eq!(receiver.recv().await, Ok(0));
+        assert!(*watermark_rx.borrow());
+        sender.send_low_priority(3).await?;
+        assert_eq!(receiver.recv().await, Ok(1));
+        assert!(*watermark_rx.borrow());
+        assert_eq!(receiver.recv().await, Ok(2));
+        assert!(!*watermark_rx.borrow());
+        sender.send_low_priority(4).await?;
+        assert!(!*watermark_rx.borrow());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_high_priority_is_unbounded() -> anyhow::Result<()> {
+        let (sender, receiver) = super::channel::<usize>(QueueCapacity::Bounded(1));
+        sender.send_low_priority(0).await?;
+        for i in 1..=1_000 {
+            sender.send_high_priority(i)?;
+        }
+        for i in 1..=1_000 {
+            assert_eq!(receiver.recv().await, Ok(i));
+        }
+        assert_eq!(receiver.recv().await, Ok(0));
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_recv_priority() -> anyhow::Result<()> {
         let (sender, receiver) = super::channel::<usize>(QueueCapacity::Unbounded);
@@ -458,6 +1136,118 @@ mod tests {
         assert_eq!(rx.try_recv(), Err(RecvError::NoMessageAvailable));
     }
 
+    #[test]
+    fn test_send_instants() {
+        let mut send_instants = SendInstants::default();
+        assert!(send_instants.oldest().is_none());
+        let first_instant = Instant::now();
+        let second_instant = first_instant + Duration::from_secs(1);
+        send_instants.record_send(first_instant);
+        send_instants.record_send(second_instant);
+        assert_eq!(send_instants.oldest(), Some(first_instant));
+        send_instants.record_recv();
+        assert_eq!(send_instants.oldest(), Some(second_instant));
+        send_instants.record_recv();
+        assert!(send_instants.oldest().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_queue_diagnostics() {
+        let (mut senders, rx) =
+            super::multi_source_channel::<usize>(&[QueueCapacity::Bounded(3)], true);
+        let tx = senders.pop().unwrap();
+        assert_eq!(tx.low_priority_capacity(), Some(3));
+        assert_eq!(tx.low_priority_len(), 0);
+        assert!(tx.oldest_low_priority_message_age().is_none());
+        tx.send_low_priority(1).await.unwrap();
+        tx.try_send_low_priority(2).unwrap();
+        assert_eq!(tx.low_priority_len(), 2);
+        assert!(tx.oldest_low_priority_message_age().is_some());
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.recv().await, Ok(2));
+        assert_eq!(tx.low_priority_len(), 0);
+        assert!(tx.oldest_low_priority_message_age().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_multi_source_channel_round_robin() {
+        let (senders, rx) = super::multi_source_channel::<usize>(
+            &[QueueCapacity::Unbounded, QueueCapacity::Unbounded],
+            false,
+        );
+        for i in 0..3 {
+            senders[0].send_low_priority(i).await.unwrap();
+        }
+        for i in 10..12 {
+            senders[1].send_low_priority(i).await.unwrap();
+        }
+        assert_eq!(rx.try_recv(), Ok(0));
+        assert_eq!(rx.recv().await, Ok(10));
+        assert_eq!(rx.try_recv(), Ok(1));
+        assert_eq!(rx.recv().await, Ok(11));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Err(RecvError::NoMessageAvailable));
+    }
+
+    #[tokio::test]
+    async fn test_multi_source_channel_per_source_backpressure() {
+        let (senders, rx) = super::multi_source_channel::<usize>(
+            &[QueueCapacity::Bounded(1), QueueCapacity::Bounded(1)],
+            false,
+        );
+        senders[0].try_send_low_priority(0).unwrap();
+        assert!(matches!(
+            senders[0].try_send_low_priority(1),
+            Err(TrySendError::Full(1))
+        ));
+        senders[1].try_send_low_priority(10).unwrap();
+        senders[0].send_high_priority(100).unwrap();
+        assert_eq!(rx.recv().await, Ok(100));
+        assert_eq!(rx.recv().await, Ok(0));
+        assert_eq!(rx.recv().await, Ok(10));
+    }
+
+    #[tokio::test]
+    async fn test_multi_source_channel_wakes_up_on_any_source() {
+        let (mut senders, rx) = super::multi_source_channel::<usize>(
+            &[QueueCapacity::Unbounded, QueueCapacity::Unbounded],
+            false,
+        );
+        let second_sender = senders.pop().unwrap();
+        tokio::task::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            second_sender.send_low_priority(1).await
+        });
+        assert_eq!(rx.recv().await, Ok(1));
+        drop(senders);
+        assert_eq!(rx.recv().await, Err(RecvError::Disconnected));
+    }
+
+    #[tokio::test]
+    async fn test_lock_free_channel_with_priority() -> anyhow::Result<()> {
+        let (sender, receiver) = super::channel::<usize>(QueueCapacity::UnboundedLockFree);
+        assert_eq!(sender.low_priority_capacity(), None);
+        sender.send_low_priority(1).await?;
+        sender.send_high_priority(2)?;
+        assert_eq!(sender.low_priority_len(), 1);
+        assert_eq!(receiver.recv().await, Ok(2));
+        assert_eq!(receiver.recv().await, Ok(1));
+        let recv_task = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Ok(msg) = receiver.recv().await {
+                received.push(msg);
+            }
+            received
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        for i in 3..100 {
+            sender.send_low_priority(i).await?;
+        }
+        drop(sender);
+        assert_eq!(recv_task.await?, (3..100).collect::<Vec<usize>>());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_try_recv_high() {
         let (tx, rx) = super::channel::<usize>(QueueCapacity::Unbounded);
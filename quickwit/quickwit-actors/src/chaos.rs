@@ -0,0 +1,137 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Chaos layer injecting random delays, reorderings and drops in between actors.
+//!
+//! This is meant for CI only: it makes it possible to check that pipelines recover
+//! (checkpointing, retries, ...) under adverse scheduling. The layer only affects
+//! regular (low priority) messages, and never the internal messages of the framework.
+
+use std::sync::RwLock;
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Describes the amount of chaos injected in the messages exchanged between actors.
+///
+/// Probabilities are expressed between 0 and 1.
+#[derive(Clone, Debug, Default)]
+pub struct ChaosConfig {
+    /// Probability for a message to be delayed before being sent.
+    pub delay_probability: f64,
+    /// Probability for a message to be delivered in the background after a random delay,
+    /// letting the messages sent after it overtake it.
+    pub reorder_probability: f64,
+    /// Probability for a message to be silently dropped.
+    pub drop_probability: f64,
+    /// Upper bound of the random delays.
+    pub max_delay: Duration,
+}
+
+static CHAOS_CONFIG: RwLock<Option<ChaosConfig>> = RwLock::new(None);
+
+/// Enables (or disables, if `None` is passed) the chaos layer for the whole process.
+pub fn set_chaos_config(chaos_config_opt: Option<ChaosConfig>) {
+    *CHAOS_CONFIG.write().unwrap() = chaos_config_opt;
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub(crate) enum ChaosAction {
+    Deliver,
+    Delay(Duration),
+    Reorder(Duration),
+    Drop,
+}
+
+pub(crate) fn chaos_action(message_type_name: &str) -> ChaosAction {
+    let chaos_config_guard = CHAOS_CONFIG.read().unwrap();
+    let Some(chaos_config) = chaos_config_guard.as_ref() else {
+        return ChaosAction::Deliver;
+    };
+    pick_chaos_action(chaos_config, message_type_name, &mut rand::thread_rng())
+}
+
+fn pick_chaos_action(
+    chaos_config: &ChaosConfig,
+    message_type_name: &str,
+    rng: &mut impl Rng,
+) -> ChaosAction {
+    // Dropping or delaying commands and observations would make the framework itself
+    // misbehave, rather than the actors under test.
+    if message_type_name.starts_with("quickwit_actors::") {
+        return ChaosAction::Deliver;
+    }
+    if rng.gen_bool(chaos_config.drop_probability.clamp(0.0, 1.0)) {
+        return ChaosAction::Drop;
+    }
+    if rng.gen_bool(chaos_config.reorder_probability.clamp(0.0, 1.0)) {
+        return ChaosAction::Reorder(rng.gen_range(Duration::ZERO..=chaos_config.max_delay));
+    }
+    if rng.gen_bool(chaos_config.delay_probability.clamp(0.0, 1.0)) {
+        return ChaosAction::Delay(rng.gen_range(Duration::ZERO..=chaos_config.max_delay));
+    }
+    ChaosAction::Deliver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_chaos_action() {
+        let mut rng = rand::thread_rng();
+        let no_chaos_config = ChaosConfig::default();
+        assert_eq!(
+            pick_chaos_action(&no_chaos_config, "my_crate::Ping", &mut rng),
+            ChaosAction::Deliver
+        );
+        let drop_config = ChaosConfig {
+            drop_probability: 1.0,
+            ..Default::default()
+        };
+        assert_eq!(
+            pick_chaos_action(&drop_config, "my_crate::Ping", &mut rng),
+            ChaosAction::Drop
+        );
+        assert_eq!(
+            pick_chaos_action(&drop_config, "quickwit_actors::Command", &mut rng),
+            ChaosAction::Deliver
+        );
+        let delay_config = ChaosConfig {
+            delay_probability: 1.0,
+            max_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        let ChaosAction::Delay(delay) =
+            pick_chaos_action(&delay_config, "my_crate::Ping", &mut rng)
+        else {
+            panic!("expected a delay");
+        };
+        assert!(delay <= Duration::from_millis(10));
+        let reorder_config = ChaosConfig {
+            reorder_probability: 1.0,
+            max_delay: Duration::from_millis(10),
+            ..Default::default()
+        };
+        assert!(matches!(
+            pick_chaos_action(&reorder_config, "my_crate::Ping", &mut rng),
+            ChaosAction::Reorder(_)
+        ));
+    }
+}
@@ -0,0 +1,202 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use thiserror::Error;
+use tokio::sync::oneshot;
+
+use crate::{Actor, ActorContext, ActorExitStatus, Handler};
+
+/// Error returned when a checkpoint barrier did not go through the whole pipeline, e.g.
+/// because an actor exited before processing it.
+#[derive(Clone, Copy, Debug, Error, Eq, PartialEq)]
+#[error("checkpoint barrier `{checkpoint_id}` was dropped before reaching the sinks")]
+pub struct CheckpointBarrierError {
+    pub checkpoint_id: u64,
+}
+
+struct BarrierTracker {
+    checkpoint_id: u64,
+    // Number of copies of the barrier not processed yet.
+    num_pending_barriers: usize,
+    completion_tx_opt: Option<oneshot::Sender<Result<(), CheckpointBarrierError>>>,
+}
+
+impl BarrierTracker {
+    fn complete(&mut self, result: Result<(), CheckpointBarrierError>) {
+        if let Some(completion_tx) = self.completion_tx_opt.take() {
+            let _ = completion_tx.send(result);
+        }
+    }
+}
+
+/// A barrier flowing through a pipeline along with the regular messages, in the fashion of
+/// distributed snapshots.
+///
+/// When an actor processes the barrier, all of the messages sent to it before the barrier
+/// have been processed. The actor then flushes its state with `Actor::flush` and forwards
+/// the barrier to its downstream actors with `Actor::forward_checkpoint_barrier`. Once all
+/// of the copies of the barrier have gone through the sinks of the pipeline, the
+/// [`CheckpointCompletion`] returned along with the barrier resolves.
+///
+/// Forwarding the barrier is done by sending clones of it. Actors fed by several upstream
+/// actors align the barrier: they wait for the copies of all of their upstream actors, as
+/// declared by `Actor::num_checkpoint_barrier_upstreams`, then flush and forward the barrier
+/// once. The messages an upstream actor sends after its copy of the barrier are not held
+/// back, so they may be included in the flush.
+pub struct CheckpointBarrier {
+    checkpoint_id: u64,
+    tracker: Arc<Mutex<BarrierTracker>>,
+    processed: bool,
+}
+
+impl CheckpointBarrier {
+    /// Creates a barrier to send to the sources of a pipeline, and the future resolving once
+    /// the barrier has gone through the whole pipeline.
+    pub fn new(checkpoint_id: u64) -> (CheckpointBarrier, CheckpointCompletion) {
+        let (completion_tx, completion_rx) = oneshot::channel();
+        let tracker = BarrierTracker {
+            checkpoint_id,
+            num_pending_barriers: 1,
+            completion_tx_opt: Some(completion_tx),
+        };
+        let checkpoint_barrier = CheckpointBarrier {
+            checkpoint_id,
+            tracker: Arc::new(Mutex::new(tracker)),
+            processed: false,
+        };
+        let checkpoint_completion = CheckpointCompletion {
+            checkpoint_id,
+            completion_rx,
+        };
+        (checkpoint_barrier, checkpoint_completion)
+    }
+
+    pub fn checkpoint_id(&self) -> u64 {
+        self.checkpoint_id
+    }
+
+    /// Marks this copy of the barrier as gone through its actor.
+    pub(crate) fn mark_processed(mut self) {
+        self.processed = true;
+    }
+}
+
+impl Clone for CheckpointBarrier {
+    fn clone(&self) -> Self {
+        self.tracker.lock().unwrap().num_pending_barriers += 1;
+        CheckpointBarrier {
+            checkpoint_id: self.checkpoint_id,
+            tracker: self.tracker.clone(),
+            processed: false,
+        }
+    }
+}
+
+impl fmt::Debug for CheckpointBarrier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CheckpointBarrier")
+            .field(&self.checkpoint_id)
+            .finish()
+    }
+}
+
+impl Drop for CheckpointBarrier {
+    fn drop(&mut self) {
+        let mut tracker = self.tracker.lock().unwrap();
+        tracker.num_pending_barriers -= 1;
+        if !self.processed {
+            let checkpoint_id = tracker.checkpoint_id;
+            tracker.complete(Err(CheckpointBarrierError { checkpoint_id }));
+        } else if tracker.num_pending_barriers == 0 {
+            tracker.complete(Ok(()));
+        }
+    }
+}
+
+/// Resolves once all of the copies of a [`CheckpointBarrier`] have gone through the sinks of
+/// the pipeline.
+pub struct CheckpointCompletion {
+    checkpoint_id: u64,
+    completion_rx: oneshot::Receiver<Result<(), CheckpointBarrierError>>,
+}
+
+impl CheckpointCompletion {
+    pub async fn wait(self) -> Result<(), CheckpointBarrierError> {
+        let checkpoint_id = self.checkpoint_id;
+        self.completion_rx
+            .await
+            .unwrap_or(Err(CheckpointBarrierError { checkpoint_id }))
+    }
+}
+
+#[async_trait]
+impl<A: Actor> Handler<CheckpointBarrier> for A {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        checkpoint_barrier: CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let num_upstreams = self.num_checkpoint_barrier_upstreams();
+        let Some(checkpoint_barriers) =
+            ctx.align_checkpoint_barrier(checkpoint_barrier, num_upstreams)
+        else {
+            // Waiting for the copies of the other upstream actors.
+            return Ok(());
+        };
+        self.flush(ctx).await?;
+        self.forward_checkpoint_barrier(&checkpoint_barriers[0], ctx)
+            .await?;
+        for checkpoint_barrier in checkpoint_barriers {
+            checkpoint_barrier.mark_processed();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_checkpoint_completes_once_all_copies_are_processed() {
+        let (checkpoint_barrier, checkpoint_completion) = CheckpointBarrier::new(1);
+        let checkpoint_barrier_copy = checkpoint_barrier.clone();
+        checkpoint_barrier.mark_processed();
+        checkpoint_barrier_copy.mark_processed();
+        checkpoint_completion.wait().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_checkpoint_fails_if_a_copy_is_dropped() {
+        let (checkpoint_barrier, checkpoint_completion) = CheckpointBarrier::new(2);
+        let checkpoint_barrier_copy = checkpoint_barrier.clone();
+        checkpoint_barrier.mark_processed();
+        drop(checkpoint_barrier_copy);
+        assert_eq!(
+            checkpoint_completion.wait().await.unwrap_err(),
+            CheckpointBarrierError { checkpoint_id: 2 }
+        );
+    }
+}
@@ -17,7 +17,11 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::any::{type_name, Any};
+use std::fmt;
+
 use async_trait::async_trait;
+use tokio::sync::oneshot;
 
 use crate::{Actor, ActorContext, ActorExitStatus, Handler};
 
@@ -55,13 +59,26 @@ pub enum Command {
     /// Asks the actor to gracefully shutdown.
     ///
     /// The actor will stop processing messages and its finalize function will
-    /// be called.
+    /// be called. Before that, the messages still queued in its mailbox are handled
+    /// according to the actor's [`DrainPolicy`](crate::DrainPolicy): they are dropped by
+    /// default, but they can also be processed or transferred to a successor mailbox.
     ///
     /// The exit status is then `ActorExitStatus::Quit`.
     ///
     /// This is the equivalent of sending SIGINT/Ctrl-C to a process.
     Quit,
 
+    /// Asks the actor to complete all of its buffered work (e.g. committing the
+    /// in-progress split) by calling [`Actor::flush`], and then acks through the
+    /// provided channel.
+    ///
+    /// When sent through the low priority channel, as done by
+    /// `ActorHandle::flush()`, it acts as a barrier: all of the messages queued
+    /// before the command are processed before the flush happens.
+    ///
+    /// If the flush fails, the actor exits and the ack channel is dropped.
+    Flush(oneshot::Sender<()>),
+
     /// Nudging is a No-op message.
     ///
     /// Its only effect is to wake-up actors that are stuck waiting
@@ -83,6 +100,49 @@ pub enum Command {
     /// The respawned actor would receive its predecessor mailbox and
     /// possibly end up process a Kill message as its first message.
     Nudge,
+
+    /// Command defined by the actor itself, handled by [`Actor::process_command`].
+    ///
+    /// This is meant for operational actions, like reloading a configuration, that
+    /// should not wait behind the messages of the data queue.
+    Custom(CustomCommand),
+}
+
+/// Type-erased custom command. See [`Command::Custom`].
+pub struct CustomCommand {
+    name: &'static str,
+    command: Box<dyn Any + Send>,
+}
+
+impl CustomCommand {
+    pub fn new<C: Any + Send>(command: C) -> Self {
+        CustomCommand {
+            name: type_name::<C>(),
+            command: Box::new(command),
+        }
+    }
+
+    /// Returns the type name of the command.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the command if it is of type `C`, or gives back the custom command otherwise.
+    pub fn downcast<C: Any>(self) -> Result<C, CustomCommand> {
+        match self.command.downcast::<C>() {
+            Ok(command) => Ok(*command),
+            Err(command) => Err(CustomCommand {
+                name: self.name,
+                command,
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for CustomCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CustomCommand").field(&self.name).finish()
+    }
 }
 
 #[async_trait]
@@ -102,11 +162,17 @@ impl<A: Actor> Handler<Command> for A {
             }
             Command::ExitWithSuccess => Err(ActorExitStatus::Success),
             Command::Quit => Err(ActorExitStatus::Quit),
+            Command::Flush(ack_tx) => {
+                self.flush(ctx).await?;
+                let _ = ack_tx.send(());
+                Ok(())
+            }
             Command::Nudge => Ok(()),
             Command::Resume => {
                 ctx.resume();
                 Ok(())
             }
+            Command::Custom(custom_command) => self.process_command(custom_command, ctx).await,
         }
     }
 }
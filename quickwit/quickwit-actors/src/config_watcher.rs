@@ -0,0 +1,159 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::Arc;
+
+use tokio::sync::watch;
+
+/// Message received by the actors subscribed to a [`ConfigWatcher`] when the value of the
+/// watched config section changes.
+#[derive(Clone, Debug)]
+pub struct ConfigUpdated<C>(pub C);
+
+/// Holds a typed config section (e.g. the commit timeout or the merge factor of indexing
+/// pipelines) that can be updated at runtime.
+///
+/// Actors subscribe to it with `ActorContext::subscribe_to_config`, and receive a
+/// [`ConfigUpdated`] message carrying the new value whenever it changes, which makes it
+/// possible to tune them without restarting their pipeline. Intermediate values are skipped
+/// if the config is updated faster than the actors process the updates.
+pub struct ConfigWatcher<C> {
+    config_tx: Arc<watch::Sender<C>>,
+}
+
+impl<C> Clone for ConfigWatcher<C> {
+    fn clone(&self) -> Self {
+        ConfigWatcher {
+            config_tx: self.config_tx.clone(),
+        }
+    }
+}
+
+impl<C: fmt::Debug> fmt::Debug for ConfigWatcher<C> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ConfigWatcher")
+            .field(&*self.config_tx.borrow())
+            .finish()
+    }
+}
+
+impl<C: Clone + PartialEq + Send + Sync + 'static> ConfigWatcher<C> {
+    pub fn new(config: C) -> Self {
+        ConfigWatcher {
+            config_tx: Arc::new(watch::channel(config).0),
+        }
+    }
+
+    /// Returns the current value of the config section.
+    pub fn current(&self) -> C {
+        self.config_tx.borrow().clone()
+    }
+
+    /// Updates the config section. Subscribers are only notified if the value changed.
+    pub fn update(&self, config: C) {
+        self.config_tx.send_if_modified(|current_config| {
+            if *current_config == config {
+                return false;
+            }
+            *current_config = config;
+            true
+        });
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<C> {
+        self.config_tx.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{Actor, ActorContext, ActorExitStatus, Handler, Universe};
+
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    struct CommitConfig {
+        commit_timeout: Duration,
+    }
+
+    struct CommitterActor {
+        config_watcher: ConfigWatcher<CommitConfig>,
+        commit_timeout: Duration,
+    }
+
+    #[async_trait]
+    impl Actor for CommitterActor {
+        type ObservableState = Duration;
+
+        fn observable_state(&self) -> Self::ObservableState {
+            self.commit_timeout
+        }
+
+        async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+            ctx.subscribe_to_config(&self.config_watcher);
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Handler<ConfigUpdated<CommitConfig>> for CommitterActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            config_updated: ConfigUpdated<CommitConfig>,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            self.commit_timeout = config_updated.0.commit_timeout;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_config_watcher() {
+        let universe = Universe::with_accelerated_time();
+        let config_watcher = ConfigWatcher::new(CommitConfig {
+            commit_timeout: Duration::from_secs(60),
+        });
+        let committer = CommitterActor {
+            config_watcher: config_watcher.clone(),
+            commit_timeout: config_watcher.current().commit_timeout,
+        };
+        let (_mailbox, handle) = universe.spawn_builder().spawn(committer);
+        assert_eq!(
+            handle.process_pending_and_observe().await.state,
+            Duration::from_secs(60)
+        );
+        config_watcher.update(CommitConfig {
+            commit_timeout: Duration::from_secs(5),
+        });
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while handle.process_pending_and_observe().await.state != Duration::from_secs(5) {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        universe.assert_quit().await;
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use futures::future::poll_fn;
+use serde::Serialize;
+
+/// Cumulative time an actor spent processing its messages.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct CpuUsage {
+    /// Time spent on a CPU.
+    pub cpu_time: Duration,
+    /// Time spent blocking the thread of the runtime without using the CPU, typically in
+    /// blocking syscalls or waiting on locks.
+    pub blocking_time: Duration,
+}
+
+#[derive(Default)]
+pub(crate) struct CpuUsageCounters {
+    cpu_time_micros: AtomicU64,
+    blocking_time_micros: AtomicU64,
+}
+
+impl CpuUsageCounters {
+    pub fn record(&self, cpu_usage: CpuUsage) {
+        self.cpu_time_micros
+            .fetch_add(cpu_usage.cpu_time.as_micros() as u64, Ordering::Relaxed);
+        self.blocking_time_micros.fetch_add(
+            cpu_usage.blocking_time.as_micros() as u64,
+            Ordering::Relaxed,
+        );
+    }
+
+    pub fn snapshot(&self) -> CpuUsage {
+        CpuUsage {
+            cpu_time: Duration::from_micros(self.cpu_time_micros.load(Ordering::Relaxed)),
+            blocking_time: Duration::from_micros(self.blocking_time_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// Runs a future, measuring the CPU time it consumes.
+///
+/// A task can move from a thread to another between two polls, so the CPU time of the
+/// current thread is measured around each individual poll. The time spent within polls but
+/// off the CPU is accounted for as blocking time.
+pub(crate) async fn measure_cpu_usage<F: Future>(future: F) -> (F::Output, CpuUsage) {
+    let mut future = pin!(future);
+    let mut cpu_usage = CpuUsage::default();
+    let output = poll_fn(|cx| {
+        let start_wall_time = Instant::now();
+        let start_cpu_time_opt = thread_cpu_time();
+        let poll = future.as_mut().poll(cx);
+        let wall_time = start_wall_time.elapsed();
+        if let (Some(start_cpu_time), Some(end_cpu_time)) = (start_cpu_time_opt, thread_cpu_time())
+        {
+            let cpu_time = end_cpu_time.saturating_sub(start_cpu_time);
+            cpu_usage.cpu_time += cpu_time;
+            cpu_usage.blocking_time += wall_time.saturating_sub(cpu_time);
+        }
+        poll
+    })
+    .await;
+    (output, cpu_usage)
+}
+
+/// Returns the CPU time consumed by the current thread so far.
+#[cfg(unix)]
+fn thread_cpu_time() -> Option<Duration> {
+    let mut timespec = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    // SAFETY: `clock_gettime` only writes to the timespec we pass it.
+    let ret = unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut timespec) };
+    if ret != 0 {
+        return None;
+    }
+    Some(Duration::new(
+        timespec.tv_sec as u64,
+        timespec.tv_nsec as u32,
+    ))
+}
+
+#[cfg(not(unix))]
+fn thread_cpu_time() -> Option<Duration> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_measure_cpu_usage() {
+        let (output, cpu_usage) = measure_cpu_usage(async {
+            let start = Instant::now();
+            let mut num_iterations: u64 = 0;
+            while start.elapsed() < Duration::from_millis(20) {
+                num_iterations = std::hint::black_box(num_iterations + 1);
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            std::thread::sleep(Duration::from_millis(20));
+            num_iterations
+        })
+        .await;
+        assert!(output > 0);
+        assert!(cpu_usage.cpu_time >= Duration::from_millis(5));
+        assert!(cpu_usage.blocking_time >= Duration::from_millis(15));
+    }
+}
@@ -0,0 +1,78 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+/// Maximum number of dead letters retained. The oldest ones are evicted first.
+const MAX_NUM_DEAD_LETTERS: usize = 1_000;
+
+/// A message that was given up on after its processing failed too many times.
+#[derive(Clone, Debug, Serialize)]
+pub struct DeadLetter {
+    pub actor_instance_id: String,
+    pub message_type: &'static str,
+    /// Debug representation of the message.
+    pub message: String,
+    pub num_attempts: usize,
+}
+
+/// Stores the poison messages of a universe, i.e. the redeliverable messages that kept
+/// failing across supervised restarts of the actor processing them.
+#[derive(Clone, Default)]
+pub(crate) struct DeadLetterQueue {
+    dead_letters: Arc<Mutex<VecDeque<DeadLetter>>>,
+}
+
+impl DeadLetterQueue {
+    pub fn push(&self, dead_letter: DeadLetter) {
+        let mut dead_letters = self.dead_letters.lock().unwrap();
+        if dead_letters.len() == MAX_NUM_DEAD_LETTERS {
+            dead_letters.pop_front();
+        }
+        dead_letters.push_back(dead_letter);
+    }
+
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dead_letter_queue_evicts_oldest_dead_letters() {
+        let dead_letter_queue = DeadLetterQueue::default();
+        for num_attempts in 0..MAX_NUM_DEAD_LETTERS + 1 {
+            dead_letter_queue.push(DeadLetter {
+                actor_instance_id: "indexer-1".to_string(),
+                message_type: "Batch",
+                message: "Batch".to_string(),
+                num_attempts,
+            });
+        }
+        let dead_letters = dead_letter_queue.dead_letters();
+        assert_eq!(dead_letters.len(), MAX_NUM_DEAD_LETTERS);
+        assert_eq!(dead_letters[0].num_attempts, 1);
+    }
+}
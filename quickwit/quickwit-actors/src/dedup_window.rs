@@ -0,0 +1,109 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{HashSet, VecDeque};
+use std::hash::Hash;
+
+/// Remembers the ids of the last messages processed by an actor, to drop the messages
+/// resent by an upstream actor after a restart (at-least-once delivery).
+///
+/// The ids are supplied by the user, e.g. a split id for a publisher. Only the last
+/// `capacity` ids are remembered, so a message resent after more than `capacity` other
+/// messages is not detected as a duplicate.
+#[derive(Debug)]
+pub struct DedupWindow<K> {
+    capacity: usize,
+    keys: HashSet<K>,
+    // Keys in insertion order, used to evict the oldest key once the window is full.
+    key_queue: VecDeque<K>,
+    num_duplicates: u64,
+}
+
+impl<K: Clone + Eq + Hash> DedupWindow<K> {
+    pub fn new(capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "The dedup window capacity should be positive."
+        );
+        DedupWindow {
+            capacity,
+            keys: HashSet::with_capacity(capacity),
+            key_queue: VecDeque::with_capacity(capacity),
+            num_duplicates: 0,
+        }
+    }
+
+    /// Records `key` and returns `true` if it was not in the window yet, or `false` if the
+    /// message is a duplicate and should be dropped.
+    pub fn insert(&mut self, key: K) -> bool {
+        if self.keys.contains(&key) {
+            self.num_duplicates += 1;
+            return false;
+        }
+        if self.key_queue.len() == self.capacity {
+            if let Some(oldest_key) = self.key_queue.pop_front() {
+                self.keys.remove(&oldest_key);
+            }
+        }
+        self.keys.insert(key.clone());
+        self.key_queue.push_back(key);
+        true
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.keys.contains(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.key_queue.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.key_queue.is_empty()
+    }
+
+    /// Returns the number of duplicates detected so far.
+    pub fn num_duplicates(&self) -> u64 {
+        self.num_duplicates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dedup_window() {
+        let mut dedup_window = DedupWindow::new(2);
+        assert!(dedup_window.is_empty());
+        assert!(dedup_window.insert("split-1"));
+        assert!(dedup_window.insert("split-2"));
+        assert!(!dedup_window.insert("split-1"));
+        assert_eq!(dedup_window.num_duplicates(), 1);
+        assert_eq!(dedup_window.len(), 2);
+
+        // `split-1` is evicted.
+        assert!(dedup_window.insert("split-3"));
+        assert!(!dedup_window.contains(&"split-1"));
+        assert!(dedup_window.contains(&"split-2"));
+        assert!(dedup_window.insert("split-1"));
+        assert_eq!(dedup_window.len(), 2);
+        assert_eq!(dedup_window.num_duplicates(), 1);
+    }
+}
@@ -0,0 +1,250 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tokio::sync::watch;
+
+use crate::envelope::CorrelationId;
+use crate::mailbox::Priority;
+use crate::{DeferableReplyHandler, Mailbox, SendError};
+
+/// Type-erased mailbox of an actor handling messages of type `M`.
+#[async_trait]
+pub(crate) trait MessageRecipient<M>: Send + Sync + 'static {
+    fn actor_instance_id(&self) -> &str;
+
+    fn mailbox_id(&self) -> u64;
+
+    async fn send_with_correlation_id(
+        &self,
+        message: M,
+        correlation_id_opt: Option<CorrelationId>,
+        priority: Priority,
+    ) -> Result<(), SendError>;
+}
+
+#[async_trait]
+impl<A, M> MessageRecipient<M> for Mailbox<A>
+where
+    A: DeferableReplyHandler<M>,
+    M: fmt::Debug + Send + 'static,
+{
+    fn actor_instance_id(&self) -> &str {
+        Mailbox::actor_instance_id(self)
+    }
+
+    fn mailbox_id(&self) -> u64 {
+        Mailbox::mailbox_id(self)
+    }
+
+    async fn send_with_correlation_id(
+        &self,
+        message: M,
+        correlation_id_opt: Option<CorrelationId>,
+        priority: Priority,
+    ) -> Result<(), SendError> {
+        self.send_message_with_correlation_id(message, correlation_id_opt, priority, None)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Placeholder for the mailbox of an actor handling messages of type `M`, that can be handed
+/// to upstream actors before the downstream actor is spawned, and bound to its mailbox later.
+///
+/// This makes it possible to build cyclic pipelines, e.g. a publisher feeding checkpoints back
+/// to the source. Messages sent before the deferred mailbox is bound wait for it to be bound.
+/// Replies are discarded.
+pub struct DeferredMailbox<M> {
+    recipient_tx: Arc<watch::Sender<Option<Arc<dyn MessageRecipient<M>>>>>,
+}
+
+impl<M> Clone for DeferredMailbox<M> {
+    fn clone(&self) -> Self {
+        DeferredMailbox {
+            recipient_tx: self.recipient_tx.clone(),
+        }
+    }
+}
+
+impl<M> Default for DeferredMailbox<M> {
+    fn default() -> Self {
+        DeferredMailbox {
+            recipient_tx: Arc::new(watch::channel(None).0),
+        }
+    }
+}
+
+impl<M> fmt::Debug for DeferredMailbox<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let actor_instance_id_opt = self
+            .recipient_tx
+            .borrow()
+            .as_ref()
+            .map(|recipient| recipient.actor_instance_id().to_string());
+        f.debug_struct("DeferredMailbox")
+            .field("actor_instance_id", &actor_instance_id_opt)
+            .finish()
+    }
+}
+
+impl<M: fmt::Debug + Send + 'static> DeferredMailbox<M> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Binds the deferred mailbox to the mailbox of the downstream actor, and delivers the
+    /// messages waiting for it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the deferred mailbox is already bound.
+    pub fn bind<A>(&self, mailbox: Mailbox<A>)
+    where A: DeferableReplyHandler<M> {
+        let mut recipient_opt = Some(Arc::new(mailbox) as Arc<dyn MessageRecipient<M>>);
+        self.recipient_tx.send_if_modified(|current_recipient_opt| {
+            if current_recipient_opt.is_some() {
+                return false;
+            }
+            *current_recipient_opt = recipient_opt.take();
+            true
+        });
+        assert!(
+            recipient_opt.is_none(),
+            "The deferred mailbox is already bound."
+        );
+    }
+
+    pub fn is_bound(&self) -> bool {
+        self.recipient_tx.borrow().is_some()
+    }
+
+    /// Sends a message to the downstream actor, waiting for the deferred mailbox to be bound
+    /// if necessary.
+    ///
+    /// From an actor context, use the `ActorContext::send_message_to_deferred` method instead.
+    pub async fn send_message(&self, message: M) -> Result<(), SendError> {
+        self.recipient()
+            .await
+            .send_with_correlation_id(message, None, Priority::Low)
+            .await
+    }
+
+    /// Returns the mailbox of the downstream actor, once bound.
+    pub(crate) async fn recipient(&self) -> Arc<dyn MessageRecipient<M>> {
+        let mut recipient_rx = self.recipient_tx.subscribe();
+        let recipient_ref = recipient_rx
+            .wait_for(Option::is_some)
+            .await
+            .expect("The sender is owned by the deferred mailbox.");
+        recipient_ref
+            .clone()
+            .expect("The recipient should be bound.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::tests::{Ping, PingReceiverActor};
+    use crate::{Actor, ActorContext, ActorExitStatus, Handler, Universe};
+
+    #[tokio::test]
+    async fn test_deferred_mailbox() {
+        let universe = Universe::with_accelerated_time();
+        let deferred_mailbox = DeferredMailbox::<Ping>::new();
+        assert!(!deferred_mailbox.is_bound());
+
+        let deferred_mailbox_clone = deferred_mailbox.clone();
+        let send_join_handle =
+            tokio::spawn(async move { deferred_mailbox_clone.send_message(Ping).await });
+
+        let (ping_mailbox, ping_handle) =
+            universe.spawn_builder().spawn(PingReceiverActor::default());
+        deferred_mailbox.bind(ping_mailbox);
+        assert!(deferred_mailbox.is_bound());
+        send_join_handle.await.unwrap().unwrap();
+        deferred_mailbox.send_message(Ping).await.unwrap();
+        assert_eq!(ping_handle.process_pending_and_observe().await.state, 2);
+        universe.assert_quit().await;
+    }
+
+    struct PingForwarderActor {
+        deferred_mailbox: DeferredMailbox<Ping>,
+    }
+
+    impl Actor for PingForwarderActor {
+        type ObservableState = ();
+
+        fn observable_state(&self) -> Self::ObservableState {}
+    }
+
+    #[async_trait]
+    impl Handler<Ping> for PingForwarderActor {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            ping: Ping,
+            ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            ctx.send_message_to_deferred(&self.deferred_mailbox, ping)
+                .await?;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_message_to_deferred_mailbox_from_actor() {
+        let universe = Universe::with_accelerated_time();
+        let deferred_mailbox = DeferredMailbox::<Ping>::new();
+        let (forwarder_mailbox, forwarder_handle) =
+            universe.spawn_builder().spawn(PingForwarderActor {
+                deferred_mailbox: deferred_mailbox.clone(),
+            });
+        forwarder_mailbox.send_message(Ping).await.unwrap();
+
+        let (ping_mailbox, ping_handle) =
+            universe.spawn_builder().spawn(PingReceiverActor::default());
+        let ping_actor_id = ping_mailbox.actor_instance_id().to_string();
+        deferred_mailbox.bind(ping_mailbox);
+        forwarder_handle.process_pending_and_observe().await;
+        assert_eq!(ping_handle.process_pending_and_observe().await.state, 1);
+        assert!(universe.topology().edges.contains(&(
+            forwarder_mailbox.actor_instance_id().to_string(),
+            ping_actor_id
+        )));
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "The deferred mailbox is already bound.")]
+    async fn test_deferred_mailbox_cannot_be_bound_twice() {
+        let universe = Universe::with_accelerated_time();
+        let deferred_mailbox = DeferredMailbox::<Ping>::new();
+        let (ping_mailbox, _ping_inbox) = universe.create_test_mailbox::<PingReceiverActor>();
+        deferred_mailbox.bind(ping_mailbox.clone());
+        deferred_mailbox.bind(ping_mailbox);
+    }
+}
@@ -17,16 +17,53 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::any::Any;
+use std::any::{type_name, Any};
 use std::fmt;
+use std::num::NonZeroU64;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use async_trait::async_trait;
+use serde::Serialize;
+use smallbox::space::S4;
+use smallbox::{smallbox, SmallBox};
 use tokio::sync::oneshot;
 
 use crate::actor::DeferableReplyHandler;
+use crate::mailbox::Priority;
+use crate::memory_budget::MemoryPermit;
 use crate::scheduler::NoAdvanceTimeGuard;
 use crate::{Actor, ActorContext, ActorExitStatus};
 
+/// Identifier attached to every message.
+///
+/// Messages sent by an actor while it is processing a message inherit the
+/// correlation id of the message being processed. This makes it possible to trace
+/// a single batch across all of the actors that touched it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, Serialize)]
+pub struct CorrelationId(NonZeroU64);
+
+impl CorrelationId {
+    pub(crate) fn new() -> CorrelationId {
+        static CORRELATION_ID_GENERATOR: AtomicU64 = AtomicU64::new(1);
+        let correlation_id = CORRELATION_ID_GENERATOR.fetch_add(1, Ordering::Relaxed);
+        CorrelationId(NonZeroU64::new(correlation_id).expect("The id generator starts at 1."))
+    }
+
+    pub(crate) fn as_u64(&self) -> u64 {
+        self.0.get()
+    }
+
+    pub(crate) fn from_u64(correlation_id: u64) -> Option<CorrelationId> {
+        NonZeroU64::new(correlation_id).map(CorrelationId)
+    }
+}
+
+impl fmt::Display for CorrelationId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
 /// An `Envelope` is just a way to capture the handler
 /// of a message and hide its type.
 ///
@@ -34,13 +71,65 @@ use crate::{Actor, ActorContext, ActorExitStatus};
 /// queue with a single type.
 /// Before appending, we capture the right handler implementation
 /// in the form of a `Box<dyn Envelope>`, and append that to the queue.
-
+///
+/// Small messages (e.g. acknowledgments) are stored inline along with their reply channel,
+/// in order to spare an allocation per message. Larger ones are boxed.
 pub struct Envelope<A> {
-    handler_envelope: Box<dyn EnvelopeT<A>>,
+    handler_envelope: SmallBox<dyn EnvelopeT<A>, HandlerEnvelopeSpace>,
+    correlation_id: CorrelationId,
     _no_advance_time_guard: Option<NoAdvanceTimeGuard>,
+    // Released once the envelope is dropped, i.e. once the message has been processed.
+    memory_permit_opt: Option<MemoryPermit>,
+    // Set for messages that can be redelivered after a failure.
+    redeliver_fn_opt: Option<RedeliverFn<A>>,
+    // Number of times the message has been delivered to the actor, including this one.
+    num_attempts: usize,
+    // Priority inherited by the messages sent by the actor while processing this message.
+    priority: Priority,
 }
 
+/// Rebuilds an envelope from a reference to the message it holds.
+type RedeliverFn<A> = fn(&dyn Any, CorrelationId) -> Envelope<A>;
+
+/// Inline space of an envelope, in words. It fits messages of up to 3 words, as the reply
+/// channel takes one.
+type HandlerEnvelopeSpace = S4;
+
 impl<A: Actor> Envelope<A> {
+    pub fn correlation_id(&self) -> CorrelationId {
+        self.correlation_id
+    }
+
+    pub(crate) fn attach_memory_permit(&mut self, memory_permit: MemoryPermit) {
+        self.memory_permit_opt = Some(memory_permit);
+    }
+
+    /// Returns a copy of the envelope, with no reply channel, if the message was sent with
+    /// `Mailbox::send_redeliverable_message` and has not been consumed yet.
+    pub(crate) fn redelivery_copy(&self) -> Option<Envelope<A>> {
+        let redeliver_fn = self.redeliver_fn_opt?;
+        let message = self.handler_envelope.message_ref()?;
+        let mut envelope_copy = redeliver_fn(message, self.correlation_id);
+        envelope_copy.num_attempts = self.num_attempts;
+        Some(envelope_copy)
+    }
+
+    pub(crate) fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    pub(crate) fn set_priority(&mut self, priority: Priority) {
+        self.priority = priority;
+    }
+
+    pub(crate) fn num_attempts(&self) -> usize {
+        self.num_attempts
+    }
+
+    pub(crate) fn increment_num_attempts(&mut self) {
+        self.num_attempts += 1;
+    }
+
     /// Returns the message as a boxed any.
     ///
     /// This method is only useful in unit tests.
@@ -56,6 +145,22 @@ impl<A: Actor> Envelope<A> {
         }
     }
 
+    /// Returns the debug representation of the message.
+    pub(crate) fn message_debug(&self) -> String {
+        self.handler_envelope.debug_msg()
+    }
+
+    /// Returns a value whose `Debug` implementation renders the message, without
+    /// allocating.
+    pub(crate) fn message_fmt(&self) -> impl fmt::Debug + '_ {
+        MessageFmt(&*self.handler_envelope)
+    }
+
+    /// Returns the type name of the message.
+    pub fn message_type_name(&self) -> &'static str {
+        self.handler_envelope.message_type_name()
+    }
+
     /// Execute the captured handle function.
     pub async fn handle_message(
         &mut self,
@@ -70,7 +175,18 @@ impl<A: Actor> Envelope<A> {
 impl<A: Actor> fmt::Debug for Envelope<A> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let msg_str = self.handler_envelope.debug_msg();
-        f.debug_tuple("Envelope").field(&msg_str).finish()
+        f.debug_tuple("Envelope")
+            .field(&self.correlation_id)
+            .field(&msg_str)
+            .finish()
+    }
+}
+
+struct MessageFmt<'a, A: Actor>(&'a dyn EnvelopeT<A>);
+
+impl<'a, A: Actor> fmt::Debug for MessageFmt<'a, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_msg(f)
     }
 }
 
@@ -78,11 +194,18 @@ impl<A: Actor> fmt::Debug for Envelope<A> {
 trait EnvelopeT<A: Actor>: Send {
     fn debug_msg(&self) -> String;
 
+    fn fmt_msg(&self, f: &mut fmt::Formatter) -> fmt::Result;
+
+    fn message_type_name(&self) -> &'static str;
+
     /// Returns the message as a boxed any.
     ///
     /// This method is only useful in unit tests.
     fn message(&mut self) -> Box<dyn Any>;
 
+    /// Returns a reference to the message, unless it was already consumed.
+    fn message_ref(&self) -> Option<&dyn Any>;
+
     /// Execute the captured handle function.
     async fn handle_message(
         &mut self,
@@ -106,6 +229,18 @@ where
         }
     }
 
+    fn fmt_msg(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some((_response_tx, msg)) = self {
+            fmt::Debug::fmt(msg, f)
+        } else {
+            f.write_str("<consumed>")
+        }
+    }
+
+    fn message_type_name(&self) -> &'static str {
+        type_name::<M>()
+    }
+
     fn message(&mut self) -> Box<dyn Any> {
         if let Some((_, message)) = self.take() {
             Box::new(message)
@@ -114,6 +249,10 @@ where
         }
     }
 
+    fn message_ref(&self) -> Option<&dyn Any> {
+        self.as_ref().map(|(_, message)| message as &dyn Any)
+    }
+
     async fn handle_message(
         &mut self,
         actor: &mut A,
@@ -139,6 +278,7 @@ where
 
 pub(crate) fn wrap_in_envelope<A, M>(
     msg: M,
+    correlation_id: CorrelationId,
     no_advance_time_guard: Option<NoAdvanceTimeGuard>,
 ) -> (Envelope<A>, oneshot::Receiver<A::Reply>)
 where
@@ -146,10 +286,135 @@ where
     M: fmt::Debug + Send + 'static,
 {
     let (response_tx, response_rx) = oneshot::channel();
-    let handler_envelope = Some((response_tx, msg));
+    let handler_envelope: SmallBox<dyn EnvelopeT<A>, HandlerEnvelopeSpace> =
+        smallbox!(Some((response_tx, msg)));
     let envelope = Envelope {
-        handler_envelope: Box::new(handler_envelope),
+        handler_envelope,
+        correlation_id,
         _no_advance_time_guard: no_advance_time_guard,
+        memory_permit_opt: None,
+        redeliver_fn_opt: None,
+        num_attempts: 1,
+        priority: Priority::Low,
     };
     (envelope, response_rx)
 }
+
+/// Same as `wrap_in_envelope`, except the message can be redelivered if its processing fails.
+pub(crate) fn wrap_in_redeliverable_envelope<A, M>(
+    msg: M,
+    correlation_id: CorrelationId,
+    no_advance_time_guard: Option<NoAdvanceTimeGuard>,
+) -> (Envelope<A>, oneshot::Receiver<A::Reply>)
+where
+    A: DeferableReplyHandler<M>,
+    M: Clone + fmt::Debug + Send + 'static,
+{
+    let (mut envelope, response_rx) = wrap_in_envelope(msg, correlation_id, no_advance_time_guard);
+    envelope.redeliver_fn_opt = Some(redeliver::<A, M>);
+    (envelope, response_rx)
+}
+
+fn redeliver<A, M>(message: &dyn Any, correlation_id: CorrelationId) -> Envelope<A>
+where
+    A: DeferableReplyHandler<M>,
+    M: Clone + fmt::Debug + Send + 'static,
+{
+    let message: M = message
+        .downcast_ref::<M>()
+        .expect("the redeliver function should match the message type")
+        .clone();
+    let (envelope, _response_rx) = wrap_in_redeliverable_envelope(message, correlation_id, None);
+    envelope
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::Handler;
+
+    #[derive(Default)]
+    struct AckCounter {
+        num_acked_bytes: usize,
+    }
+
+    impl Actor for AckCounter {
+        type ObservableState = usize;
+
+        fn observable_state(&self) -> usize {
+            self.num_acked_bytes
+        }
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    struct Ack(u64);
+
+    #[derive(Debug)]
+    struct Payload([u8; 64]);
+
+    #[async_trait]
+    impl Handler<Ack> for AckCounter {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            _ack: Ack,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            Ok(())
+        }
+    }
+
+    #[async_trait]
+    impl Handler<Payload> for AckCounter {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            payload: Payload,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            self.num_acked_bytes += payload.0.len();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_small_messages_are_stored_inline() {
+        let (mut ack_envelope, _) =
+            wrap_in_envelope::<AckCounter, _>(Ack(42), CorrelationId::new(), None);
+        assert!(!ack_envelope.handler_envelope.is_heap());
+        assert_eq!(ack_envelope.message_typed::<Ack>().unwrap().0, 42);
+
+        let (mut payload_envelope, _) =
+            wrap_in_envelope::<AckCounter, _>(Payload([1; 64]), CorrelationId::new(), None);
+        assert!(payload_envelope.handler_envelope.is_heap());
+        assert_eq!(
+            payload_envelope.message_typed::<Payload>().unwrap().0,
+            [1; 64]
+        );
+    }
+
+    #[test]
+    fn test_redelivery_copy() {
+        let (ack_envelope, _) =
+            wrap_in_envelope::<AckCounter, _>(Ack(42), CorrelationId::new(), None);
+        assert!(ack_envelope.redelivery_copy().is_none());
+
+        let (mut ack_envelope, _) =
+            wrap_in_redeliverable_envelope::<AckCounter, _>(Ack(42), CorrelationId::new(), None);
+        ack_envelope.increment_num_attempts();
+        let mut ack_envelope_copy = ack_envelope.redelivery_copy().unwrap();
+        assert_eq!(
+            ack_envelope_copy.correlation_id(),
+            ack_envelope.correlation_id()
+        );
+        assert_eq!(ack_envelope_copy.num_attempts(), 2);
+        assert_eq!(ack_envelope_copy.message_typed::<Ack>().unwrap().0, 42);
+
+        ack_envelope.message();
+        assert!(ack_envelope.redelivery_copy().is_none());
+    }
+}
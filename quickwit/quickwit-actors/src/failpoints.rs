@@ -0,0 +1,161 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Failure injection points consulted by actors through `ActorContext::failpoint`.
+//!
+//! This is meant for integration tests: they enable a failpoint by name (e.g.
+//! `uploader.before_put`) to fail an actor at a precise point of the pipeline, and check
+//! that supervision and retries behave as expected.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// What happens when an actor reaches an enabled failpoint.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FailpointAction {
+    /// `ActorContext::failpoint` returns an `ActorExitStatus::Failure`.
+    ReturnError,
+    /// The actor panics.
+    Panic,
+    /// The actor sleeps for the given duration before going on.
+    Sleep(Duration),
+}
+
+#[derive(Debug)]
+struct Failpoint {
+    action: FailpointAction,
+    // `None` if the failpoint triggers until it is disabled.
+    num_remaining_triggers_opt: Option<usize>,
+}
+
+/// Set of the failpoints enabled in a universe.
+///
+/// Failpoints are disabled by default. Each universe has its own registry, so that tests
+/// running in parallel do not interfere with each other.
+#[derive(Clone, Debug, Default)]
+pub struct FailpointRegistry {
+    failpoints: Arc<Mutex<HashMap<String, Failpoint>>>,
+    num_triggers: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl FailpointRegistry {
+    /// Enables the failpoint `name` until it is disabled.
+    pub fn enable(&self, name: impl ToString, action: FailpointAction) {
+        self.insert(name.to_string(), action, None);
+    }
+
+    /// Enables the failpoint `name` for its next `num_triggers` hits only.
+    pub fn enable_times(&self, name: impl ToString, action: FailpointAction, num_triggers: usize) {
+        if num_triggers == 0 {
+            self.disable(&name.to_string());
+            return;
+        }
+        self.insert(name.to_string(), action, Some(num_triggers));
+    }
+
+    pub fn disable(&self, name: &str) {
+        self.failpoints.lock().unwrap().remove(name);
+    }
+
+    pub fn disable_all(&self) {
+        self.failpoints.lock().unwrap().clear();
+    }
+
+    /// Returns the number of times the failpoint `name` was triggered.
+    pub fn num_triggers(&self, name: &str) -> usize {
+        self.num_triggers
+            .lock()
+            .unwrap()
+            .get(name)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    fn insert(
+        &self,
+        name: String,
+        action: FailpointAction,
+        num_remaining_triggers_opt: Option<usize>,
+    ) {
+        let failpoint = Failpoint {
+            action,
+            num_remaining_triggers_opt,
+        };
+        self.failpoints.lock().unwrap().insert(name, failpoint);
+    }
+
+    /// Returns the action to execute if the failpoint `name` is enabled.
+    pub(crate) fn trigger(&self, name: &str) -> Option<FailpointAction> {
+        let mut failpoints_guard = self.failpoints.lock().unwrap();
+        let failpoint = failpoints_guard.get_mut(name)?;
+        let action = failpoint.action.clone();
+        if let Some(num_remaining_triggers) = failpoint.num_remaining_triggers_opt.as_mut() {
+            *num_remaining_triggers -= 1;
+            if *num_remaining_triggers == 0 {
+                failpoints_guard.remove(name);
+            }
+        }
+        drop(failpoints_guard);
+        *self
+            .num_triggers
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_default() += 1;
+        Some(action)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failpoint_registry() {
+        let failpoint_registry = FailpointRegistry::default();
+        assert_eq!(failpoint_registry.trigger("uploader.before_put"), None);
+
+        failpoint_registry.enable_times("uploader.before_put", FailpointAction::ReturnError, 2);
+        assert_eq!(
+            failpoint_registry.trigger("uploader.before_put"),
+            Some(FailpointAction::ReturnError)
+        );
+        assert_eq!(
+            failpoint_registry.trigger("uploader.before_put"),
+            Some(FailpointAction::ReturnError)
+        );
+        assert_eq!(failpoint_registry.trigger("uploader.before_put"), None);
+        assert_eq!(failpoint_registry.num_triggers("uploader.before_put"), 2);
+
+        failpoint_registry.enable("publisher.before_publish", FailpointAction::Panic);
+        for _ in 0..3 {
+            assert_eq!(
+                failpoint_registry.trigger("publisher.before_publish"),
+                Some(FailpointAction::Panic)
+            );
+        }
+        failpoint_registry.disable("publisher.before_publish");
+        assert_eq!(failpoint_registry.trigger("publisher.before_publish"), None);
+        assert_eq!(
+            failpoint_registry.num_triggers("publisher.before_publish"),
+            3
+        );
+    }
+}
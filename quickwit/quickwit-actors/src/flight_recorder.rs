@@ -0,0 +1,96 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::envelope::CorrelationId;
+use crate::ActorExitStatus;
+
+/// Number of processed messages kept by the flight recorder of each actor.
+const FLIGHT_RECORDER_CAPACITY: usize = 64;
+
+/// Record of a message processed by an actor.
+#[derive(Clone, Debug)]
+pub struct FlightRecord {
+    pub message_type: &'static str,
+    pub correlation_id: CorrelationId,
+    pub processing_time: Duration,
+    /// Exit status returned by the handler of the message, if it failed.
+    pub exit_status_opt: Option<ActorExitStatus>,
+}
+
+/// Keeps the records of the last `FLIGHT_RECORDER_CAPACITY` messages processed by an actor.
+///
+/// Like the black box of a plane, it is dumped when the actor fails or panics, which helps
+/// diagnosing crashes without enabling debug logging. It is opt-in, see
+/// `Universe::with_flight_recorder`.
+#[derive(Default)]
+pub(crate) struct FlightRecorder {
+    records: Mutex<VecDeque<FlightRecord>>,
+}
+
+impl FlightRecorder {
+    pub fn record(&self, flight_record: FlightRecord) {
+        let mut records = self.records.lock().unwrap();
+        if records.len() == FLIGHT_RECORDER_CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(flight_record);
+    }
+
+    /// Returns the records, from the oldest to the most recent one.
+    pub fn records(&self) -> Vec<FlightRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flight_recorder_keeps_last_records() {
+        let flight_recorder = FlightRecorder::default();
+        assert!(flight_recorder.records().is_empty());
+
+        for _ in 0..FLIGHT_RECORDER_CAPACITY + 1 {
+            flight_recorder.record(FlightRecord {
+                message_type: "Ping",
+                correlation_id: CorrelationId::new(),
+                processing_time: Duration::from_millis(1),
+                exit_status_opt: None,
+            });
+        }
+        flight_recorder.record(FlightRecord {
+            message_type: "Pong",
+            correlation_id: CorrelationId::new(),
+            processing_time: Duration::from_millis(1),
+            exit_status_opt: Some(ActorExitStatus::Quit),
+        });
+        let records = flight_recorder.records();
+        assert_eq!(records.len(), FLIGHT_RECORDER_CAPACITY);
+        assert_eq!(records.last().unwrap().message_type, "Pong");
+        assert!(matches!(
+            records.last().unwrap().exit_status_opt,
+            Some(ActorExitStatus::Quit)
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::time::Duration;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::{Actor, ActorHandle, DeferableReplyHandler, Mailbox, Universe};
+
+/// Maximum duration we accept to wait for a pipeline to terminate after its entry actor
+/// was asked to exit.
+const TERMINATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs a sequence of messages through a pipeline under several interleavings, and checks
+/// an invariant on the final state of one of its actors.
+///
+/// The messages are dispatched randomly to several concurrent senders, which preserve
+/// their relative order but yield to the runtime a random number of times between two
+/// sends. Interleavings are derived from a seed, so that a failure can be replayed.
+///
+/// This plays well with `proptest`: the strategy generates the messages, and the
+/// `InterleavingFailure` is turned into a test case failure.
+pub struct InterleavingChecker {
+    seed: u64,
+    num_interleavings: usize,
+    num_senders: usize,
+}
+
+/// Describes the interleaving under which an invariant was violated.
+#[derive(Debug)]
+pub struct InterleavingFailure {
+    /// Seed of the failing interleaving, to be passed to `InterleavingChecker::new` to
+    /// replay it.
+    pub seed: u64,
+    /// Index of the sender each message was dispatched to.
+    pub sender_ids: Vec<usize>,
+    pub reason: String,
+}
+
+impl fmt::Display for InterleavingFailure {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "Invariant violated (seed={}, sender_ids={:?}): {}",
+            self.seed, self.sender_ids, self.reason
+        )
+    }
+}
+
+impl std::error::Error for InterleavingFailure {}
+
+impl InterleavingChecker {
+    pub fn new(seed: u64) -> Self {
+        InterleavingChecker {
+            seed,
+            num_interleavings: 8,
+            num_senders: 2,
+        }
+    }
+
+    pub fn num_interleavings(mut self, num_interleavings: usize) -> Self {
+        self.num_interleavings = num_interleavings;
+        self
+    }
+
+    pub fn num_senders(mut self, num_senders: usize) -> Self {
+        assert!(num_senders > 0, "At least one sender is required.");
+        self.num_senders = num_senders;
+        self
+    }
+
+    /// Runs the interleavings.
+    ///
+    /// For each of them, `setup` spawns a fresh pipeline in the universe it is given and
+    /// returns the mailbox of its entry actor, as well as the handle of the actor whose
+    /// state is checked. Once all messages are sent, the entry actor is asked to exit with
+    /// success, and `invariant` is checked on the final state of the observed actor. The
+    /// pipeline is therefore expected to terminate once its entry actor has exited.
+    pub async fn check<E, O, M>(
+        &self,
+        messages: &[M],
+        setup: impl Fn(&Universe) -> (Mailbox<E>, ActorHandle<O>),
+        invariant: impl Fn(&[M], &O::ObservableState) -> Result<(), String>,
+    ) -> Result<(), InterleavingFailure>
+    where
+        E: Actor + DeferableReplyHandler<M>,
+        O: Actor,
+        M: Clone + fmt::Debug + Send + Sync + 'static,
+    {
+        for interleaving_id in 0..self.num_interleavings as u64 {
+            let seed = self.seed.wrapping_add(interleaving_id);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let sender_ids: Vec<usize> = messages
+                .iter()
+                .map(|_| rng.gen_range(0..self.num_senders))
+                .collect();
+            let universe = Universe::with_accelerated_time();
+            let (entry_mailbox, observed_handle) = setup(&universe);
+
+            let mut sender_join_handles = Vec::with_capacity(self.num_senders);
+            for sender_id in 0..self.num_senders {
+                let sender_messages: Vec<M> = messages
+                    .iter()
+                    .zip(&sender_ids)
+                    .filter(|(_, message_sender_id)| **message_sender_id == sender_id)
+                    .map(|(message, _)| message.clone())
+                    .collect();
+                let sender_mailbox = entry_mailbox.clone();
+                let mut sender_rng = StdRng::seed_from_u64(rng.gen());
+                let sender_join_handle = tokio::spawn(async move {
+                    for message in sender_messages {
+                        for _ in 0..sender_rng.gen_range(0..4) {
+                            tokio::task::yield_now().await;
+                        }
+                        if sender_mailbox.send_message(message).await.is_err() {
+                            return;
+                        }
+                    }
+                });
+                sender_join_handles.push(sender_join_handle);
+            }
+            for sender_join_handle in sender_join_handles {
+                sender_join_handle
+                    .await
+                    .expect("The sender task should not panic.");
+            }
+            let _ = universe.send_exit_with_success(&entry_mailbox).await;
+            drop(entry_mailbox);
+
+            let join_res = tokio::time::timeout(TERMINATION_TIMEOUT, observed_handle.join()).await;
+            universe.assert_quit().await;
+            let invariant_res = match join_res {
+                Ok((_exit_status, final_state)) => invariant(messages, &final_state),
+                Err(_) => Err("The pipeline did not terminate.".to_string()),
+            };
+            if let Err(reason) = invariant_res {
+                return Err(InterleavingFailure {
+                    seed,
+                    sender_ids,
+                    reason,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use proptest::prelude::*;
+
+    use super::*;
+    use crate::{ActorContext, ActorExitStatus, Handler};
+
+    #[derive(Default)]
+    struct Recorder {
+        received: Vec<u32>,
+    }
+
+    #[async_trait]
+    impl Actor for Recorder {
+        type ObservableState = Vec<u32>;
+
+        fn observable_state(&self) -> Self::ObservableState {
+            self.received.clone()
+        }
+    }
+
+    #[async_trait]
+    impl Handler<u32> for Recorder {
+        type Reply = ();
+
+        async fn handle(
+            &mut self,
+            message: u32,
+            _ctx: &ActorContext<Self>,
+        ) -> Result<(), ActorExitStatus> {
+            self.received.push(message);
+            Ok(())
+        }
+    }
+
+    fn spawn_recorder(universe: &Universe) -> (Mailbox<Recorder>, ActorHandle<Recorder>) {
+        universe.spawn_builder().spawn(Recorder::default())
+    }
+
+    #[tokio::test]
+    async fn test_interleaving_checker_detects_reorderings() {
+        let messages: Vec<u32> = (0..32).collect();
+        let failure = InterleavingChecker::new(0)
+            .num_interleavings(16)
+            .check(&messages, spawn_recorder, |messages, received| {
+                if received == messages {
+                    Ok(())
+                } else {
+                    Err("Messages were reordered.".to_string())
+                }
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(failure.sender_ids.len(), 32);
+        assert_eq!(failure.reason, "Messages were reordered.");
+    }
+
+    proptest! {
+        #[test]
+        fn proptest_no_message_is_lost(messages in prop::collection::vec(any::<u32>(), 0..50)) {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap();
+            let check_res = runtime.block_on(
+                InterleavingChecker::new(0)
+                    .num_senders(3)
+                    .check(&messages, spawn_recorder, |messages, received| {
+                        let mut expected = messages.to_vec();
+                        let mut received = received.clone();
+                        expected.sort();
+                        received.sort();
+                        if received == expected {
+                            Ok(())
+                        } else {
+                            Err(format!("Expected {expected:?}, got {received:?}."))
+                        }
+                    }),
+            );
+            prop_assert!(check_res.is_ok(), "{}", check_res.unwrap_err());
+        }
+    }
+}
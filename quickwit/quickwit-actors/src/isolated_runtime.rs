@@ -0,0 +1,125 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::runtime::{Handle, Runtime};
+
+/// A tokio runtime with its own worker threads, dedicated to a pipeline or a group of actors.
+///
+/// Actors spawned with `SpawnBuilder::set_isolated_runtime` run on this runtime
+/// rather than on the runtime returned by [`crate::Actor::runtime_handle`], and so do the
+/// actors they spawn in turn. A misbehaving actor blocking its executor then only stalls the
+/// actors sharing its isolated runtime.
+///
+/// The runtime is shut down, without waiting for its tasks, when the last clone is dropped.
+#[derive(Clone)]
+pub struct IsolatedRuntime {
+    inner: Arc<IsolatedRuntimeInner>,
+}
+
+struct IsolatedRuntimeInner {
+    name: String,
+    num_worker_threads: usize,
+    runtime_opt: Option<Runtime>,
+}
+
+impl Drop for IsolatedRuntimeInner {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks until its tasks are done, which panics if it happens
+        // within an async context.
+        if let Some(runtime) = self.runtime_opt.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl fmt::Debug for IsolatedRuntime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IsolatedRuntime")
+            .field("name", &self.inner.name)
+            .field("num_worker_threads", &self.inner.num_worker_threads)
+            .finish()
+    }
+}
+
+impl IsolatedRuntime {
+    /// Starts a runtime with `num_worker_threads` worker threads named `{name}-{id}`.
+    pub fn new(name: impl ToString, num_worker_threads: usize) -> std::io::Result<Self> {
+        let name = name.to_string();
+        let num_worker_threads = num_worker_threads.max(1);
+        let thread_name_prefix = name.clone();
+        let thread_id = AtomicUsize::new(0);
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(num_worker_threads)
+            .thread_name_fn(move || {
+                let id = thread_id.fetch_add(1, Ordering::Relaxed);
+                format!("{thread_name_prefix}-{id}")
+            })
+            .enable_all()
+            .build()?;
+        Ok(IsolatedRuntime {
+            inner: Arc::new(IsolatedRuntimeInner {
+                name,
+                num_worker_threads,
+                runtime_opt: Some(runtime),
+            }),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn num_worker_threads(&self) -> usize {
+        self.inner.num_worker_threads
+    }
+
+    pub fn handle(&self) -> Handle {
+        self.inner
+            .runtime_opt
+            .as_ref()
+            .expect("the runtime should only be taken on drop")
+            .handle()
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_isolated_runtime_runs_tasks_on_its_own_threads() {
+        let isolated_runtime = IsolatedRuntime::new("test-isolated", 2).unwrap();
+        assert_eq!(isolated_runtime.name(), "test-isolated");
+        assert_eq!(isolated_runtime.num_worker_threads(), 2);
+        let thread_name = isolated_runtime
+            .handle()
+            .spawn(async { std::thread::current().name().map(str::to_string) })
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(thread_name.starts_with("test-isolated-"));
+        // Dropping the runtime within an async context must not panic.
+        drop(isolated_runtime);
+    }
+}
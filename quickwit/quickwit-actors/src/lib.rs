@@ -32,20 +32,64 @@ use std::num::NonZeroU64;
 
 use once_cell::sync::Lazy;
 use tokio::time::Duration;
+mod acked_mailbox;
 mod actor;
 mod actor_context;
 mod actor_handle;
 mod actor_state;
+#[cfg(any(test, feature = "testsuite"))]
+mod actor_tester;
+mod addr;
 #[doc(hidden)]
 pub mod channel_with_priority;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod checkpoint_barrier;
 mod command;
+mod config_watcher;
+mod cpu_time;
+mod dead_letter_queue;
+mod dedup_window;
+mod deferred_mailbox;
 mod envelope;
+#[cfg(feature = "failpoints")]
+mod failpoints;
+mod flight_recorder;
+#[cfg(any(test, feature = "testsuite"))]
+mod interleavings;
+mod isolated_runtime;
+mod lifecycle_events;
+mod lock_free_queue;
 mod mailbox;
+mod memory_budget;
+mod message_counters;
+mod message_tracing;
+mod metrics;
+mod metrics_sink;
 mod observation;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod panic_backtrace;
+mod panic_policy;
+mod payload;
+mod pipeline_builder;
+mod processing_time;
+mod rate_limiter;
+mod readiness;
 mod registry;
+mod reported_error;
+mod resource_watchdog;
+mod retry;
+mod scheduled_message_store;
 pub(crate) mod scheduler;
+mod sharded_mailbox;
 mod spawn_builder;
 mod supervisor;
+mod sync;
+mod sync_actor;
+mod timer_wheel;
+mod topology;
+mod watermark;
 
 pub use scheduler::{start_scheduler, SchedulerClient};
 
@@ -53,21 +97,66 @@ pub use scheduler::{start_scheduler, SchedulerClient};
 pub(crate) mod tests;
 mod universe;
 
-pub use actor::{Actor, ActorExitStatus, DeferableReplyHandler, Handler};
+pub use acked_mailbox::{Acked, AckedMailbox};
+pub use actor::{
+    Actor, ActorExitStatus, ActorTermination, DeferableReplyHandler, DrainPolicy, Handler,
+    ResponseHandle, TerminationDetails, UpstreamTerminated,
+};
 pub use actor_handle::{ActorHandle, Health, Healthz, Supervisable};
-pub use command::Command;
-pub use observation::{Observation, ObservationType};
+#[cfg(any(test, feature = "testsuite"))]
+pub use actor_tester::ActorTester;
+pub use addr::Addr;
+#[cfg(feature = "chaos")]
+pub use chaos::{set_chaos_config, ChaosConfig};
+pub use checkpoint_barrier::{CheckpointBarrier, CheckpointBarrierError, CheckpointCompletion};
+pub use command::{Command, CustomCommand};
+pub use config_watcher::{ConfigUpdated, ConfigWatcher};
+pub use cpu_time::CpuUsage;
+pub use dead_letter_queue::DeadLetter;
+pub use dedup_window::DedupWindow;
+pub use deferred_mailbox::DeferredMailbox;
+pub use envelope::CorrelationId;
+#[cfg(feature = "failpoints")]
+pub use failpoints::{FailpointAction, FailpointRegistry};
+pub use flight_recorder::FlightRecord;
+#[cfg(any(test, feature = "testsuite"))]
+pub use interleavings::{InterleavingChecker, InterleavingFailure};
+pub use isolated_runtime::IsolatedRuntime;
+pub use lifecycle_events::{ActorLifecycleEvent, ActorLifecycleEventKind};
+pub use memory_budget::MemoryBudget;
+pub use message_tracing::{set_message_tracing_config, MessageTracingConfig};
+pub use metrics_sink::MetricsSink;
+pub use observation::{FieldDiff, Observation, ObservationDiff, ObservationType};
+pub use panic_backtrace::install_panic_hook;
+pub use panic_policy::PanicPolicy;
+pub use payload::{MessageSize, Payload};
+pub use pipeline_builder::{Pipeline, PipelineBuilder, StageBuilder};
+pub use processing_time::SlowActorReport;
 use quickwit_common::KillSwitch;
+pub use rate_limiter::RateLimiter;
+pub use readiness::{ReadinessError, ReadinessGate};
+pub use reported_error::ReportedError;
+pub use resource_watchdog::{
+    ResourceThresholds, ResourceUsage, ResourceWatchdog, ResourceWatchdogState, WatchdogAction,
+};
+pub use retry::{FailedAttempt, RetryError, RetryPolicy};
+pub use scheduled_message_store::ScheduledMessageStore;
+pub use sharded_mailbox::ShardedMailbox;
 pub use spawn_builder::SpawnContext;
+pub use sync_actor::{SyncActor, SyncActorAdapter, SyncHandler};
 use thiserror::Error;
+pub use topology::PipelineTopology;
 use tracing::info;
 use tracing::log::warn;
 pub use universe::Universe;
+pub use watermark::{Watermark, WatermarkTracker};
 
 pub use self::actor_context::ActorContext;
 pub use self::actor_state::ActorState;
 pub use self::channel_with_priority::{QueueCapacity, RecvError, SendError, TrySendError};
-pub use self::mailbox::{Inbox, Mailbox};
+pub use self::mailbox::{
+    Inbox, Mailbox, MailboxReservation, MailboxSink, QueueDiagnostics, WeakMailbox,
+};
 pub use self::registry::ActorObservation;
 pub use self::supervisor::{Supervisor, SupervisorState};
 
@@ -0,0 +1,70 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use tokio::sync::broadcast;
+
+use crate::ActorExitStatus;
+
+/// Number of events a lagging subscriber can fall behind before missing some of them.
+const LIFECYCLE_EVENT_BUS_CAPACITY: usize = 1_024;
+
+/// Event published on the universe lifecycle event bus.
+/// See `Universe::subscribe_lifecycle_events`.
+#[derive(Clone, Debug)]
+pub struct ActorLifecycleEvent {
+    pub actor_instance_id: String,
+    pub kind: ActorLifecycleEventKind,
+}
+
+#[derive(Clone, Debug)]
+pub enum ActorLifecycleEventKind {
+    Spawned,
+    Paused,
+    Resumed,
+    /// The actor was respawned by its supervisor after failing.
+    Restarted,
+    Terminated(ActorExitStatus),
+}
+
+#[derive(Clone)]
+pub(crate) struct LifecycleEventBus {
+    event_tx: broadcast::Sender<ActorLifecycleEvent>,
+}
+
+impl Default for LifecycleEventBus {
+    fn default() -> Self {
+        let (event_tx, _event_rx) = broadcast::channel(LIFECYCLE_EVENT_BUS_CAPACITY);
+        LifecycleEventBus { event_tx }
+    }
+}
+
+impl LifecycleEventBus {
+    pub fn publish(&self, actor_instance_id: &str, kind: ActorLifecycleEventKind) {
+        let event = ActorLifecycleEvent {
+            actor_instance_id: actor_instance_id.to_string(),
+            kind,
+        };
+        // Sending only fails if there are no subscribers.
+        let _ = self.event_tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ActorLifecycleEvent> {
+        self.event_tx.subscribe()
+    }
+}
@@ -0,0 +1,205 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Unbounded lock-free single-consumer queue, used as an alternative to flume for the low
+//! priority queue of actors processing a very large number of small messages.
+//!
+//! Pushing a message never takes a lock, and the receiver is only woken up if it is
+//! parked waiting for messages. An actor busy processing its backlog is therefore not
+//! woken up once per message: wakeups are batched.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crossbeam_queue::SegQueue;
+use flume::TryRecvError;
+use tokio::sync::Notify;
+
+struct Shared<T> {
+    queue: SegQueue<T>,
+    is_receiver_parked: AtomicBool,
+    is_sender_dropped: AtomicBool,
+    receiver_notify: Notify,
+}
+
+pub(crate) fn lock_free_channel<T>() -> (LockFreeSender<T>, LockFreeReceiver<T>) {
+    let shared = Arc::new(Shared {
+        queue: SegQueue::new(),
+        is_receiver_parked: AtomicBool::new(false),
+        is_sender_dropped: AtomicBool::new(false),
+        receiver_notify: Notify::new(),
+    });
+    let sender = LockFreeSender {
+        shared: shared.clone(),
+    };
+    let receiver = LockFreeReceiver { shared };
+    (sender, receiver)
+}
+
+pub(crate) struct LockFreeSender<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> LockFreeSender<T> {
+    /// Returns true if the receiver was dropped.
+    pub fn is_disconnected(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.queue.len()
+    }
+
+    /// Pushes a message to the queue, and wakes up the receiver if it is parked.
+    ///
+    /// The message is returned if the receiver was dropped.
+    pub fn send(&self, msg: T) -> Result<(), T> {
+        if self.is_disconnected() {
+            return Err(msg);
+        }
+        self.shared.queue.push(msg);
+        self.wake_up_receiver_if_parked();
+        Ok(())
+    }
+
+    fn wake_up_receiver_if_parked(&self) {
+        if self.shared.is_receiver_parked.swap(false, Ordering::SeqCst) {
+            // If the receiver is not awaiting yet, the permit is stored and consumed by its
+            // next call to `notified().await`.
+            self.shared.receiver_notify.notify_one();
+        }
+    }
+}
+
+impl<T> Drop for LockFreeSender<T> {
+    fn drop(&mut self) {
+        self.shared.is_sender_dropped.store(true, Ordering::SeqCst);
+        self.wake_up_receiver_if_parked();
+    }
+}
+
+pub(crate) struct LockFreeReceiver<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> LockFreeReceiver<T> {
+    /// Returns true if the sender was dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.shared.is_sender_dropped.load(Ordering::SeqCst)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.queue.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.shared.queue.len()
+    }
+
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        if let Some(msg) = self.shared.queue.pop() {
+            return Ok(msg);
+        }
+        if self.is_disconnected() {
+            // The sender may have pushed a last message before being dropped.
+            return self.shared.queue.pop().ok_or(TryRecvError::Disconnected);
+        }
+        Err(TryRecvError::Empty)
+    }
+
+    /// Waits for the next message. Returns `None` once the sender is dropped and the queue
+    /// is empty.
+    pub async fn recv(&self) -> Option<T> {
+        loop {
+            match self.try_recv() {
+                Ok(msg) => return Some(msg),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+            self.shared.is_receiver_parked.store(true, Ordering::SeqCst);
+            // We need to check the queue again: a message may have been pushed before the
+            // receiver was flagged as parked.
+            match self.try_recv() {
+                Ok(msg) => {
+                    self.shared
+                        .is_receiver_parked
+                        .store(false, Ordering::SeqCst);
+                    return Some(msg);
+                }
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+            self.shared.receiver_notify.notified().await;
+        }
+    }
+
+    /// Drops all of the pending messages.
+    pub fn clear(&self) {
+        while self.shared.queue.pop().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_lock_free_channel() {
+        let (sender, receiver) = lock_free_channel::<usize>();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        sender.send(1).unwrap();
+        sender.send(2).unwrap();
+        assert_eq!(sender.len(), 2);
+        assert_eq!(receiver.recv().await, Some(1));
+        assert_eq!(receiver.try_recv(), Ok(2));
+        assert!(receiver.is_empty());
+
+        let recv_task = tokio::spawn(async move {
+            let mut received = Vec::new();
+            while let Some(msg) = receiver.recv().await {
+                received.push(msg);
+            }
+            received
+        });
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        for i in 3..1_000 {
+            sender.send(i).unwrap();
+        }
+        drop(sender);
+        let received = recv_task.await.unwrap();
+        assert_eq!(received, (3..1_000).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn test_lock_free_channel_disconnected() {
+        let (sender, receiver) = lock_free_channel::<usize>();
+        sender.send(1).unwrap();
+        drop(sender);
+        assert!(receiver.is_disconnected());
+        assert_eq!(receiver.try_recv(), Ok(1));
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+
+        let (sender, receiver) = lock_free_channel::<usize>();
+        drop(receiver);
+        assert!(sender.is_disconnected());
+        assert_eq!(sender.send(1), Err(1));
+    }
+}
@@ -20,17 +20,27 @@
 use std::any::Any;
 use std::convert::Infallible;
 use std::fmt;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::{Arc, Weak};
-use std::time::Instant;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex, Weak};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+use futures::{pin_mut, ready, Future, Sink, Stream, StreamExt};
 use quickwit_common::metrics::IntCounter;
-use tokio::sync::oneshot;
+use serde::Serialize;
+use tokio::sync::{oneshot, watch};
+use tracing::debug;
 
-use crate::channel_with_priority::{Receiver, Sender, TrySendError};
-use crate::envelope::{wrap_in_envelope, Envelope};
+use crate::channel_with_priority::{LowPriorityReservation, Receiver, Sender, TrySendError};
+use crate::envelope::{wrap_in_envelope, wrap_in_redeliverable_envelope, CorrelationId, Envelope};
+use crate::memory_budget::MemoryPermit;
+use crate::metrics_sink::MetricsSink;
 use crate::scheduler::SchedulerClient;
+use crate::sync::{AtomicUsize, Ordering};
 use crate::{
     Actor, ActorContext, ActorExitStatus, AskError, DeferableReplyHandler, Handler, QueueCapacity,
     RecvError, SendError,
@@ -110,7 +120,7 @@ impl<A: Actor> Handler<LastMailbox> for A {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub(crate) enum Priority {
     High,
     Low,
@@ -140,10 +150,38 @@ impl<A: Actor> Mailbox<A> {
     }
 }
 
+/// Snapshot of the state of the low priority queue of a mailbox.
+///
+/// It is logged when sending a message blocks or fails, in order to help identifying
+/// the actor causing the backpressure or the failure.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct QueueDiagnostics {
+    pub actor_instance_id: String,
+    pub queue_depth: usize,
+    /// `None` if the queue is unbounded.
+    pub queue_capacity_opt: Option<usize>,
+    /// `None` if the queue is empty, or if the ages of the messages are not tracked (see
+    /// `Universe::with_message_age_tracking`).
+    pub oldest_message_age_opt: Option<Duration>,
+}
+
+// Source of the mailbox ids, starting at 1 so that 0 can stand for "no mailbox".
+static NEXT_MAILBOX_ID: AtomicU64 = AtomicU64::new(1);
+
+fn new_mailbox_id() -> u64 {
+    NEXT_MAILBOX_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
 struct Inner<A: Actor> {
     pub(crate) tx: Sender<Envelope<A>>,
     scheduler_client_opt: Option<SchedulerClient>,
+    metrics_sink_opt: Option<Arc<dyn MetricsSink>>,
     instance_id: String,
+    // Unique across all of the mailboxes of the process, unlike the instance id which is shared
+    // by the mailboxes of a multi-source actor.
+    mailbox_id: u64,
+    // Copy of the redeliverable message being processed by the actor.
+    in_flight_envelope: Mutex<Option<Envelope<A>>>,
 }
 
 impl<A: Actor> fmt::Debug for Mailbox<A> {
@@ -159,10 +197,71 @@ impl<A: Actor> Mailbox<A> {
         &self.inner.instance_id
     }
 
+    pub(crate) fn mailbox_id(&self) -> u64 {
+        self.inner.mailbox_id
+    }
+
     pub fn is_disconnected(&self) -> bool {
         self.inner.tx.is_disconnected()
     }
 
+    /// Returns the Debug renderings of the next `num_messages` messages queued in the low
+    /// priority channel of the mailbox, without consuming them.
+    ///
+    /// This is useful to inspect what a stuck actor is waiting to process.
+    #[cfg(feature = "peek-pending")]
+    pub fn peek_pending(&self, num_messages: usize) -> Vec<String> {
+        self.inner.tx.peek_renderings(num_messages)
+    }
+
+    /// Returns the number of messages queued in the low priority queue of the mailbox.
+    pub(crate) fn queue_depth(&self) -> usize {
+        self.inner.tx.low_priority_len()
+    }
+
+    /// Returns a snapshot of the state of the low priority queue of the mailbox.
+    pub fn queue_diagnostics(&self) -> QueueDiagnostics {
+        QueueDiagnostics {
+            actor_instance_id: self.actor_instance_id().to_string(),
+            queue_depth: self.queue_depth(),
+            queue_capacity_opt: self.inner.tx.low_priority_capacity(),
+            oldest_message_age_opt: self.inner.tx.oldest_low_priority_message_age(),
+        }
+    }
+
+    /// Attaches the current state of the queue of the mailbox to a send error.
+    pub(crate) fn attach_queue_diagnostics(&self, send_error: SendError) -> SendError {
+        if send_error.queue_diagnostics().is_some() {
+            return send_error;
+        }
+        SendError::WithQueueDiagnostics {
+            source: Box::new(send_error),
+            queue_diagnostics: Box::new(self.queue_diagnostics()),
+        }
+    }
+
+    /// Returns a watch channel signaling `true` once the number of messages queued in the
+    /// mailbox reaches `high_watermark`, and `false` again once it falls to `low_watermark`.
+    ///
+    /// Sources can use it to pause pulling from their upstream (e.g. Kafka) while the
+    /// downstream actor is saturated, and resume once it has caught up, without
+    /// oscillating on every send.
+    ///
+    /// Calling this method again replaces the watermarks of the mailbox.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `low_watermark` is not lower than `high_watermark`.
+    pub fn watermark_signal(
+        &self,
+        high_watermark: usize,
+        low_watermark: usize,
+    ) -> watch::Receiver<bool> {
+        self.inner
+            .tx
+            .watermark_signal(high_watermark, low_watermark)
+    }
+
     /// Sends a message to the actor owning the associated inbox.
     ///
     /// From an actor context, use the `ActorContext::send_message` method instead.
@@ -191,24 +290,93 @@ impl<A: Actor> Mailbox<A> {
         A: DeferableReplyHandler<M>,
         M: fmt::Debug + Send + 'static,
     {
-        let (envelope, response_rx) = self.wrap_in_envelope(message);
-        self.inner
-            .tx
-            .try_send_low_priority(envelope)
-            .map_err(|err| {
-                match err {
-                    TrySendError::Disconnected => TrySendError::Disconnected,
-                    TrySendError::Full(mut envelope) => {
-                        // We need to un pack the envelope.
-                        let message: M = envelope.message_typed().unwrap();
-                        TrySendError::Full(message)
-                    }
+        let (envelope, response_rx) = self.wrap_in_envelope(message, None);
+        self.try_send_low_priority(envelope).map_err(|err| {
+            match err {
+                TrySendError::Disconnected => TrySendError::Disconnected,
+                TrySendError::Full(mut envelope) => {
+                    // We need to un pack the envelope.
+                    let message: M = envelope.message_typed().unwrap();
+                    TrySendError::Full(message)
                 }
-            })?;
+            }
+        })?;
         Ok(response_rx)
     }
 
-    fn wrap_in_envelope<M>(&self, message: M) -> (Envelope<A>, oneshot::Receiver<A::Reply>)
+    /// Waits until `num_slots` slots of the low priority queue are free, and reserves
+    /// them. The messages sent through the returned reservation are queued without waiting.
+    ///
+    /// This is useful for sources that pull a batch of records from an external system:
+    /// by reserving the slots first, the batch is only pulled once the downstream
+    /// actor is guaranteed to have room for it. Unused slots are released when the
+    /// reservation is dropped.
+    ///
+    /// SendError is returned if the actor has already exited.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_slots` exceeds the capacity of the mailbox.
+    pub async fn reserve(&self, num_slots: usize) -> Result<MailboxReservation<A>, SendError> {
+        let reservation = self.inner.tx.reserve_low_priority(num_slots).await?;
+        Ok(MailboxReservation {
+            mailbox: self.clone(),
+            reservation,
+        })
+    }
+
+    /// Forwards all of the items of a stream to the actor, and sends `end_of_stream_msg`
+    /// once the stream is exhausted.
+    ///
+    /// The next item is only pulled from the stream once the previous one has been
+    /// queued, so a full mailbox applies backpressure to the stream.
+    ///
+    /// If the actor exits before the end of the stream, the forwarding stops and
+    /// `SendError` is returned. Dropping the returned future stops the forwarding
+    /// without sending `end_of_stream_msg`. In that case, the item being sent, if any,
+    /// is lost.
+    pub async fn forward_stream<M, S, EndOfStream>(
+        &self,
+        stream: S,
+        end_of_stream_msg: EndOfStream,
+    ) -> Result<(), SendError>
+    where
+        A: DeferableReplyHandler<M> + DeferableReplyHandler<EndOfStream>,
+        M: fmt::Debug + Send + 'static,
+        S: Stream<Item = M>,
+        EndOfStream: fmt::Debug + Send + 'static,
+    {
+        pin_mut!(stream);
+        while let Some(message) = stream.next().await {
+            self.send_message(message).await?;
+        }
+        self.send_message(end_of_stream_msg).await?;
+        Ok(())
+    }
+
+    /// Returns a [`Sink`] sending messages of type `M` to the actor.
+    ///
+    /// This makes it possible to compose mailboxes with the `futures` ecosystem,
+    /// e.g. `stream.map(Ok).forward(mailbox.sink())`.
+    pub fn sink<M>(&self) -> MailboxSink<A, M>
+    where
+        A: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        MailboxSink {
+            mailbox: self.clone(),
+            pending_send_opt: None,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Wraps the message in an envelope. If no correlation id is passed,
+    /// a new one is generated.
+    fn wrap_in_envelope<M>(
+        &self,
+        message: M,
+        correlation_id_opt: Option<CorrelationId>,
+    ) -> (Envelope<A>, oneshot::Receiver<A::Reply>)
     where
         A: DeferableReplyHandler<M>,
         M: fmt::Debug + Send + 'static,
@@ -218,7 +386,8 @@ impl<A: Actor> Mailbox<A> {
             .scheduler_client_opt
             .as_ref()
             .map(|scheduler_client| scheduler_client.no_advance_time_guard());
-        wrap_in_envelope(message, guard)
+        let correlation_id = correlation_id_opt.unwrap_or_else(CorrelationId::new);
+        wrap_in_envelope(message, correlation_id, guard)
     }
 
     /// Sends a message to the actor owning the associated inbox.
@@ -235,24 +404,220 @@ impl<A: Actor> Mailbox<A> {
         A: DeferableReplyHandler<M>,
         M: fmt::Debug + Send + 'static,
     {
-        let (envelope, response_rx) = self.wrap_in_envelope(message);
-        match self.inner.tx.try_send_low_priority(envelope) {
-            Ok(()) => Ok(response_rx),
+        self.send_message_with_correlation_id(
+            message,
+            None,
+            Priority::Low,
+            backpressure_micros_counter_opt,
+        )
+        .await
+    }
+
+    /// Sends a message that survives the failure of the actor processing it.
+    ///
+    /// If the actor fails or panics while processing the message, the message is processed
+    /// again by the next instance of the actor, for instance after a supervised restart. The
+    /// reply of such attempts is discarded. After `Actor::max_message_attempts` attempts,
+    /// the message is moved to the dead letter queue of the universe.
+    pub async fn send_redeliverable_message<M>(
+        &self,
+        message: M,
+    ) -> Result<oneshot::Receiver<A::Reply>, SendError>
+    where
+        A: DeferableReplyHandler<M>,
+        M: Clone + fmt::Debug + Send + 'static,
+    {
+        let guard = self
+            .inner
+            .scheduler_client_opt
+            .as_ref()
+            .map(|scheduler_client| scheduler_client.no_advance_time_guard());
+        let (envelope, response_rx) =
+            wrap_in_redeliverable_envelope(message, CorrelationId::new(), guard);
+        self.send_envelope_with_backpressure_counter(envelope, None)
+            .await?;
+        Ok(response_rx)
+    }
+
+    pub(crate) fn set_in_flight_envelope(&self, envelope_opt: Option<Envelope<A>>) {
+        *self.inner.in_flight_envelope.lock().unwrap() = envelope_opt;
+    }
+
+    pub(crate) fn take_in_flight_envelope(&self) -> Option<Envelope<A>> {
+        self.inner.in_flight_envelope.lock().unwrap().take()
+    }
+
+    /// Sends a message to the actor owning the associated inbox, tagged with the given
+    /// correlation id. If no correlation id is passed, a new one is generated.
+    ///
+    /// High priority messages skip the queue of regular messages, and the messages sent by the
+    /// actor while processing them are sent with high priority as well.
+    pub(crate) async fn send_message_with_correlation_id<M>(
+        &self,
+        message: M,
+        correlation_id_opt: Option<CorrelationId>,
+        priority: Priority,
+        backpressure_micros_counter_opt: Option<&IntCounter>,
+    ) -> Result<oneshot::Receiver<A::Reply>, SendError>
+    where
+        A: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        let (mut envelope, response_rx) = self.wrap_in_envelope(message, correlation_id_opt);
+        match priority {
+            Priority::High => {
+                envelope.set_priority(Priority::High);
+                self.inner.tx.send_high_priority(envelope)?;
+            }
+            Priority::Low => {
+                self.send_envelope_with_backpressure_counter(
+                    envelope,
+                    backpressure_micros_counter_opt,
+                )
+                .await?;
+            }
+        }
+        Ok(response_rx)
+    }
+
+    /// Sends a message holding a share of the memory budget of the universe. The share is
+    /// released once the message has been processed.
+    pub(crate) async fn send_message_with_memory_permit<M>(
+        &self,
+        message: M,
+        correlation_id_opt: Option<CorrelationId>,
+        backpressure_micros_counter_opt: Option<&IntCounter>,
+        memory_permit: MemoryPermit,
+    ) -> Result<oneshot::Receiver<A::Reply>, SendError>
+    where
+        A: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        let (mut envelope, response_rx) = self.wrap_in_envelope(message, correlation_id_opt);
+        envelope.attach_memory_permit(memory_permit);
+        self.send_envelope_with_backpressure_counter(envelope, backpressure_micros_counter_opt)
+            .await?;
+        Ok(response_rx)
+    }
+
+    async fn send_envelope_with_backpressure_counter(
+        &self,
+        envelope: Envelope<A>,
+        backpressure_micros_counter_opt: Option<&IntCounter>,
+    ) -> Result<(), SendError> {
+        match self.try_send_low_priority(envelope) {
+            Ok(()) => Ok(()),
             Err(TrySendError::Full(envelope)) => {
+                debug!(queue_diagnostics=?self.queue_diagnostics(), "send-message-blocked");
+                let now = Instant::now();
+                self.send_low_priority(envelope)
+                    .await
+                    .map_err(|send_error| self.attach_queue_diagnostics(send_error))?;
                 if let Some(backpressure_micros_counter) = backpressure_micros_counter_opt {
-                    let now = Instant::now();
-                    self.inner.tx.send_low_priority(envelope).await?;
                     let elapsed = now.elapsed();
                     backpressure_micros_counter.inc_by(elapsed.as_micros() as u64);
-                } else {
-                    self.inner.tx.send_low_priority(envelope).await?;
                 }
-                Ok(response_rx)
+                Ok(())
             }
-            Err(TrySendError::Disconnected) => Err(SendError::Disconnected),
+            Err(TrySendError::Disconnected) => {
+                Err(self.attach_queue_diagnostics(SendError::Disconnected))
+            }
+        }
+    }
+
+    /// Sends an envelope as is, preserving its correlation id and its reply channel.
+    pub(crate) async fn send_envelope(&self, envelope: Envelope<A>) -> Result<(), SendError> {
+        self.send_low_priority(envelope).await
+    }
+
+    fn try_send_low_priority(
+        &self,
+        envelope: Envelope<A>,
+    ) -> Result<(), TrySendError<Envelope<A>>> {
+        // We cannot wait in this synchronous path, so only drops are injected here.
+        #[cfg(feature = "chaos")]
+        if crate::chaos::chaos_action(envelope.message_type_name())
+            == crate::chaos::ChaosAction::Drop
+        {
+            return Ok(());
+        }
+        #[cfg(feature = "peek-pending")]
+        let rendering = format!("{envelope:?}");
+        let message_type = envelope.message_type_name();
+        self.inner.tx.try_send_low_priority(envelope)?;
+        #[cfg(feature = "peek-pending")]
+        self.inner.tx.record_rendering(rendering);
+        self.record_enqueue(message_type);
+        Ok(())
+    }
+
+    fn send_reserved_low_priority(
+        &self,
+        reservation: &mut LowPriorityReservation,
+        envelope: Envelope<A>,
+    ) -> Result<(), TrySendError<Envelope<A>>> {
+        // The slot is not consumed when the message is dropped.
+        #[cfg(feature = "chaos")]
+        if crate::chaos::chaos_action(envelope.message_type_name())
+            == crate::chaos::ChaosAction::Drop
+        {
+            return Ok(());
+        }
+        #[cfg(feature = "peek-pending")]
+        let rendering = format!("{envelope:?}");
+        let message_type = envelope.message_type_name();
+        self.inner
+            .tx
+            .send_reserved_low_priority(reservation, envelope)?;
+        #[cfg(feature = "peek-pending")]
+        self.inner.tx.record_rendering(rendering);
+        self.record_enqueue(message_type);
+        Ok(())
+    }
+
+    async fn send_low_priority(&self, envelope: Envelope<A>) -> Result<(), SendError> {
+        #[cfg(feature = "chaos")]
+        match crate::chaos::chaos_action(envelope.message_type_name()) {
+            crate::chaos::ChaosAction::Deliver => {}
+            crate::chaos::ChaosAction::Delay(delay) => self.chaos_sleep(delay).await,
+            crate::chaos::ChaosAction::Reorder(delay) => {
+                let mailbox = self.clone();
+                tokio::spawn(async move {
+                    mailbox.chaos_sleep(delay).await;
+                    let _ = mailbox.inner.tx.send_low_priority(envelope).await;
+                });
+                return Ok(());
+            }
+            crate::chaos::ChaosAction::Drop => return Ok(()),
+        }
+        #[cfg(feature = "peek-pending")]
+        let rendering = format!("{envelope:?}");
+        let message_type = envelope.message_type_name();
+        self.inner.tx.send_low_priority(envelope).await?;
+        #[cfg(feature = "peek-pending")]
+        self.inner.tx.record_rendering(rendering);
+        self.record_enqueue(message_type);
+        Ok(())
+    }
+
+    fn record_enqueue(&self, message_type: &'static str) {
+        if let Some(metrics_sink) = &self.inner.metrics_sink_opt {
+            metrics_sink.record_enqueue(self.actor_instance_id(), message_type);
+        }
+    }
+
+    #[cfg(feature = "chaos")]
+    async fn chaos_sleep(&self, delay: Duration) {
+        if let Some(scheduler_client) = self.scheduler_client() {
+            scheduler_client.sleep(delay).await;
+        } else {
+            tokio::time::sleep(delay).await;
         }
     }
 
+    /// Sends a control message (command, observation request, scheduled message, ...) to
+    /// the high priority queue. The high priority queue is unbounded, so that control
+    /// messages are never dropped.
     pub(crate) fn send_message_with_high_priority<M>(
         &self,
         message: M,
@@ -261,7 +626,7 @@ impl<A: Actor> Mailbox<A> {
         A: DeferableReplyHandler<M>,
         M: fmt::Debug + Send + 'static,
     {
-        let (envelope, response_rx) = self.wrap_in_envelope(message);
+        let (envelope, response_rx) = self.wrap_in_envelope(message, None);
         self.inner.tx.send_high_priority(envelope)?;
         Ok(response_rx)
     }
@@ -275,11 +640,11 @@ impl<A: Actor> Mailbox<A> {
         A: DeferableReplyHandler<M>,
         M: fmt::Debug + Send + 'static,
     {
-        let (envelope, response_rx) = self.wrap_in_envelope(message);
+        let (envelope, response_rx) = self.wrap_in_envelope(message, None);
         match priority {
             Priority::High => self.inner.tx.send_high_priority(envelope)?,
             Priority::Low => {
-                self.inner.tx.send_low_priority(envelope).await?;
+                self.send_low_priority(envelope).await?;
             }
         }
         Ok(response_rx)
@@ -325,6 +690,36 @@ impl<A: Actor> Mailbox<A> {
             .map_err(|_| AskError::ProcessMessageError)
     }
 
+    /// Similar to `ask`, except the message skips the queue of regular messages, as commands
+    /// do. The messages sent and asks issued by the actor while processing it inherit its
+    /// priority, so that latency-critical requests do not queue behind bulk work at each hop.
+    ///
+    /// High priority messages are processed even when the actor is paused, and are not subject
+    /// to backpressure. Use sparingly.
+    pub async fn ask_with_high_priority<M, T>(&self, message: M) -> Result<T, AskError<Infallible>>
+    where
+        A: DeferableReplyHandler<M, Reply = T>,
+        M: fmt::Debug + Send + 'static,
+    {
+        self.send_message_with_correlation_id(message, None, Priority::High, None)
+            .await
+            .map_err(|_send_error| AskError::MessageNotDelivered)?
+            .await
+            .map_err(|_| AskError::ProcessMessageError)
+    }
+
+    /// Blocking version of `ask`, for callers running outside of an async context,
+    /// typically a `SyncActor` handler.
+    ///
+    /// Calling this method from an async context blocks the executor thread.
+    pub fn ask_blocking<M, T>(&self, message: M) -> Result<T, AskError<Infallible>>
+    where
+        A: DeferableReplyHandler<M, Reply = T>,
+        M: fmt::Debug + Send + 'static,
+    {
+        futures::executor::block_on(self.ask(message))
+    }
+
     /// Similar to `send_message`, except this method
     /// waits asynchronously for the actor reply.
     ///
@@ -344,6 +739,55 @@ impl<A: Actor> Mailbox<A> {
     }
 }
 
+/// Slots of a mailbox reserved with [`Mailbox::reserve`].
+///
+/// The slots that were not used are released when the reservation is dropped.
+pub struct MailboxReservation<A: Actor> {
+    mailbox: Mailbox<A>,
+    reservation: LowPriorityReservation,
+}
+
+impl<A: Actor> MailboxReservation<A> {
+    /// Returns the number of reserved slots that have not been used yet.
+    pub fn num_reserved_slots(&self) -> usize {
+        self.reservation.num_reserved_slots()
+    }
+
+    /// Queues a message in one of the reserved slots, without waiting.
+    ///
+    /// Returns `TrySendError::Full(message)` once all of the slots have been used, and
+    /// `TrySendError::Disconnected` if the actor has exited.
+    pub fn send_message<M>(
+        &mut self,
+        message: M,
+    ) -> Result<oneshot::Receiver<A::Reply>, TrySendError<M>>
+    where
+        A: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        let (envelope, response_rx) = self.mailbox.wrap_in_envelope(message, None);
+        self.mailbox
+            .send_reserved_low_priority(&mut self.reservation, envelope)
+            .map_err(|err| match err {
+                TrySendError::Disconnected => TrySendError::Disconnected,
+                TrySendError::Full(mut envelope) => {
+                    let message: M = envelope.message_typed().unwrap();
+                    TrySendError::Full(message)
+                }
+            })?;
+        Ok(response_rx)
+    }
+}
+
+impl<A: Actor> fmt::Debug for MailboxReservation<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MailboxReservation")
+            .field("actor_instance_id", &self.mailbox.actor_instance_id())
+            .field("num_reserved_slots", &self.num_reserved_slots())
+            .finish()
+    }
+}
+
 pub struct Inbox<A: Actor> {
     rx: Arc<Receiver<Envelope<A>>>,
 }
@@ -356,11 +800,41 @@ impl<A: Actor> Clone for Inbox<A> {
     }
 }
 
+/// Weak reference to an `Inbox`, which does not prevent its queue from being dropped.
+pub(crate) struct WeakInbox<A: Actor> {
+    rx: Weak<Receiver<Envelope<A>>>,
+}
+
+impl<A: Actor> Clone for WeakInbox<A> {
+    fn clone(&self) -> Self {
+        WeakInbox {
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<A: Actor> WeakInbox<A> {
+    pub fn upgrade(&self) -> Option<Inbox<A>> {
+        let rx = self.rx.upgrade()?;
+        Some(Inbox { rx })
+    }
+}
+
 impl<A: Actor> Inbox<A> {
+    pub(crate) fn downgrade(&self) -> WeakInbox<A> {
+        WeakInbox {
+            rx: Arc::downgrade(&self.rx),
+        }
+    }
+
     pub(crate) fn is_empty(&self) -> bool {
         self.rx.is_empty()
     }
 
+    pub(crate) fn drain_low_priority(&self) -> Vec<Envelope<A>> {
+        self.rx.drain_low_priority()
+    }
+
     pub(crate) async fn recv(&self) -> Result<Envelope<A>, RecvError> {
         self.rx.recv().await
     }
@@ -418,14 +892,23 @@ pub(crate) fn create_mailbox<A: Actor>(
     actor_name: String,
     queue_capacity: QueueCapacity,
     scheduler_client_opt: Option<SchedulerClient>,
+    metrics_sink_opt: Option<Arc<dyn MetricsSink>>,
+    track_message_ages: bool,
 ) -> (Mailbox<A>, Inbox<A>) {
-    let (tx, rx) = crate::channel_with_priority::channel(queue_capacity);
+    let (mut txs, rx) =
+        crate::channel_with_priority::multi_source_channel(&[queue_capacity], track_message_ages);
+    let tx = txs
+        .pop()
+        .expect("The channel should have exactly one source.");
     let ref_count = Arc::new(AtomicUsize::new(1));
     let mailbox = Mailbox {
         inner: Arc::new(Inner {
             tx,
             instance_id: quickwit_common::new_coolid(&actor_name),
+            mailbox_id: new_mailbox_id(),
             scheduler_client_opt,
+            metrics_sink_opt,
+            in_flight_envelope: Mutex::default(),
         }),
         ref_count,
     };
@@ -433,6 +916,102 @@ pub(crate) fn create_mailbox<A: Actor>(
     (mailbox, inbox)
 }
 
+/// Creates several mailboxes feeding the same inbox, each with its own queue capacity.
+///
+/// This is useful for actors consuming messages from different sources (e.g. a data
+/// stream and a control channel driven by another subsystem): the inbox consumes the
+/// sources in a fair, round-robin fashion, and a saturated source only applies
+/// backpressure to its own senders.
+///
+/// All of the mailboxes share the same actor instance id, and the actor is
+/// considered orphan only once all of them have been dropped.
+pub(crate) fn create_multi_source_mailbox<A: Actor>(
+    actor_name: String,
+    queue_capacities: &[QueueCapacity],
+    scheduler_client_opt: Option<SchedulerClient>,
+    metrics_sink_opt: Option<Arc<dyn MetricsSink>>,
+    track_message_ages: bool,
+) -> (Vec<Mailbox<A>>, Inbox<A>) {
+    let (txs, rx) =
+        crate::channel_with_priority::multi_source_channel(queue_capacities, track_message_ages);
+    let instance_id = quickwit_common::new_coolid(&actor_name);
+    let ref_count = Arc::new(AtomicUsize::new(txs.len()));
+    let mailboxes = txs
+        .into_iter()
+        .map(|tx| Mailbox {
+            inner: Arc::new(Inner {
+                tx,
+                instance_id: instance_id.clone(),
+                mailbox_id: new_mailbox_id(),
+                scheduler_client_opt: scheduler_client_opt.clone(),
+                metrics_sink_opt: metrics_sink_opt.clone(),
+                in_flight_envelope: Mutex::default(),
+            }),
+            ref_count: ref_count.clone(),
+        })
+        .collect();
+    let inbox = Inbox { rx: Arc::new(rx) };
+    (mailboxes, inbox)
+}
+
+/// A [`Sink`] sending messages to an actor, obtained via [`Mailbox::sink`].
+///
+/// The sink buffers at most one message: if the mailbox is full, the message is
+/// held by the sink and `poll_ready` returns `Poll::Pending` until it has been
+/// queued in the mailbox.
+pub struct MailboxSink<A: Actor, M> {
+    mailbox: Mailbox<A>,
+    pending_send_opt: Option<BoxFuture<'static, Result<(), SendError>>>,
+    _phantom: PhantomData<fn(M)>,
+}
+
+impl<A, M> Sink<M> for MailboxSink<A, M>
+where
+    A: DeferableReplyHandler<M>,
+    M: fmt::Debug + Send + 'static,
+{
+    type Error = SendError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        if let Some(pending_send) = self.pending_send_opt.as_mut() {
+            ready!(pending_send.as_mut().poll(cx))?;
+            self.pending_send_opt = None;
+        }
+        if self.mailbox.is_disconnected() {
+            return Poll::Ready(Err(SendError::Disconnected));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, message: M) -> Result<(), SendError> {
+        assert!(
+            self.pending_send_opt.is_none(),
+            "`poll_ready` must return `Poll::Ready(Ok(()))` before calling `start_send`."
+        );
+        match self.mailbox.try_send_message(message) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(message)) => {
+                let mailbox = self.mailbox.clone();
+                let pending_send = async move {
+                    mailbox.send_message(message).await?;
+                    Ok(())
+                };
+                self.pending_send_opt = Some(Box::pin(pending_send));
+                Ok(())
+            }
+            Err(TrySendError::Disconnected) => Err(SendError::Disconnected),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.poll_ready(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.poll_flush(cx)
+    }
+}
+
 pub struct WeakMailbox<A: Actor> {
     inner: Weak<Inner<A>>,
     ref_count: Weak<AtomicUsize>,
@@ -451,6 +1030,8 @@ mod tests {
     use std::mem;
     use std::time::Duration;
 
+    use futures::{FutureExt, SinkExt};
+
     use super::*;
     use crate::tests::{Ping, PingReceiverActor};
     use crate::Universe;
@@ -602,6 +1183,210 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_queue_diagnostics() {
+        let universe = Universe::with_accelerated_time().with_message_age_tracking();
+        let (mailbox, _inbox) = universe
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Bounded(2));
+        let queue_diagnostics = mailbox.queue_diagnostics();
+        assert_eq!(
+            queue_diagnostics.actor_instance_id,
+            mailbox.actor_instance_id()
+        );
+        assert_eq!(queue_diagnostics.queue_depth, 0);
+        assert_eq!(queue_diagnostics.queue_capacity_opt, Some(2));
+        assert!(queue_diagnostics.oldest_message_age_opt.is_none());
+
+        mailbox.try_send_message(Ping).unwrap();
+        let queue_diagnostics = mailbox.queue_diagnostics();
+        assert_eq!(queue_diagnostics.queue_depth, 1);
+        assert!(queue_diagnostics.oldest_message_age_opt.is_some());
+
+        // The ages of the messages are not tracked by default.
+        let universe_without_tracking = Universe::with_accelerated_time();
+        let (mailbox, _inbox) = universe_without_tracking
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Bounded(2));
+        mailbox.try_send_message(Ping).unwrap();
+        let queue_diagnostics = mailbox.queue_diagnostics();
+        assert_eq!(queue_diagnostics.queue_depth, 1);
+        assert!(queue_diagnostics.oldest_message_age_opt.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_multi_source_mailbox() {
+        let universe = Universe::with_accelerated_time();
+        let (mut mailboxes, inbox) = universe.create_multi_source_mailbox::<PingReceiverActor>(
+            "hello",
+            &[QueueCapacity::Bounded(1), QueueCapacity::Bounded(1)],
+        );
+        let control_mailbox = mailboxes.pop().unwrap();
+        let data_mailbox = mailboxes.pop().unwrap();
+        assert_eq!(
+            data_mailbox.actor_instance_id(),
+            control_mailbox.actor_instance_id()
+        );
+
+        // A saturated source does not prevent the other source from accepting messages.
+        data_mailbox.try_send_message(Ping).unwrap();
+        assert!(matches!(
+            data_mailbox.try_send_message(Ping).unwrap_err(),
+            TrySendError::Full(Ping)
+        ));
+        control_mailbox.try_send_message(Ping).unwrap();
+
+        let (data_mailbox, handle) = universe
+            .spawn_builder()
+            .set_mailboxes(data_mailbox, inbox)
+            .spawn(PingReceiverActor::default());
+        data_mailbox.send_message(Ping).await.unwrap();
+        control_mailbox.send_message(Ping).await.unwrap();
+        assert_eq!(handle.process_pending_and_observe().await.state, 4);
+
+        // The actor exits once all of the mailboxes have been dropped.
+        mem::drop(data_mailbox);
+        mem::drop(control_mailbox);
+        let (exit_status, ping_count) = handle.join().await;
+        assert!(exit_status.is_success());
+        assert_eq!(ping_count, 4);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_forward_stream() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Bounded(1));
+        let (mailbox, handle) = universe
+            .spawn_builder()
+            .set_mailboxes(mailbox, inbox)
+            .spawn(PingReceiverActor::default());
+        let ping_stream = futures::stream::iter(std::iter::repeat_with(|| Ping).take(10));
+        mailbox.forward_stream(ping_stream, Ping).await.unwrap();
+        assert_eq!(handle.process_pending_and_observe().await.state, 11);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_sink() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Bounded(1));
+        let mut sink = mailbox.sink::<Ping>();
+        sink.send(Ping).await.unwrap();
+        // The mailbox is full: the message is held by the sink until there is room
+        // for it.
+        assert!(sink.send(Ping).now_or_never().is_none());
+        assert_eq!(inbox.drain_for_test_typed::<Ping>().len(), 1);
+        sink.flush().await.unwrap();
+        assert_eq!(inbox.drain_for_test_typed::<Ping>().len(), 1);
+
+        mem::drop(inbox);
+        assert!(matches!(
+            sink.send(Ping).await.unwrap_err(),
+            SendError::Disconnected
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_reserve() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Bounded(3));
+        let mut reservation = mailbox.reserve(2).await.unwrap();
+        assert_eq!(reservation.num_reserved_slots(), 2);
+        // Only one slot is left for regular senders.
+        mailbox.try_send_message(Ping).unwrap();
+        assert!(matches!(
+            mailbox.try_send_message(Ping).unwrap_err(),
+            TrySendError::Full(Ping)
+        ));
+        // Waiting for more slots than available blocks until some are released.
+        assert!(mailbox.reserve(2).now_or_never().is_none());
+
+        reservation.send_message(Ping).unwrap();
+        assert_eq!(reservation.num_reserved_slots(), 1);
+        assert_eq!(inbox.drain_for_test_typed::<Ping>().len(), 2);
+
+        // The unused slot is released when the reservation is dropped.
+        mem::drop(reservation);
+        let mut reservation = mailbox.reserve(3).await.unwrap();
+        for _ in 0..3 {
+            reservation.send_message(Ping).unwrap();
+        }
+        assert!(matches!(
+            reservation.send_message(Ping).unwrap_err(),
+            TrySendError::Full(Ping)
+        ));
+        assert_eq!(inbox.drain_for_test_typed::<Ping>().len(), 3);
+
+        mem::drop(inbox);
+        assert!(matches!(
+            mailbox.reserve(1).await.unwrap_err(),
+            SendError::Disconnected
+        ));
+    }
+
+    #[cfg(feature = "peek-pending")]
+    #[tokio::test]
+    async fn test_mailbox_peek_pending() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Unbounded);
+        assert!(mailbox.peek_pending(2).is_empty());
+        mailbox.send_message(Ping).await.unwrap();
+        mailbox.send_message(Ping).await.unwrap();
+        mailbox.send_message(Ping).await.unwrap();
+        let pending_messages = mailbox.peek_pending(2);
+        assert_eq!(pending_messages.len(), 2);
+        assert!(pending_messages[0].contains("Ping"));
+        assert_eq!(inbox.drain_for_test_typed::<Ping>().len(), 3);
+        assert!(mailbox.peek_pending(2).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mailbox_watermark_signal() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+        let mut watermark_rx = mailbox.watermark_signal(2, 0);
+        handle.pause();
+        mailbox.send_message(Ping).await.unwrap();
+        assert!(!*watermark_rx.borrow_and_update());
+        mailbox.send_message(Ping).await.unwrap();
+        assert!(*watermark_rx.borrow_and_update());
+        handle.resume();
+        watermark_rx.changed().await.unwrap();
+        assert!(!*watermark_rx.borrow());
+        assert_eq!(handle.process_pending_and_observe().await.state, 2);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_stream_forward_to_mailbox_sink() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+        futures::stream::iter(std::iter::repeat_with(|| Ok(Ping)).take(10))
+            .forward(mailbox.sink())
+            .await
+            .unwrap();
+        assert_eq!(handle.process_pending_and_observe().await.state, 10);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_forward_stream_disconnected() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, inbox) = universe
+            .create_mailbox::<PingReceiverActor>("hello".to_string(), QueueCapacity::Bounded(1));
+        mem::drop(inbox);
+        let ping_stream = futures::stream::iter(std::iter::repeat_with(|| Ping));
+        let send_error = mailbox.forward_stream(ping_stream, Ping).await.unwrap_err();
+        assert!(send_error.is_disconnected());
+        assert_eq!(
+            send_error.queue_diagnostics().unwrap().actor_instance_id,
+            mailbox.actor_instance_id()
+        );
+    }
+
     #[tokio::test]
     async fn test_try_send_disconnect() {
         let universe = Universe::with_accelerated_time();
@@ -615,3 +1400,30 @@ mod tests {
         ));
     }
 }
+
+#[cfg(loom)]
+mod loom_tests {
+    use super::*;
+    use crate::tests::PingReceiverActor;
+
+    #[test]
+    fn loom_mailbox_ref_count() {
+        loom::model(|| {
+            let (mailbox, _inbox) = create_mailbox::<PingReceiverActor>(
+                "loom".to_string(),
+                QueueCapacity::Unbounded,
+                None,
+                None,
+                false,
+            );
+            let mailbox_clone = mailbox.clone();
+            let clone_and_drop_thread = loom::thread::spawn(move || {
+                drop(mailbox_clone.clone());
+                drop(mailbox_clone);
+            });
+            drop(mailbox.clone());
+            clone_and_drop_thread.join().unwrap();
+            assert!(mailbox.is_last_mailbox());
+        });
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::Notify;
+
+/// Bounds the cumulative size of the messages in flight across all of the mailboxes of a
+/// universe.
+///
+/// Sources send their batches with `ActorContext::send_sized_message`, which waits while the
+/// budget is exhausted. The stages downstream of the sources forward their output with
+/// `ActorContext::forward_sized_message`, which accounts for it without ever waiting: a stage
+/// waiting on the budget while the messages it holds are the ones exhausting it would
+/// deadlock. Either way, the size of a message is released once it has been processed (or
+/// dropped).
+///
+/// This applies backpressure at the sources of the pipelines rather than letting a slow stage
+/// accumulate messages until the process runs out of memory. The bound is soft: downstream
+/// stages may overshoot it by the messages they are forwarding.
+pub struct MemoryBudget {
+    capacity_bytes: usize,
+    in_flight_bytes: AtomicUsize,
+    released: Notify,
+}
+
+/// Share of the memory budget held by a message in flight.
+pub(crate) struct MemoryPermit {
+    memory_budget: Arc<MemoryBudget>,
+    num_bytes: usize,
+}
+
+impl Drop for MemoryPermit {
+    fn drop(&mut self) {
+        self.memory_budget
+            .in_flight_bytes
+            .fetch_sub(self.num_bytes, Ordering::Release);
+        self.memory_budget.released.notify_waiters();
+    }
+}
+
+impl MemoryBudget {
+    pub fn new(capacity_bytes: usize) -> Self {
+        MemoryBudget {
+            capacity_bytes,
+            in_flight_bytes: AtomicUsize::new(0),
+            released: Notify::new(),
+        }
+    }
+
+    pub fn capacity_bytes(&self) -> usize {
+        self.capacity_bytes
+    }
+
+    /// Returns the cumulative size of the messages in flight.
+    pub fn in_flight_bytes(&self) -> usize {
+        self.in_flight_bytes.load(Ordering::Acquire)
+    }
+
+    /// Waits until `num_bytes` fit in the budget and reserves them.
+    ///
+    /// Messages larger than the whole budget are admitted once nothing else is in flight, so
+    /// that they are still delivered eventually.
+    pub(crate) async fn acquire(self: &Arc<Self>, num_bytes: usize) -> MemoryPermit {
+        loop {
+            let released = self.released.notified();
+            tokio::pin!(released);
+            // Registers the waiter before checking the budget so that a release happening in
+            // between is not missed.
+            released.as_mut().enable();
+            let in_flight_bytes = self.in_flight_bytes();
+            if in_flight_bytes == 0 || in_flight_bytes + num_bytes <= self.capacity_bytes {
+                return self.reserve(num_bytes);
+            }
+            released.await;
+        }
+    }
+
+    /// Reserves `num_bytes` without waiting, even if the budget is exhausted.
+    pub(crate) fn reserve(self: &Arc<Self>, num_bytes: usize) -> MemoryPermit {
+        self.in_flight_bytes.fetch_add(num_bytes, Ordering::AcqRel);
+        MemoryPermit {
+            memory_budget: self.clone(),
+            num_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_memory_budget() {
+        let memory_budget = Arc::new(MemoryBudget::new(100));
+        let first_permit = memory_budget.acquire(60).await;
+        assert_eq!(memory_budget.in_flight_bytes(), 60);
+        let memory_budget_clone = memory_budget.clone();
+        let mut acquire_task =
+            tokio::spawn(async move { memory_budget_clone.acquire(1_000).await });
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut acquire_task)
+                .await
+                .is_err()
+        );
+        drop(first_permit);
+        let second_permit = acquire_task.await.unwrap();
+        assert_eq!(memory_budget.in_flight_bytes(), 1_000);
+        // Reserving never waits, even if the budget is exhausted.
+        let third_permit = memory_budget.reserve(10);
+        assert_eq!(memory_budget.in_flight_bytes(), 1_010);
+        drop(second_permit);
+        drop(third_permit);
+        assert_eq!(memory_budget.in_flight_bytes(), 0);
+    }
+}
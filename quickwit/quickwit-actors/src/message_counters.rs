@@ -0,0 +1,158 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use once_cell::sync::OnceCell;
+
+/// Actors handle a handful of message types. The messages of the types beyond this limit
+/// are counted together.
+const MAX_NUM_MESSAGE_TYPES: usize = 16;
+
+/// Key under which the messages of the types beyond `MAX_NUM_MESSAGE_TYPES` are counted.
+const OTHER_MESSAGE_TYPES: &str = "other";
+
+#[derive(Default)]
+struct MessageCounter {
+    message_type_name: OnceCell<&'static str>,
+    count: AtomicU64,
+}
+
+/// Counts the messages processed by an actor, broken down by message type.
+///
+/// Recording a message is lock-free: message types claim one of a fixed number of slots
+/// the first time a message of that type is processed.
+#[derive(Default)]
+pub(crate) struct MessageCounters {
+    counters: [MessageCounter; MAX_NUM_MESSAGE_TYPES],
+    num_other_messages: AtomicU64,
+}
+
+impl MessageCounters {
+    pub fn record(&self, message_type_name: &'static str) {
+        for counter in &self.counters {
+            let slot_message_type_name =
+                *counter.message_type_name.get_or_init(|| message_type_name);
+            if slot_message_type_name == message_type_name {
+                counter.count.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+        }
+        self.num_other_messages.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the total number of messages processed so far.
+    pub fn total(&self) -> u64 {
+        self.counters
+            .iter()
+            .map(|counter| counter.count.load(Ordering::Relaxed))
+            .sum::<u64>()
+            + self.num_other_messages.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of messages processed so far, keyed by the message type name
+    /// stripped from its module path (e.g. `ProcessedDocBatch`).
+    pub fn snapshot(&self) -> BTreeMap<String, u64> {
+        let mut snapshot = BTreeMap::new();
+        for counter in &self.counters {
+            let Some(message_type_name) = counter.message_type_name.get() else {
+                break;
+            };
+            *snapshot
+                .entry(short_type_name(message_type_name))
+                .or_default() += counter.count.load(Ordering::Relaxed);
+        }
+        let num_other_messages = self.num_other_messages.load(Ordering::Relaxed);
+        if num_other_messages > 0 {
+            *snapshot.entry(OTHER_MESSAGE_TYPES.to_string()).or_default() += num_other_messages;
+        }
+        snapshot
+    }
+}
+
+/// Strips the module paths from a type name, including the ones of its generic parameters.
+///
+/// For instance, `alloc::vec::Vec<my_crate::Doc>` becomes `Vec<Doc>`.
+fn short_type_name(type_name: &str) -> String {
+    let mut short_type_name = String::with_capacity(type_name.len());
+    let mut segment_start = 0;
+    for (pos, c) in type_name.char_indices() {
+        if matches!(c, '<' | '>' | ',' | ' ' | '(' | ')' | '[' | ']' | ';' | '&') {
+            short_type_name.push_str(strip_module_path(&type_name[segment_start..pos]));
+            short_type_name.push(c);
+            segment_start = pos + c.len_utf8();
+        }
+    }
+    short_type_name.push_str(strip_module_path(&type_name[segment_start..]));
+    short_type_name
+}
+
+fn strip_module_path(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_type_name() {
+        assert_eq!(short_type_name("u64"), "u64");
+        assert_eq!(short_type_name("quickwit_actors::Command"), "Command");
+        assert_eq!(
+            short_type_name("alloc::vec::Vec<quickwit_indexing::models::ProcessedDoc>"),
+            "Vec<ProcessedDoc>"
+        );
+        assert_eq!(
+            short_type_name("(core::option::Option<a::B>, [c::D; 2])"),
+            "(Option<B>, [D; 2])"
+        );
+    }
+
+    #[test]
+    fn test_message_counters() {
+        let message_counters = MessageCounters::default();
+        assert!(message_counters.snapshot().is_empty());
+        message_counters.record("crate_a::Commit");
+        message_counters.record("crate_a::Batch");
+        message_counters.record("crate_a::Batch");
+        message_counters.record("crate_b::Batch");
+        let snapshot = message_counters.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot["Batch"], 3);
+        assert_eq!(snapshot["Commit"], 1);
+    }
+
+    #[test]
+    fn test_message_counters_beyond_max_num_message_types() {
+        let message_counters = MessageCounters::default();
+        let message_type_names: Vec<&'static str> = (0..MAX_NUM_MESSAGE_TYPES + 2)
+            .map(|i| &*Box::leak(format!("Message{i}").into_boxed_str()))
+            .collect();
+        for message_type_name in &message_type_names {
+            message_counters.record(message_type_name);
+        }
+        message_counters.record(message_type_names[0]);
+        let snapshot = message_counters.snapshot();
+        assert_eq!(snapshot.len(), MAX_NUM_MESSAGE_TYPES + 1);
+        assert_eq!(snapshot["Message0"], 2);
+        assert_eq!(snapshot[OTHER_MESSAGE_TYPES], 2);
+        assert_eq!(message_counters.total(), MAX_NUM_MESSAGE_TYPES as u64 + 3);
+    }
+}
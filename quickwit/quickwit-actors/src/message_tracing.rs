@@ -0,0 +1,149 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! Sampling and truncation of the debug logs emitted for every message sent or received.
+//!
+//! Rendering every message with its `Debug` implementation is prohibitively expensive at
+//! high throughput, so these logs can be sampled and truncated at runtime.
+
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use tracing::Level;
+
+/// Controls the debug logs emitted for the messages sent and received by actors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MessageTracingConfig {
+    /// Only one message out of `sample_one_in` is logged. 0 disables the logs entirely.
+    pub sample_one_in: u64,
+    /// Renderings of the messages longer than this number of bytes are truncated.
+    pub max_message_debug_len: usize,
+}
+
+impl Default for MessageTracingConfig {
+    fn default() -> Self {
+        MessageTracingConfig {
+            sample_one_in: 1,
+            max_message_debug_len: 1_024,
+        }
+    }
+}
+
+static SAMPLE_ONE_IN: AtomicU64 = AtomicU64::new(1);
+static MAX_MESSAGE_DEBUG_LEN: AtomicUsize = AtomicUsize::new(1_024);
+static NUM_MESSAGES: AtomicU64 = AtomicU64::new(0);
+
+/// Updates the message tracing configuration for the whole process.
+pub fn set_message_tracing_config(message_tracing_config: MessageTracingConfig) {
+    SAMPLE_ONE_IN.store(message_tracing_config.sample_one_in, Ordering::Relaxed);
+    MAX_MESSAGE_DEBUG_LEN.store(
+        message_tracing_config.max_message_debug_len,
+        Ordering::Relaxed,
+    );
+}
+
+/// Returns true if the message being sent or received should be logged.
+pub(crate) fn should_trace_message() -> bool {
+    if !tracing::enabled!(Level::DEBUG) {
+        return false;
+    }
+    let sample_one_in = SAMPLE_ONE_IN.load(Ordering::Relaxed);
+    if sample_one_in == 0 {
+        return false;
+    }
+    NUM_MESSAGES.fetch_add(1, Ordering::Relaxed) % sample_one_in == 0
+}
+
+/// Renders a message with its `Debug` implementation, truncated to the configured length.
+///
+/// The rendering is written directly to the formatter, and stops as soon as the configured
+/// length is reached: large messages are neither rendered entirely nor buffered.
+pub(crate) struct TruncatedDebug<'a, T>(pub &'a T);
+
+impl<'a, T: fmt::Debug> fmt::Display for TruncatedDebug<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let max_len = MAX_MESSAGE_DEBUG_LEN.load(Ordering::Relaxed);
+        write_truncated_debug(f, self.0, max_len)
+    }
+}
+
+fn write_truncated_debug(
+    writer: &mut dyn fmt::Write,
+    value: &dyn fmt::Debug,
+    max_len: usize,
+) -> fmt::Result {
+    let mut bounded_writer = BoundedWriter {
+        writer,
+        remaining_len: max_len,
+        is_truncated: false,
+    };
+    let write_res = write!(bounded_writer, "{value:?}");
+    if bounded_writer.is_truncated {
+        return bounded_writer.writer.write_str("...");
+    }
+    write_res
+}
+
+/// Forwards at most `remaining_len` bytes to the underlying writer, then fails so that the
+/// `Debug` implementation writing to it bails out early.
+struct BoundedWriter<'a> {
+    writer: &'a mut dyn fmt::Write,
+    remaining_len: usize,
+    is_truncated: bool,
+}
+
+impl<'a> fmt::Write for BoundedWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if s.len() <= self.remaining_len {
+            self.remaining_len -= s.len();
+            return self.writer.write_str(s);
+        }
+        // Truncation happens on a char boundary.
+        let mut end = self.remaining_len;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.writer.write_str(&s[..end])?;
+        self.remaining_len = 0;
+        self.is_truncated = true;
+        Err(fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn truncated_debug(value: &dyn fmt::Debug, max_len: usize) -> String {
+        let mut rendering = String::new();
+        write_truncated_debug(&mut rendering, value, max_len).unwrap();
+        rendering
+    }
+
+    #[test]
+    fn test_truncated_debug() {
+        assert_eq!(truncated_debug(&"Ping", 10), "\"Ping\"");
+        assert_eq!(truncated_debug(&"Ping", 6), "\"Ping\"");
+        assert_eq!(truncated_debug(&"Ping", 3), "\"Pi...");
+        // Truncation happens on a char boundary.
+        assert_eq!(truncated_debug(&'é', 2), "'...");
+        // The rendering stops at the configured length.
+        assert_eq!(truncated_debug(&vec![0; 1_000_000], 5), "[0, 0...");
+    }
+}
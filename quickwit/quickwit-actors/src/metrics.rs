@@ -0,0 +1,88 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use once_cell::sync::Lazy;
+use quickwit_common::metrics::{
+    new_counter, new_counter_vec, new_gauge, IntCounter, IntCounterVec, IntGauge,
+};
+
+pub struct ActorMetrics {
+    pub dropped_messages_total: IntCounterVec<1>,
+    pub processing_time_budget_violations_total: IntCounterVec<1>,
+    pub cpu_time_micros_total: IntCounterVec<1>,
+    pub blocking_time_micros_total: IntCounterVec<1>,
+    pub dead_letters_total: IntCounterVec<1>,
+    pub scheduler_queue_depth: IntGauge,
+    pub scheduler_local_timers_total: IntCounter,
+}
+
+impl Default for ActorMetrics {
+    fn default() -> Self {
+        ActorMetrics {
+            dropped_messages_total: new_counter_vec(
+                "dropped_messages_total",
+                "Number of messages still queued in the mailbox of an actor that were dropped \
+                 when the actor quit.",
+                "quickwit_actors",
+                ["actor_name"],
+            ),
+            processing_time_budget_violations_total: new_counter_vec(
+                "processing_time_budget_violations_total",
+                "Number of messages whose processing took longer than the processing time budget \
+                 of the actor.",
+                "quickwit_actors",
+                ["actor_name"],
+            ),
+            cpu_time_micros_total: new_counter_vec(
+                "cpu_time_micros_total",
+                "CPU time spent by actors processing messages, in microseconds.",
+                "quickwit_actors",
+                ["actor_name"],
+            ),
+            blocking_time_micros_total: new_counter_vec(
+                "blocking_time_micros_total",
+                "Time spent by actors processing messages while blocking the thread without using \
+                 the CPU, in microseconds.",
+                "quickwit_actors",
+                ["actor_name"],
+            ),
+            dead_letters_total: new_counter_vec(
+                "dead_letters_total",
+                "Number of messages moved to the dead letter queue after their processing failed \
+                 too many times.",
+                "quickwit_actors",
+                ["actor_name"],
+            ),
+            scheduler_queue_depth: new_gauge(
+                "scheduler_queue_depth",
+                "Number of messages queued in the scheduler mailbox.",
+                "quickwit_actors",
+            ),
+            scheduler_local_timers_total: new_counter(
+                "scheduler_local_timers_total",
+                "Number of events scheduled using a local timer because the scheduler was \
+                 saturated.",
+                "quickwit_actors",
+            ),
+        }
+    }
+}
+
+/// `ACTOR_METRICS` exposes the actor framework metrics through a prometheus endpoint.
+pub static ACTOR_METRICS: Lazy<ActorMetrics> = Lazy::new(ActorMetrics::default);
@@ -0,0 +1,44 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::time::Duration;
+
+/// Hook notified of the activity of the mailboxes and of the actors of a universe.
+///
+/// This makes it possible to export the activity of the actors to a metrics system other
+/// than Prometheus (statsd, ...), or to assert on it in tests. It is installed with
+/// `Universe::with_metrics_sink`.
+///
+/// The methods are called on the hot path of every message, so they should be cheap.
+pub trait MetricsSink: Send + Sync + 'static {
+    /// Called once a message has been queued in the (low priority) queue of an actor.
+    fn record_enqueue(&self, _actor_instance_id: &str, _message_type: &'static str) {}
+
+    /// Called when an actor pulls a message from its mailbox, before processing it.
+    fn record_dequeue(&self, _actor_instance_id: &str, _message_type: &'static str) {}
+
+    /// Called once an actor has processed a message.
+    fn record_processing_time(
+        &self,
+        _actor_instance_id: &str,
+        _message_type: &'static str,
+        _processing_time: Duration,
+    ) {
+    }
+}
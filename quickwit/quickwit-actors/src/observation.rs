@@ -17,9 +17,13 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::BTreeMap;
 use std::fmt;
 use std::ops::Deref;
 
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+
 #[derive(Debug)]
 pub struct Observation<ObservableState> {
     pub obs_type: ObservationType,
@@ -55,3 +59,136 @@ impl<State: fmt::Debug + PartialEq> PartialEq for Observation<State> {
 }
 
 impl<State: fmt::Debug + PartialEq + Eq> Eq for Observation<State> {}
+
+/// Change of a single field of an observable state between two observations.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldDiff {
+    /// `JsonValue::Null` if the field did not exist in the previous observation.
+    pub previous: JsonValue,
+    /// `JsonValue::Null` if the field does not exist in the current observation.
+    pub current: JsonValue,
+    /// Difference between the current and previous values, if both are integers.
+    pub delta_opt: Option<i64>,
+}
+
+/// Fields of an observable state that changed between two observations.
+///
+/// Fields of nested objects are identified by their dot-separated path
+/// (e.g. `pipeline_metrics.num_docs`). A state that is not an object is identified
+/// by the empty path.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ObservationDiff {
+    pub changed_fields: BTreeMap<String, FieldDiff>,
+}
+
+impl ObservationDiff {
+    pub(crate) fn compute(previous: &JsonValue, current: &JsonValue) -> ObservationDiff {
+        let mut observation_diff = ObservationDiff::default();
+        observation_diff.diff_values(String::new(), previous, current);
+        observation_diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed_fields.is_empty()
+    }
+
+    fn diff_values(&mut self, path: String, previous: &JsonValue, current: &JsonValue) {
+        if previous == current {
+            return;
+        }
+        if let (JsonValue::Object(previous_fields), JsonValue::Object(current_fields)) =
+            (previous, current)
+        {
+            for (field_name, current_value) in current_fields {
+                let previous_value = previous_fields.get(field_name).unwrap_or(&JsonValue::Null);
+                self.diff_values(field_path(&path, field_name), previous_value, current_value);
+            }
+            for (field_name, previous_value) in previous_fields {
+                if !current_fields.contains_key(field_name) {
+                    self.diff_values(
+                        field_path(&path, field_name),
+                        previous_value,
+                        &JsonValue::Null,
+                    );
+                }
+            }
+            return;
+        }
+        let delta_opt = previous
+            .as_i64()
+            .zip(current.as_i64())
+            .map(|(previous_int, current_int)| current_int.wrapping_sub(previous_int));
+        let field_diff = FieldDiff {
+            previous: previous.clone(),
+            current: current.clone(),
+            delta_opt,
+        };
+        self.changed_fields.insert(path, field_diff);
+    }
+}
+
+fn field_path(parent_path: &str, field_name: &str) -> String {
+    if parent_path.is_empty() {
+        field_name.to_string()
+    } else {
+        format!("{parent_path}.{field_name}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_observation_diff_scalar() {
+        let observation_diff = ObservationDiff::compute(&json!(3), &json!(3));
+        assert!(observation_diff.is_empty());
+
+        let observation_diff = ObservationDiff::compute(&json!(3), &json!(5));
+        assert_eq!(observation_diff.changed_fields.len(), 1);
+        let field_diff = &observation_diff.changed_fields[""];
+        assert_eq!(field_diff.previous, json!(3));
+        assert_eq!(field_diff.current, json!(5));
+        assert_eq!(field_diff.delta_opt, Some(2));
+    }
+
+    #[test]
+    fn test_observation_diff_nested_objects() {
+        let previous = json!({
+            "num_docs": 10,
+            "index_id": "my-index",
+            "metrics": {"num_splits": 1, "status": "idle"},
+            "removed": true,
+        });
+        let current = json!({
+            "num_docs": 25,
+            "index_id": "my-index",
+            "metrics": {"num_splits": 1, "status": "busy"},
+            "added": 1,
+        });
+        let observation_diff = ObservationDiff::compute(&previous, &current);
+        let changed_field_paths: Vec<&str> = observation_diff
+            .changed_fields
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            changed_field_paths,
+            ["added", "metrics.status", "num_docs", "removed"]
+        );
+        assert_eq!(
+            observation_diff.changed_fields["num_docs"].delta_opt,
+            Some(15)
+        );
+        assert_eq!(
+            observation_diff.changed_fields["metrics.status"].delta_opt,
+            None
+        );
+        assert_eq!(
+            observation_diff.changed_fields["removed"].current,
+            JsonValue::Null
+        );
+    }
+}
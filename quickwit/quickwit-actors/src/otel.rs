@@ -0,0 +1,92 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+//! OpenTelemetry instrumentation of the actors, enabled by the `opentelemetry` feature.
+//!
+//! Message spans go through `tracing`, and reach OpenTelemetry via the
+//! `tracing-opentelemetry` layer installed by the application. Metrics are recorded with the
+//! global meter provider, which the application is expected to set up with an OTLP exporter.
+
+use std::time::Duration;
+
+use opentelemetry::metrics::{Counter, Histogram};
+use opentelemetry::{global, Context, KeyValue};
+use tracing::{info_span, Span};
+
+use crate::envelope::CorrelationId;
+
+const METER_NAME: &str = "quickwit-actors";
+
+/// Creates the span wrapping the processing of a message.
+pub(crate) fn message_span(
+    actor_instance_id: &str,
+    message_type: &'static str,
+    correlation_id: CorrelationId,
+) -> Span {
+    info_span!(
+        "message",
+        otel.name = message_type,
+        actor_id = %actor_instance_id,
+        correlation_id = %correlation_id,
+    )
+}
+
+/// OpenTelemetry instruments of an actor.
+///
+/// They are created when the actor is spawned, rather than once for all, so that they are
+/// bound to the meter provider installed by then.
+pub(crate) struct ActorOtelMetrics {
+    attributes: [KeyValue; 1],
+    processed_messages: Counter<u64>,
+    processing_time_secs: Histogram<f64>,
+    queue_depth: Histogram<u64>,
+}
+
+impl ActorOtelMetrics {
+    pub fn new(actor_name: &str) -> Self {
+        let meter = global::meter(METER_NAME);
+        ActorOtelMetrics {
+            attributes: [KeyValue::new("actor_name", actor_name.to_string())],
+            processed_messages: meter
+                .u64_counter("quickwit_actors.processed_messages")
+                .with_description("Number of messages processed by actors.")
+                .init(),
+            processing_time_secs: meter
+                .f64_histogram("quickwit_actors.processing_time_secs")
+                .with_description("Time spent by actors processing a message, in seconds.")
+                .init(),
+            queue_depth: meter
+                .u64_histogram("quickwit_actors.mailbox.queue_depth")
+                .with_description(
+                    "Number of messages queued in the mailbox of an actor, sampled after each \
+                     processed message.",
+                )
+                .init(),
+        }
+    }
+
+    pub fn record_processed_message(&self, processing_time: Duration, queue_depth: usize) {
+        let cx = Context::current();
+        self.processed_messages.add(&cx, 1, &self.attributes);
+        self.processing_time_secs
+            .record(&cx, processing_time.as_secs_f64(), &self.attributes);
+        self.queue_depth
+            .record(&cx, queue_depth as u64, &self.attributes);
+    }
+}
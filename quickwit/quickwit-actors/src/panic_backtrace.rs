@@ -0,0 +1,80 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::any::Any;
+use std::backtrace::Backtrace;
+use std::cell::RefCell;
+use std::sync::Once;
+
+thread_local! {
+    static LAST_PANIC_BACKTRACE: RefCell<Option<Backtrace>> = RefCell::new(None);
+}
+
+/// Installs, once per process, a panic hook recording the backtrace of the last panic
+/// of the current thread, so that it is reported in the termination details of the actors
+/// that panic. The previously installed hook is still called.
+///
+/// The backtrace cannot be captured after the panic has been caught, as the stack has
+/// been unwound by then. This is opt-in: the hook is process-global, so binaries should
+/// install it at startup. Backtraces are captured according to the `RUST_BACKTRACE` and
+/// `RUST_LIB_BACKTRACE` environment variables.
+pub fn install_panic_hook() {
+    static INSTALL_PANIC_HOOK: Once = Once::new();
+
+    INSTALL_PANIC_HOOK.call_once(|| {
+        let previous_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |panic_info| {
+            let backtrace = Backtrace::capture();
+            LAST_PANIC_BACKTRACE.with(|last_panic_backtrace| {
+                *last_panic_backtrace.borrow_mut() = Some(backtrace);
+            });
+            previous_hook(panic_info);
+        }));
+    });
+}
+
+/// Returns the backtrace of the last panic of the current thread, if it was recorded.
+pub(crate) fn take_last_panic_backtrace() -> Option<Backtrace> {
+    LAST_PANIC_BACKTRACE.with(|last_panic_backtrace| last_panic_backtrace.borrow_mut().take())
+}
+
+/// Extracts the message of a panic from its payload.
+pub(crate) fn panic_message(panic_payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic_payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic_payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "<non-string panic payload>".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_backtrace() {
+        install_panic_hook();
+        let panic_payload = std::panic::catch_unwind(|| panic!("Oops")).unwrap_err();
+        assert_eq!(panic_message(panic_payload.as_ref()), "Oops");
+        assert!(take_last_panic_backtrace().is_some());
+        assert!(take_last_panic_backtrace().is_none());
+    }
+}
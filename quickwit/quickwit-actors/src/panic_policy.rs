@@ -0,0 +1,34 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+/// Determines what happens when an actor of a universe panics.
+///
+/// Embedders running the actors as part of a larger program usually want to contain the
+/// failure, whereas a standalone server may prefer to fail fast.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum PanicPolicy {
+    /// Only the actor that panicked exits, with the `Panicked` exit status.
+    #[default]
+    KillActor,
+    /// The kill switch of the actor is activated, which kills all of the actors sharing it,
+    /// typically its pipeline.
+    KillPipeline,
+    /// The process is aborted.
+    AbortProcess,
+}
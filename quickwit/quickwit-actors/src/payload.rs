@@ -0,0 +1,167 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::ops::{Deref, RangeBounds};
+
+use bytes::Bytes;
+
+/// Immutable buffer that can be passed between actors without being copied.
+///
+/// Cloning or slicing a `Payload` only increments a reference count, so large
+/// document batches can be split and dispatched to several actors for free.
+#[derive(Clone, Default, Eq, PartialEq)]
+pub struct Payload {
+    bytes: Bytes,
+}
+
+impl Payload {
+    pub fn new(bytes: impl Into<Bytes>) -> Self {
+        Payload {
+            bytes: bytes.into(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns a payload sharing the given range of this payload.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Payload {
+        Payload {
+            bytes: self.bytes.slice(range),
+        }
+    }
+
+    /// Splits the payload on line feeds, without copying, dropping the line feeds and
+    /// the empty lines.
+    ///
+    /// This is typically used to turn a newline-delimited batch of documents into one
+    /// payload per document.
+    pub fn lines(&self) -> impl Iterator<Item = Payload> + '_ {
+        self.bytes
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| Payload {
+                bytes: self.bytes.slice_ref(line),
+            })
+    }
+
+    pub fn into_bytes(self) -> Bytes {
+        self.bytes
+    }
+}
+
+impl Deref for Payload {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        // Payloads are typically large, we do not want to dump them in logs.
+        formatter
+            .debug_struct("Payload")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl From<Bytes> for Payload {
+    fn from(bytes: Bytes) -> Self {
+        Payload { bytes }
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(bytes: Vec<u8>) -> Self {
+        Payload::new(bytes)
+    }
+}
+
+impl From<String> for Payload {
+    fn from(text: String) -> Self {
+        Payload::new(text)
+    }
+}
+
+impl From<&'static str> for Payload {
+    fn from(text: &'static str) -> Self {
+        Payload::new(text)
+    }
+}
+
+/// Reports the amount of memory held by a message, as accounted for by memory budgets.
+///
+/// Buffers shared between several messages are accounted for by each of them, which
+/// overestimates memory usage rather than underestimating it.
+pub trait MessageSize {
+    fn size_in_bytes(&self) -> usize;
+}
+
+impl MessageSize for Payload {
+    fn size_in_bytes(&self) -> usize {
+        self.len()
+    }
+}
+
+impl MessageSize for Bytes {
+    fn size_in_bytes(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: MessageSize> MessageSize for Vec<T> {
+    fn size_in_bytes(&self) -> usize {
+        self.iter().map(MessageSize::size_in_bytes).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_lines() {
+        let payload = Payload::from("doc1\ndoc2\n\ndoc3");
+        let lines: Vec<Payload> = payload.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(&lines[0][..], b"doc1");
+        assert_eq!(&lines[2][..], b"doc3");
+        // The lines share the buffer of the original payload.
+        assert_eq!(lines[1].as_ptr(), payload[5..].as_ptr());
+        assert_eq!(lines.size_in_bytes(), 12);
+    }
+
+    #[test]
+    fn test_payload_slice() {
+        let payload = Payload::from(vec![0, 1, 2, 3]);
+        let slice = payload.slice(1..3);
+        assert_eq!(&slice[..], &[1, 2]);
+        assert_eq!(slice.size_in_bytes(), 2);
+        assert_eq!(format!("{slice:?}"), "Payload { len: 2 }");
+    }
+}
@@ -0,0 +1,193 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use quickwit_common::KillSwitch;
+
+use crate::{Actor, Health, Mailbox, QueueCapacity, SpawnContext, Supervisable};
+
+/// Declares and spawns the stages of a pipeline of actors sharing the same kill switch.
+///
+/// Stages are declared from the most downstream to the most upstream one, so that the
+/// mailbox returned for a stage can be passed to the stages feeding it. Cyclic pipelines can
+/// use a [`crate::DeferredMailbox`].
+///
+/// ```ignore
+/// let mut pipeline_builder = PipelineBuilder::new(universe.spawn_ctx());
+/// let publisher_mailbox = pipeline_builder.stage(Publisher::default()).spawn();
+/// let uploader_mailbox = pipeline_builder
+///     .supervised_stage(move || Uploader::new(publisher_mailbox.clone()))
+///     .set_queue_capacity(QueueCapacity::Bounded(4))
+///     .spawn();
+/// let pipeline = pipeline_builder.build();
+/// ```
+pub struct PipelineBuilder {
+    spawn_ctx: SpawnContext,
+    stages: Vec<Box<dyn Supervisable + Send>>,
+}
+
+impl PipelineBuilder {
+    /// Creates a pipeline builder. The pipeline gets its own kill switch, child of the kill
+    /// switch of `spawn_ctx`.
+    pub fn new(spawn_ctx: &SpawnContext) -> Self {
+        PipelineBuilder {
+            spawn_ctx: spawn_ctx.child_context(),
+            stages: Vec::new(),
+        }
+    }
+
+    /// Declares an unsupervised stage.
+    pub fn stage<A: Actor>(&mut self, actor: A) -> StageBuilder<'_, A> {
+        StageBuilder {
+            pipeline_builder: self,
+            stage_actor: StageActor::Unsupervised(actor),
+            queue_capacity_opt: None,
+        }
+    }
+
+    /// Declares a stage whose actor is restarted by a supervisor on failure, using
+    /// `actor_factory` to build a new instance.
+    pub fn supervised_stage<A, F>(&mut self, actor_factory: F) -> StageBuilder<'_, A>
+    where
+        A: Actor,
+        F: Fn() -> A + Send + 'static,
+    {
+        StageBuilder {
+            pipeline_builder: self,
+            stage_actor: StageActor::Supervised(Box::new(actor_factory)),
+            queue_capacity_opt: None,
+        }
+    }
+
+    pub fn build(self) -> Pipeline {
+        Pipeline {
+            kill_switch: self.spawn_ctx.kill_switch,
+            stages: self.stages,
+        }
+    }
+}
+
+enum StageActor<A> {
+    Unsupervised(A),
+    Supervised(Box<dyn Fn() -> A + Send>),
+}
+
+/// Configures a stage of a pipeline. See [`PipelineBuilder`].
+pub struct StageBuilder<'a, A: Actor> {
+    pipeline_builder: &'a mut PipelineBuilder,
+    stage_actor: StageActor<A>,
+    queue_capacity_opt: Option<QueueCapacity>,
+}
+
+impl<'a, A: Actor> StageBuilder<'a, A> {
+    /// Overrides the queue capacity of the stage, which defaults to
+    /// [`Actor::queue_capacity`].
+    pub fn set_queue_capacity(mut self, queue_capacity: QueueCapacity) -> Self {
+        self.queue_capacity_opt = Some(queue_capacity);
+        self
+    }
+
+    /// Spawns the actor of the stage and returns its mailbox.
+    pub fn spawn(self) -> Mailbox<A> {
+        let mut spawn_builder = self.pipeline_builder.spawn_ctx.spawn_builder::<A>();
+        if let Some(queue_capacity) = self.queue_capacity_opt {
+            spawn_builder = spawn_builder.set_queue_capacity(queue_capacity);
+        }
+        let (mailbox, stage): (Mailbox<A>, Box<dyn Supervisable + Send>) = match self.stage_actor {
+            StageActor::Unsupervised(actor) => {
+                let (mailbox, actor_handle) = spawn_builder.spawn(actor);
+                (mailbox, Box::new(actor_handle))
+            }
+            StageActor::Supervised(actor_factory) => {
+                let (mailbox, supervisor_handle) = spawn_builder.supervise_fn(actor_factory);
+                (mailbox, Box::new(supervisor_handle))
+            }
+        };
+        self.pipeline_builder.stages.push(stage);
+        mailbox
+    }
+}
+
+/// Handles to the stages of a pipeline built with a [`PipelineBuilder`].
+pub struct Pipeline {
+    kill_switch: KillSwitch,
+    stages: Vec<Box<dyn Supervisable + Send>>,
+}
+
+impl Pipeline {
+    /// Returns the stages of the pipeline, in the order they were declared.
+    pub fn stages(&self) -> Vec<&dyn Supervisable> {
+        self.stages
+            .iter()
+            .map(|stage| stage.as_ref() as &dyn Supervisable)
+            .collect()
+    }
+
+    /// Harvests the health of all of the stages. The pipeline is healthy if all of its stages
+    /// are, and successful once all of its stages have exited with success.
+    pub fn harvest_health(&self) -> Health {
+        let mut health = Health::Success;
+        for stage in &self.stages {
+            match stage.harvest_health() {
+                Health::FailureOrUnhealthy => return Health::FailureOrUnhealthy,
+                Health::Healthy => health = Health::Healthy,
+                Health::Success => {}
+            }
+        }
+        health
+    }
+
+    /// Kills all of the stages of the pipeline.
+    pub fn kill(&self) {
+        self.kill_switch.kill();
+    }
+
+    pub fn kill_switch(&self) -> &KillSwitch {
+        &self.kill_switch
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{Ping, PingReceiverActor};
+    use crate::Universe;
+
+    #[tokio::test]
+    async fn test_pipeline_builder() {
+        let universe = Universe::with_accelerated_time();
+        let mut pipeline_builder = PipelineBuilder::new(universe.spawn_ctx());
+        let sink_mailbox = pipeline_builder
+            .stage(PingReceiverActor::default())
+            .set_queue_capacity(QueueCapacity::Bounded(3))
+            .spawn();
+        let supervised_sink_mailbox = pipeline_builder
+            .supervised_stage(PingReceiverActor::default)
+            .spawn();
+        let pipeline = pipeline_builder.build();
+        assert_eq!(pipeline.stages().len(), 2);
+        assert_eq!(sink_mailbox.queue_diagnostics().queue_capacity_opt, Some(3));
+        sink_mailbox.ask(Ping).await.unwrap();
+        supervised_sink_mailbox.ask(Ping).await.unwrap();
+
+        pipeline.kill();
+        assert!(pipeline.kill_switch().is_dead());
+        assert!(universe.spawn_ctx().kill_switch.is_alive());
+        universe.assert_quit().await;
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// Number of processing time samples kept to compute percentiles.
+const NUM_PROCESSING_TIME_SAMPLES: usize = 1_024;
+
+/// Keeps track of the time it took an actor to process its last
+/// `NUM_PROCESSING_TIME_SAMPLES` messages.
+#[derive(Default)]
+pub(crate) struct ProcessingTimeTracker {
+    samples: Mutex<VecDeque<Duration>>,
+}
+
+impl ProcessingTimeTracker {
+    pub fn record(&self, processing_time: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == NUM_PROCESSING_TIME_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(processing_time);
+    }
+
+    /// Returns the 99th percentile of the processing time over the last processed messages,
+    /// or `None` if no message was processed yet.
+    pub fn p99(&self) -> Option<Duration> {
+        let mut sorted_samples: Vec<Duration> =
+            self.samples.lock().unwrap().iter().copied().collect();
+        if sorted_samples.is_empty() {
+            return None;
+        }
+        sorted_samples.sort_unstable();
+        let rank = (sorted_samples.len() * 99 + 99) / 100;
+        Some(sorted_samples[rank - 1])
+    }
+}
+
+/// Report emitted when the 99th percentile of the processing time of an actor
+/// exceeds its processing time budget.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+pub struct SlowActorReport {
+    pub processing_time_p99: Duration,
+    pub processing_time_budget: Duration,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_processing_time_tracker_empty() {
+        let tracker = ProcessingTimeTracker::default();
+        assert_eq!(tracker.p99(), None);
+    }
+
+    #[test]
+    fn test_processing_time_tracker_p99() {
+        let tracker = ProcessingTimeTracker::default();
+        for millis in 1..=100 {
+            tracker.record(Duration::from_millis(millis));
+        }
+        assert_eq!(tracker.p99(), Some(Duration::from_millis(99)));
+        tracker.record(Duration::from_secs(10));
+        tracker.record(Duration::from_secs(10));
+        assert_eq!(tracker.p99(), Some(Duration::from_secs(10)));
+    }
+
+    #[test]
+    fn test_processing_time_tracker_keeps_last_samples() {
+        let tracker = ProcessingTimeTracker::default();
+        tracker.record(Duration::from_secs(10));
+        for _ in 0..NUM_PROCESSING_TIME_SAMPLES {
+            tracker.record(Duration::from_millis(1));
+        }
+        assert_eq!(tracker.p99(), Some(Duration::from_millis(1)));
+    }
+}
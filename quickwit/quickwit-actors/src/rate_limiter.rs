@@ -0,0 +1,141 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+
+use tokio::time::{Duration, Instant};
+
+use crate::{Actor, ActorContext};
+
+/// Token bucket throttle that can be shared by several actors, for instance by all of
+/// the uploaders sharing a requests per second budget with an object storage.
+///
+/// Cloning a `RateLimiter` returns a handle to the same bucket.
+#[derive(Clone)]
+pub struct RateLimiter {
+    token_bucket: Arc<Mutex<TokenBucket>>,
+}
+
+struct TokenBucket {
+    // Goes negative when tokens are reserved ahead of their refill.
+    num_tokens: f64,
+    max_num_tokens: f64,
+    num_tokens_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed_secs = now.duration_since(self.last_refill).as_secs_f64();
+        self.num_tokens =
+            (self.num_tokens + elapsed_secs * self.num_tokens_per_sec).min(self.max_num_tokens);
+        self.last_refill = now;
+    }
+}
+
+impl RateLimiter {
+    /// Creates a rate limiter refilled with `num_tokens_per_sec` tokens per second, and
+    /// holding up to `burst_num_tokens` tokens. The bucket starts full.
+    pub fn new(num_tokens_per_sec: u64, burst_num_tokens: u64) -> Self {
+        assert!(
+            num_tokens_per_sec > 0,
+            "The rate must be strictly positive."
+        );
+        let token_bucket = TokenBucket {
+            num_tokens: burst_num_tokens as f64,
+            max_num_tokens: burst_num_tokens as f64,
+            num_tokens_per_sec: num_tokens_per_sec as f64,
+            last_refill: Instant::now(),
+        };
+        RateLimiter {
+            token_bucket: Arc::new(Mutex::new(token_bucket)),
+        }
+    }
+
+    /// Takes `num_tokens` tokens if they are available right away.
+    pub fn try_acquire(&self, num_tokens: u64) -> bool {
+        let mut token_bucket = self.token_bucket.lock().unwrap();
+        token_bucket.refill(Instant::now());
+        if token_bucket.num_tokens < num_tokens as f64 {
+            return false;
+        }
+        token_bucket.num_tokens -= num_tokens as f64;
+        true
+    }
+
+    /// Takes `num_tokens` tokens, waiting for them to be refilled if necessary.
+    ///
+    /// Tokens are reserved right away, so callers are served in order and large
+    /// acquisitions cannot be starved by small ones. The wait goes through `ActorContext::sleep`,
+    /// so that a throttled actor is not mistaken for a dead one by its supervisor.
+    pub async fn acquire<A: Actor>(&self, ctx: &ActorContext<A>, num_tokens: u64) {
+        let wait_duration = self.reserve(num_tokens);
+        if !wait_duration.is_zero() {
+            ctx.sleep(wait_duration).await;
+        }
+    }
+
+    /// Takes `num_tokens` tokens, possibly ahead of their refill, and returns how long to wait
+    /// for them to be refilled.
+    fn reserve(&self, num_tokens: u64) -> Duration {
+        let mut token_bucket = self.token_bucket.lock().unwrap();
+        token_bucket.refill(Instant::now());
+        token_bucket.num_tokens -= num_tokens as f64;
+        if token_bucket.num_tokens >= 0.0 {
+            return Duration::ZERO;
+        }
+        Duration::from_secs_f64(-token_bucket.num_tokens / token_bucket.num_tokens_per_sec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::watch;
+
+    use super::*;
+    use crate::tests::PingReceiverActor;
+    use crate::Universe;
+
+    #[tokio::test]
+    async fn test_rate_limiter() {
+        let universe = Universe::with_accelerated_time();
+        let (mailbox, _inbox) = universe.create_test_mailbox::<PingReceiverActor>();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(0);
+        let ctx = ActorContext::for_test(&universe, mailbox, observable_state_tx);
+
+        let rate_limiter = RateLimiter::new(1_000, 10);
+        assert!(rate_limiter.try_acquire(5));
+        assert!(!rate_limiter.clone().try_acquire(10));
+
+        assert_eq!(rate_limiter.reserve(5), Duration::ZERO);
+        let wait_duration = rate_limiter.reserve(50);
+        assert!(wait_duration > Duration::from_millis(45));
+        assert!(wait_duration <= Duration::from_millis(50));
+
+        // The wait goes through the scheduler of the universe. As in the actor loop, time is
+        // only accelerated while the actor sleeps.
+        let _no_advance_time_guard = universe
+            .spawn_ctx()
+            .scheduler_client
+            .no_advance_time_guard();
+        rate_limiter.acquire(&ctx, 1_000).await;
+        assert!(!rate_limiter.try_acquire(1));
+        universe.assert_quit().await;
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::{Arc, Mutex};
+
+use thiserror::Error;
+use tokio::sync::watch;
+
+/// Error returned when one of the actors sharing a [`ReadinessGate`] failed to become ready.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+#[error("actor `{actor_id}` failed to become ready: {reason}")]
+pub struct ReadinessError {
+    pub actor_id: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug)]
+enum ReadinessState {
+    Pending,
+    Ready,
+    Failed(ReadinessError),
+}
+
+#[derive(Default)]
+struct ReadinessCounts {
+    num_registered_actors: usize,
+    num_ready_actors: usize,
+    sealed: bool,
+}
+
+/// Delays message processing in a group of actors (typically a pipeline) until all of them
+/// are ready.
+///
+/// Actors spawned with `SpawnBuilder::set_readiness_gate` run `Actor::initialize`, then check
+/// their dependencies with `Actor::check_readiness`, and report ready. They only start
+/// processing their messages, which are queued in the meantime, once all of the actors of the
+/// group are ready and the owner of the gate has called [`ReadinessGate::wait_ready`].
+///
+/// If any of the actors fails to become ready, all of them exit with a failure and
+/// `wait_ready` returns the error of the first one.
+#[derive(Clone)]
+pub struct ReadinessGate {
+    inner: Arc<ReadinessGateInner>,
+}
+
+struct ReadinessGateInner {
+    counts: Mutex<ReadinessCounts>,
+    state_tx: watch::Sender<ReadinessState>,
+}
+
+impl Default for ReadinessGate {
+    fn default() -> Self {
+        ReadinessGate::new()
+    }
+}
+
+impl ReadinessGate {
+    pub fn new() -> Self {
+        let (state_tx, _state_rx) = watch::channel(ReadinessState::Pending);
+        ReadinessGate {
+            inner: Arc::new(ReadinessGateInner {
+                counts: Mutex::default(),
+                state_tx,
+            }),
+        }
+    }
+
+    /// Waits for all of the actors spawned with this gate to be ready and lets them process
+    /// their messages.
+    ///
+    /// It must be called once all of the actors of the group have been spawned.
+    pub async fn wait_ready(&self) -> Result<(), ReadinessError> {
+        let mut counts = self.inner.counts.lock().unwrap();
+        counts.sealed = true;
+        self.open_if_all_ready(&counts);
+        drop(counts);
+        self.wait().await
+    }
+
+    pub(crate) fn register_actor(&self) {
+        self.inner.counts.lock().unwrap().num_registered_actors += 1;
+    }
+
+    pub(crate) fn report_ready(&self) {
+        let mut counts = self.inner.counts.lock().unwrap();
+        counts.num_ready_actors += 1;
+        self.open_if_all_ready(&counts);
+    }
+
+    /// Fails the gate. Only the first failure is retained.
+    pub(crate) fn report_failure(&self, actor_id: &str, reason: String) {
+        self.inner.state_tx.send_if_modified(|state| {
+            if !matches!(state, ReadinessState::Pending) {
+                return false;
+            }
+            *state = ReadinessState::Failed(ReadinessError {
+                actor_id: actor_id.to_string(),
+                reason,
+            });
+            true
+        });
+    }
+
+    fn open_if_all_ready(&self, counts: &ReadinessCounts) {
+        if !counts.sealed || counts.num_ready_actors < counts.num_registered_actors {
+            return;
+        }
+        self.inner.state_tx.send_if_modified(|state| {
+            if !matches!(state, ReadinessState::Pending) {
+                return false;
+            }
+            *state = ReadinessState::Ready;
+            true
+        });
+    }
+
+    pub(crate) async fn wait(&self) -> Result<(), ReadinessError> {
+        let mut state_rx = self.inner.state_tx.subscribe();
+        loop {
+            let state = state_rx.borrow_and_update().clone();
+            match state {
+                ReadinessState::Pending => {}
+                ReadinessState::Ready => return Ok(()),
+                ReadinessState::Failed(readiness_error) => return Err(readiness_error),
+            }
+            // The sender lives as long as `self`.
+            let _ = state_rx.changed().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_readiness_gate_opens_once_sealed_and_all_ready() {
+        let readiness_gate = ReadinessGate::new();
+        readiness_gate.register_actor();
+        readiness_gate.register_actor();
+        readiness_gate.report_ready();
+        readiness_gate.report_ready();
+        let wait_handle = tokio::spawn({
+            let readiness_gate = readiness_gate.clone();
+            async move { readiness_gate.wait().await }
+        });
+        tokio::task::yield_now().await;
+        assert!(!wait_handle.is_finished());
+        readiness_gate.wait_ready().await.unwrap();
+        wait_handle.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_readiness_gate_keeps_first_failure() {
+        let readiness_gate = ReadinessGate::new();
+        readiness_gate.register_actor();
+        readiness_gate.register_actor();
+        readiness_gate.report_failure("uploader-1", "storage unreachable".to_string());
+        readiness_gate.report_failure("publisher-1", "metastore unreachable".to_string());
+        readiness_gate.report_ready();
+        let readiness_error = readiness_gate.wait_ready().await.unwrap_err();
+        assert_eq!(
+            readiness_error.to_string(),
+            "actor `uploader-1` failed to become ready: storage unreachable"
+        );
+    }
+}
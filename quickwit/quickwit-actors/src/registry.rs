@@ -18,7 +18,7 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::any::{Any, TypeId};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::pin::Pin;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
@@ -29,24 +29,56 @@ use futures::{Future, FutureExt};
 use serde::Serialize;
 use serde_json::Value as JsonValue;
 use tokio::task::JoinHandle;
+use tracing::{error, info};
 
+use crate::actor_context::WeakActorContext;
 use crate::command::Observe;
-use crate::mailbox::WeakMailbox;
-use crate::{Actor, ActorExitStatus, Command, Mailbox};
+use crate::cpu_time::CpuUsage;
+use crate::mailbox::{WeakInbox, WeakMailbox};
+use crate::{
+    Actor, ActorContext, ActorExitStatus, ActorHandle, ActorState, Command, Mailbox,
+    PipelineTopology,
+};
 
 struct TypedJsonObservable<A: Actor> {
+    actor_name: String,
     actor_instance_id: String,
     weak_mailbox: WeakMailbox<A>,
+    weak_ctx: WeakActorContext<A>,
+    weak_inbox: WeakInbox<A>,
     join_handle: ActorJoinHandle,
 }
 
+impl<A: Actor> TypedJsonObservable<A> {
+    fn actor_handle(&self) -> Option<ActorHandle<A>> {
+        let ctx = self.weak_ctx.upgrade()?;
+        let state_rx = ctx.subscribe_observable_state();
+        Some(ActorHandle::new(
+            state_rx,
+            self.join_handle.clone(),
+            ctx,
+            self.weak_inbox.clone(),
+        ))
+    }
+}
+
 #[async_trait]
 trait JsonObservable: Sync + Send {
     fn is_disconnected(&self) -> bool;
     fn any(&self) -> &dyn Any;
     fn actor_instance_id(&self) -> &str;
+    fn actor_state(&self) -> Option<ActorState>;
+    fn queue_depth(&self) -> Option<usize>;
+    fn last_progress_timestamp_millis(&self) -> Option<u64>;
+    fn processed_message_counts(&self) -> Option<BTreeMap<String, u64>>;
+    fn cpu_usage(&self) -> Option<CpuUsage>;
+    fn protection_depth(&self) -> Option<u32>;
+    fn generation(&self) -> Option<u64>;
+    fn num_reported_errors(&self) -> Option<u64>;
     async fn observe(&self) -> Option<JsonValue>;
+    async fn send_command(&self, command: Command);
     async fn quit(&self) -> ActorExitStatus;
+    async fn exit_with_success(&self) -> ActorExitStatus;
     async fn join(&self) -> ActorExitStatus;
 }
 
@@ -59,11 +91,43 @@ impl<A: Actor> JsonObservable for TypedJsonObservable<A> {
             .unwrap_or(true)
     }
     fn any(&self) -> &dyn Any {
-        &self.weak_mailbox
+        self
     }
     fn actor_instance_id(&self) -> &str {
         self.actor_instance_id.as_str()
     }
+    fn actor_state(&self) -> Option<ActorState> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.state())
+    }
+    fn queue_depth(&self) -> Option<usize> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.mailbox().queue_depth())
+    }
+    fn last_progress_timestamp_millis(&self) -> Option<u64> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.progress().last_progress_timestamp_millis())
+    }
+    fn processed_message_counts(&self) -> Option<BTreeMap<String, u64>> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.processed_message_counts())
+    }
+    fn cpu_usage(&self) -> Option<CpuUsage> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.cpu_usage())
+    }
+    fn protection_depth(&self) -> Option<u32> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.progress().protection_depth())
+    }
+    fn generation(&self) -> Option<u64> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.generation())
+    }
+    fn num_reported_errors(&self) -> Option<u64> {
+        let ctx = self.weak_ctx.upgrade()?;
+        Some(ctx.num_reported_errors())
+    }
     async fn observe(&self) -> Option<JsonValue> {
         let mailbox = self.weak_mailbox.upgrade()?;
         let oneshot_rx = mailbox.send_message_with_high_priority(Observe).ok()?;
@@ -71,6 +135,16 @@ impl<A: Actor> JsonObservable for TypedJsonObservable<A> {
         serde_json::to_value(&state).ok()
     }
 
+    async fn send_command(&self, command: Command) {
+        let Some(mailbox) = self.weak_mailbox.upgrade() else {
+            return;
+        };
+        if let Ok(oneshot_rx) = mailbox.send_message_with_high_priority(command) {
+            // The command is processed once the in-flight message, if any, has been handled.
+            let _ = oneshot_rx.await;
+        }
+    }
+
     async fn quit(&self) -> ActorExitStatus {
         if let Some(mailbox) = self.weak_mailbox.upgrade() {
             let _ = mailbox.send_message_with_high_priority(Command::Quit);
@@ -78,6 +152,15 @@ impl<A: Actor> JsonObservable for TypedJsonObservable<A> {
         self.join().await
     }
 
+    async fn exit_with_success(&self) -> ActorExitStatus {
+        if let Some(mailbox) = self.weak_mailbox.upgrade() {
+            // The command is queued like a regular message, so that the actor
+            // processes its pending messages first.
+            let _ = mailbox.send_message(Command::ExitWithSuccess).await;
+        }
+        self.join().await
+    }
+
     async fn join(&self) -> ActorExitStatus {
         self.join_handle.join().await
     }
@@ -86,6 +169,8 @@ impl<A: Actor> JsonObservable for TypedJsonObservable<A> {
 #[derive(Default, Clone)]
 pub(crate) struct ActorRegistry {
     actors: Arc<RwLock<HashMap<TypeId, ActorRegistryForSpecificType>>>,
+    // Actor instance id -> instance ids of the actors it sends messages to.
+    downstream_actor_ids: Arc<RwLock<HashMap<String, HashSet<String>>>>,
 }
 
 struct ActorRegistryForSpecificType {
@@ -117,25 +202,50 @@ impl ActorRegistryForSpecificType {
 pub struct ActorObservation {
     pub type_name: &'static str,
     pub instance_id: String,
+    pub state: Option<ActorState>,
+    pub queue_depth: Option<usize>,
+    /// Unix timestamp in milliseconds of the last progress recorded by the actor.
+    pub last_progress_timestamp_millis: Option<u64>,
+    /// Number of messages processed by the actor, broken down by message type.
+    pub processed_message_counts: Option<BTreeMap<String, u64>>,
+    /// CPU and blocking time spent by the actor while handling messages.
+    pub cpu_usage: Option<CpuUsage>,
+    /// Number of nested protected zones the actor is currently in.
+    pub protection_depth: Option<u32>,
+    /// Incarnation of the actor, incremented each time it is respawned.
+    pub generation: Option<u64>,
+    /// Number of non-fatal errors reported by the actor.
+    pub num_reported_errors: Option<u64>,
     pub obs: Option<JsonValue>,
 }
 
 impl ActorRegistry {
-    pub fn register<A: Actor>(&self, mailbox: &Mailbox<A>, join_handle: ActorJoinHandle) {
+    pub fn register<A: Actor>(
+        &self,
+        ctx: &ActorContext<A>,
+        actor_name: String,
+        join_handle: ActorJoinHandle,
+        weak_inbox: WeakInbox<A>,
+    ) {
         let typed_id = TypeId::of::<A>();
-        let actor_instance_id = mailbox.actor_instance_id().to_string();
-        let weak_mailbox = mailbox.downgrade();
-        self.actors
-            .write()
-            .unwrap()
+        let actor_instance_id = ctx.actor_instance_id().to_string();
+        let weak_mailbox = ctx.mailbox().downgrade();
+        let weak_ctx = ctx.downgrade();
+        let mut actors_guard = self.actors.write().unwrap();
+        let observables = &mut actors_guard
             .entry(typed_id)
             .or_insert_with(|| ActorRegistryForSpecificType::for_type::<A>())
-            .observables
-            .push(Arc::new(TypedJsonObservable {
-                weak_mailbox,
-                actor_instance_id,
-                join_handle,
-            }));
+            .observables;
+        // An actor respawned with the same mailbox replaces its previous instance.
+        observables.retain(|observable| observable.actor_instance_id() != actor_instance_id);
+        observables.push(Arc::new(TypedJsonObservable {
+            actor_name,
+            weak_mailbox,
+            weak_ctx,
+            weak_inbox,
+            actor_instance_id,
+            join_handle,
+        }));
     }
 
     pub async fn observe(&self, timeout: Duration) -> Vec<ActorObservation> {
@@ -156,6 +266,14 @@ impl ActorRegistry {
                     ActorObservation {
                         type_name,
                         instance_id,
+                        state: obs_clone.actor_state(),
+                        queue_depth: obs_clone.queue_depth(),
+                        last_progress_timestamp_millis: obs_clone.last_progress_timestamp_millis(),
+                        processed_message_counts: obs_clone.processed_message_counts(),
+                        cpu_usage: obs_clone.cpu_usage(),
+                        protection_depth: obs_clone.protection_depth(),
+                        generation: obs_clone.generation(),
+                        num_reported_errors: obs_clone.num_reported_errors(),
                         obs,
                     }
                 });
@@ -164,6 +282,19 @@ impl ActorRegistry {
         future::join_all(obs_futures.into_iter()).await
     }
 
+    /// Observes all of the actors and logs a JSON dump of their observations.
+    pub async fn log_observations(&self, timeout: Duration) {
+        let observations = self.observe(timeout).await;
+        match serde_json::to_string(&observations) {
+            Ok(observations_json) => {
+                info!(num_actors = observations.len(), observations = %observations_json, "actor-observations-dump");
+            }
+            Err(serialize_error) => {
+                error!(error = ?serialize_error, "serialize-actor-observations-failed");
+            }
+        }
+    }
+
     pub fn get<A: Actor>(&self) -> Vec<Mailbox<A>> {
         let mut lock = self.actors.write().unwrap();
         get_iter::<A>(&mut lock).collect()
@@ -175,10 +306,155 @@ impl ActorRegistry {
         opt
     }
 
+    /// Returns a handle of a live actor of type `A` whose name or instance id is `name`.
+    pub fn get_handle<A: Actor>(&self, name: &str) -> Option<ActorHandle<A>> {
+        let actors_guard = self.actors.read().unwrap();
+        actors_guard
+            .get(&TypeId::of::<A>())?
+            .observables
+            .iter()
+            .filter(|observable| !observable.is_disconnected())
+            .flat_map(|observable| observable.any().downcast_ref::<TypedJsonObservable<A>>())
+            .filter(|typed_observable| {
+                typed_observable.actor_name == name || typed_observable.actor_instance_id == name
+            })
+            .find_map(|typed_observable| typed_observable.actor_handle())
+    }
+
     fn gc(&self) {
+        let mut live_actor_ids = HashSet::new();
         for registry_for_type in self.actors.write().unwrap().values_mut() {
             registry_for_type.gc();
+            live_actor_ids.extend(
+                registry_for_type
+                    .observables
+                    .iter()
+                    .map(|obs| obs.actor_instance_id().to_string()),
+            );
+        }
+        self.downstream_actor_ids
+            .write()
+            .unwrap()
+            .retain(|actor_id, _| live_actor_ids.contains(actor_id));
+    }
+
+    /// Records that the actor `upstream_actor_id` sends messages to the actor
+    /// `downstream_actor_id`.
+    pub(crate) fn record_wiring(&self, upstream_actor_id: &str, downstream_actor_id: &str) {
+        if upstream_actor_id == downstream_actor_id {
+            return;
+        }
+        self.downstream_actor_ids
+            .write()
+            .unwrap()
+            .entry(upstream_actor_id.to_string())
+            .or_default()
+            .insert(downstream_actor_id.to_string());
+    }
+
+    /// Returns the graph of the live actors and of the mailboxes they send messages to.
+    pub fn topology(&self) -> PipelineTopology {
+        self.gc();
+        let actors: BTreeMap<String, &'static str> = self
+            .actors
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|registry_for_type| {
+                registry_for_type.observables.iter().map(|obs| {
+                    (
+                        obs.actor_instance_id().to_string(),
+                        registry_for_type.type_name,
+                    )
+                })
+            })
+            .collect();
+        let edges: BTreeSet<(String, String)> = self
+            .downstream_actor_ids
+            .read()
+            .unwrap()
+            .iter()
+            .flat_map(|(upstream_actor_id, downstream_actor_ids)| {
+                downstream_actor_ids.iter().map(|downstream_actor_id| {
+                    (upstream_actor_id.clone(), downstream_actor_id.clone())
+                })
+            })
+            .collect();
+        PipelineTopology { actors, edges }
+    }
+
+    /// Returns the instance ids of the live actors, grouped in stages such that the
+    /// actors of a stage only send messages to actors of later stages.
+    ///
+    /// Actors involved in a cycle are all placed in the last stage.
+    pub fn shutdown_stages(&self) -> Vec<Vec<String>> {
+        self.gc();
+        let actor_ids: Vec<String> = self
+            .actors
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|registry_for_type| {
+                registry_for_type
+                    .observables
+                    .iter()
+                    .map(|obs| obs.actor_instance_id().to_string())
+            })
+            .collect();
+        let downstream_actor_ids = self.downstream_actor_ids.read().unwrap();
+        compute_shutdown_stages(actor_ids, &downstream_actor_ids)
+    }
+
+    /// Gracefully drains all registered actors, upstream actors first.
+    ///
+    /// The wiring between actors is inferred from the messages they sent to each other. Each
+    /// actor is asked to exit once all of its upstream actors have exited, after processing
+    /// its pending messages. As a result, no actor exits while an upstream actor may still
+    /// send it messages.
+    pub async fn drain_in_topological_order(&self) -> HashMap<String, ActorExitStatus> {
+        let mut exit_statuses = HashMap::new();
+        for stage in self.shutdown_stages() {
+            let stage_ids: HashSet<String> = stage.into_iter().collect();
+            let mut obs_futures = Vec::new();
+            let mut actor_ids = Vec::new();
+            for registry_for_type in self.actors.read().unwrap().values() {
+                for obs in &registry_for_type.observables {
+                    if !stage_ids.contains(obs.actor_instance_id()) {
+                        continue;
+                    }
+                    let obs_clone = obs.clone();
+                    obs_futures.push(async move { obs_clone.exit_with_success().await });
+                    actor_ids.push(obs.actor_instance_id().to_string());
+                }
+            }
+            let res = future::join_all(obs_futures).await;
+            exit_statuses.extend(actor_ids.into_iter().zip(res));
         }
+        exit_statuses
+    }
+
+    /// Pauses all of the registered actors, and waits until they are all paused, i.e. until
+    /// they have all completed the processing of their in-flight message.
+    pub async fn pause_all(&self) {
+        self.send_command_to_all(|| Command::Pause).await;
+    }
+
+    /// Resumes all of the registered actors.
+    pub async fn resume_all(&self) {
+        self.send_command_to_all(|| Command::Resume).await;
+    }
+
+    async fn send_command_to_all(&self, command_fn: impl Fn() -> Command) {
+        self.gc();
+        let mut obs_futures = Vec::new();
+        for registry_for_type in self.actors.read().unwrap().values() {
+            for obs in &registry_for_type.observables {
+                let obs_clone = obs.clone();
+                let command = command_fn();
+                obs_futures.push(async move { obs_clone.send_command(command).await });
+            }
+        }
+        future::join_all(obs_futures).await;
     }
 
     pub async fn quit(&self) -> HashMap<String, ActorExitStatus> {
@@ -209,6 +485,53 @@ impl ActorRegistry {
     }
 }
 
+/// Groups the actors into stages using Kahn's algorithm.
+fn compute_shutdown_stages(
+    actor_ids: Vec<String>,
+    downstream_actor_ids: &HashMap<String, HashSet<String>>,
+) -> Vec<Vec<String>> {
+    let mut num_upstreams: HashMap<&str, usize> = actor_ids
+        .iter()
+        .map(|actor_id| (actor_id.as_str(), 0))
+        .collect();
+    for actor_id in &actor_ids {
+        for downstream_actor_id in downstream_actor_ids.get(actor_id).into_iter().flatten() {
+            if let Some(count) = num_upstreams.get_mut(downstream_actor_id.as_str()) {
+                *count += 1;
+            }
+        }
+    }
+    let mut stages = Vec::new();
+    while !num_upstreams.is_empty() {
+        let mut stage: Vec<String> = num_upstreams
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(actor_id, _)| actor_id.to_string())
+            .collect();
+        if stage.is_empty() {
+            // The remaining actors form at least one cycle.
+            let mut remaining: Vec<String> = num_upstreams
+                .keys()
+                .map(|actor_id| actor_id.to_string())
+                .collect();
+            remaining.sort();
+            stages.push(remaining);
+            break;
+        }
+        stage.sort();
+        for actor_id in &stage {
+            num_upstreams.remove(actor_id.as_str());
+            for downstream_actor_id in downstream_actor_ids.get(actor_id).into_iter().flatten() {
+                if let Some(count) = num_upstreams.get_mut(downstream_actor_id.as_str()) {
+                    *count -= 1;
+                }
+            }
+        }
+        stages.push(stage);
+    }
+    stages
+}
+
 fn get_iter<A: Actor>(
     actors: &mut HashMap<TypeId, ActorRegistryForSpecificType>,
 ) -> impl Iterator<Item = Mailbox<A>> + '_ {
@@ -220,8 +543,8 @@ fn get_iter<A: Actor>(
             registry_for_type
                 .observables
                 .iter()
-                .flat_map(|box_any| box_any.any().downcast_ref::<WeakMailbox<A>>())
-                .flat_map(|weak_mailbox| weak_mailbox.upgrade())
+                .flat_map(|box_any| box_any.any().downcast_ref::<TypedJsonObservable<A>>())
+                .flat_map(|typed_observable| typed_observable.weak_mailbox.upgrade())
         })
         .filter(|mailbox| !mailbox.is_disconnected())
 }
@@ -259,8 +582,10 @@ impl ActorJoinHandle {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::{HashMap, HashSet};
     use std::time::Duration;
 
+    use super::compute_shutdown_stages;
     use crate::tests::PingReceiverActor;
     use crate::Universe;
 
@@ -299,6 +624,49 @@ mod tests {
         let (_mailbox, _handle) = universe.spawn_builder().spawn(test_actor);
         let obs = universe.observe(Duration::from_millis(1000)).await;
         assert_eq!(obs.len(), 1);
+        assert!(obs[0].state.unwrap().is_running());
+        assert_eq!(obs[0].queue_depth, Some(0));
+        assert!(obs[0].last_progress_timestamp_millis.unwrap() > 0);
         universe.assert_quit().await;
     }
+
+    #[test]
+    fn test_compute_shutdown_stages() {
+        let wiring = |edges: &[(&str, &str)]| {
+            let mut downstream_actor_ids: HashMap<String, HashSet<String>> = HashMap::new();
+            for (upstream, downstream) in edges {
+                downstream_actor_ids
+                    .entry(upstream.to_string())
+                    .or_default()
+                    .insert(downstream.to_string());
+            }
+            downstream_actor_ids
+        };
+        let actor_ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            compute_shutdown_stages(
+                actor_ids(&["uploader", "indexer", "source", "packager", "janitor"]),
+                &wiring(&[
+                    ("source", "indexer"),
+                    ("indexer", "packager"),
+                    ("packager", "uploader"),
+                    // Not registered anymore.
+                    ("uploader", "publisher"),
+                ])
+            ),
+            vec![
+                actor_ids(&["janitor", "source"]),
+                actor_ids(&["indexer"]),
+                actor_ids(&["packager"]),
+                actor_ids(&["uploader"]),
+            ]
+        );
+        assert_eq!(
+            compute_shutdown_stages(
+                actor_ids(&["a", "b", "c"]),
+                &wiring(&[("a", "b"), ("b", "c"), ("c", "b")])
+            ),
+            vec![actor_ids(&["a"]), actor_ids(&["b", "c"])]
+        );
+    }
 }
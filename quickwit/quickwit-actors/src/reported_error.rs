@@ -0,0 +1,33 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+
+/// Number of reported errors a lagging subscriber can fall behind before missing some.
+pub(crate) const REPORTED_ERRORS_CHANNEL_CAPACITY: usize = 64;
+
+/// Non-fatal error reported by an actor with `ActorContext::report_error`.
+///
+/// Unlike the errors returned by handlers, reported errors do not terminate the actor.
+/// They can be followed with `ActorHandle::subscribe_reported_errors`.
+#[derive(Clone, Debug)]
+pub struct ReportedError {
+    pub actor_instance_id: String,
+    pub error: Arc<anyhow::Error>,
+}
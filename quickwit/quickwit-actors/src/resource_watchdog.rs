@@ -0,0 +1,324 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use quickwit_common::runtimes::RuntimeType;
+use quickwit_common::KillSwitch;
+use serde::Serialize;
+use tokio::runtime::Handle;
+use tracing::{error, info};
+
+use crate::{Actor, ActorContext, ActorExitStatus, Handler, Supervisable};
+
+const DEFAULT_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Resource usage of the process, as sampled by the [`ResourceWatchdog`].
+///
+/// Metrics that cannot be measured on the current platform are `None`.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct ResourceUsage {
+    pub rss_bytes_opt: Option<u64>,
+    pub num_open_fds_opt: Option<usize>,
+    /// Time it took for a no-op task spawned on the probed runtime (the blocking runtime by
+    /// default) to start. It grows when the runtime is saturated.
+    pub probe_latency_opt: Option<Duration>,
+}
+
+/// Thresholds beyond which the [`ResourceWatchdog`] triggers its actions. Thresholds set to
+/// `None` are not checked.
+#[derive(Clone, Debug, Default)]
+pub struct ResourceThresholds {
+    pub max_rss_bytes_opt: Option<u64>,
+    pub max_open_fds_opt: Option<usize>,
+    pub max_probe_latency_opt: Option<Duration>,
+}
+
+impl ResourceThresholds {
+    /// Returns the names of the resources whose usage exceeds their threshold.
+    fn exceeded_resources(&self, resource_usage: &ResourceUsage) -> Vec<&'static str> {
+        let mut exceeded_resources = Vec::new();
+        if exceeds(resource_usage.rss_bytes_opt, self.max_rss_bytes_opt) {
+            exceeded_resources.push("rss");
+        }
+        if exceeds(resource_usage.num_open_fds_opt, self.max_open_fds_opt) {
+            exceeded_resources.push("open_fds");
+        }
+        if exceeds(resource_usage.probe_latency_opt, self.max_probe_latency_opt) {
+            exceeded_resources.push("probe_latency");
+        }
+        exceeded_resources
+    }
+}
+
+fn exceeds<T: PartialOrd>(value_opt: Option<T>, threshold_opt: Option<T>) -> bool {
+    matches!((value_opt, threshold_opt), (Some(value), Some(threshold)) if value > threshold)
+}
+
+/// What the [`ResourceWatchdog`] does when a threshold is exceeded.
+pub enum WatchdogAction {
+    /// Logs the resource usage.
+    Log,
+    /// Pauses the actors (e.g. the sources of the indexing pipelines) until the resource
+    /// usage is back under the thresholds.
+    PauseActors(Vec<Arc<dyn Supervisable + Send + Sync>>),
+    /// Kills the kill switch, e.g. the one of the indexing pipelines.
+    TripKillSwitch(KillSwitch),
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ResourceWatchdogState {
+    pub resource_usage: ResourceUsage,
+    /// Resources whose usage exceeded their threshold during the last check.
+    pub exceeded_resources: Vec<&'static str>,
+    /// Number of times the resource usage went beyond the thresholds.
+    pub num_breaches: u64,
+}
+
+/// Actor periodically sampling the resource usage of the process, and triggering actions
+/// when it exceeds the configured thresholds.
+///
+/// It protects the services co-located with indexing (e.g. search) against a pipeline
+/// exhausting the memory, the file descriptors, or the blocking runtime of the process.
+/// The actions are triggered once when the usage goes beyond the thresholds, and the paused
+/// actors are resumed once it is back under.
+pub struct ResourceWatchdog {
+    thresholds: ResourceThresholds,
+    actions: Vec<WatchdogAction>,
+    check_interval: Duration,
+    probe_runtime_handle_opt: Option<Handle>,
+    state: ResourceWatchdogState,
+}
+
+impl ResourceWatchdog {
+    pub fn new(thresholds: ResourceThresholds, actions: Vec<WatchdogAction>) -> Self {
+        ResourceWatchdog {
+            thresholds,
+            actions,
+            check_interval: DEFAULT_CHECK_INTERVAL,
+            probe_runtime_handle_opt: None,
+            state: ResourceWatchdogState::default(),
+        }
+    }
+
+    pub fn set_check_interval(mut self, check_interval: Duration) -> Self {
+        self.check_interval = check_interval;
+        self
+    }
+
+    /// Sets the runtime whose saturation is probed. Defaults to the blocking runtime.
+    pub fn set_probe_runtime_handle(mut self, probe_runtime_handle: Handle) -> Self {
+        self.probe_runtime_handle_opt = Some(probe_runtime_handle);
+        self
+    }
+
+    async fn sample_resource_usage(&self, ctx: &ActorContext<Self>) -> ResourceUsage {
+        let probe_runtime_handle = self
+            .probe_runtime_handle_opt
+            .clone()
+            .unwrap_or_else(|| RuntimeType::Blocking.get_runtime_handle());
+        let start = Instant::now();
+        let probe_latency_opt = ctx
+            .protect_future(probe_runtime_handle.spawn(async move { start.elapsed() }))
+            .await
+            .ok();
+        ResourceUsage {
+            rss_bytes_opt: rss_bytes(),
+            num_open_fds_opt: num_open_fds(),
+            probe_latency_opt,
+        }
+    }
+
+    fn on_breach(&self) {
+        for action in &self.actions {
+            match action {
+                WatchdogAction::Log => {
+                    error!(
+                        exceeded_resources=?self.state.exceeded_resources,
+                        resource_usage=?self.state.resource_usage,
+                        "resource-thresholds-exceeded"
+                    );
+                }
+                WatchdogAction::PauseActors(actors) => {
+                    for actor in actors {
+                        actor.pause();
+                    }
+                }
+                WatchdogAction::TripKillSwitch(kill_switch) => {
+                    let reason = format!(
+                        "resource thresholds exceeded: {:?}",
+                        self.state.exceeded_resources
+                    );
+                    kill_switch.kill_with_reason(reason);
+                }
+            }
+        }
+    }
+
+    fn on_recovery(&self) {
+        info!(resource_usage=?self.state.resource_usage, "resource-usage-recovered");
+        for action in &self.actions {
+            if let WatchdogAction::PauseActors(actors) = action {
+                for actor in actors {
+                    actor.resume();
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CheckResources;
+
+#[async_trait]
+impl Actor for ResourceWatchdog {
+    type ObservableState = ResourceWatchdogState;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.state.clone()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.handle(CheckResources, ctx).await
+    }
+}
+
+#[async_trait]
+impl Handler<CheckResources> for ResourceWatchdog {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: CheckResources,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let resource_usage = self.sample_resource_usage(ctx).await;
+        let exceeded_resources = self.thresholds.exceeded_resources(&resource_usage);
+        let was_breached = !self.state.exceeded_resources.is_empty();
+        let is_breached = !exceeded_resources.is_empty();
+        self.state.resource_usage = resource_usage;
+        self.state.exceeded_resources = exceeded_resources;
+
+        if is_breached && !was_breached {
+            self.state.num_breaches += 1;
+            self.on_breach();
+        } else if was_breached && !is_breached {
+            self.on_recovery();
+        }
+        ctx.schedule_self_msg(self.check_interval, CheckResources)
+            .await;
+        Ok(())
+    }
+}
+
+/// Returns the resident set size of the process.
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let num_resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: `sysconf` has no preconditions.
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    if page_size <= 0 {
+        return None;
+    }
+    Some(num_resident_pages * page_size as u64)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss_bytes() -> Option<u64> {
+    None
+}
+
+/// Returns the number of file descriptors opened by the process.
+#[cfg(target_os = "linux")]
+fn num_open_fds() -> Option<usize> {
+    let fd_dir = std::fs::read_dir("/proc/self/fd").ok()?;
+    Some(fd_dir.count())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn num_open_fds() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::PingReceiverActor;
+    use crate::{ActorState, Universe};
+
+    #[test]
+    fn test_resource_thresholds_exceeded_resources() {
+        let thresholds = ResourceThresholds {
+            max_rss_bytes_opt: Some(1_000),
+            max_open_fds_opt: None,
+            max_probe_latency_opt: Some(Duration::from_millis(100)),
+        };
+        let resource_usage = ResourceUsage {
+            rss_bytes_opt: Some(2_000),
+            num_open_fds_opt: Some(10_000),
+            probe_latency_opt: None,
+        };
+        assert_eq!(thresholds.exceeded_resources(&resource_usage), vec!["rss"]);
+        let resource_usage = ResourceUsage {
+            rss_bytes_opt: Some(1_000),
+            num_open_fds_opt: Some(10_000),
+            probe_latency_opt: Some(Duration::from_millis(200)),
+        };
+        assert_eq!(
+            thresholds.exceeded_resources(&resource_usage),
+            vec!["probe_latency"]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_resource_watchdog_triggers_actions() {
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, source_handle) =
+            universe.spawn_builder().spawn(PingReceiverActor::default());
+        let source_handle = Arc::new(source_handle);
+        let kill_switch = KillSwitch::default();
+        let thresholds = ResourceThresholds {
+            max_open_fds_opt: Some(0),
+            ..Default::default()
+        };
+        let actions = vec![
+            WatchdogAction::Log,
+            WatchdogAction::PauseActors(vec![source_handle.clone()]),
+            WatchdogAction::TripKillSwitch(kill_switch.clone()),
+        ];
+        let resource_watchdog =
+            ResourceWatchdog::new(thresholds, actions).set_probe_runtime_handle(Handle::current());
+        let (_watchdog_mailbox, watchdog_handle) =
+            universe.spawn_builder().spawn(resource_watchdog);
+        let watchdog_state = watchdog_handle.process_pending_and_observe().await.state;
+        assert_eq!(watchdog_state.exceeded_resources, vec!["open_fds"]);
+        assert_eq!(watchdog_state.num_breaches, 1);
+        assert!(watchdog_state.resource_usage.rss_bytes_opt.is_some());
+        assert!(watchdog_state.resource_usage.probe_latency_opt.is_some());
+        assert!(kill_switch.is_dead());
+        source_handle.observe().await;
+        assert_eq!(source_handle.state(), ActorState::Paused);
+        drop(source_handle);
+        universe.assert_quit().await;
+    }
+}
@@ -0,0 +1,103 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::fmt;
+use std::time::Duration;
+
+use rand::Rng;
+use thiserror::Error;
+
+/// Exponential backoff policy used by `ActorContext::retry`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: usize,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(20),
+            max_attempts: 10,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the delay to wait after the given failed attempt (starting at 1): a random
+    /// duration between 0 and `min(base_delay * 2^attempt, max_delay)`, a.k.a. full jitter.
+    pub(crate) fn backoff(&self, num_attempts: usize, rng: &mut impl Rng) -> Duration {
+        let ceiling = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(num_attempts.min(31) as u32))
+            .min(self.max_delay);
+        if ceiling.is_zero() {
+            return Duration::ZERO;
+        }
+        rng.gen_range(Duration::ZERO..=ceiling)
+    }
+}
+
+/// Failed attempt of an operation retried with `ActorContext::retry`.
+#[derive(Clone, Debug)]
+pub struct FailedAttempt {
+    /// Debug rendering of the error returned by the attempt.
+    pub error: String,
+    /// Delay waited before the next attempt, if any.
+    pub backoff_opt: Option<Duration>,
+}
+
+/// Error returned by `ActorContext::retry` once all of the attempts failed.
+#[derive(Debug, Error)]
+#[error("operation failed after {} attempts: {last_error:?}", .attempts.len())]
+pub struct RetryError<E: fmt::Debug> {
+    pub last_error: E,
+    pub attempts: Vec<FailedAttempt>,
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_backoff() {
+        let retry_policy = RetryPolicy {
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            max_attempts: 10,
+        };
+        let mut rng = StdRng::seed_from_u64(0);
+        for _ in 0..100 {
+            assert!(retry_policy.backoff(1, &mut rng) <= Duration::from_millis(200));
+            assert!(retry_policy.backoff(3, &mut rng) <= Duration::from_millis(800));
+            assert!(retry_policy.backoff(100, &mut rng) <= Duration::from_secs(1));
+        }
+        let no_delay_policy = RetryPolicy {
+            base_delay: Duration::ZERO,
+            ..retry_policy
+        };
+        assert_eq!(no_delay_policy.backoff(1, &mut rng), Duration::ZERO);
+    }
+}
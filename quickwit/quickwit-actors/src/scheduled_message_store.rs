@@ -0,0 +1,328 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::error;
+
+/// A message scheduled by an actor for itself, waiting to be delivered.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ScheduledMessageRecord {
+    record_id: u64,
+    persistence_key: String,
+    message_type: String,
+    deadline_unix_millis: u64,
+    message: JsonValue,
+}
+
+/// An entry of the log backing the store.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum LogEntry {
+    Insert(ScheduledMessageRecord),
+    Remove { record_id: u64 },
+}
+
+/// Persists the messages that actors schedule for themselves with
+/// `ActorContext::schedule_persistent_self_msg`, so that scheduled work (e.g. "retry
+/// upload in 5 minutes") survives a process restart.
+///
+/// Changes are appended to a log file, one JSON entry per line, and synced to disk before
+/// they are acknowledged. The log is compacted when the store is opened. Actors get their
+/// pending messages back by calling `ActorContext::restore_persistent_self_msgs`, typically
+/// in `Actor::initialize`.
+pub struct ScheduledMessageStore {
+    path: PathBuf,
+    log_file: tokio::sync::Mutex<File>,
+    // In-memory view of the log. The lock is never held while doing IO.
+    records: Mutex<BTreeMap<u64, ScheduledMessageRecord>>,
+    next_record_id: AtomicU64,
+}
+
+impl ScheduledMessageStore {
+    /// Opens the store backed by the file at `path`, loading the records persisted by a
+    /// previous process, if any.
+    ///
+    /// This is meant to be called once at startup: it reads and compacts the log
+    /// synchronously.
+    pub fn open(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let log = match std::fs::read_to_string(&path) {
+            Ok(log) => log,
+            Err(io_error) if io_error.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(io_error) => {
+                return Err(io_error).with_context(|| {
+                    format!(
+                        "Failed to read scheduled messages file `{}`.",
+                        path.display()
+                    )
+                });
+            }
+        };
+        let mut records = BTreeMap::new();
+        let mut next_record_id = 0;
+
+        for line in log.lines() {
+            // The last entry may be incomplete if the process stopped while appending it. It
+            // was not acknowledged, so it can be skipped.
+            let Ok(log_entry) = serde_json::from_str::<LogEntry>(line) else {
+                error!(path=%path.display(), "skipping-invalid-scheduled-message-entry");
+                continue;
+            };
+            match log_entry {
+                LogEntry::Insert(record) => {
+                    next_record_id = next_record_id.max(record.record_id + 1);
+                    records.insert(record.record_id, record);
+                }
+                LogEntry::Remove { record_id } => {
+                    records.remove(&record_id);
+                }
+            }
+        }
+        let log_file = compact_log(&path, records.values())?;
+
+        Ok(ScheduledMessageStore {
+            path,
+            log_file: tokio::sync::Mutex::new(File::from_std(log_file)),
+            records: Mutex::new(records),
+            next_record_id: AtomicU64::new(next_record_id),
+        })
+    }
+
+    /// Returns the number of messages currently persisted.
+    pub fn len(&self) -> usize {
+        self.records.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) async fn insert(
+        &self,
+        persistence_key: &str,
+        message_type: &str,
+        after_duration: Duration,
+        message: JsonValue,
+    ) -> anyhow::Result<u64> {
+        let deadline = SystemTime::now() + after_duration;
+        let deadline_unix_millis = deadline
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        let record_id = self.next_record_id.fetch_add(1, Ordering::Relaxed);
+        let record = ScheduledMessageRecord {
+            record_id,
+            persistence_key: persistence_key.to_string(),
+            message_type: message_type.to_string(),
+            deadline_unix_millis,
+            message,
+        };
+        self.append(&[LogEntry::Insert(record.clone())]).await?;
+        self.records.lock().unwrap().insert(record_id, record);
+        Ok(record_id)
+    }
+
+    /// Removes a delivered message. The removal is persisted in the background.
+    pub(crate) fn remove(self: Arc<Self>, record_id: u64) {
+        if !self.remove_record(record_id) {
+            return;
+        }
+        tokio::spawn(async move {
+            if let Err(error) = self.persist_removal(record_id).await {
+                error!(error=?error, "failed-to-persist-scheduled-messages");
+            }
+        });
+    }
+
+    fn remove_record(&self, record_id: u64) -> bool {
+        self.records.lock().unwrap().remove(&record_id).is_some()
+    }
+
+    async fn persist_removal(&self, record_id: u64) -> anyhow::Result<()> {
+        self.append(&[LogEntry::Remove { record_id }]).await
+    }
+
+    /// Removes and returns the messages of type `message_type` persisted under
+    /// `persistence_key`, along with the time left until their deadline.
+    pub(crate) async fn take(
+        &self,
+        persistence_key: &str,
+        message_type: &str,
+    ) -> anyhow::Result<Vec<(Duration, JsonValue)>> {
+        let taken_records: Vec<ScheduledMessageRecord> = {
+            let mut records = self.records.lock().unwrap();
+            let taken_record_ids: Vec<u64> = records
+                .values()
+                .filter(|record| {
+                    record.persistence_key == persistence_key && record.message_type == message_type
+                })
+                .map(|record| record.record_id)
+                .collect();
+            taken_record_ids
+                .iter()
+                .flat_map(|record_id| records.remove(record_id))
+                .collect()
+        };
+        let log_entries: Vec<LogEntry> = taken_records
+            .iter()
+            .map(|record| LogEntry::Remove {
+                record_id: record.record_id,
+            })
+            .collect();
+        self.append(&log_entries).await?;
+
+        let now = SystemTime::now();
+        let messages = taken_records
+            .into_iter()
+            .map(|record| {
+                let deadline = UNIX_EPOCH + Duration::from_millis(record.deadline_unix_millis);
+                let time_left = deadline.duration_since(now).unwrap_or_default();
+                (time_left, record.message)
+            })
+            .collect();
+        Ok(messages)
+    }
+
+    /// Appends entries to the log and syncs them to disk.
+    async fn append(&self, log_entries: &[LogEntry]) -> anyhow::Result<()> {
+        if log_entries.is_empty() {
+            return Ok(());
+        }
+        let mut buffer = Vec::new();
+
+        for log_entry in log_entries {
+            serde_json::to_writer(&mut buffer, log_entry)?;
+            buffer.push(b'\n');
+        }
+        let mut log_file = self.log_file.lock().await;
+        log_file
+            .write_all(&buffer)
+            .await
+            .and(log_file.sync_data().await)
+            .with_context(|| {
+                format!(
+                    "Failed to write scheduled messages file `{}`.",
+                    self.path.display()
+                )
+            })?;
+        Ok(())
+    }
+}
+
+/// Rewrites the log with the live records only, and returns the log file opened in append
+/// mode.
+fn compact_log<'a>(
+    path: &Path,
+    records: impl Iterator<Item = &'a ScheduledMessageRecord>,
+) -> anyhow::Result<std::fs::File> {
+    let write_error = || {
+        format!(
+            "Failed to write scheduled messages file `{}`.",
+            path.display()
+        )
+    };
+    let tmp_path = path.with_extension("tmp");
+    let mut tmp_file = std::fs::File::create(&tmp_path).with_context(write_error)?;
+
+    for record in records {
+        serde_json::to_writer(&mut tmp_file, &LogEntry::Insert(record.clone()))?;
+        tmp_file.write_all(b"\n").with_context(write_error)?;
+    }
+    tmp_file.sync_all().with_context(write_error)?;
+    std::fs::rename(&tmp_path, path).with_context(write_error)?;
+
+    let log_file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(path)
+        .with_context(write_error)?;
+    Ok(log_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_scheduled_message_store() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let path = temp_dir.path().join("scheduled-messages.json");
+        let store = ScheduledMessageStore::open(&path)?;
+        assert!(store.is_empty());
+        let first_record_id = store
+            .insert(
+                "pipeline-1",
+                "RetryUpload",
+                Duration::from_secs(300),
+                JsonValue::from(1),
+            )
+            .await?;
+        store
+            .insert(
+                "pipeline-1",
+                "RetryUpload",
+                Duration::ZERO,
+                JsonValue::from(2),
+            )
+            .await?;
+        store
+            .insert(
+                "pipeline-2",
+                "RetryUpload",
+                Duration::ZERO,
+                JsonValue::from(3),
+            )
+            .await?;
+        assert!(store.remove_record(first_record_id));
+        store.persist_removal(first_record_id).await?;
+        store
+            .insert(
+                "pipeline-1",
+                "RetryUpload",
+                Duration::from_secs(300),
+                JsonValue::from(4),
+            )
+            .await?;
+        assert_eq!(store.len(), 3);
+
+        // Simulates a restart.
+        drop(store);
+        let store = ScheduledMessageStore::open(&path)?;
+        assert_eq!(store.len(), 3);
+        let mut messages = store.take("pipeline-1", "RetryUpload").await?;
+        messages.sort_by_key(|(time_left, _)| *time_left);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].0, Duration::ZERO);
+        assert_eq!(messages[0].1, JsonValue::from(2));
+        assert!(messages[1].0 > Duration::from_secs(200));
+        assert_eq!(messages[1].1, JsonValue::from(4));
+        assert!(store.take("pipeline-1", "RetryUpload").await?.is_empty());
+        assert_eq!(store.len(), 1);
+        Ok(())
+    }
+}
@@ -17,9 +17,6 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::cmp::Reverse;
-use std::collections::binary_heap::PeekMut;
-use std::collections::BinaryHeap;
 use std::future::Future;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
@@ -28,6 +25,13 @@ use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 use tokio::task::JoinHandle;
 
+use crate::metrics::ACTOR_METRICS;
+use crate::timer_wheel::TimerWheel;
+
+/// Number of messages queued in the scheduler mailbox above which events are scheduled
+/// using local timers rather than by the scheduler.
+const DEFAULT_SCHEDULER_SATURATION_THRESHOLD: usize = 10_000;
+
 type Callback = Box<dyn FnOnce() + Sync + Send + 'static>;
 
 struct TimeoutEvent {
@@ -74,6 +78,7 @@ pub struct SchedulerClient {
 struct SchedulerClientInner {
     no_advance_time_guard_count: AtomicUsize,
     accelerate_time: AtomicBool,
+    saturation_threshold: usize,
     tx: flume::Sender<SchedulerMessage>,
 }
 
@@ -97,17 +102,35 @@ impl SchedulerClient {
     ///
     /// `fut` will be executed in the scheduler task, so it is
     /// required to be short.
+    ///
+    /// If the scheduler is saturated, the event is scheduled using a local timer instead, unless
+    /// time is accelerated.
     pub fn schedule_event<F: FnOnce() + Send + Sync + 'static>(
         &self,
         callback: F,
         timeout: Duration,
     ) {
+        if self.queue_depth() >= self.inner.saturation_threshold && !self.time_is_accelerated() {
+            if let Ok(runtime_handle) = tokio::runtime::Handle::try_current() {
+                ACTOR_METRICS.scheduler_local_timers_total.inc();
+                runtime_handle.spawn(async move {
+                    tokio::time::sleep(timeout).await;
+                    callback();
+                });
+                return;
+            }
+        }
         let _ = self.inner.tx.send(SchedulerMessage::Schedule {
             callback: Box::new(callback),
             timeout,
         });
     }
 
+    /// Returns the number of messages queued in the scheduler mailbox.
+    pub fn queue_depth(&self) -> usize {
+        self.inner.tx.len()
+    }
+
     // Increases the number of reasons to not simulate advance time.
     pub(crate) fn inc_no_advance_time(&self) {
         self.inner
@@ -194,17 +217,23 @@ impl Drop for NoAdvanceTimeGuard {
 }
 
 pub fn start_scheduler() -> SchedulerClient {
+    start_scheduler_with_saturation_threshold(DEFAULT_SCHEDULER_SATURATION_THRESHOLD)
+}
+
+fn start_scheduler_with_saturation_threshold(saturation_threshold: usize) -> SchedulerClient {
     let (tx, rx) = flume::unbounded::<SchedulerMessage>();
     let scheduler_client = SchedulerClient {
         inner: Arc::new(SchedulerClientInner {
             no_advance_time_guard_count: AtomicUsize::default(),
             accelerate_time: Default::default(),
+            saturation_threshold,
             tx,
         }),
     };
     let mut scheduler = Scheduler::new(&scheduler_client);
     tokio::spawn(async move {
         while let Ok(scheduler_message) = rx.recv_async().await {
+            ACTOR_METRICS.scheduler_queue_depth.set(rx.len() as i64);
             match scheduler_message {
                 SchedulerMessage::ProcessTime => scheduler.process_time(),
                 SchedulerMessage::Schedule { callback, timeout } => {
@@ -224,7 +253,7 @@ struct Scheduler {
     // Instant::now() + simulated_time_shift`. By default `simulated_time_shift` is set to 0
     // but it can be shifted when the scheduler has to process a simulate sleep event`.
     simulated_time_shift: Duration,
-    future_events: BinaryHeap<Reverse<TimeoutEvent>>,
+    future_events: TimerWheel<TimeoutEvent>,
     next_timeout: Option<JoinHandle<()>>,
     weak_scheduler_client: Weak<SchedulerClientInner>,
 }
@@ -238,14 +267,11 @@ impl Scheduler {
     /// - schedule a message to make sure process_time is called in time for the next event.
     fn process_time(&mut self) {
         let now = self.simulated_now();
-        // Pops all elapsed events and executes the associated callback.
-        while let Some(next_event_peek) = self.future_events.peek_mut() {
-            if next_event_peek.0.deadline > now {
-                // The next event is out of scope.
-                break;
-            }
-            let next_event = PeekMut::pop(next_event_peek);
-            (next_event.0.callback)();
+        // Pops all elapsed events and executes the associated callback, in order.
+        let mut elapsed_events = self.future_events.pop_expired(now);
+        elapsed_events.sort_unstable();
+        for elapsed_event in elapsed_events {
+            (elapsed_event.callback)();
         }
 
         // If the condition to accelerate time are met, we can
@@ -258,7 +284,7 @@ impl Scheduler {
     fn process_schedule(&mut self, callback: Callback, timeout: Duration) {
         let new_evt_deadline = self.simulated_now() + timeout;
         let timeout_event = self.timeout_event(new_evt_deadline, callback);
-        self.future_events.push(Reverse(timeout_event));
+        self.future_events.insert(new_evt_deadline, timeout_event);
         self.process_time();
     }
 
@@ -310,7 +336,7 @@ impl Scheduler {
         Scheduler {
             event_id_generator: 0u64,
             simulated_time_shift: Duration::default(),
-            future_events: Default::default(),
+            future_events: TimerWheel::new(Instant::now()),
             next_timeout: None,
             weak_scheduler_client: Arc::downgrade(&scheduler_client.inner),
         }
@@ -343,7 +369,7 @@ impl Scheduler {
     }
 
     fn next_event_deadline(&self) -> Option<Instant> {
-        self.future_events.peek().map(|rev| rev.0.deadline)
+        self.future_events.next_deadline()
     }
 
     fn simulated_now(&self) -> Instant {
@@ -369,6 +395,8 @@ mod tests {
 
     use async_trait::async_trait;
 
+    use super::start_scheduler_with_saturation_threshold;
+    use crate::metrics::ACTOR_METRICS;
     use crate::{Actor, ActorContext, ActorExitStatus, Handler, Universe};
 
     struct ClockActor {
@@ -418,6 +446,15 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_scheduler_falls_back_to_local_timers_when_saturated() {
+        let scheduler_client = start_scheduler_with_saturation_threshold(0);
+        let local_timers_before = ACTOR_METRICS.scheduler_local_timers_total.get();
+        scheduler_client.sleep(Duration::from_millis(10)).await;
+        assert_eq!(scheduler_client.queue_depth(), 0);
+        assert!(ACTOR_METRICS.scheduler_local_timers_total.get() > local_timers_before);
+    }
+
     #[tokio::test]
     async fn test_scheduler_advance_time_fast_forward_scheduled_message() {
         let start = Instant::now();
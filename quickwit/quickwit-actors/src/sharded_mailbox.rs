@@ -0,0 +1,173 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::oneshot;
+
+use crate::{Actor, DeferableReplyHandler, Mailbox, SendError, TrySendError};
+
+/// A single logical mailbox sharded across several channels, all consumed by the same
+/// actor, in a fair, round-robin fashion.
+///
+/// This removes the contention on the channel of actors fed by a lot of concurrent
+/// producers, like routers or aggregators. It is obtained via
+/// `SpawnBuilder::spawn_sharded`.
+///
+/// Messages sent with `send_message` are spread over the shards, so two messages sent by
+/// the same producer may be processed out of order. Producers relying on ordering
+/// should send their messages to the shard returned by [`ShardedMailbox::shard`].
+pub struct ShardedMailbox<A: Actor> {
+    inner: Arc<Inner<A>>,
+}
+
+struct Inner<A: Actor> {
+    shards: Vec<Mailbox<A>>,
+    next_shard: AtomicUsize,
+}
+
+impl<A: Actor> Clone for ShardedMailbox<A> {
+    fn clone(&self) -> Self {
+        ShardedMailbox {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<A: Actor> fmt::Debug for ShardedMailbox<A> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("ShardedMailbox")
+            .field(
+                "actor_instance_id",
+                &self.inner.shards[0].actor_instance_id(),
+            )
+            .field("num_shards", &self.num_shards())
+            .finish()
+    }
+}
+
+impl<A: Actor> ShardedMailbox<A> {
+    pub(crate) fn new(shards: Vec<Mailbox<A>>) -> Self {
+        assert!(
+            !shards.is_empty(),
+            "A sharded mailbox requires at least one shard."
+        );
+        ShardedMailbox {
+            inner: Arc::new(Inner {
+                shards,
+                next_shard: AtomicUsize::new(0),
+            }),
+        }
+    }
+
+    pub fn num_shards(&self) -> usize {
+        self.inner.shards.len()
+    }
+
+    /// Returns the shard associated with the given key. The same key is always associated
+    /// with the same shard.
+    pub fn shard(&self, key: impl Hash) -> &Mailbox<A> {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        let shard_ord = (hasher.finish() % self.num_shards() as u64) as usize;
+        &self.inner.shards[shard_ord]
+    }
+
+    fn next_shard(&self) -> &Mailbox<A> {
+        let shard_ord = self.inner.next_shard.fetch_add(1, Ordering::Relaxed) % self.num_shards();
+        &self.inner.shards[shard_ord]
+    }
+
+    /// Sends a message to one of the shards, waiting for some room in its queue if it is
+    /// full.
+    pub async fn send_message<M>(
+        &self,
+        message: M,
+    ) -> Result<oneshot::Receiver<A::Reply>, SendError>
+    where
+        A: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        self.next_shard().send_message(message).await
+    }
+
+    /// Tries to send a message to one of the shards, without waiting.
+    pub fn try_send_message<M>(
+        &self,
+        message: M,
+    ) -> Result<oneshot::Receiver<A::Reply>, TrySendError<M>>
+    where
+        A: DeferableReplyHandler<M>,
+        M: fmt::Debug + Send + 'static,
+    {
+        self.next_shard().try_send_message(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::mem;
+
+    use crate::tests::{Ping, PingReceiverActor};
+    use crate::Universe;
+
+    #[tokio::test]
+    async fn test_sharded_mailbox() {
+        let universe = Universe::with_accelerated_time();
+        let (sharded_mailbox, handle) = universe
+            .spawn_builder()
+            .spawn_sharded(PingReceiverActor::default(), 4);
+        assert_eq!(sharded_mailbox.num_shards(), 4);
+        let mut producer_join_handles = Vec::new();
+        for producer_id in 0..8 {
+            let sharded_mailbox = sharded_mailbox.clone();
+            let producer_join_handle = tokio::spawn(async move {
+                for _ in 0..10 {
+                    sharded_mailbox.send_message(Ping).await.unwrap();
+                }
+                sharded_mailbox
+                    .shard(producer_id)
+                    .send_message(Ping)
+                    .await
+                    .unwrap();
+            });
+            producer_join_handles.push(producer_join_handle);
+        }
+        for producer_join_handle in producer_join_handles {
+            producer_join_handle.await.unwrap();
+        }
+        assert_eq!(
+            sharded_mailbox.shard("key").actor_instance_id(),
+            sharded_mailbox.shard("key").actor_instance_id()
+        );
+        assert_eq!(handle.process_pending_and_observe().await.state, 88);
+
+        // The actor exits once all of the clones of the sharded mailbox have been dropped.
+        mem::drop(sharded_mailbox);
+        let (exit_status, ping_count) = handle.join().await;
+        assert!(exit_status.is_success());
+        assert_eq!(ping_count, 88);
+        universe.assert_quit().await;
+    }
+}
@@ -17,19 +17,36 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::panic::AssertUnwindSafe;
+use std::time::{Duration, Instant};
+
 use anyhow::Context;
+use futures::FutureExt;
 use quickwit_common::metrics::IntCounter;
 use sync_wrapper::SyncWrapper;
 use tokio::sync::watch;
-use tracing::{debug, error, info};
+use tracing::{debug, debug_span, error, info, warn, Instrument};
 
+use crate::cpu_time::{measure_cpu_usage, CpuUsage};
+use crate::dead_letter_queue::{DeadLetter, DeadLetterQueue};
 use crate::envelope::Envelope;
-use crate::mailbox::{create_mailbox, Inbox};
+use crate::flight_recorder::FlightRecord;
+use crate::isolated_runtime::IsolatedRuntime;
+use crate::lifecycle_events::{ActorLifecycleEventKind, LifecycleEventBus};
+use crate::mailbox::{create_mailbox, create_multi_source_mailbox, Inbox, Priority};
+use crate::memory_budget::MemoryBudget;
+use crate::message_tracing::{should_trace_message, TruncatedDebug};
+use crate::metrics::ACTOR_METRICS;
+use crate::metrics_sink::MetricsSink;
+use crate::panic_policy::PanicPolicy;
+use crate::readiness::ReadinessGate;
 use crate::registry::{ActorJoinHandle, ActorRegistry};
+use crate::scheduled_message_store::ScheduledMessageStore;
 use crate::scheduler::{NoAdvanceTimeGuard, SchedulerClient};
 use crate::supervisor::Supervisor;
 use crate::{
-    Actor, ActorContext, ActorExitStatus, ActorHandle, KillSwitch, Mailbox, QueueCapacity,
+    Actor, ActorContext, ActorExitStatus, ActorHandle, Command, DrainPolicy, KillSwitch, Mailbox,
+    QueueCapacity, ShardedMailbox,
 };
 
 #[derive(Clone)]
@@ -37,6 +54,18 @@ pub struct SpawnContext {
     pub(crate) scheduler_client: SchedulerClient,
     pub(crate) kill_switch: KillSwitch,
     pub(crate) registry: ActorRegistry,
+    pub(crate) scheduled_message_store_opt: Option<Arc<ScheduledMessageStore>>,
+    pub(crate) memory_budget_opt: Option<Arc<MemoryBudget>>,
+    pub(crate) isolated_runtime_opt: Option<IsolatedRuntime>,
+    pub(crate) dead_letter_queue: DeadLetterQueue,
+    pub(crate) lifecycle_event_bus: LifecycleEventBus,
+    pub(crate) metrics_sink_opt: Option<Arc<dyn MetricsSink>>,
+    pub(crate) panic_policy: PanicPolicy,
+    pub(crate) track_message_ages: bool,
+    pub(crate) record_flights: bool,
+    pub(crate) measure_cpu_usage: bool,
+    #[cfg(feature = "failpoints")]
+    pub(crate) failpoint_registry: crate::FailpointRegistry,
 }
 
 impl SpawnContext {
@@ -45,6 +74,18 @@ impl SpawnContext {
             scheduler_client,
             kill_switch: Default::default(),
             registry: ActorRegistry::default(),
+            scheduled_message_store_opt: None,
+            memory_budget_opt: None,
+            isolated_runtime_opt: None,
+            dead_letter_queue: DeadLetterQueue::default(),
+            lifecycle_event_bus: LifecycleEventBus::default(),
+            metrics_sink_opt: None,
+            panic_policy: PanicPolicy::default(),
+            track_message_ages: false,
+            record_flights: false,
+            measure_cpu_usage: false,
+            #[cfg(feature = "failpoints")]
+            failpoint_registry: crate::FailpointRegistry::default(),
         }
     }
 
@@ -61,6 +102,26 @@ impl SpawnContext {
             actor_name.to_string(),
             queue_capacity,
             Some(self.scheduler_client.clone()),
+            self.metrics_sink_opt.clone(),
+            self.track_message_ages,
+        )
+    }
+
+    /// Creates one mailbox per queue capacity, all feeding the same inbox.
+    ///
+    /// The resulting inbox can be used with [`SpawnBuilder::set_mailboxes`] along with
+    /// any of the mailboxes.
+    pub fn create_multi_source_mailbox<A: Actor>(
+        &self,
+        actor_name: impl ToString,
+        queue_capacities: &[QueueCapacity],
+    ) -> (Vec<Mailbox<A>>, Inbox<A>) {
+        create_multi_source_mailbox(
+            actor_name.to_string(),
+            queue_capacities,
+            Some(self.scheduler_client.clone()),
+            self.metrics_sink_opt.clone(),
+            self.track_message_ages,
         )
     }
 
@@ -69,6 +130,18 @@ impl SpawnContext {
             scheduler_client: self.scheduler_client.clone(),
             kill_switch: self.kill_switch.child(),
             registry: self.registry.clone(),
+            scheduled_message_store_opt: self.scheduled_message_store_opt.clone(),
+            memory_budget_opt: self.memory_budget_opt.clone(),
+            isolated_runtime_opt: self.isolated_runtime_opt.clone(),
+            dead_letter_queue: self.dead_letter_queue.clone(),
+            lifecycle_event_bus: self.lifecycle_event_bus.clone(),
+            metrics_sink_opt: self.metrics_sink_opt.clone(),
+            panic_policy: self.panic_policy,
+            track_message_ages: self.track_message_ages,
+            record_flights: self.record_flights,
+            measure_cpu_usage: self.measure_cpu_usage,
+            #[cfg(feature = "failpoints")]
+            failpoint_registry: self.failpoint_registry.clone(),
         }
     }
 }
@@ -80,6 +153,9 @@ pub struct SpawnBuilder<A: Actor> {
     #[allow(clippy::type_complexity)]
     mailboxes: Option<(Mailbox<A>, Inbox<A>)>,
     backpressure_micros_counter_opt: Option<IntCounter>,
+    readiness_gate_opt: Option<ReadinessGate>,
+    queue_capacity_opt: Option<QueueCapacity>,
+    generation: u64,
 }
 
 impl<A: Actor> SpawnBuilder<A> {
@@ -88,9 +164,25 @@ impl<A: Actor> SpawnBuilder<A> {
             spawn_ctx,
             mailboxes: None,
             backpressure_micros_counter_opt: None,
+            readiness_gate_opt: None,
+            queue_capacity_opt: None,
+            generation: 1,
         }
     }
 
+    /// Sets the generation of an actor respawned on the mailboxes of its predecessor.
+    pub(crate) fn set_generation(mut self, generation: u64) -> Self {
+        self.generation = generation;
+        self
+    }
+
+    /// Holds the processing of messages until all of the actors spawned with the same
+    /// readiness gate are ready. See [`ReadinessGate`].
+    pub fn set_readiness_gate(mut self, readiness_gate: ReadinessGate) -> Self {
+        self.readiness_gate_opt = Some(readiness_gate);
+        self
+    }
+
     /// Sets a specific kill switch for the actor.
     ///
     /// By default, the kill switch is inherited from the context that was used to
@@ -100,6 +192,16 @@ impl<A: Actor> SpawnBuilder<A> {
         self
     }
 
+    /// Runs the actor, and the actors it spawns, on the given isolated runtime.
+    ///
+    /// By default, the isolated runtime is inherited from the context that was used to
+    /// spawn the actor. Without isolated runtime, the actor runs on
+    /// [`Actor::runtime_handle`].
+    pub fn set_isolated_runtime(mut self, isolated_runtime: IsolatedRuntime) -> Self {
+        self.spawn_ctx.isolated_runtime_opt = Some(isolated_runtime);
+        self
+    }
+
     /// Sets a specific set of mailbox.
     ///
     /// By default, a brand new set of mailboxes will be created
@@ -112,6 +214,20 @@ impl<A: Actor> SpawnBuilder<A> {
         self
     }
 
+    /// Overrides the queue capacity of the mailbox of the actor, which defaults to
+    /// [`Actor::queue_capacity`].
+    ///
+    /// It has no effect if the mailboxes are set with `set_mailboxes`.
+    pub fn set_queue_capacity(mut self, queue_capacity: QueueCapacity) -> Self {
+        self.queue_capacity_opt = Some(queue_capacity);
+        self
+    }
+
+    fn queue_capacity(&self, actor: &A) -> QueueCapacity {
+        self.queue_capacity_opt
+            .unwrap_or_else(|| actor.queue_capacity())
+    }
+
     /// Adds a counter to track the amount of time the actor is
     /// spending in "backpressure".
     ///
@@ -130,7 +246,7 @@ impl<A: Actor> SpawnBuilder<A> {
             return (mailbox, inbox);
         }
         let actor_name = actor.name();
-        let queue_capacity = actor.queue_capacity();
+        let queue_capacity = self.queue_capacity(actor);
         self.spawn_ctx.create_mailbox(actor_name, queue_capacity)
     }
 
@@ -150,27 +266,96 @@ impl<A: Actor> SpawnBuilder<A> {
             self.spawn_ctx.clone(),
             state_tx,
             self.backpressure_micros_counter_opt,
+            actor.heartbeat(),
+            actor.processing_time_budget(),
+            self.generation,
         );
         (ctx, inbox, state_rx)
     }
 
     /// Spawns an async actor.
-    pub fn spawn(self, actor: A) -> (Mailbox<A>, ActorHandle<A>) {
+    pub fn spawn(mut self, actor: A) -> (Mailbox<A>, ActorHandle<A>) {
         // We prevent fast forward of the scheduler during  initialization.
         let no_advance_time_guard = self.spawn_ctx.scheduler_client.no_advance_time_guard();
-        let runtime_handle = actor.runtime_handle();
+        let runtime_handle = match &self.spawn_ctx.isolated_runtime_opt {
+            Some(isolated_runtime) => isolated_runtime.handle(),
+            None => actor.runtime_handle(),
+        };
+        let readiness_gate_opt = self.readiness_gate_opt.take();
+        if let Some(readiness_gate) = &readiness_gate_opt {
+            readiness_gate.register_actor();
+        }
+        let actor_name = actor.name();
         let (ctx, inbox, state_rx) = self.create_actor_context_and_inbox(&actor);
-        debug!(actor_id = %ctx.actor_instance_id(), "spawn-actor");
+        debug!(actor_id = %ctx.actor_instance_id(), generation = ctx.generation(), "spawn-actor");
         let mailbox = ctx.mailbox().clone();
         let ctx_clone = ctx.clone();
-        let loop_async_actor_future =
-            async move { actor_loop(actor, inbox, no_advance_time_guard, ctx).await };
+        let weak_inbox = inbox.downgrade();
+        let panicking_ctx = ctx.clone();
+        let loop_async_actor_future = async move {
+            let actor_loop_future =
+                actor_loop(actor, inbox, no_advance_time_guard, readiness_gate_opt, ctx);
+            match AssertUnwindSafe(actor_loop_future).catch_unwind().await {
+                Ok(exit_status) => exit_status,
+                Err(panic_payload) => {
+                    // We only catch the panic to record its details and apply the panic
+                    // policy. The task still panics, so the actor exits with
+                    // `ActorExitStatus::Panicked`.
+                    panicking_ctx.record_panic(panic_payload.as_ref());
+                    panicking_ctx.apply_panic_policy();
+                    panicking_ctx.run_deferred_cleanups().await;
+                    std::panic::resume_unwind(panic_payload)
+                }
+            }
+        };
         let join_handle = ActorJoinHandle::new(runtime_handle.spawn(loop_async_actor_future));
-        ctx_clone.registry().register(&mailbox, join_handle.clone());
-        let actor_handle = ActorHandle::new(state_rx, join_handle, ctx_clone);
+        ctx_clone.registry().register(
+            &ctx_clone,
+            actor_name,
+            join_handle.clone(),
+            weak_inbox.clone(),
+        );
+        self.spawn_ctx.lifecycle_event_bus.publish(
+            ctx_clone.actor_instance_id(),
+            ActorLifecycleEventKind::Spawned,
+        );
+        let actor_handle = ActorHandle::new(state_rx, join_handle, ctx_clone, weak_inbox);
         (mailbox, actor_handle)
     }
 
+    /// Spawns an actor whose mailbox is sharded across `num_shards` channels, each with the
+    /// queue capacity of the actor.
+    ///
+    /// This is meant for actors fed by a lot of concurrent producers. See [`ShardedMailbox`].
+    pub fn spawn_sharded(self, actor: A, num_shards: usize) -> (ShardedMailbox<A>, ActorHandle<A>) {
+        let queue_capacities = vec![self.queue_capacity(&actor); num_shards];
+        let (shards, inbox) = self
+            .spawn_ctx
+            .create_multi_source_mailbox(actor.name(), &queue_capacities);
+        let sharded_mailbox = ShardedMailbox::new(shards);
+        let (_mailbox, actor_handle) = self
+            .set_mailboxes(sharded_mailbox.shard(0).clone(), inbox)
+            .spawn(actor);
+        (sharded_mailbox, actor_handle)
+    }
+
+    /// Spawns an actor fed by `num_upstreams` upstream actors, returning one dedicated
+    /// mailbox per upstream actor, each with the queue capacity of the actor.
+    ///
+    /// The actor consumes the mailboxes in a round-robin fashion, so a chatty upstream actor
+    /// cannot monopolize its processing: it only gets its turn like the others, and it is the
+    /// only one to experience backpressure when its own queue is full.
+    pub fn spawn_fan_in(self, actor: A, num_upstreams: usize) -> (Vec<Mailbox<A>>, ActorHandle<A>) {
+        let queue_capacities = vec![self.queue_capacity(&actor); num_upstreams];
+        let (upstream_mailboxes, inbox) = self
+            .spawn_ctx
+            .create_multi_source_mailbox(actor.name(), &queue_capacities);
+        let (_mailbox, actor_handle) = self
+            .set_mailboxes(upstream_mailboxes[0].clone(), inbox)
+            .spawn(actor);
+        (upstream_mailboxes, actor_handle)
+    }
+
     pub fn supervise_fn<F: Fn() -> A + Send + 'static>(
         mut self,
         actor_factory: F,
@@ -201,16 +386,31 @@ impl<A: Actor + Default> SpawnBuilder<A> {
     }
 }
 
-/// Returns `None` if no message is available at the moment.
-async fn recv_envelope<A: Actor>(inbox: &mut Inbox<A>, ctx: &ActorContext<A>) -> Envelope<A> {
+/// Returns `None` if the receive timeout elapsed before any message was received.
+async fn recv_envelope<A: Actor>(
+    inbox: &mut Inbox<A>,
+    ctx: &ActorContext<A>,
+    receive_timeout_opt: Option<Duration>,
+) -> Option<Envelope<A>> {
     if ctx.state().is_running() {
-        ctx.protect_future(inbox.recv()).await.expect(
+        let envelope_res = if let Some(receive_timeout) = receive_timeout_opt {
+            let scheduler_client = &ctx.spawn_ctx().scheduler_client;
+            ctx.protect_future(scheduler_client.timeout(receive_timeout, inbox.recv()))
+                .await
+                .ok()?
+        } else {
+            ctx.protect_future(inbox.recv()).await
+        };
+        let envelope = envelope_res.expect(
             "Disconnection should be impossible because the ActorContext holds a Mailbox too",
-        )
+        );
+        Some(envelope)
     } else {
         // The actor is paused. We only process command and scheduled message.
-        ctx.protect_future(inbox.recv_cmd_and_scheduled_msg_only())
-            .await
+        let envelope = ctx
+            .protect_future(inbox.recv_cmd_and_scheduled_msg_only())
+            .await;
+        Some(envelope)
     }
 }
 
@@ -228,6 +428,13 @@ struct ActorExecutionEnv<A: Actor> {
     actor: SyncWrapper<A>,
     inbox: Inbox<A>,
     ctx: ActorContext<A>,
+    // Number of messages processed since the actor last yielded, used to yield only once
+    // per batch of `Actor::max_batch_size` messages.
+    num_messages_since_yield: usize,
+    cpu_time_micros_counter: IntCounter,
+    blocking_time_micros_counter: IntCounter,
+    #[cfg(feature = "opentelemetry")]
+    otel_metrics: crate::otel::ActorOtelMetrics,
 }
 
 impl<A: Actor> ActorExecutionEnv<A> {
@@ -235,7 +442,34 @@ impl<A: Actor> ActorExecutionEnv<A> {
         self.actor.get_mut().initialize(&self.ctx).await
     }
 
+    /// Checks the readiness of the actor, reports it to the gate, and waits for all of the
+    /// actors sharing the gate to be ready.
+    async fn wait_for_readiness(
+        &mut self,
+        readiness_gate: &ReadinessGate,
+        initialize_exit_status_res: Result<(), ActorExitStatus>,
+    ) -> Result<(), ActorExitStatus> {
+        let actor_id = self.ctx.actor_instance_id().to_string();
+        if let Err(initialize_exit_status) = initialize_exit_status_res {
+            readiness_gate.report_failure(&actor_id, initialize_exit_status.to_string());
+            return Err(initialize_exit_status);
+        }
+        if let Err(readiness_error) = self.actor.get_mut().check_readiness(&self.ctx).await {
+            warn!(actor_id=%actor_id, error=?readiness_error, "actor-not-ready");
+            readiness_gate.report_failure(&actor_id, format!("{readiness_error:#}"));
+            return Err(ActorExitStatus::from(readiness_error));
+        }
+        readiness_gate.report_ready();
+        self.ctx
+            .protect_future(readiness_gate.wait())
+            .await
+            .map_err(|readiness_error| ActorExitStatus::from(anyhow::Error::from(readiness_error)))
+    }
+
     async fn process_messages(&mut self) -> ActorExitStatus {
+        if let Err(exit_status) = self.redeliver_in_flight_message().await {
+            return exit_status;
+        }
         loop {
             if let Err(exit_status) = self.process_all_available_messages().await {
                 return exit_status;
@@ -243,35 +477,181 @@ impl<A: Actor> ActorExecutionEnv<A> {
         }
     }
 
+    /// Processes again the message a previous instance of the actor failed on, if any. After
+    /// `Actor::max_message_attempts` attempts, the message is moved to the dead letter queue.
+    async fn redeliver_in_flight_message(&mut self) -> Result<(), ActorExitStatus> {
+        let Some(mut envelope) = self.ctx.mailbox().take_in_flight_envelope() else {
+            return Ok(());
+        };
+        let actor = self.actor.get_mut();
+        if envelope.num_attempts() >= actor.max_message_attempts() {
+            let actor_name = actor.name();
+            let dead_letter = DeadLetter {
+                actor_instance_id: self.ctx.actor_instance_id().to_string(),
+                message_type: envelope.message_type_name(),
+                message: envelope.message_debug(),
+                num_attempts: envelope.num_attempts(),
+            };
+            warn!(
+                actor_id = %dead_letter.actor_instance_id,
+                message = %dead_letter.message,
+                num_attempts = dead_letter.num_attempts,
+                "message-moved-to-dead-letter-queue"
+            );
+            ACTOR_METRICS
+                .dead_letters_total
+                .with_label_values([&actor_name])
+                .inc();
+            self.ctx.spawn_ctx().dead_letter_queue.push(dead_letter);
+            return Ok(());
+        }
+        envelope.increment_num_attempts();
+        self.ctx.process();
+        self.process_one_message(envelope).await?;
+        self.ctx.idle();
+        Ok(())
+    }
+
     async fn process_one_message(
         &mut self,
         mut envelope: Envelope<A>,
     ) -> Result<(), ActorExitStatus> {
         self.yield_and_check_if_killed().await?;
-        envelope
+        let correlation_id = envelope.correlation_id();
+        let message_type = envelope.message_type_name();
+        if let Some(metrics_sink) = &self.ctx.spawn_ctx().metrics_sink_opt {
+            metrics_sink.record_dequeue(self.ctx.actor_instance_id(), message_type);
+        }
+        if should_trace_message() {
+            debug!(
+                actor_id = %self.ctx.actor_instance_id(),
+                correlation_id = %correlation_id,
+                msg = %TruncatedDebug(&envelope.message_fmt()),
+                "receive-message"
+            );
+        }
+        self.ctx.set_current_message_id(Some(correlation_id));
+        self.ctx.set_current_message_priority(envelope.priority());
+        #[cfg(not(feature = "opentelemetry"))]
+        let span = debug_span!("message", correlation_id = %correlation_id);
+        #[cfg(feature = "opentelemetry")]
+        let span =
+            crate::otel::message_span(self.ctx.actor_instance_id(), message_type, correlation_id);
+        let processing_time_budget_opt = self.ctx.processing_time_budget();
+        // A copy of a redeliverable message is kept in the mailbox until the message is
+        // processed, so that a restarted instance of the actor can process it again.
+        let redelivery_copy_opt = envelope.redelivery_copy();
+        let is_redeliverable = redelivery_copy_opt.is_some();
+        if is_redeliverable {
+            self.ctx
+                .mailbox()
+                .set_in_flight_envelope(redelivery_copy_opt);
+        }
+        let should_measure_cpu_usage = self.ctx.spawn_ctx().measure_cpu_usage;
+        let start = Instant::now();
+        let handle_message_fut = envelope
             .handle_message(self.actor.get_mut(), &self.ctx)
-            .await?;
-        Ok(())
+            .instrument(span);
+        let (handle_message_res, cpu_usage_opt) = if should_measure_cpu_usage {
+            let (handle_message_res, cpu_usage) = measure_cpu_usage(handle_message_fut).await;
+            (handle_message_res, Some(cpu_usage))
+        } else {
+            (handle_message_fut.await, None)
+        };
+        let processing_time = start.elapsed();
+        self.ctx.record_processing_time(processing_time);
+        self.ctx.record_flight(FlightRecord {
+            message_type,
+            correlation_id,
+            processing_time,
+            exit_status_opt: handle_message_res.as_ref().err().cloned(),
+        });
+        if let Some(cpu_usage) = cpu_usage_opt {
+            self.record_cpu_usage(cpu_usage);
+        }
+        if let Some(metrics_sink) = &self.ctx.spawn_ctx().metrics_sink_opt {
+            metrics_sink.record_processing_time(
+                self.ctx.actor_instance_id(),
+                message_type,
+                processing_time,
+            );
+        }
+        #[cfg(feature = "opentelemetry")]
+        self.otel_metrics
+            .record_processed_message(processing_time, self.ctx.mailbox().queue_depth());
+        if let Some(processing_time_budget) = processing_time_budget_opt {
+            if processing_time > processing_time_budget {
+                let actor_name = self.actor.get_mut().name();
+                ACTOR_METRICS
+                    .processing_time_budget_violations_total
+                    .with_label_values([&actor_name])
+                    .inc();
+                // The message was consumed by its handler. Rendering every message upfront
+                // for the rare ones over budget would be too costly.
+                warn!(
+                    actor_id = %self.ctx.actor_instance_id(),
+                    message_type = %message_type,
+                    correlation_id = %correlation_id,
+                    processing_time = ?processing_time,
+                    processing_time_budget = ?processing_time_budget,
+                    "processing-time-budget-exceeded"
+                );
+            }
+        }
+        if is_redeliverable
+            && !matches!(
+                handle_message_res,
+                Err(ActorExitStatus::Failure(_) | ActorExitStatus::Panicked)
+            )
+        {
+            self.ctx.mailbox().set_in_flight_envelope(None);
+        }
+        self.ctx.record_processed_message(message_type);
+        self.ctx.set_current_message_id(None);
+        self.ctx.set_current_message_priority(Priority::Low);
+        handle_message_res.map_err(|exit_status| {
+            exit_status.with_failure_context(self.ctx.actor_instance_id(), Some(message_type))
+        })
+    }
+
+    fn record_cpu_usage(&self, cpu_usage: CpuUsage) {
+        self.ctx.record_cpu_usage(cpu_usage);
+        self.cpu_time_micros_counter
+            .inc_by(cpu_usage.cpu_time.as_micros() as u64);
+        self.blocking_time_micros_counter
+            .inc_by(cpu_usage.blocking_time.as_micros() as u64);
     }
 
     async fn yield_and_check_if_killed(&mut self) -> Result<(), ActorExitStatus> {
         if self.ctx.kill_switch().is_dead() {
             return Err(ActorExitStatus::Killed);
         }
-        if self.actor.get_mut().yield_after_each_message() {
-            self.ctx.yield_now().await;
-            if self.ctx.kill_switch().is_dead() {
-                return Err(ActorExitStatus::Killed);
+        let actor = self.actor.get_mut();
+        if actor.yield_after_each_message() {
+            self.num_messages_since_yield += 1;
+            if self.num_messages_since_yield >= actor.max_batch_size() {
+                self.num_messages_since_yield = 0;
+                self.ctx.yield_now().await;
+                if self.ctx.kill_switch().is_dead() {
+                    return Err(ActorExitStatus::Killed);
+                }
+                return Ok(());
             }
-        } else {
-            self.ctx.record_progress();
         }
+        self.ctx.record_progress();
         Ok(())
     }
 
     async fn process_all_available_messages(&mut self) -> Result<(), ActorExitStatus> {
         self.yield_and_check_if_killed().await?;
-        let envelope = recv_envelope(&mut self.inbox, &self.ctx).await;
+        let receive_timeout_opt = self.actor.get_mut().receive_timeout();
+        let Some(envelope) = recv_envelope(&mut self.inbox, &self.ctx, receive_timeout_opt).await
+        else {
+            self.ctx.process();
+            self.actor.get_mut().on_receive_timeout(&self.ctx).await?;
+            self.ctx.idle();
+            return Ok(());
+        };
         self.ctx.process();
         self.process_one_message(envelope).await?;
         loop {
@@ -289,12 +669,53 @@ impl<A: Actor> ActorExecutionEnv<A> {
         if self.ctx.mailbox().is_last_mailbox() {
             // No one will be able to send us more messages.
             // We can exit the actor.
+            debug!(actor_id = %self.ctx.actor_instance_id(), "eof");
+            self.ctx.record_eof();
+            self.actor.get_mut().on_eof(&self.ctx).await?;
             return Err(ActorExitStatus::Success);
         }
 
         Ok(())
     }
 
+    /// Applies the drain policy of the actor to the messages still queued in its inbox.
+    async fn drain_queued_messages(&mut self) -> Result<(), ActorExitStatus> {
+        if self.ctx.is_queue_kept_on_quit() {
+            return Ok(());
+        }
+        let num_dropped_messages = match self.actor.get_mut().drain_policy() {
+            DrainPolicy::Drop => self.inbox.drain_low_priority().len(),
+            DrainPolicy::Process => {
+                for envelope in self.inbox.drain_low_priority() {
+                    self.process_one_message(envelope).await?;
+                }
+                0
+            }
+            DrainPolicy::TransferTo(successor_mailbox) => {
+                let mut num_dropped_messages = 0;
+                for envelope in self.inbox.drain_low_priority() {
+                    if successor_mailbox.send_envelope(envelope).await.is_err() {
+                        num_dropped_messages += 1;
+                    }
+                }
+                num_dropped_messages
+            }
+        };
+        if num_dropped_messages > 0 {
+            let actor_name = self.actor.get_mut().name();
+            ACTOR_METRICS
+                .dropped_messages_total
+                .with_label_values([&actor_name])
+                .inc_by(num_dropped_messages as u64);
+            warn!(
+                actor_id = %self.ctx.actor_instance_id(),
+                num_dropped_messages,
+                "dropped-queued-messages"
+            );
+        }
+        Ok(())
+    }
+
     async fn finalize(&mut self, exit_status: ActorExitStatus) -> ActorExitStatus {
         let _no_advance_time_guard = self
             .ctx
@@ -309,8 +730,10 @@ impl<A: Actor> ActorExecutionEnv<A> {
             .with_context(|| format!("Finalization of actor {}", self.actor.get_mut().name()))
         {
             error!(error=?finalize_error, "Finalizing failed, set exit status to panicked.");
+            self.ctx.run_deferred_cleanups().await;
             return ActorExitStatus::Panicked;
         }
+        self.ctx.run_deferred_cleanups().await;
         exit_status
     }
 
@@ -327,7 +750,12 @@ impl<A: Actor> ActorExecutionEnv<A> {
                 error!(exit_status=?exit_status, "actor-failure");
             }
         }
-        info!(actor_id = %self.ctx.actor_instance_id(), exit_status = %exit_status, "actor-exit");
+        info!(
+            actor_id = %self.ctx.actor_instance_id(),
+            generation = self.ctx.generation(),
+            exit_status = %exit_status,
+            "actor-exit"
+        );
         self.ctx.exit(exit_status);
     }
 }
@@ -344,16 +772,46 @@ async fn actor_loop<A: Actor>(
     actor: A,
     inbox: Inbox<A>,
     no_advance_time_guard: NoAdvanceTimeGuard,
+    readiness_gate_opt: Option<ReadinessGate>,
     ctx: ActorContext<A>,
 ) -> ActorExitStatus {
+    let actor_name = actor.name();
+    let cpu_time_micros_counter = ACTOR_METRICS
+        .cpu_time_micros_total
+        .with_label_values([&actor_name]);
+    let blocking_time_micros_counter = ACTOR_METRICS
+        .blocking_time_micros_total
+        .with_label_values([&actor_name]);
+    // Asks the actor to quit gracefully when its kill switch is soft killed, unless it opted
+    // out. Either way, the registration is dropped once the actor has been finalized.
+    let weak_mailbox = ctx.mailbox().downgrade();
+    let quit_on_soft_kill = actor.quit_on_soft_kill();
+    let _soft_kill_registration = ctx.kill_switch().register_soft_kill_callback(move || {
+        if !quit_on_soft_kill {
+            return;
+        }
+        if let Some(mailbox) = weak_mailbox.upgrade() {
+            let _ = mailbox.send_message_with_high_priority(Command::Quit);
+        }
+    });
     let mut actor_env = ActorExecutionEnv {
         actor: SyncWrapper::new(actor),
         inbox,
         ctx,
+        num_messages_since_yield: 0,
+        cpu_time_micros_counter,
+        blocking_time_micros_counter,
+        #[cfg(feature = "opentelemetry")]
+        otel_metrics: crate::otel::ActorOtelMetrics::new(&actor_name),
     };
 
-    let initialize_exit_status_res: Result<(), ActorExitStatus> = actor_env.initialize().await;
+    let mut initialize_exit_status_res: Result<(), ActorExitStatus> = actor_env.initialize().await;
     drop(no_advance_time_guard);
+    if let Some(readiness_gate) = readiness_gate_opt {
+        initialize_exit_status_res = actor_env
+            .wait_for_readiness(&readiness_gate, initialize_exit_status_res)
+            .await;
+    }
 
     let after_process_exit_status = if let Err(initialize_exit_status) = initialize_exit_status_res
     {
@@ -363,10 +821,22 @@ async fn actor_loop<A: Actor>(
     } else {
         actor_env.process_messages().await
     };
+    let after_process_exit_status = if matches!(after_process_exit_status, ActorExitStatus::Quit) {
+        actor_env
+            .drain_queued_messages()
+            .await
+            .err()
+            .unwrap_or(after_process_exit_status)
+    } else {
+        after_process_exit_status
+    };
 
     // TODO the no advance time guard for finalize has a race condition. Ideally we would
     // like to have the guard before we drop the last envelope.
+    let after_process_exit_status =
+        after_process_exit_status.with_failure_context(actor_env.ctx.actor_instance_id(), None);
     let final_exit_status = actor_env.finalize(after_process_exit_status).await;
+    actor_env.ctx.notify_termination(&final_exit_status).await;
     // The last observation is collected on `ActorExecutionEnv::Drop`.
     actor_env.process_exit_status(&final_exit_status);
     final_exit_status
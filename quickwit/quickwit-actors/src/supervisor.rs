@@ -17,13 +17,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::time::Duration;
+
 use async_trait::async_trait;
 use serde::Serialize;
 use tracing::{info, warn};
 
+use crate::lifecycle_events::ActorLifecycleEventKind;
 use crate::mailbox::Inbox;
 use crate::{
-    Actor, ActorContext, ActorExitStatus, ActorHandle, ActorState, Handler, Health, Supervisable,
+    Actor, ActorContext, ActorExitStatus, ActorHandle, ActorState, Handler, Health,
+    SlowActorReport, Supervisable,
 };
 
 #[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize)]
@@ -31,6 +35,9 @@ pub struct SupervisorState {
     pub num_panics: usize,
     pub num_errors: usize,
     pub num_kills: usize,
+    /// Set if the supervised actor exceeded its processing time budget
+    /// during the last supervision loop.
+    pub slow_actor_report_opt: Option<SlowActorReport>,
 }
 
 pub struct Supervisor<A: Actor> {
@@ -61,7 +68,7 @@ impl<A: Actor> Actor for Supervisor<A> {
     }
 
     async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
-        ctx.schedule_self_msg(*crate::HEARTBEAT, SuperviseLoop)
+        ctx.schedule_self_msg(self.supervised_actor_heartbeat(), SuperviseLoop)
             .await;
         Ok(())
     }
@@ -109,17 +116,33 @@ impl<A: Actor> Supervisor<A> {
         }
     }
 
+    /// Returns the heartbeat of the supervised actor, which defines the supervision interval.
+    fn supervised_actor_heartbeat(&self) -> Duration {
+        self.handle_opt
+            .as_ref()
+            .map(|handle| handle.heartbeat())
+            .unwrap_or_else(|| *crate::HEARTBEAT)
+    }
+
     async fn supervise(
         &mut self,
         ctx: &ActorContext<Supervisor<A>>,
     ) -> Result<(), ActorExitStatus> {
-        match self
+        let handle = self
             .handle_opt
             .as_ref()
-            .expect("The actor handle should always be set.")
-            .harvest_health()
-        {
+            .expect("The actor handle should always be set.");
+        match handle.harvest_health() {
             Health::Healthy => {
+                self.state.slow_actor_report_opt = handle.slow_actor_report();
+                if let Some(slow_actor_report) = &self.state.slow_actor_report_opt {
+                    warn!(
+                        actor = handle.name(),
+                        processing_time_p99 = ?slow_actor_report.processing_time_p99,
+                        processing_time_budget = ?slow_actor_report.processing_time_budget,
+                        "slow-actor"
+                    );
+                }
                 return Ok(());
             }
             Health::FailureOrUnhealthy => {}
@@ -131,6 +154,7 @@ impl<A: Actor> Supervisor<A> {
         // The actor is failing we need to restart it.
         let actor_handle = self.handle_opt.take().unwrap();
         let actor_mailbox = actor_handle.mailbox().clone();
+        let generation = actor_handle.generation() + 1;
         let (actor_exit_status, _last_state) = if actor_handle.state() == ActorState::Processing {
             // The actor is probably frozen.
             // Let's kill it.
@@ -164,7 +188,11 @@ impl<A: Actor> Supervisor<A> {
             .spawn_actor()
             .set_mailboxes(actor_mailbox, self.inbox.clone())
             .set_kill_switch(ctx.kill_switch().child())
+            .set_generation(generation)
             .spawn((*self.actor_factory)());
+        ctx.spawn_ctx()
+            .lifecycle_event_bus
+            .publish(actor_handle.name(), ActorLifecycleEventKind::Restarted);
         self.handle_opt = Some(actor_handle);
         Ok(())
     }
@@ -180,7 +208,7 @@ impl<A: Actor> Handler<SuperviseLoop> for Supervisor<A> {
         ctx: &ActorContext<Self>,
     ) -> Result<Self::Reply, ActorExitStatus> {
         self.supervise(ctx).await?;
-        ctx.schedule_self_msg(*crate::HEARTBEAT, SuperviseLoop)
+        ctx.schedule_self_msg(self.supervised_actor_heartbeat(), SuperviseLoop)
             .await;
         Ok(())
     }
@@ -193,6 +221,7 @@ mod tests {
     use async_trait::async_trait;
     use tracing::info;
 
+    use crate::metrics::ACTOR_METRICS;
     use crate::supervisor::SupervisorState;
     use crate::{Actor, ActorContext, ActorExitStatus, AskError, Handler, Universe};
 
@@ -202,11 +231,14 @@ mod tests {
         ReturnError,
         Increment,
         Freeze(Duration),
+        Sleep(Duration),
     }
 
     #[derive(Default, Clone)]
     struct FailingActor {
         counter: usize,
+        heartbeat_opt: Option<Duration>,
+        processing_time_budget_opt: Option<Duration>,
     }
 
     #[async_trait]
@@ -221,6 +253,14 @@ mod tests {
             self.counter
         }
 
+        fn heartbeat(&self) -> Duration {
+            self.heartbeat_opt.unwrap_or(*crate::HEARTBEAT)
+        }
+
+        fn processing_time_budget(&self) -> Option<Duration> {
+            self.processing_time_budget_opt
+        }
+
         async fn finalize(
             &mut self,
             _exit_status: &ActorExitStatus,
@@ -255,6 +295,9 @@ mod tests {
                 FailingActorMessage::Freeze(wait_duration) => {
                     ctx.sleep(wait_duration).await;
                 }
+                FailingActorMessage::Sleep(sleep_duration) => {
+                    tokio::time::sleep(sleep_duration).await;
+                }
             }
             Ok(self.counter)
         }
@@ -284,7 +327,8 @@ mod tests {
             SupervisorState {
                 num_panics: 1,
                 num_errors: 0,
-                num_kills: 0
+                num_kills: 0,
+                slow_actor_report_opt: None,
             }
         );
         assert!(!matches!(
@@ -316,7 +360,8 @@ mod tests {
             SupervisorState {
                 num_panics: 0,
                 num_errors: 1,
-                num_kills: 0
+                num_kills: 0,
+                slow_actor_report_opt: None,
             }
         );
         assert!(!matches!(
@@ -343,7 +388,8 @@ mod tests {
             SupervisorState {
                 num_panics: 0,
                 num_errors: 0,
-                num_kills: 0
+                num_kills: 0,
+                slow_actor_report_opt: None,
             }
         );
         mailbox
@@ -361,7 +407,45 @@ mod tests {
             SupervisorState {
                 num_panics: 0,
                 num_errors: 0,
-                num_kills: 1
+                num_kills: 1,
+                slow_actor_report_opt: None,
+            }
+        );
+        assert!(!matches!(
+            supervisor_handle.quit().await.0,
+            ActorExitStatus::Panicked
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_uses_supervised_actor_heartbeat() {
+        let universe = Universe::with_accelerated_time();
+        let actor = FailingActor {
+            heartbeat_opt: Some(crate::HEARTBEAT.mul_f32(10.0f32)),
+            ..Default::default()
+        };
+        let (mailbox, supervisor_handle) = universe.spawn_builder().supervise(actor);
+        assert_eq!(
+            mailbox.ask(FailingActorMessage::Increment).await.unwrap(),
+            1
+        );
+        mailbox
+            .send_message(FailingActorMessage::Freeze(
+                crate::HEARTBEAT.mul_f32(3.0f32),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(
+            mailbox.ask(FailingActorMessage::Increment).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            *supervisor_handle.observe().await,
+            SupervisorState {
+                num_panics: 0,
+                num_errors: 0,
+                num_kills: 0,
+                slow_actor_report_opt: None,
             }
         );
         assert!(!matches!(
@@ -370,6 +454,40 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_supervisor_reports_slow_actor() {
+        let universe = Universe::with_accelerated_time();
+        let actor = FailingActor {
+            processing_time_budget_opt: Some(Duration::from_millis(1)),
+            ..Default::default()
+        };
+        let budget_violations_counter = ACTOR_METRICS
+            .processing_time_budget_violations_total
+            .with_label_values(["FailingActor"]);
+        let num_budget_violations_before = budget_violations_counter.get();
+        let (mailbox, supervisor_handle) = universe.spawn_builder().supervise(actor);
+        for _ in 0..3 {
+            mailbox
+                .ask(FailingActorMessage::Sleep(Duration::from_millis(10)))
+                .await
+                .unwrap();
+        }
+        universe.sleep(crate::HEARTBEAT.mul_f32(1.5f32)).await;
+        let supervisor_state = *supervisor_handle.observe().await;
+        assert_eq!(supervisor_state.num_kills, 0);
+        let slow_actor_report = supervisor_state.slow_actor_report_opt.unwrap();
+        assert_eq!(
+            slow_actor_report.processing_time_budget,
+            Duration::from_millis(1)
+        );
+        assert!(slow_actor_report.processing_time_p99 >= Duration::from_millis(10));
+        assert!(budget_violations_counter.get() >= num_budget_violations_before + 3);
+        assert!(!matches!(
+            supervisor_handle.quit().await.0,
+            ActorExitStatus::Panicked
+        ));
+    }
+
     #[tokio::test]
     async fn test_supervisor_forwards_quit_commands() {
         let universe = Universe::with_accelerated_time();
@@ -421,4 +539,39 @@ mod tests {
         let (exit_status, _state) = supervisor_handle.join().await;
         assert!(matches!(exit_status, ActorExitStatus::Success));
     }
+
+    #[tokio::test]
+    async fn test_supervisor_moves_poison_message_to_dead_letter_queue() {
+        let universe = Universe::with_accelerated_time();
+        let actor = FailingActor::default();
+        let (mailbox, supervisor_handle) = universe.spawn_builder().supervise(actor);
+        mailbox
+            .send_redeliverable_message(FailingActorMessage::ReturnError)
+            .await
+            .unwrap();
+        mailbox
+            .send_redeliverable_message(FailingActorMessage::Increment)
+            .await
+            .unwrap();
+        assert_eq!(
+            mailbox.ask(FailingActorMessage::Increment).await.unwrap(),
+            2
+        );
+        assert_eq!(supervisor_handle.observe().await.num_errors, 3);
+        let dead_letters = universe.dead_letters();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(
+            dead_letters[0].actor_instance_id,
+            mailbox.actor_instance_id()
+        );
+        assert!(dead_letters[0]
+            .message_type
+            .ends_with("FailingActorMessage"));
+        assert_eq!(dead_letters[0].message, "ReturnError");
+        assert_eq!(dead_letters[0].num_attempts, 3);
+        assert!(!matches!(
+            supervisor_handle.quit().await.0,
+            ActorExitStatus::Panicked
+        ));
+    }
 }
@@ -0,0 +1,189 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::any::type_name;
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::{Actor, ActorContext, ActorExitStatus, Handler, QueueCapacity};
+
+/// An actor whose handlers are blocking functions.
+///
+/// A `SyncActor` is not spawned directly: it is wrapped into a [`SyncActorAdapter`], which
+/// implements [`Actor`] and runs each handler on tokio's blocking thread pool. The resulting
+/// mailbox can be used by async callers like any other mailbox, and by blocking callers via
+/// `Mailbox::ask_blocking`.
+pub trait SyncActor: Send + Sized + 'static {
+    /// Piece of state that can be copied for assert in unit test, admin, etc.
+    type ObservableState: fmt::Debug + serde::Serialize + Send + Sync + Clone;
+
+    /// A name identifying the type of actor.
+    fn name(&self) -> String {
+        type_name::<Self>().to_string()
+    }
+
+    /// Extracts an observable state.
+    fn observable_state(&self) -> Self::ObservableState;
+
+    /// See [`Actor::queue_capacity`].
+    fn queue_capacity(&self) -> QueueCapacity {
+        QueueCapacity::Unbounded
+    }
+}
+
+/// Blocking counterpart of [`Handler`].
+///
+/// The handler runs on a blocking thread, inside a protected zone, so it may take longer
+/// than a heartbeat without being considered dead. Long handlers should still call
+/// `ctx.record_progress()` regularly so that their liveness remains observable.
+pub trait SyncHandler<M>: SyncActor {
+    type Reply: Send + 'static;
+
+    /// Processes a message, blocking the current thread.
+    fn handle(
+        &mut self,
+        message: M,
+        ctx: &ActorContext<SyncActorAdapter<Self>>,
+    ) -> Result<Self::Reply, ActorExitStatus>;
+}
+
+/// Wraps a [`SyncActor`] into an [`Actor`].
+pub struct SyncActorAdapter<A> {
+    // The actor is moved to the blocking thread while a message is being handled.
+    // It is only missing if a handler panicked.
+    actor_opt: Option<A>,
+}
+
+impl<A: SyncActor> SyncActorAdapter<A> {
+    pub fn new(actor: A) -> Self {
+        SyncActorAdapter {
+            actor_opt: Some(actor),
+        }
+    }
+
+    /// Returns the wrapped actor, unless one of its handlers panicked.
+    pub fn into_inner(self) -> Option<A> {
+        self.actor_opt
+    }
+}
+
+impl<A: SyncActor> Actor for SyncActorAdapter<A> {
+    type ObservableState = Option<A::ObservableState>;
+
+    fn name(&self) -> String {
+        self.actor_opt
+            .as_ref()
+            .map(SyncActor::name)
+            .unwrap_or_else(|| type_name::<A>().to_string())
+    }
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.actor_opt.as_ref().map(SyncActor::observable_state)
+    }
+
+    fn queue_capacity(&self) -> QueueCapacity {
+        self.actor_opt
+            .as_ref()
+            .map(SyncActor::queue_capacity)
+            .unwrap_or(QueueCapacity::Unbounded)
+    }
+}
+
+#[async_trait]
+impl<A, M> Handler<M> for SyncActorAdapter<A>
+where
+    A: SyncHandler<M>,
+    M: fmt::Debug + Send + 'static,
+{
+    type Reply = A::Reply;
+
+    async fn handle(
+        &mut self,
+        message: M,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let Some(mut actor) = self.actor_opt.take() else {
+            return Err(ActorExitStatus::Panicked);
+        };
+        let blocking_ctx = ctx.clone();
+        let (actor, reply_res) = ctx
+            .run_blocking(move || {
+                let reply_res = actor.handle(message, &blocking_ctx);
+                (actor, reply_res)
+            })
+            .await?;
+        self.actor_opt = Some(actor);
+        reply_res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Universe;
+
+    #[derive(Default)]
+    struct BlockingSummer {
+        sum: u64,
+    }
+
+    impl SyncActor for BlockingSummer {
+        type ObservableState = u64;
+
+        fn observable_state(&self) -> u64 {
+            self.sum
+        }
+    }
+
+    #[derive(Debug)]
+    struct Add(u64);
+
+    impl SyncHandler<Add> for BlockingSummer {
+        type Reply = u64;
+
+        fn handle(
+            &mut self,
+            add: Add,
+            ctx: &ActorContext<SyncActorAdapter<Self>>,
+        ) -> Result<u64, ActorExitStatus> {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            ctx.record_progress();
+            self.sum += add.0;
+            Ok(self.sum)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_actor_adapter() {
+        let universe = Universe::new();
+        let (mailbox, handle) = universe
+            .spawn_builder()
+            .spawn(SyncActorAdapter::new(BlockingSummer::default()));
+        assert_eq!(mailbox.ask(Add(2)).await.unwrap(), 2);
+        let blocking_mailbox = mailbox.clone();
+        let sum = tokio::task::spawn_blocking(move || blocking_mailbox.ask_blocking(Add(3)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sum, 5);
+        assert_eq!(handle.process_pending_and_observe().await.state, Some(5));
+        universe.assert_quit().await;
+    }
+}
@@ -19,16 +19,26 @@
 
 use std::cell::Cell;
 use std::collections::HashMap;
+use std::future;
 use std::ops::Mul;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
 
 use async_trait::async_trait;
+use quickwit_common::test_utils::wait_until_predicate;
+use quickwit_common::KillSwitch;
 use serde::Serialize;
+use tokio::sync::Notify;
 
+use crate::metrics::ACTOR_METRICS;
 use crate::observation::ObservationType;
 use crate::{
-    Actor, ActorContext, ActorExitStatus, ActorHandle, ActorState, Command, Handler, Health,
-    Mailbox, Observation, Supervisable, Universe,
+    Actor, ActorContext, ActorExitStatus, ActorHandle, ActorLifecycleEventKind, ActorState,
+    CheckpointBarrier, Command, CorrelationId, CpuUsage, CustomCommand, DeferableReplyHandler,
+    DrainPolicy, Handler, Health, IsolatedRuntime, Mailbox, MemoryBudget, MetricsSink, Observation,
+    Payload, ReadinessGate, ResponseHandle, RetryPolicy, ScheduledMessageStore, Supervisable,
+    Universe, UpstreamTerminated, Watermark, WatermarkTracker,
 };
 
 // An actor that receives ping messages.
@@ -311,6 +321,70 @@ async fn test_pause_actor() {
     universe.assert_quit().await;
 }
 
+#[tokio::test]
+async fn test_universe_pause_all() {
+    let universe = Universe::with_accelerated_time();
+    let (ping_mailbox, ping_handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    let (other_ping_mailbox, other_ping_handle) =
+        universe.spawn_builder().spawn(PingReceiverActor::default());
+    universe.pause_all().await;
+    assert_eq!(ping_handle.state(), ActorState::Paused);
+    assert_eq!(other_ping_handle.state(), ActorState::Paused);
+
+    ping_mailbox.send_message(Ping).await.unwrap();
+    other_ping_mailbox.send_message(Ping).await.unwrap();
+    assert_eq!(ping_handle.observe().await.state, 0);
+    assert_eq!(other_ping_handle.observe().await.state, 0);
+
+    universe.resume_all().await;
+    assert_eq!(ping_handle.process_pending_and_observe().await.state, 1);
+    assert_eq!(
+        other_ping_handle.process_pending_and_observe().await.state,
+        1
+    );
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_flight_records() {
+    let universe = Universe::with_accelerated_time().with_flight_recorder();
+    let (ping_mailbox, ping_handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    for _ in 0..3 {
+        ping_mailbox.send_message(Ping).await.unwrap();
+    }
+    assert_eq!(ping_handle.process_pending_and_observe().await.state, 3);
+    let ping_records: Vec<_> = ping_handle
+        .flight_records()
+        .into_iter()
+        .filter(|flight_record| flight_record.message_type.ends_with("Ping"))
+        .collect();
+    assert_eq!(ping_records.len(), 3);
+    assert!(ping_records
+        .iter()
+        .all(|flight_record| flight_record.exit_status_opt.is_none()));
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_cpu_usage_is_opt_in() {
+    let universe = Universe::with_accelerated_time();
+    let (ping_mailbox, ping_handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    ping_mailbox.send_message(Ping).await.unwrap();
+    assert_eq!(ping_handle.process_pending_and_observe().await.state, 1);
+    assert_eq!(ping_handle.cpu_usage(), CpuUsage::default());
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_flight_recorder_is_opt_in() {
+    let universe = Universe::with_accelerated_time();
+    let (ping_mailbox, ping_handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    ping_mailbox.send_message(Ping).await.unwrap();
+    assert_eq!(ping_handle.process_pending_and_observe().await.state, 1);
+    assert!(ping_handle.flight_records().is_empty());
+    universe.assert_quit().await;
+}
+
 #[tokio::test]
 async fn test_actor_running_states() {
     quickwit_common::setup_logging_for_tests();
@@ -725,3 +799,1954 @@ async fn test_unsync_actor_message() {
 
     universe.assert_quit().await;
 }
+
+#[derive(Debug)]
+struct RecordCorrelationId;
+
+#[derive(Default)]
+struct CorrelationIdRecorderActor {
+    correlation_ids: Vec<CorrelationId>,
+}
+
+impl Actor for CorrelationIdRecorderActor {
+    type ObservableState = Vec<CorrelationId>;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.correlation_ids.clone()
+    }
+}
+
+#[async_trait]
+impl Handler<RecordCorrelationId> for CorrelationIdRecorderActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: RecordCorrelationId,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.correlation_ids.push(ctx.current_message_id().unwrap());
+        Ok(())
+    }
+}
+
+struct CorrelationIdForwarderActor {
+    recorder_mailbox: Mailbox<CorrelationIdRecorderActor>,
+}
+
+impl Actor for CorrelationIdForwarderActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[async_trait]
+impl Handler<RecordCorrelationId> for CorrelationIdForwarderActor {
+    type Reply = CorrelationId;
+
+    async fn handle(
+        &mut self,
+        message: RecordCorrelationId,
+        ctx: &ActorContext<Self>,
+    ) -> Result<CorrelationId, ActorExitStatus> {
+        ctx.send_message(&self.recorder_mailbox, message).await?;
+        Ok(ctx.current_message_id().unwrap())
+    }
+}
+
+#[tokio::test]
+async fn test_correlation_id_propagation() {
+    let universe = Universe::with_accelerated_time();
+    let (recorder_mailbox, recorder_handle) = universe
+        .spawn_builder()
+        .spawn(CorrelationIdRecorderActor::default());
+    let forwarder = CorrelationIdForwarderActor { recorder_mailbox };
+    let (forwarder_mailbox, _forwarder_handle) = universe.spawn_builder().spawn(forwarder);
+    let first_correlation_id = forwarder_mailbox.ask(RecordCorrelationId).await.unwrap();
+    let second_correlation_id = forwarder_mailbox.ask(RecordCorrelationId).await.unwrap();
+    assert_ne!(first_correlation_id, second_correlation_id);
+    let recorded_correlation_ids = recorder_handle.process_pending_and_observe().await.state;
+    assert_eq!(
+        recorded_correlation_ids,
+        vec![first_correlation_id, second_correlation_id]
+    );
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct ReceiveTimeoutActor {
+    num_receive_timeouts: usize,
+}
+
+#[async_trait]
+impl Actor for ReceiveTimeoutActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.num_receive_timeouts
+    }
+
+    fn receive_timeout(&self) -> Option<Duration> {
+        Some(Duration::from_secs(1))
+    }
+
+    async fn on_receive_timeout(
+        &mut self,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.num_receive_timeouts += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_actor_receive_timeout() {
+    let universe = Universe::with_accelerated_time();
+    let (_mailbox, handle) = universe
+        .spawn_builder()
+        .spawn(ReceiveTimeoutActor::default());
+    universe.sleep(Duration::from_millis(3_500)).await;
+    assert_eq!(handle.observe().await.state, 3);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct DownstreamActor {
+    upstream_exit_statuses: Vec<String>,
+}
+
+impl Actor for DownstreamActor {
+    type ObservableState = Vec<String>;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.upstream_exit_statuses.clone()
+    }
+}
+
+#[async_trait]
+impl Handler<UpstreamTerminated> for DownstreamActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        upstream_terminated: UpstreamTerminated,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.upstream_exit_statuses
+            .push(upstream_terminated.exit_status.to_string());
+        Ok(())
+    }
+}
+
+struct UpstreamActor {
+    downstream_mailbox: Mailbox<DownstreamActor>,
+}
+
+#[async_trait]
+impl Actor for UpstreamActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        ctx.notify_termination_to(self.downstream_mailbox.clone());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for UpstreamActor {
+    type Reply = ();
+
+    async fn handle(&mut self, _: Ping, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        Err(ActorExitStatus::from(anyhow::anyhow!("upstream failure")))
+    }
+}
+
+#[tokio::test]
+async fn test_upstream_terminated_is_sent_downstream() {
+    let universe = Universe::with_accelerated_time();
+    let (downstream_mailbox, downstream_handle) =
+        universe.spawn_builder().spawn(DownstreamActor::default());
+
+    let (upstream_mailbox, upstream_handle) = universe.spawn_builder().spawn(UpstreamActor {
+        downstream_mailbox: downstream_mailbox.clone(),
+    });
+    upstream_mailbox.send_message(Ping).await.unwrap();
+    let (exit_status, _) = upstream_handle.join().await;
+    assert!(matches!(exit_status, ActorExitStatus::Failure(_)));
+
+    let (_upstream_mailbox, upstream_handle) = universe.spawn_builder().spawn(UpstreamActor {
+        downstream_mailbox: downstream_mailbox.clone(),
+    });
+    upstream_handle.quit().await;
+
+    let upstream_exit_statuses = downstream_handle.process_pending_and_observe().await.state;
+    assert_eq!(upstream_exit_statuses.len(), 2);
+    assert!(upstream_exit_statuses[0].starts_with("Failure"));
+    assert_eq!(upstream_exit_statuses[1], "Quit");
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_failure_termination_context() {
+    let universe = Universe::with_accelerated_time();
+    let (downstream_mailbox, _downstream_handle) =
+        universe.spawn_builder().spawn(DownstreamActor::default());
+    let (upstream_mailbox, upstream_handle) = universe
+        .spawn_builder()
+        .spawn(UpstreamActor { downstream_mailbox });
+    upstream_mailbox.send_message(Ping).await.unwrap();
+    let (exit_status, _) = upstream_handle.join().await;
+
+    let termination = exit_status.termination().unwrap();
+    assert_eq!(
+        termination.actor_instance_id(),
+        Some(upstream_mailbox.actor_instance_id())
+    );
+    assert!(termination.message_type().unwrap().ends_with("Ping"));
+    assert_eq!(termination.error().to_string(), "upstream failure");
+    assert!(termination.upstream_termination().is_none());
+
+    let upstream_terminated = UpstreamTerminated {
+        upstream_actor_id: upstream_mailbox.actor_instance_id().to_string(),
+        exit_status,
+    };
+    let cascading_exit_status =
+        upstream_terminated.into_cascading_failure(anyhow::anyhow!("downstream failure"));
+    let cascading_termination = cascading_exit_status.termination().unwrap();
+    assert_eq!(
+        cascading_termination.error().to_string(),
+        "downstream failure"
+    );
+    assert_eq!(
+        cascading_termination.root_cause().error().to_string(),
+        "upstream failure"
+    );
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct DrainingActor {
+    ping_count: usize,
+    process_on_quit: bool,
+    successor_mailbox_opt: Option<Mailbox<DrainingActor>>,
+}
+
+#[async_trait]
+impl Actor for DrainingActor {
+    type ObservableState = usize;
+
+    fn drain_policy(&self) -> DrainPolicy<Self> {
+        if let Some(successor_mailbox) = &self.successor_mailbox_opt {
+            DrainPolicy::TransferTo(successor_mailbox.clone())
+        } else if self.process_on_quit {
+            DrainPolicy::Process
+        } else {
+            DrainPolicy::Drop
+        }
+    }
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.ping_count
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for DrainingActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: Ping,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.ping_count += 1;
+        Ok(())
+    }
+}
+
+async fn quit_with_queued_pings(
+    universe: &Universe,
+    actor: DrainingActor,
+) -> (ActorExitStatus, usize) {
+    let (mailbox, handle) = universe.spawn_builder().spawn(actor);
+    handle.pause();
+    for _ in 0..3 {
+        mailbox.send_message(Ping).await.unwrap();
+    }
+    handle.quit().await
+}
+
+#[tokio::test]
+async fn test_drain_policy_drop() {
+    let dropped_messages_counter = ACTOR_METRICS
+        .dropped_messages_total
+        .with_label_values([&DrainingActor::default().name()]);
+    let num_dropped_messages_before = dropped_messages_counter.get();
+    let universe = Universe::with_accelerated_time();
+    let (exit_status, ping_count) =
+        quit_with_queued_pings(&universe, DrainingActor::default()).await;
+    assert!(matches!(exit_status, ActorExitStatus::Quit));
+    assert_eq!(ping_count, 0);
+    assert!(dropped_messages_counter.get() >= num_dropped_messages_before + 3);
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_drain_policy_process() {
+    let universe = Universe::with_accelerated_time();
+    let actor = DrainingActor {
+        process_on_quit: true,
+        ..Default::default()
+    };
+    let (exit_status, ping_count) = quit_with_queued_pings(&universe, actor).await;
+    assert!(matches!(exit_status, ActorExitStatus::Quit));
+    assert_eq!(ping_count, 3);
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_drain_policy_transfer_to_successor() {
+    let universe = Universe::with_accelerated_time();
+    let (successor_mailbox, successor_handle) =
+        universe.spawn_builder().spawn(DrainingActor::default());
+    let actor = DrainingActor {
+        successor_mailbox_opt: Some(successor_mailbox.clone()),
+        ..Default::default()
+    };
+    let (exit_status, ping_count) = quit_with_queued_pings(&universe, actor).await;
+    assert!(matches!(exit_status, ActorExitStatus::Quit));
+    assert_eq!(ping_count, 0);
+    assert_eq!(
+        successor_handle.process_pending_and_observe().await.state,
+        3
+    );
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_processed_message_counts() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    assert!(handle.processed_message_counts().is_empty());
+    mailbox.send_message(Ping).await.unwrap();
+    mailbox.send_message(Ping).await.unwrap();
+    handle.process_pending_and_observe().await;
+    let processed_message_counts = handle.processed_message_counts();
+    assert_eq!(processed_message_counts["Ping"], 2);
+
+    let observations = universe.observe(Duration::from_secs(1)).await;
+    let observation = observations
+        .iter()
+        .find(|observation| observation.instance_id == mailbox.actor_instance_id())
+        .unwrap();
+    assert_eq!(
+        observation.processed_message_counts.as_ref().unwrap()["Ping"],
+        2
+    );
+    universe.assert_quit().await;
+}
+
+#[derive(Debug, Serialize, serde::Deserialize)]
+struct RetryUpload {
+    split_id: String,
+}
+
+#[derive(Default)]
+struct RetryingActor {
+    retried_split_ids: Vec<String>,
+}
+
+#[async_trait]
+impl Actor for RetryingActor {
+    type ObservableState = Vec<String>;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.retried_split_ids.clone()
+    }
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        ctx.restore_persistent_self_msgs::<RetryUpload>("retrying-actor")
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<RetryUpload> for RetryingActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        retry_upload: RetryUpload,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.retried_split_ids.push(retry_upload.split_id);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for RetryingActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _ping: Ping,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let retry_upload = RetryUpload {
+            split_id: "split-1".to_string(),
+        };
+        ctx.schedule_persistent_self_msg("retrying-actor", Duration::from_secs(300), retry_upload)
+            .await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_scheduled_messages_survive_restart() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let store_path = temp_dir.path().join("scheduled-messages.json");
+    let scheduled_message_store = Arc::new(ScheduledMessageStore::open(&store_path).unwrap());
+    let universe = Universe::new().with_scheduled_message_store(scheduled_message_store);
+    let (mailbox, handle) = universe.spawn_builder().spawn(RetryingActor::default());
+    mailbox.ask(Ping).await.unwrap();
+    assert!(handle.process_pending_and_observe().await.is_empty());
+    // The process stops before the message is delivered.
+    universe.assert_quit().await;
+
+    let scheduled_message_store = Arc::new(ScheduledMessageStore::open(&store_path).unwrap());
+    assert_eq!(scheduled_message_store.len(), 1);
+    let universe = Universe::with_accelerated_time()
+        .with_scheduled_message_store(scheduled_message_store.clone());
+    let (_mailbox, handle) = universe.spawn_builder().spawn(RetryingActor::default());
+    universe.sleep(Duration::from_secs(400)).await;
+    assert_eq!(
+        *handle.process_pending_and_observe().await,
+        vec!["split-1".to_string()]
+    );
+    assert!(scheduled_message_store.is_empty());
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct BatchingPingReceiverActor {
+    ping_count: usize,
+}
+
+impl Actor for BatchingPingReceiverActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.ping_count
+    }
+
+    fn max_batch_size(&self) -> usize {
+        8
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for BatchingPingReceiverActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: Ping,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.ping_count += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_batched_actor_processes_all_messages_and_honors_pause() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe
+        .spawn_builder()
+        .spawn(BatchingPingReceiverActor::default());
+    for _ in 0..1_000 {
+        mailbox.send_message(Ping).await.unwrap();
+    }
+    mailbox
+        .send_message_with_high_priority(Command::Pause)
+        .unwrap();
+    let paused_ping_count = *handle.observe().await;
+    assert!(paused_ping_count < 1_000);
+    assert_eq!(*handle.observe().await, paused_ping_count);
+    mailbox
+        .send_message_with_high_priority(Command::Resume)
+        .unwrap();
+    assert_eq!(*handle.process_pending_and_observe().await, 1_000);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct PayloadConsumer {
+    num_bytes: usize,
+}
+
+impl Actor for PayloadConsumer {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> usize {
+        self.num_bytes
+    }
+}
+
+#[async_trait]
+impl Handler<Payload> for PayloadConsumer {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        payload: Payload,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.num_bytes += payload.len();
+        Ok(())
+    }
+}
+
+struct PayloadSource {
+    consumer_mailbox: Mailbox<PayloadConsumer>,
+}
+
+impl Actor for PayloadSource {
+    type ObservableState = ();
+
+    fn observable_state(&self) {}
+}
+
+#[derive(Debug)]
+struct EmitPayloads(usize);
+
+#[async_trait]
+impl Handler<EmitPayloads> for PayloadSource {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        emit_payloads: EmitPayloads,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        for _ in 0..emit_payloads.0 {
+            ctx.send_sized_message(&self.consumer_mailbox, Payload::from(vec![0u8; 40]))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ForwardPayloads(usize);
+
+#[async_trait]
+impl Handler<ForwardPayloads> for PayloadSource {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        forward_payloads: ForwardPayloads,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        for _ in 0..forward_payloads.0 {
+            ctx.forward_sized_message(&self.consumer_mailbox, Payload::from(vec![0u8; 40]))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_memory_budget_applies_backpressure_to_sources() {
+    let memory_budget = Arc::new(MemoryBudget::new(100));
+    let universe = Universe::with_accelerated_time().with_memory_budget(memory_budget.clone());
+    let (consumer_mailbox, consumer_handle) =
+        universe.spawn_builder().spawn(PayloadConsumer::default());
+    consumer_handle.pause();
+    let (source_mailbox, _source_handle) = universe.spawn_builder().spawn(PayloadSource {
+        consumer_mailbox: consumer_mailbox.clone(),
+    });
+    source_mailbox.send_message(EmitPayloads(5)).await.unwrap();
+    // The source is blocked on the third payload.
+    wait_until_predicate(
+        || {
+            future::ready(
+                consumer_mailbox.queue_diagnostics().queue_depth == 2
+                    && memory_budget.in_flight_bytes() == 80,
+            )
+        },
+        Duration::from_secs(5),
+        Duration::from_millis(1),
+    )
+    .await
+    .unwrap();
+
+    consumer_handle.resume();
+    // Once this message is processed, all of the payloads have been sent.
+    source_mailbox.ask(EmitPayloads(0)).await.unwrap();
+    assert_eq!(*consumer_handle.process_pending_and_observe().await, 200);
+    assert_eq!(memory_budget.in_flight_bytes(), 0);
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_memory_budget_accounts_for_forwarded_messages() {
+    let memory_budget = Arc::new(MemoryBudget::new(100));
+    let universe = Universe::with_accelerated_time().with_memory_budget(memory_budget.clone());
+    let (consumer_mailbox, consumer_handle) =
+        universe.spawn_builder().spawn(PayloadConsumer::default());
+    consumer_handle.pause();
+    let (source_mailbox, _source_handle) = universe.spawn_builder().spawn(PayloadSource {
+        consumer_mailbox: consumer_mailbox.clone(),
+    });
+    // Forwarding does not wait on the budget, but is accounted for.
+    source_mailbox.ask(ForwardPayloads(5)).await.unwrap();
+    assert_eq!(memory_budget.in_flight_bytes(), 200);
+    assert_eq!(consumer_mailbox.queue_diagnostics().queue_depth, 5);
+
+    consumer_handle.resume();
+    assert_eq!(*consumer_handle.process_pending_and_observe().await, 200);
+    assert_eq!(memory_budget.in_flight_bytes(), 0);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct ThreadNameActor;
+
+impl Actor for ThreadNameActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[async_trait]
+impl Handler<Ping> for ThreadNameActor {
+    type Reply = Option<String>;
+
+    async fn handle(
+        &mut self,
+        _message: Ping,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<Option<String>, ActorExitStatus> {
+        Ok(std::thread::current().name().map(str::to_string))
+    }
+}
+
+#[tokio::test]
+async fn test_actor_runs_on_isolated_runtime() {
+    let universe = Universe::with_accelerated_time();
+    let isolated_runtime = IsolatedRuntime::new("test-pipeline", 1).unwrap();
+    let (mailbox, _handle) = universe
+        .spawn_builder()
+        .set_isolated_runtime(isolated_runtime)
+        .spawn(ThreadNameActor);
+    let thread_name = mailbox.ask(Ping).await.unwrap().unwrap();
+    assert!(thread_name.starts_with("test-pipeline-"));
+    universe.assert_quit().await;
+}
+
+struct ForwardingActor {
+    downstream_mailbox: Mailbox<PingReceiverActor>,
+}
+
+#[async_trait]
+impl Actor for ForwardingActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_message(&self.downstream_mailbox, checkpoint_barrier.clone())
+            .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for ForwardingActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: Ping,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_message(&self.downstream_mailbox, message).await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_drain_in_topological_order() {
+    let universe = Universe::with_accelerated_time();
+    let (receiver_mailbox, receiver_handle) =
+        universe.spawn_builder().spawn(PingReceiverActor::default());
+    let (forwarder_mailbox, forwarder_handle) = universe.spawn_builder().spawn(ForwardingActor {
+        downstream_mailbox: receiver_mailbox.clone(),
+    });
+    // The wiring is inferred from the first message forwarded.
+    forwarder_mailbox.ask(Ping).await.unwrap();
+    for _ in 0..100 {
+        forwarder_mailbox.send_message(Ping).await.unwrap();
+    }
+    let exit_statuses = universe.drain_in_topological_order().await;
+    assert_eq!(exit_statuses.len(), 2);
+    assert!(exit_statuses
+        .values()
+        .all(|exit_status| matches!(exit_status, ActorExitStatus::Success)));
+    let (_, ping_count) = receiver_handle.join().await;
+    assert_eq!(ping_count, 101);
+    forwarder_handle.join().await;
+}
+
+struct DependentActor {
+    dependency_reachable: bool,
+    ping_count: usize,
+}
+
+#[async_trait]
+impl Actor for DependentActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.ping_count
+    }
+
+    async fn check_readiness(&mut self, _ctx: &ActorContext<Self>) -> anyhow::Result<()> {
+        if !self.dependency_reachable {
+            anyhow::bail!("dependency unreachable");
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for DependentActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: Ping,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.ping_count += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_readiness_gate_delays_message_processing() {
+    let universe = Universe::with_accelerated_time();
+    let readiness_gate = ReadinessGate::new();
+    let (mailbox, handle) = universe
+        .spawn_builder()
+        .set_readiness_gate(readiness_gate.clone())
+        .spawn(DependentActor {
+            dependency_reachable: true,
+            ping_count: 0,
+        });
+    mailbox.send_message(Ping).await.unwrap();
+    universe.sleep(Duration::from_secs(1)).await;
+    assert_eq!(handle.last_observation(), 0);
+
+    readiness_gate.wait_ready().await.unwrap();
+    assert_eq!(*handle.process_pending_and_observe().await, 1);
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_readiness_gate_surfaces_startup_failure() {
+    let universe = Universe::with_accelerated_time();
+    let readiness_gate = ReadinessGate::new();
+    let (_mailbox, ready_handle) = universe
+        .spawn_builder()
+        .set_readiness_gate(readiness_gate.clone())
+        .spawn(DependentActor {
+            dependency_reachable: true,
+            ping_count: 0,
+        });
+    let (not_ready_mailbox, not_ready_handle) = universe
+        .spawn_builder()
+        .set_readiness_gate(readiness_gate.clone())
+        .spawn(DependentActor {
+            dependency_reachable: false,
+            ping_count: 0,
+        });
+    let readiness_error = readiness_gate.wait_ready().await.unwrap_err();
+    assert_eq!(
+        readiness_error.actor_id,
+        not_ready_mailbox.actor_instance_id()
+    );
+    assert_eq!(readiness_error.reason, "dependency unreachable");
+    let (ready_exit_status, _) = ready_handle.join().await;
+    assert!(matches!(ready_exit_status, ActorExitStatus::Failure(_)));
+    let (not_ready_exit_status, _) = not_ready_handle.join().await;
+    assert!(matches!(not_ready_exit_status, ActorExitStatus::Failure(_)));
+}
+
+#[tokio::test]
+async fn test_wait_until_quiescent() {
+    let universe = Universe::with_accelerated_time();
+    let (receiver_mailbox, receiver_handle) =
+        universe.spawn_builder().spawn(PingReceiverActor::default());
+    let (forwarder_mailbox, forwarder_handle) = universe.spawn_builder().spawn(ForwardingActor {
+        downstream_mailbox: receiver_mailbox,
+    });
+    for _ in 0..100 {
+        forwarder_mailbox.send_message(Ping).await.unwrap();
+    }
+    universe
+        .wait_until_quiescent(
+            &[&forwarder_handle, &receiver_handle],
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+    assert_eq!(receiver_handle.num_processed_messages(), 100);
+    assert_eq!(*receiver_handle.observe().await, 100);
+    universe.assert_quit().await;
+}
+
+struct FanInActor {
+    watermark_tracker: WatermarkTracker,
+    merged_epochs: Vec<u64>,
+}
+
+impl Actor for FanInActor {
+    type ObservableState = Vec<u64>;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.merged_epochs.clone()
+    }
+}
+
+#[async_trait]
+impl Handler<Watermark> for FanInActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        watermark: Watermark,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if let Some(merged_epoch) = self.watermark_tracker.observe(&watermark) {
+            self.merged_epochs.push(merged_epoch);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct EmitWatermark(u64);
+
+struct WatermarkEmitterActor {
+    downstream_mailbox: Mailbox<FanInActor>,
+}
+
+impl Actor for WatermarkEmitterActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[async_trait]
+impl Handler<EmitWatermark> for WatermarkEmitterActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        emit_watermark: EmitWatermark,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_watermark(&self.downstream_mailbox, emit_watermark.0)
+            .await?;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_watermarks_are_merged_at_fan_in() {
+    let universe = Universe::with_accelerated_time();
+    let (fan_in_mailbox, fan_in_handle) = universe.spawn_builder().spawn(FanInActor {
+        watermark_tracker: WatermarkTracker::new(2),
+        merged_epochs: Vec::new(),
+    });
+    let (emitter_1_mailbox, _emitter_1_handle) =
+        universe.spawn_builder().spawn(WatermarkEmitterActor {
+            downstream_mailbox: fan_in_mailbox.clone(),
+        });
+    let (emitter_2_mailbox, _emitter_2_handle) =
+        universe.spawn_builder().spawn(WatermarkEmitterActor {
+            downstream_mailbox: fan_in_mailbox,
+        });
+    emitter_1_mailbox.ask(EmitWatermark(1)).await.unwrap();
+    emitter_1_mailbox.ask(EmitWatermark(2)).await.unwrap();
+    emitter_2_mailbox.ask(EmitWatermark(2)).await.unwrap();
+    emitter_2_mailbox.ask(EmitWatermark(3)).await.unwrap();
+    emitter_1_mailbox.ask(EmitWatermark(4)).await.unwrap();
+    assert_eq!(
+        *fan_in_handle.process_pending_and_observe().await,
+        vec![2, 3]
+    );
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_checkpoint_barrier_goes_through_pipeline() {
+    let universe = Universe::with_accelerated_time();
+    let (receiver_mailbox, receiver_handle) =
+        universe.spawn_builder().spawn(PingReceiverActor::default());
+    let (forwarder_mailbox, _forwarder_handle) = universe.spawn_builder().spawn(ForwardingActor {
+        downstream_mailbox: receiver_mailbox,
+    });
+    for _ in 0..10 {
+        forwarder_mailbox.send_message(Ping).await.unwrap();
+    }
+    let checkpoint_completion = universe
+        .inject_checkpoint_barrier(1, &[forwarder_mailbox.clone()])
+        .await;
+    checkpoint_completion.wait().await.unwrap();
+    // The barrier went through the receiver after all of the pings sent before it.
+    assert_eq!(
+        receiver_handle.process_pending_and_observe().await.state,
+        10
+    );
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_checkpoint_barrier_fails_if_source_exited() {
+    let universe = Universe::with_accelerated_time();
+    let (receiver_mailbox, receiver_handle) =
+        universe.spawn_builder().spawn(PingReceiverActor::default());
+    receiver_handle.quit().await;
+    let checkpoint_completion = universe
+        .inject_checkpoint_barrier(2, &[receiver_mailbox])
+        .await;
+    let checkpoint_barrier_error = checkpoint_completion.wait().await.unwrap_err();
+    assert_eq!(checkpoint_barrier_error.checkpoint_id, 2);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct BlockingCallActor;
+
+impl Actor for BlockingCallActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[derive(Debug)]
+struct BlockingCall {
+    should_panic: bool,
+}
+
+#[async_trait]
+impl Handler<BlockingCall> for BlockingCallActor {
+    type Reply = usize;
+
+    async fn handle(
+        &mut self,
+        blocking_call: BlockingCall,
+        ctx: &ActorContext<Self>,
+    ) -> Result<usize, ActorExitStatus> {
+        let num_bytes = ctx
+            .run_blocking(move || {
+                if blocking_call.should_panic {
+                    panic!("blocking call failed");
+                }
+                std::thread::sleep(Duration::from_millis(10));
+                42
+            })
+            .await?;
+        Ok(num_bytes)
+    }
+}
+
+#[tokio::test]
+async fn test_run_blocking() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(BlockingCallActor);
+    let reply = mailbox
+        .ask(BlockingCall {
+            should_panic: false,
+        })
+        .await
+        .unwrap();
+    assert_eq!(reply, 42);
+    mailbox
+        .send_message(BlockingCall { should_panic: true })
+        .await
+        .unwrap();
+    let (exit_status, _) = handle.join().await;
+    assert!(matches!(exit_status, ActorExitStatus::Panicked));
+}
+
+#[derive(Default)]
+struct SleepyActor;
+
+impl Actor for SleepyActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[derive(Debug)]
+struct Sleep(Duration);
+
+#[async_trait]
+impl Handler<Sleep> for SleepyActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        sleep: Sleep,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.sleep(sleep.0).await;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_sleep_records_progress() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(SleepyActor);
+    mailbox
+        .send_message(Sleep(crate::HEARTBEAT.mul(4)))
+        .await
+        .unwrap();
+    universe.sleep(Duration::from_millis(1)).await;
+    assert_eq!(handle.harvest_health(), Health::Healthy);
+    universe.sleep(crate::HEARTBEAT.mul(2)).await;
+    assert_eq!(handle.harvest_health(), Health::Healthy);
+    assert_eq!(handle.state(), ActorState::Processing);
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_soft_kill_quits_actors_gracefully() {
+    let universe = Universe::new();
+    let kill_switch = KillSwitch::default();
+    let (mailbox, handle) = universe
+        .spawn_builder()
+        .set_kill_switch(kill_switch.clone())
+        .spawn(PingReceiverActor::default());
+    for _ in 0..10 {
+        mailbox.send_message(Ping).await.unwrap();
+    }
+    tokio::time::timeout(
+        Duration::from_secs(5),
+        kill_switch.soft_kill(Duration::from_secs(60)),
+    )
+    .await
+    .unwrap();
+    let (exit_status, ping_count) = handle.join().await;
+    assert!(matches!(exit_status, ActorExitStatus::Quit));
+    // The messages queued before the soft kill were processed.
+    assert_eq!(ping_count, 10);
+}
+
+#[derive(Default)]
+struct SoftKillOptOutActor;
+
+impl Actor for SoftKillOptOutActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) {}
+
+    fn quit_on_soft_kill(&self) -> bool {
+        false
+    }
+}
+
+#[tokio::test]
+async fn test_soft_kill_waits_for_actors_opting_out() {
+    let universe = Universe::with_accelerated_time();
+    let kill_switch = KillSwitch::default();
+    let (_mailbox, handle) = universe
+        .spawn_builder()
+        .set_kill_switch(kill_switch.clone())
+        .spawn(SoftKillOptOutActor);
+    // The actor registers its soft kill callback when it starts.
+    handle.process_pending_and_observe().await;
+    let soft_killed = Arc::new(Notify::new());
+    let soft_kill_registration = kill_switch.register_soft_kill_callback({
+        let soft_killed = soft_killed.clone();
+        move || soft_killed.notify_one()
+    });
+    let soft_kill_task = tokio::spawn({
+        let kill_switch = kill_switch.clone();
+        async move { kill_switch.soft_kill(Duration::from_millis(200)).await }
+    });
+    soft_killed.notified().await;
+    drop(soft_kill_registration);
+    // The actor was not asked to quit, and the soft kill waits for it.
+    let observation = handle.process_pending_and_observe().await;
+    assert_eq!(observation.obs_type, ObservationType::Alive);
+    assert!(kill_switch.is_alive());
+    assert_eq!(handle.state(), ActorState::Idle);
+    soft_kill_task.await.unwrap();
+    // The grace period elapsed.
+    assert!(kill_switch.is_dead());
+    let (exit_status, _) = handle.kill().await;
+    assert!(matches!(exit_status, ActorExitStatus::Killed));
+}
+
+#[tokio::test]
+async fn test_lifecycle_events() {
+    let universe = Universe::with_accelerated_time();
+    let mut lifecycle_event_rx = universe.subscribe_lifecycle_events();
+    let (_mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    let actor_instance_id = handle.name().to_string();
+    handle.pause();
+    handle.resume();
+    handle.quit().await;
+
+    let mut event_kinds = Vec::new();
+    for _ in 0..4 {
+        let event = lifecycle_event_rx.recv().await.unwrap();
+        assert_eq!(event.actor_instance_id, actor_instance_id);
+        event_kinds.push(event.kind);
+    }
+    assert!(matches!(
+        &event_kinds[..],
+        [
+            ActorLifecycleEventKind::Spawned,
+            ActorLifecycleEventKind::Paused,
+            ActorLifecycleEventKind::Resumed,
+            ActorLifecycleEventKind::Terminated(ActorExitStatus::Quit),
+        ]
+    ));
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_universe_topology() {
+    let universe = Universe::with_accelerated_time();
+    let (receiver_mailbox, receiver_handle) =
+        universe.spawn_builder().spawn(PingReceiverActor::default());
+    let (forwarder_mailbox, forwarder_handle) = universe.spawn_builder().spawn(ForwardingActor {
+        downstream_mailbox: receiver_mailbox,
+    });
+    let topology = universe.topology();
+    assert_eq!(topology.actors.len(), 2);
+    assert!(topology.edges.is_empty());
+
+    forwarder_mailbox.ask(Ping).await.unwrap();
+    let topology = universe.topology();
+    let forwarder_id = forwarder_handle.name().to_string();
+    let receiver_id = receiver_handle.name().to_string();
+    assert_eq!(
+        topology.actors.get(&forwarder_id).copied(),
+        Some(std::any::type_name::<ForwardingActor>())
+    );
+    assert!(topology
+        .edges
+        .contains(&(forwarder_id.clone(), receiver_id.clone())));
+    assert!(topology
+        .to_dot()
+        .contains(&format!("\"{forwarder_id}\" -> \"{receiver_id}\";")));
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct ReloadableActor {
+    num_reloads: usize,
+    ping_count: usize,
+}
+
+#[derive(Debug)]
+struct ReloadConfig;
+
+#[async_trait]
+impl Actor for ReloadableActor {
+    type ObservableState = (usize, usize);
+
+    fn observable_state(&self) -> Self::ObservableState {
+        (self.num_reloads, self.ping_count)
+    }
+
+    async fn process_command(
+        &mut self,
+        command: CustomCommand,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        if command.downcast::<ReloadConfig>().is_ok() {
+            self.num_reloads += 1;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Ping> for ReloadableActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _ping: Ping,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.ping_count += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_custom_command_bypasses_data_queue() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(ReloadableActor::default());
+    handle.pause();
+    for _ in 0..3 {
+        mailbox.send_message(Ping).await.unwrap();
+    }
+    handle.send_command(ReloadConfig);
+    // Unknown commands are ignored.
+    handle.send_command("rotate-log-file");
+    assert_eq!(handle.observe().await.state, (1, 0));
+    handle.resume();
+    assert_eq!(handle.process_pending_and_observe().await.state, (1, 3));
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct CommitTimeoutActor {
+    num_commits: usize,
+}
+
+impl Actor for CommitTimeoutActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.num_commits
+    }
+}
+
+#[derive(Debug)]
+struct PostponeCommit;
+
+#[derive(Debug)]
+struct Commit;
+
+#[async_trait]
+impl Handler<PostponeCommit> for CommitTimeoutActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: PostponeCommit,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.schedule_self_msg_with_dedup_key("commit-split", Duration::from_secs(10), Commit)
+            .await;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<Commit> for CommitTimeoutActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: Commit,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.num_commits += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_schedule_self_msg_with_dedup_key() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe
+        .spawn_builder()
+        .spawn(CommitTimeoutActor::default());
+    for _ in 0..3 {
+        mailbox.ask(PostponeCommit).await.unwrap();
+        universe.sleep(Duration::from_secs(5)).await;
+    }
+    assert_eq!(handle.process_pending_and_observe().await.state, 0);
+    universe.sleep(Duration::from_secs(6)).await;
+    assert_eq!(handle.process_pending_and_observe().await.state, 1);
+    // Once delivered, the key can be reused.
+    mailbox.ask(PostponeCommit).await.unwrap();
+    universe.sleep(Duration::from_secs(11)).await;
+    assert_eq!(handle.process_pending_and_observe().await.state, 2);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct FlakyUploaderActor;
+
+impl Actor for FlakyUploaderActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[derive(Debug)]
+struct Upload;
+
+#[async_trait]
+impl Handler<Upload> for FlakyUploaderActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _upload: Upload,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.report_error(anyhow::anyhow!("failed to put object"));
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_report_error() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(FlakyUploaderActor);
+    let mut reported_errors_rx = handle.subscribe_reported_errors();
+    mailbox.ask(Upload).await.unwrap();
+    let reported_error = reported_errors_rx.recv().await.unwrap();
+    assert_eq!(reported_error.actor_instance_id, handle.name());
+    assert_eq!(reported_error.error.to_string(), "failed to put object");
+    assert_eq!(handle.num_reported_errors(), 1);
+    // Reporting an error does not terminate the actor.
+    assert!(matches!(
+        handle.state(),
+        ActorState::Idle | ActorState::Processing
+    ));
+    universe.assert_quit().await;
+}
+
+#[derive(Debug)]
+struct UploadWithRetries {
+    num_failures: usize,
+}
+
+#[async_trait]
+impl Handler<UploadWithRetries> for FlakyUploaderActor {
+    type Reply = Result<usize, String>;
+
+    async fn handle(
+        &mut self,
+        upload: UploadWithRetries,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Self::Reply, ActorExitStatus> {
+        let retry_policy = RetryPolicy {
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(10),
+            max_attempts: 3,
+        };
+        let num_failures = upload.num_failures;
+        let mut num_attempts = 0;
+        let upload_res = ctx
+            .retry(&retry_policy, || {
+                num_attempts += 1;
+                let attempt = num_attempts;
+                async move {
+                    if attempt <= num_failures {
+                        Err(format!("attempt {attempt} failed"))
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await;
+        Ok(upload_res.map_err(|retry_error| {
+            assert_eq!(retry_error.attempts.len(), 3);
+            assert!(retry_error.attempts[..2]
+                .iter()
+                .all(|attempt| attempt.backoff_opt.is_some()));
+            assert!(retry_error.attempts[2].backoff_opt.is_none());
+            retry_error.last_error
+        }))
+    }
+}
+
+#[tokio::test]
+async fn test_retry() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, _handle) = universe.spawn_builder().spawn(FlakyUploaderActor);
+    let upload_res = mailbox
+        .ask(UploadWithRetries { num_failures: 2 })
+        .await
+        .unwrap();
+    assert_eq!(upload_res, Ok(3));
+    let upload_res = mailbox
+        .ask(UploadWithRetries { num_failures: 5 })
+        .await
+        .unwrap();
+    assert_eq!(upload_res, Err("attempt 3 failed".to_string()));
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct CountingMetricsSink {
+    num_enqueued: AtomicUsize,
+    num_dequeued: AtomicUsize,
+    num_processed: AtomicUsize,
+}
+
+impl MetricsSink for CountingMetricsSink {
+    fn record_enqueue(&self, _actor_instance_id: &str, message_type: &'static str) {
+        if message_type.ends_with("Ping") {
+            self.num_enqueued.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn record_dequeue(&self, _actor_instance_id: &str, message_type: &'static str) {
+        if message_type.ends_with("Ping") {
+            self.num_dequeued.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn record_processing_time(
+        &self,
+        _actor_instance_id: &str,
+        message_type: &'static str,
+        _processing_time: Duration,
+    ) {
+        if message_type.ends_with("Ping") {
+            self.num_processed.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_metrics_sink() {
+    let metrics_sink = Arc::new(CountingMetricsSink::default());
+    let universe = Universe::with_accelerated_time().with_metrics_sink(metrics_sink.clone());
+    let (mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    for _ in 0..5 {
+        mailbox.send_message(Ping).await.unwrap();
+    }
+    assert_eq!(handle.process_pending_and_observe().await.state, 5);
+    assert_eq!(metrics_sink.num_enqueued.load(Ordering::SeqCst), 5);
+    assert_eq!(metrics_sink.num_dequeued.load(Ordering::SeqCst), 5);
+    assert_eq!(metrics_sink.num_processed.load(Ordering::SeqCst), 5);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct DeferredUploaderActor {
+    pending_responses: Vec<ResponseHandle<usize>>,
+    num_uploads: usize,
+}
+
+impl Actor for DeferredUploaderActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.pending_responses.len()
+    }
+}
+
+#[async_trait]
+impl DeferableReplyHandler<Upload> for DeferredUploaderActor {
+    type Reply = usize;
+
+    async fn handle_message(
+        &mut self,
+        _upload: Upload,
+        reply: impl FnOnce(usize) + Send + Sync + 'static,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.pending_responses.push(ResponseHandle::new(reply));
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct UploadCompleted;
+
+#[async_trait]
+impl Handler<UploadCompleted> for DeferredUploaderActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: UploadCompleted,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        for response_handle in self.pending_responses.drain(..) {
+            self.num_uploads += 1;
+            response_handle.respond(self.num_uploads);
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_deferred_reply() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe
+        .spawn_builder()
+        .spawn(DeferredUploaderActor::default());
+    let first_reply_rx = mailbox.send_message(Upload).await.unwrap();
+    let second_reply_rx = mailbox.send_message(Upload).await.unwrap();
+    assert_eq!(handle.process_pending_and_observe().await.state, 2);
+    mailbox.send_message(UploadCompleted).await.unwrap();
+    assert_eq!(first_reply_rx.await.unwrap(), 1);
+    assert_eq!(second_reply_rx.await.unwrap(), 2);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct LeafSearcherActor {
+    num_searches: usize,
+}
+
+impl Actor for LeafSearcherActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.num_searches
+    }
+}
+
+#[derive(Debug)]
+struct LeafSearch;
+
+#[async_trait]
+impl Handler<LeafSearch> for LeafSearcherActor {
+    type Reply = usize;
+
+    async fn handle(
+        &mut self,
+        _leaf_search: LeafSearch,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<usize, ActorExitStatus> {
+        self.num_searches += 1;
+        Ok(self.num_searches)
+    }
+}
+
+struct RootSearcherActor {
+    leaf_mailbox: Mailbox<LeafSearcherActor>,
+}
+
+impl Actor for RootSearcherActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[derive(Debug)]
+struct RootSearch;
+
+#[async_trait]
+impl Handler<RootSearch> for RootSearcherActor {
+    type Reply = usize;
+
+    async fn handle(
+        &mut self,
+        _root_search: RootSearch,
+        ctx: &ActorContext<Self>,
+    ) -> Result<usize, ActorExitStatus> {
+        let num_searches = ctx
+            .ask(&self.leaf_mailbox, LeafSearch)
+            .await
+            .map_err(|_| ActorExitStatus::DownstreamClosed)?;
+        Ok(num_searches)
+    }
+}
+
+#[tokio::test]
+async fn test_ask_with_high_priority_propagates_priority() {
+    let universe = Universe::with_accelerated_time();
+    let (leaf_mailbox, leaf_handle) = universe.spawn_builder().spawn(LeafSearcherActor::default());
+    let (root_mailbox, _root_handle) = universe.spawn_builder().spawn(RootSearcherActor {
+        leaf_mailbox: leaf_mailbox.clone(),
+    });
+    // The leaf searcher is paused, so regular messages pile up in its queue.
+    leaf_handle.pause();
+    let bulk_search_rx = leaf_mailbox.send_message(LeafSearch).await.unwrap();
+
+    // The leaf search issued on behalf of the high priority root search skips the queue.
+    let num_searches = root_mailbox
+        .ask_with_high_priority(RootSearch)
+        .await
+        .unwrap();
+    assert_eq!(num_searches, 1);
+
+    leaf_handle.resume();
+    assert_eq!(bulk_search_rx.await.unwrap(), 2);
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct FanInRecorderActor {
+    upstream_ords: Vec<usize>,
+}
+
+impl Actor for FanInRecorderActor {
+    type ObservableState = Vec<usize>;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.upstream_ords.clone()
+    }
+}
+
+#[derive(Debug)]
+struct FromUpstream(usize);
+
+#[async_trait]
+impl Handler<FromUpstream> for FanInRecorderActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        message: FromUpstream,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.upstream_ords.push(message.0);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_fan_in_is_fair_across_upstreams() {
+    let universe = Universe::with_accelerated_time();
+    let (upstream_mailboxes, handle) = universe
+        .spawn_builder()
+        .spawn_fan_in(FanInRecorderActor::default(), 2);
+    assert_eq!(upstream_mailboxes.len(), 2);
+    handle.pause();
+    for _ in 0..10 {
+        upstream_mailboxes[0]
+            .send_message(FromUpstream(0))
+            .await
+            .unwrap();
+    }
+    for _ in 0..2 {
+        upstream_mailboxes[1]
+            .send_message(FromUpstream(1))
+            .await
+            .unwrap();
+    }
+    handle.resume();
+    let upstream_ords = handle.process_pending_and_observe().await.state;
+    assert_eq!(upstream_ords.len(), 12);
+    // The chatty upstream does not starve the quiet one.
+    assert_eq!(
+        upstream_ords[..4]
+            .iter()
+            .filter(|upstream_ord| **upstream_ord == 1)
+            .count(),
+        2
+    );
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct LineBufferActor {
+    buffered_lines: Vec<String>,
+    num_flushed_lines: usize,
+}
+
+#[async_trait]
+impl Actor for LineBufferActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.num_flushed_lines
+    }
+
+    async fn on_eof(&mut self, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.num_flushed_lines += self.buffered_lines.len();
+        self.buffered_lines.clear();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Handler<String> for LineBufferActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        line: String,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.buffered_lines.push(line);
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_eof_when_all_mailboxes_are_dropped() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(LineBufferActor::default());
+    mailbox.send_message("line-1".to_string()).await.unwrap();
+    mailbox.send_message("line-2".to_string()).await.unwrap();
+    assert_eq!(handle.process_pending_and_observe().await.state, 0);
+    // The actor is starving, but more messages may still come.
+    assert!(!handle.reached_eof());
+
+    drop(mailbox);
+    tokio::time::timeout(Duration::from_secs(1), async {
+        while !handle.reached_eof() {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+    })
+    .await
+    .unwrap();
+    let (exit_status, num_flushed_lines) = handle.join().await;
+    assert!(matches!(exit_status, ActorExitStatus::Success));
+    assert_eq!(num_flushed_lines, 2);
+}
+
+#[derive(Default)]
+struct MultipartUploaderActor {
+    aborted_uploads: Arc<std::sync::Mutex<Vec<String>>>,
+}
+
+#[async_trait]
+impl Actor for MultipartUploaderActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+
+    async fn initialize(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        for upload_id in ["upload-1", "upload-2"] {
+            let aborted_uploads = self.aborted_uploads.clone();
+            ctx.defer_async(async move {
+                tokio::task::yield_now().await;
+                aborted_uploads.lock().unwrap().push(upload_id.to_string());
+            });
+        }
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_defer_async_runs_on_kill() {
+    let universe = Universe::with_accelerated_time();
+    let uploader = MultipartUploaderActor::default();
+    let aborted_uploads = uploader.aborted_uploads.clone();
+    let (_mailbox, handle) = universe.spawn_builder().spawn(uploader);
+    handle.process_pending_and_observe().await;
+    assert!(aborted_uploads.lock().unwrap().is_empty());
+    let (exit_status, _) = handle.kill().await;
+    assert!(matches!(exit_status, ActorExitStatus::Killed));
+    assert_eq!(*aborted_uploads.lock().unwrap(), ["upload-2", "upload-1"]);
+}
+
+#[derive(Default)]
+struct RetentionActor {
+    num_retention_runs: usize,
+}
+
+impl Actor for RetentionActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.num_retention_runs
+    }
+}
+
+#[derive(Debug)]
+struct EnforceRetention;
+
+#[async_trait]
+impl Handler<EnforceRetention> for RetentionActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: EnforceRetention,
+        _ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        self.num_retention_runs += 1;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ScheduleRetention;
+
+#[async_trait]
+impl Handler<ScheduleRetention> for RetentionActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        _message: ScheduleRetention,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let next_hour = std::time::SystemTime::now() + Duration::from_secs(3_600);
+        ctx.schedule_self_msg_at(next_hour, EnforceRetention).await;
+        // A deadline in the past is delivered right away.
+        let past_instant = std::time::Instant::now() - Duration::from_secs(1);
+        ctx.schedule_self_msg_at_instant(past_instant, EnforceRetention)
+            .await;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_schedule_self_msg_at_absolute_deadline() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(RetentionActor::default());
+    mailbox.send_message(ScheduleRetention).await.unwrap();
+    universe.sleep(Duration::from_secs(60)).await;
+    assert_eq!(handle.process_pending_and_observe().await.state, 1);
+    universe.sleep(Duration::from_secs(3_600)).await;
+    assert_eq!(handle.process_pending_and_observe().await.state, 2);
+    universe.assert_quit().await;
+}
+
+#[derive(Debug)]
+struct ObservePeer;
+
+#[async_trait]
+impl Handler<ObservePeer> for ThreadNameActor {
+    type Reply = Option<usize>;
+
+    async fn handle(
+        &mut self,
+        _message: ObservePeer,
+        ctx: &ActorContext<Self>,
+    ) -> Result<Option<usize>, ActorExitStatus> {
+        let Some(ping_handle) = ctx.handle_of::<PingReceiverActor>("Ping") else {
+            return Ok(None);
+        };
+        Ok(Some(ping_handle.process_pending_and_observe().await.state))
+    }
+}
+
+#[tokio::test]
+async fn test_handle_of_peer_actor() {
+    let universe = Universe::with_accelerated_time();
+    let (ping_mailbox, ping_handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    let (observer_mailbox, _observer_handle) = universe.spawn_builder().spawn(ThreadNameActor);
+    ping_mailbox.send_message(Ping).await.unwrap();
+    assert_eq!(observer_mailbox.ask(ObservePeer).await.unwrap(), Some(1));
+
+    let ping_actor_instance_id = ping_handle.mailbox().actor_instance_id();
+    assert!(universe
+        .spawn_ctx()
+        .registry
+        .get_handle::<PingReceiverActor>(ping_actor_instance_id)
+        .is_some());
+    assert!(universe
+        .spawn_ctx()
+        .registry
+        .get_handle::<ThreadNameActor>("Ping")
+        .is_none());
+    universe.assert_quit().await;
+}
+
+#[derive(Default)]
+struct MergeTaskActor;
+
+impl Actor for MergeTaskActor {
+    type ObservableState = ();
+
+    fn observable_state(&self) -> Self::ObservableState {}
+}
+
+#[derive(Debug)]
+struct MergeSplits(Vec<String>);
+
+#[async_trait]
+impl Handler<MergeSplits> for MergeTaskActor {
+    type Reply = ();
+
+    async fn handle(
+        &mut self,
+        merge_splits: MergeSplits,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let merged_split = merge_splits.0.join("+");
+        Err(ctx.finish_with(merged_split))
+    }
+}
+
+#[tokio::test]
+async fn test_join_with_output() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(MergeTaskActor);
+    mailbox
+        .send_message(MergeSplits(vec![
+            "split-1".to_string(),
+            "split-2".to_string(),
+        ]))
+        .await
+        .unwrap();
+    let (exit_status, merged_split_opt) = handle.join_with_output::<String>().await;
+    assert!(matches!(exit_status, ActorExitStatus::Success));
+    assert_eq!(merged_split_opt.as_deref(), Some("split-1+split-2"));
+}
+
+#[derive(Default)]
+struct FlushCounterActor {
+    num_upstreams: usize,
+    num_flushes: usize,
+}
+
+#[async_trait]
+impl Actor for FlushCounterActor {
+    type ObservableState = usize;
+
+    fn observable_state(&self) -> Self::ObservableState {
+        self.num_flushes
+    }
+
+    fn num_checkpoint_barrier_upstreams(&self) -> usize {
+        self.num_upstreams
+    }
+
+    async fn flush(&mut self, _ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.num_flushes += 1;
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_checkpoint_barrier_is_aligned_at_fan_in() {
+    let universe = Universe::with_accelerated_time();
+    let (fan_in_mailbox, fan_in_handle) = universe.spawn_builder().spawn(FlushCounterActor {
+        num_upstreams: 2,
+        ..Default::default()
+    });
+    let checkpoint_completion = universe
+        .inject_checkpoint_barrier(1, &[fan_in_mailbox.clone(), fan_in_mailbox.clone()])
+        .await;
+    checkpoint_completion.wait().await.unwrap();
+    // The actor flushed once, after receiving the copies of both upstream actors.
+    assert_eq!(*fan_in_handle.process_pending_and_observe().await, 1);
+
+    // The copy of the second upstream actor never arrives.
+    let checkpoint_completion = universe
+        .inject_checkpoint_barrier(2, &[fan_in_mailbox])
+        .await;
+    assert_eq!(*fan_in_handle.process_pending_and_observe().await, 1);
+    fan_in_handle.quit().await;
+    let checkpoint_barrier_error = checkpoint_completion.wait().await.unwrap_err();
+    assert_eq!(checkpoint_barrier_error.checkpoint_id, 2);
+    universe.assert_quit().await;
+}
+
+#[tokio::test]
+async fn test_processing_time_is_not_recorded_without_budget() {
+    let universe = Universe::with_accelerated_time();
+    let (mailbox, handle) = universe.spawn_builder().spawn(PingReceiverActor::default());
+    mailbox.ask(Ping).await.unwrap();
+    assert!(handle.processing_time_p99().is_none());
+    universe.assert_quit().await;
+}
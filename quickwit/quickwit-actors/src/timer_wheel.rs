@@ -0,0 +1,304 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+use std::time::{Duration, Instant};
+
+const LEVEL_BITS: usize = 6;
+const NUM_SLOTS: usize = 1 << LEVEL_BITS;
+const SLOT_MASK: u64 = NUM_SLOTS as u64 - 1;
+const NUM_LEVELS: usize = 6;
+/// Duration of a tick, the resolution of the wheel.
+const TICK: Duration = Duration::from_millis(1);
+
+struct Entry<T> {
+    deadline: Instant,
+    tick: u64,
+    value: T,
+}
+
+struct Level<T> {
+    slots: Vec<Vec<Entry<T>>>,
+    // Bitset of the non-empty slots.
+    occupied: u64,
+}
+
+impl<T> Default for Level<T> {
+    fn default() -> Self {
+        Level {
+            slots: (0..NUM_SLOTS).map(|_| Vec::new()).collect(),
+            occupied: 0,
+        }
+    }
+}
+
+/// Hierarchical timer wheel.
+///
+/// Inserting an entry is O(1), and expiring entries is amortized O(1) per entry, no matter
+/// how many entries are pending. This makes it possible to keep tens of thousands of
+/// scheduled events without the heap churn of a binary heap.
+///
+/// The wheel has `NUM_LEVELS` levels of `NUM_SLOTS` slots each. A slot of level `l` spans
+/// `NUM_SLOTS^l` ticks. Entries are stored in the lowest level whose range covers their
+/// deadline, and cascade down to the lower levels as time advances. Entries too far in
+/// the future for the wheel (about 2 years ahead) are kept in an overflow list.
+///
+/// Deadlines are tracked with a resolution of one tick, but entries are only returned once
+/// their exact deadline has passed.
+pub(crate) struct TimerWheel<T> {
+    origin: Instant,
+    // Number of ticks elapsed since `origin`, as of the last call to `pop_expired`.
+    elapsed: u64,
+    levels: Vec<Level<T>>,
+    overflow: Vec<Entry<T>>,
+    // Entries whose tick is elapsed, but whose exact deadline may not be.
+    ready: Vec<Entry<T>>,
+    len: usize,
+}
+
+impl<T> TimerWheel<T> {
+    pub fn new(origin: Instant) -> Self {
+        TimerWheel {
+            origin,
+            elapsed: 0,
+            levels: (0..NUM_LEVELS).map(|_| Level::default()).collect(),
+            overflow: Vec::new(),
+            ready: Vec::new(),
+            len: 0,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn insert(&mut self, deadline: Instant, value: T) {
+        let tick = self.tick(deadline);
+        self.insert_entry(Entry {
+            deadline,
+            tick,
+            value,
+        });
+        self.len += 1;
+    }
+
+    /// Removes and returns the entries whose deadline is lower or equal to `now`.
+    ///
+    /// The entries are not returned in any specific order.
+    pub fn pop_expired(&mut self, now: Instant) -> Vec<T> {
+        self.advance(self.tick(now));
+        let mut expired = Vec::new();
+        let mut i = 0;
+        while i < self.ready.len() {
+            if self.ready[i].deadline <= now {
+                expired.push(self.ready.swap_remove(i).value);
+            } else {
+                i += 1;
+            }
+        }
+        self.len -= expired.len();
+        expired
+    }
+
+    /// Returns the earliest deadline of the wheel.
+    pub fn next_deadline(&self) -> Option<Instant> {
+        if self.len == 0 {
+            return None;
+        }
+        if let Some(deadline) = min_deadline(&self.ready) {
+            return Some(deadline);
+        }
+        if let Some((level, slot, _)) = self.next_expiration() {
+            return min_deadline(&self.levels[level].slots[slot]);
+        }
+        min_deadline(&self.overflow)
+    }
+
+    fn tick(&self, instant: Instant) -> u64 {
+        (instant.saturating_duration_since(self.origin).as_nanos() / TICK.as_nanos()) as u64
+    }
+
+    fn insert_entry(&mut self, entry: Entry<T>) {
+        if entry.tick <= self.elapsed {
+            self.ready.push(entry);
+            return;
+        }
+        let level = level_for(self.elapsed, entry.tick);
+        if level >= NUM_LEVELS {
+            self.overflow.push(entry);
+            return;
+        }
+        let slot = slot_for(entry.tick, level);
+        self.levels[level].slots[slot].push(entry);
+        self.levels[level].occupied |= 1 << slot;
+    }
+
+    /// Advances the wheel to `target_tick`, cascading the entries of the slots it goes
+    /// through down to the lower levels, and eventually to the ready list.
+    fn advance(&mut self, target_tick: u64) {
+        while let Some((level, slot, slot_start)) = self.next_expiration() {
+            if slot_start > target_tick {
+                break;
+            }
+            self.elapsed = self.elapsed.max(slot_start);
+            self.levels[level].occupied &= !(1 << slot);
+            let entries = std::mem::take(&mut self.levels[level].slots[slot]);
+            for entry in entries {
+                self.insert_entry(entry);
+            }
+        }
+        self.elapsed = self.elapsed.max(target_tick);
+        if !self.overflow.is_empty() {
+            for entry in std::mem::take(&mut self.overflow) {
+                self.insert_entry(entry);
+            }
+        }
+    }
+
+    /// Returns the level, the slot, and the first tick of the next non-empty slot.
+    ///
+    /// The entries of a level always expire before the ones of the levels above, so the
+    /// next non-empty slot is the first one found on the lowest non-empty level.
+    fn next_expiration(&self) -> Option<(usize, usize, u64)> {
+        for (level_ord, level) in self.levels.iter().enumerate() {
+            if level.occupied == 0 {
+                continue;
+            }
+            let slot_range = 1u64 << (LEVEL_BITS * level_ord);
+            let level_range = slot_range << LEVEL_BITS;
+            let current_slot = slot_for(self.elapsed, level_ord) as u32;
+            let slot = (level.occupied.rotate_right(current_slot).trailing_zeros() + current_slot)
+                as usize
+                % NUM_SLOTS;
+            let level_start = self.elapsed & !(level_range - 1);
+            let slot_start = level_start + slot as u64 * slot_range;
+            return Some((level_ord, slot, slot_start));
+        }
+        None
+    }
+}
+
+fn level_for(elapsed: u64, tick: u64) -> usize {
+    let masked = (elapsed ^ tick) | SLOT_MASK;
+    let significant_bit = 63 - masked.leading_zeros() as usize;
+    significant_bit / LEVEL_BITS
+}
+
+fn slot_for(tick: u64, level: usize) -> usize {
+    ((tick >> (LEVEL_BITS * level)) & SLOT_MASK) as usize
+}
+
+fn min_deadline<T>(entries: &[Entry<T>]) -> Option<Instant> {
+    entries.iter().map(|entry| entry.deadline).min()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timer_wheel_empty() {
+        let mut timer_wheel: TimerWheel<usize> = TimerWheel::new(Instant::now());
+        assert_eq!(timer_wheel.len(), 0);
+        assert!(timer_wheel.next_deadline().is_none());
+        assert!(timer_wheel.pop_expired(Instant::now()).is_empty());
+    }
+
+    #[test]
+    fn test_timer_wheel_expires_at_exact_deadline() {
+        let origin = Instant::now();
+        let mut timer_wheel = TimerWheel::new(origin);
+        let deadline = origin + Duration::from_micros(1_500);
+        timer_wheel.insert(deadline, 1);
+        assert_eq!(timer_wheel.next_deadline(), Some(deadline));
+        assert!(timer_wheel
+            .pop_expired(origin + Duration::from_millis(1))
+            .is_empty());
+        assert!(timer_wheel
+            .pop_expired(origin + Duration::from_micros(1_499))
+            .is_empty());
+        assert_eq!(timer_wheel.next_deadline(), Some(deadline));
+        assert_eq!(timer_wheel.pop_expired(deadline), vec![1]);
+        assert_eq!(timer_wheel.len(), 0);
+        assert!(timer_wheel.next_deadline().is_none());
+    }
+
+    #[test]
+    fn test_timer_wheel_past_deadline() {
+        let origin = Instant::now();
+        let mut timer_wheel = TimerWheel::new(origin);
+        timer_wheel.pop_expired(origin + Duration::from_secs(10));
+        timer_wheel.insert(origin + Duration::from_secs(1), 1);
+        assert_eq!(
+            timer_wheel.pop_expired(origin + Duration::from_secs(10)),
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn test_timer_wheel_many_levels() {
+        let origin = Instant::now();
+        let mut timer_wheel = TimerWheel::new(origin);
+        let delays_millis: Vec<u64> = vec![
+            3,
+            63,
+            64,
+            65,
+            4_095,
+            4_096,
+            70_000,
+            3_600_000,
+            86_400_000,
+            // Beyond the range of the wheel.
+            1 << 40,
+        ];
+        for (i, delay_millis) in delays_millis.iter().enumerate().rev() {
+            timer_wheel.insert(origin + Duration::from_millis(*delay_millis), i);
+        }
+        assert_eq!(timer_wheel.len(), delays_millis.len());
+        for (i, delay_millis) in delays_millis.iter().enumerate() {
+            let deadline = origin + Duration::from_millis(*delay_millis);
+            assert_eq!(timer_wheel.next_deadline(), Some(deadline));
+            assert!(timer_wheel
+                .pop_expired(deadline - Duration::from_micros(1))
+                .is_empty());
+            assert_eq!(timer_wheel.pop_expired(deadline), vec![i]);
+        }
+        assert_eq!(timer_wheel.len(), 0);
+    }
+
+    #[test]
+    fn test_timer_wheel_jump() {
+        let origin = Instant::now();
+        let mut timer_wheel = TimerWheel::new(origin);
+        for i in 0..10_000u64 {
+            timer_wheel.insert(origin + Duration::from_millis(i * 37 % 100_000), i);
+        }
+        let mut expired = timer_wheel.pop_expired(origin + Duration::from_secs(50));
+        expired.sort();
+        let mut expected: Vec<u64> = (0..10_000u64)
+            .filter(|i| i * 37 % 100_000 <= 50_000)
+            .collect();
+        expected.sort();
+        assert_eq!(expired, expected);
+        assert_eq!(timer_wheel.len(), 10_000 - expected.len());
+        let remaining = timer_wheel.pop_expired(origin + Duration::from_secs(100));
+        assert_eq!(remaining.len(), 10_000 - expected.len());
+        assert_eq!(timer_wheel.len(), 0);
+    }
+}
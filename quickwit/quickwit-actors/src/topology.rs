@@ -0,0 +1,87 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use serde::Serialize;
+
+/// Directed graph of the actors of a universe, with an edge from each actor to the actors
+/// it sent messages to through its `ActorContext`.
+///
+/// Edges are only recorded upon the first message sent, so an actor that has not sent
+/// anything yet has no outgoing edges.
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize)]
+pub struct PipelineTopology {
+    /// Actor instance id -> actor type name, for the live actors.
+    pub actors: BTreeMap<String, &'static str>,
+    /// (upstream actor instance id, downstream actor instance id)
+    pub edges: BTreeSet<(String, String)>,
+}
+
+impl PipelineTopology {
+    /// Renders the topology in the Graphviz DOT format, e.g. to be piped into `dot -Tsvg`.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph pipeline {\n");
+        for (actor_id, type_name) in &self.actors {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\\n{}\"];",
+                escape(actor_id),
+                escape(actor_id),
+                escape(type_name)
+            );
+        }
+        for (upstream_actor_id, downstream_actor_id) in &self.edges {
+            let _ = writeln!(
+                dot,
+                "    \"{}\" -> \"{}\";",
+                escape(upstream_actor_id),
+                escape(downstream_actor_id)
+            );
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn escape(id: &str) -> String {
+    id.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pipeline_topology_to_dot() {
+        let topology = PipelineTopology {
+            actors: BTreeMap::from([
+                ("indexer-1".to_string(), "Indexer"),
+                ("source-1".to_string(), "Source"),
+            ]),
+            edges: BTreeSet::from([("source-1".to_string(), "indexer-1".to_string())]),
+        };
+        assert_eq!(
+            topology.to_dot(),
+            "digraph pipeline {\n    \"indexer-1\" [label=\"indexer-1\\nIndexer\"];\n    \
+             \"source-1\" [label=\"source-1\\nSource\"];\n    \"source-1\" -> \"indexer-1\";\n}\n"
+        );
+    }
+}
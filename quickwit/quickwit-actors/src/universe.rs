@@ -18,14 +18,28 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+use tokio::sync::broadcast;
+use tokio::time::error::Elapsed;
+
+use crate::checkpoint_barrier::{CheckpointBarrier, CheckpointCompletion};
+use crate::dead_letter_queue::DeadLetter;
+use crate::lifecycle_events::ActorLifecycleEvent;
 use crate::mailbox::create_mailbox;
+use crate::memory_budget::MemoryBudget;
+use crate::metrics_sink::MetricsSink;
+use crate::panic_policy::PanicPolicy;
 use crate::registry::ActorObservation;
+use crate::scheduled_message_store::ScheduledMessageStore;
 use crate::scheduler::start_scheduler;
 use crate::spawn_builder::{SpawnBuilder, SpawnContext};
-use crate::{Actor, ActorExitStatus, Command, Inbox, Mailbox, QueueCapacity};
+use crate::{
+    Actor, ActorExitStatus, ActorHandle, Command, Inbox, Mailbox, PipelineTopology, QueueCapacity,
+    Supervisable,
+};
 
 /// Universe serves as the top-level context in which Actor can be spawned.
 /// It is *not* a singleton. A typical application will usually have only one universe hosting all
@@ -65,12 +79,96 @@ impl Universe {
         universe
     }
 
+    /// Persists the messages scheduled with `ActorContext::schedule_persistent_self_msg`
+    /// in `scheduled_message_store`, so that they survive a process restart.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_scheduled_message_store(
+        mut self,
+        scheduled_message_store: Arc<ScheduledMessageStore>,
+    ) -> Universe {
+        self.spawn_ctx.scheduled_message_store_opt = Some(scheduled_message_store);
+        self
+    }
+
+    /// Accounts for the messages sent with `ActorContext::send_sized_message` in
+    /// `memory_budget`, making their senders wait while the budget is exhausted.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_memory_budget(mut self, memory_budget: Arc<MemoryBudget>) -> Universe {
+        self.spawn_ctx.memory_budget_opt = Some(memory_budget);
+        self
+    }
+
+    /// Notifies `metrics_sink` of the activity of the mailboxes and of the actors of the
+    /// universe.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_metrics_sink(mut self, metrics_sink: Arc<dyn MetricsSink>) -> Universe {
+        self.spawn_ctx.metrics_sink_opt = Some(metrics_sink);
+        self
+    }
+
+    /// Keeps track of the instants at which the queued messages were sent, so that the age
+    /// of the oldest one is part of the `QueueDiagnostics` of the mailboxes. It costs a lock
+    /// per message sent and received.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_message_age_tracking(mut self) -> Universe {
+        self.spawn_ctx.track_message_ages = true;
+        self
+    }
+
+    /// Keeps the records of the last messages processed by each actor, which are dumped when
+    /// the actor fails or panics. It costs a lock per message processed.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_flight_recorder(mut self) -> Universe {
+        self.spawn_ctx.record_flights = true;
+        self
+    }
+
+    /// Measures the CPU and blocking time spent by the actors processing their messages. It
+    /// costs two `clock_gettime` syscalls per poll of a message handler.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_cpu_usage_tracking(mut self) -> Universe {
+        self.spawn_ctx.measure_cpu_usage = true;
+        self
+    }
+
+    /// Sets what happens when an actor of the universe panics. By default, only the actor
+    /// that panicked exits.
+    ///
+    /// It should be set before spawning any actor.
+    pub fn with_panic_policy(mut self, panic_policy: PanicPolicy) -> Universe {
+        self.spawn_ctx.panic_policy = panic_policy;
+        self
+    }
+
+    /// Returns the registry of the failpoints consulted by the actors of the universe.
+    #[cfg(feature = "failpoints")]
+    pub fn failpoints(&self) -> &crate::FailpointRegistry {
+        &self.spawn_ctx.failpoint_registry
+    }
+
+    /// Returns the number of messages queued in the mailbox of the scheduler of the universe.
+    pub fn scheduler_queue_depth(&self) -> usize {
+        self.spawn_ctx.scheduler_client.queue_depth()
+    }
+
     pub fn spawn_ctx(&self) -> &SpawnContext {
         &self.spawn_ctx
     }
 
     pub fn create_test_mailbox<A: Actor>(&self) -> (Mailbox<A>, Inbox<A>) {
-        create_mailbox("test-mailbox".to_string(), QueueCapacity::Unbounded, None)
+        create_mailbox(
+            "test-mailbox".to_string(),
+            QueueCapacity::Unbounded,
+            None,
+            None,
+            false,
+        )
     }
 
     pub fn create_mailbox<A: Actor>(
@@ -81,6 +179,15 @@ impl Universe {
         self.spawn_ctx.create_mailbox(actor_name, queue_capacity)
     }
 
+    pub fn create_multi_source_mailbox<A: Actor>(
+        &self,
+        actor_name: impl ToString,
+        queue_capacities: &[QueueCapacity],
+    ) -> (Vec<Mailbox<A>>, Inbox<A>) {
+        self.spawn_ctx
+            .create_multi_source_mailbox(actor_name, queue_capacities)
+    }
+
     pub fn get<A: Actor>(&self) -> Vec<Mailbox<A>> {
         self.spawn_ctx.registry.get::<A>()
     }
@@ -93,6 +200,47 @@ impl Universe {
         self.spawn_ctx.registry.observe(timeout).await
     }
 
+    /// Observes all of the actors of the universe and logs a JSON dump of their name,
+    /// state, mailbox depth, last progress timestamp, and observable state.
+    ///
+    /// This is useful to diagnose a stuck pipeline.
+    pub async fn log_observations(&self) {
+        self.spawn_ctx
+            .registry
+            .log_observations(crate::OBSERVE_TIMEOUT)
+            .await
+    }
+
+    /// Installs a SIGUSR1 handler that logs a JSON dump of all of the actors of
+    /// the universe. See [`Universe::log_observations`].
+    ///
+    /// This function must be called from within a tokio runtime.
+    #[cfg(unix)]
+    pub fn log_observations_on_sigusr1(&self) -> std::io::Result<()> {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut sigusr1 = signal(SignalKind::user_defined1())?;
+        let registry = self.spawn_ctx.registry.clone();
+        tokio::spawn(async move {
+            while sigusr1.recv().await.is_some() {
+                registry.log_observations(crate::OBSERVE_TIMEOUT).await;
+            }
+        });
+        Ok(())
+    }
+
+    /// Subscribes to the lifecycle events of all of the actors of the universe spawned
+    /// from now on.
+    pub fn subscribe_lifecycle_events(&self) -> broadcast::Receiver<ActorLifecycleEvent> {
+        self.spawn_ctx.lifecycle_event_bus.subscribe()
+    }
+
+    /// Returns the messages given up on after their processing failed too many times.
+    /// See `Mailbox::send_redeliverable_message`.
+    pub fn dead_letters(&self) -> Vec<DeadLetter> {
+        self.spawn_ctx.dead_letter_queue.dead_letters()
+    }
+
     pub fn kill(&self) {
         self.spawn_ctx.kill_switch.kill();
     }
@@ -120,6 +268,101 @@ impl Universe {
         Ok(())
     }
 
+    /// Waits until none of the given actors is processing a message and all of their
+    /// mailboxes are empty, or until `timeout` elapses.
+    ///
+    /// To rule out a message being in flight between two actors when their states are
+    /// sampled, the actors must be found idle twice in a row without processing any message
+    /// in between.
+    ///
+    /// This is meant to replace the "sleep a bit and observe" pattern in tests.
+    pub async fn wait_until_quiescent(
+        &self,
+        actors: &[&dyn Supervisable],
+        timeout: Duration,
+    ) -> Result<(), Elapsed> {
+        tokio::time::timeout(timeout, async {
+            let mut previous_num_processed_messages_opt: Option<u64> = None;
+            loop {
+                let num_processed_messages_opt = quiescent_num_processed_messages(actors);
+                if num_processed_messages_opt.is_some()
+                    && num_processed_messages_opt == previous_num_processed_messages_opt
+                {
+                    return;
+                }
+                previous_num_processed_messages_opt = num_processed_messages_opt;
+                tokio::time::sleep(QUIESCENCE_POLL_INTERVAL).await;
+            }
+        })
+        .await
+    }
+
+    /// Waits concurrently for the given actors to exit, and returns their exit status and
+    /// final state, in the order of `actor_handles`.
+    ///
+    /// The actors still running once `timeout` has elapsed are killed. Unlike joining the
+    /// handles one after the other, a failure is observed as soon as it happens, whatever
+    /// the position of the failing actor.
+    pub async fn join_all<A: Actor>(
+        &self,
+        actor_handles: Vec<ActorHandle<A>>,
+        timeout: Duration,
+    ) -> Vec<(ActorExitStatus, A::ObservableState)> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        let join_futures = actor_handles
+            .into_iter()
+            .map(|actor_handle| actor_handle.join_or_kill_at(deadline));
+        futures::future::join_all(join_futures).await
+    }
+
+    /// Sends a [`CheckpointBarrier`] to the given source actors and returns a future
+    /// resolving once the barrier has gone through all of the sinks of their pipelines.
+    pub async fn inject_checkpoint_barrier<A: Actor>(
+        &self,
+        checkpoint_id: u64,
+        source_mailboxes: &[Mailbox<A>],
+    ) -> CheckpointCompletion {
+        let (checkpoint_barrier, checkpoint_completion) = CheckpointBarrier::new(checkpoint_id);
+        for source_mailbox in source_mailboxes {
+            // If the source exited, the barrier is dropped and the checkpoint fails.
+            let _ = source_mailbox
+                .send_message(checkpoint_barrier.clone())
+                .await;
+        }
+        checkpoint_barrier.mark_processed();
+        checkpoint_completion
+    }
+
+    /// Gracefully drains all registered actors, upstream actors first.
+    ///
+    /// The dependency order is inferred from the messages the actors sent to each other
+    /// (e.g. source -> indexer -> packager -> uploader -> publisher): an actor is only asked
+    /// to exit, after processing its pending messages, once all of its upstream actors
+    /// have exited.
+    pub async fn drain_in_topological_order(&self) -> HashMap<String, ActorExitStatus> {
+        self.spawn_ctx.registry.drain_in_topological_order().await
+    }
+
+    /// Returns the graph of the actors of the universe and of the mailboxes they send
+    /// messages to. See [`PipelineTopology::to_dot`] to visualize it.
+    pub fn topology(&self) -> PipelineTopology {
+        self.spawn_ctx.registry.topology()
+    }
+
+    /// Pauses all of the actors of the universe, e.g. to quiesce indexing during a maintenance
+    /// window. Returns once every actor has completed its in-flight message and is paused.
+    ///
+    /// Paused actors still process commands and high priority messages. Actors spawned after
+    /// this call are not paused.
+    pub async fn pause_all(&self) {
+        self.spawn_ctx.registry.pause_all().await
+    }
+
+    /// Resumes all of the actors of the universe.
+    pub async fn resume_all(&self) {
+        self.spawn_ctx.registry.resume_all().await
+    }
+
     /// Gracefully quits all registered actors.
     pub async fn quit(&self) -> HashMap<String, ActorExitStatus> {
         self.spawn_ctx.registry.quit().await
@@ -138,6 +381,21 @@ impl Universe {
     }
 }
 
+/// Interval at which the states of the actors are sampled by `Universe::wait_until_quiescent`.
+const QUIESCENCE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Returns the total number of messages processed by the actors if they are all idle.
+fn quiescent_num_processed_messages(actors: &[&dyn Supervisable]) -> Option<u64> {
+    let mut num_processed_messages = 0;
+    for actor in actors {
+        if !actor.is_idle() {
+            return None;
+        }
+        num_processed_messages += actor.num_processed_messages();
+    }
+    Some(num_processed_messages)
+}
+
 impl Drop for Universe {
     fn drop(&mut self) {
         if cfg!(any(test, feature = "testsuite"))
@@ -268,6 +526,31 @@ mod tests {
             .any(|status| matches!(status, ActorExitStatus::Panicked)));
     }
 
+    #[tokio::test]
+    async fn test_universe_join_all() {
+        let universe = Universe::new();
+        let (exiting_mailbox, exiting_handle) = universe
+            .spawn_builder()
+            .spawn(CountingMinutesActor::default());
+        let (_mailbox, running_handle) = universe
+            .spawn_builder()
+            .spawn(CountingMinutesActor::default());
+        universe
+            .send_exit_with_success(&exiting_mailbox)
+            .await
+            .unwrap();
+        let terminations = universe
+            .join_all(
+                vec![exiting_handle, running_handle],
+                Duration::from_millis(100),
+            )
+            .await;
+        assert_eq!(terminations.len(), 2);
+        assert!(matches!(terminations[0], (ActorExitStatus::Success, 1)));
+        assert!(matches!(terminations[1], (ActorExitStatus::Killed, 1)));
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     #[should_panic(
         expected = "There are still running actors at the end of the test. Did you call \
@@ -0,0 +1,133 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+/// Message signaling that all of the data up to `epoch` (e.g. a checkpoint) has been sent by
+/// the upstream actor `upstream_actor_id`.
+///
+/// Epochs sent by a given actor are expected to increase monotonically. Actors forward
+/// watermarks downstream with `ActorContext::send_watermark` once they are done with the
+/// data of an epoch, so that the last stage of a pipeline (e.g. a publisher) knows when all
+/// of the data up to a checkpoint has gone through every stage.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Watermark {
+    pub upstream_actor_id: String,
+    pub epoch: u64,
+}
+
+/// Merges the watermarks received by an actor fed by several upstream actors.
+///
+/// The merged watermark is the lowest epoch among all of the upstream actors. It is only
+/// defined once every upstream actor has sent a watermark.
+#[derive(Debug)]
+pub struct WatermarkTracker {
+    num_upstreams: usize,
+    epochs: HashMap<String, u64>,
+    merged_epoch_opt: Option<u64>,
+}
+
+impl WatermarkTracker {
+    pub fn new(num_upstreams: usize) -> Self {
+        assert!(
+            num_upstreams > 0,
+            "An actor should have at least one upstream."
+        );
+        WatermarkTracker {
+            num_upstreams,
+            epochs: HashMap::with_capacity(num_upstreams),
+            merged_epoch_opt: None,
+        }
+    }
+
+    /// Returns the merged watermark, if all of the upstream actors have sent one.
+    pub fn merged_epoch(&self) -> Option<u64> {
+        self.merged_epoch_opt
+    }
+
+    /// Records a watermark and returns the new merged watermark if it advanced.
+    ///
+    /// Watermarks lower than the last one received from the same upstream actor are ignored.
+    pub fn observe(&mut self, watermark: &Watermark) -> Option<u64> {
+        let epoch = self
+            .epochs
+            .entry(watermark.upstream_actor_id.clone())
+            .or_insert(watermark.epoch);
+        *epoch = (*epoch).max(watermark.epoch);
+        if self.epochs.len() < self.num_upstreams {
+            return None;
+        }
+        let merged_epoch = self.epochs.values().copied().min()?;
+        if self
+            .merged_epoch_opt
+            .map(|previous_merged_epoch| merged_epoch <= previous_merged_epoch)
+            .unwrap_or(false)
+        {
+            return None;
+        }
+        self.merged_epoch_opt = Some(merged_epoch);
+        Some(merged_epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watermark(upstream_actor_id: &str, epoch: u64) -> Watermark {
+        Watermark {
+            upstream_actor_id: upstream_actor_id.to_string(),
+            epoch,
+        }
+    }
+
+    #[test]
+    fn test_watermark_tracker_single_upstream() {
+        let mut watermark_tracker = WatermarkTracker::new(1);
+        assert_eq!(watermark_tracker.merged_epoch(), None);
+        assert_eq!(watermark_tracker.observe(&watermark("source", 1)), Some(1));
+        assert_eq!(watermark_tracker.observe(&watermark("source", 1)), None);
+        assert_eq!(watermark_tracker.observe(&watermark("source", 0)), None);
+        assert_eq!(watermark_tracker.observe(&watermark("source", 3)), Some(3));
+        assert_eq!(watermark_tracker.merged_epoch(), Some(3));
+    }
+
+    #[test]
+    fn test_watermark_tracker_merges_upstreams_at_fan_in() {
+        let mut watermark_tracker = WatermarkTracker::new(2);
+        assert_eq!(watermark_tracker.observe(&watermark("indexer-1", 2)), None);
+        assert_eq!(watermark_tracker.observe(&watermark("indexer-1", 3)), None);
+        assert_eq!(
+            watermark_tracker.observe(&watermark("indexer-2", 1)),
+            Some(1)
+        );
+        assert_eq!(
+            watermark_tracker.observe(&watermark("indexer-2", 5)),
+            Some(3)
+        );
+        assert_eq!(watermark_tracker.observe(&watermark("indexer-2", 6)), None);
+        assert_eq!(
+            watermark_tracker.observe(&watermark("indexer-1", 7)),
+            Some(6)
+        );
+        assert_eq!(watermark_tracker.merged_epoch(), Some(6));
+    }
+}
@@ -21,6 +21,7 @@ use anyhow::{bail, Context};
 use clap::{arg, Arg, ArgAction, ArgMatches, Command};
 use tracing::Level;
 
+use crate::delete_task::{build_delete_task_command, DeleteTaskCliCommand};
 use crate::index::{build_index_command, IndexCliCommand};
 use crate::service::{build_run_command, RunCliCommand};
 use crate::source::{build_source_command, SourceCliCommand};
@@ -48,7 +49,8 @@ pub fn build_cli() -> Command {
         .subcommand(build_index_command().display_order(2))
         .subcommand(build_source_command().display_order(3))
         .subcommand(build_split_command().display_order(4))
-        .subcommand(build_tool_command().display_order(5))
+        .subcommand(build_delete_task_command().display_order(5))
+        .subcommand(build_tool_command().display_order(6))
         .arg_required_else_help(true)
         .disable_help_subcommand(true)
         .subcommand_required(true)
@@ -60,6 +62,7 @@ pub enum CliCommand {
     Index(IndexCliCommand),
     Split(SplitCliCommand),
     Source(SourceCliCommand),
+    DeleteTask(DeleteTaskCliCommand),
     Tool(ToolCliCommand),
 }
 
@@ -70,6 +73,7 @@ impl CliCommand {
             CliCommand::Index(subcommand) => subcommand.default_log_level(),
             CliCommand::Source(_) => Level::ERROR,
             CliCommand::Split(_) => Level::ERROR,
+            CliCommand::DeleteTask(_) => Level::ERROR,
             CliCommand::Tool(_) => Level::ERROR,
         }
     }
@@ -83,6 +87,9 @@ impl CliCommand {
             "run" => RunCliCommand::parse_cli_args(submatches).map(CliCommand::Run),
             "source" => SourceCliCommand::parse_cli_args(submatches).map(CliCommand::Source),
             "split" => SplitCliCommand::parse_cli_args(submatches).map(CliCommand::Split),
+            "delete-task" => {
+                DeleteTaskCliCommand::parse_cli_args(submatches).map(CliCommand::DeleteTask)
+            }
             "tool" => ToolCliCommand::parse_cli_args(submatches).map(CliCommand::Tool),
             _ => bail!("Unknown command `{subcommand}`."),
         }
@@ -94,6 +101,7 @@ impl CliCommand {
             CliCommand::Run(subcommand) => subcommand.execute().await,
             CliCommand::Source(subcommand) => subcommand.execute().await,
             CliCommand::Split(subcommand) => subcommand.execute().await,
+            CliCommand::DeleteTask(subcommand) => subcommand.execute().await,
             CliCommand::Tool(subcommand) => subcommand.execute().await,
         }
     }
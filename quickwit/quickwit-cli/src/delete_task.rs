@@ -0,0 +1,273 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use anyhow::{bail, Context};
+use clap::{arg, ArgMatches, Command};
+use colored::Colorize;
+use quickwit_proto::metastore::DeleteTask;
+use quickwit_serve::DeleteQueryRequest;
+use tabled::{Table, Tabled};
+use tracing::debug;
+
+use crate::checklist::GREEN_COLOR;
+use crate::{client_args, make_table, ClientArgs};
+
+pub fn build_delete_task_command() -> Command {
+    Command::new("delete-task")
+        .about("Manages delete tasks: creates, lists...")
+        .args(client_args())
+        .subcommand(
+            Command::new("create")
+                .about("Creates a delete task removing the documents of an index matching a query.")
+                .args(&[
+                    arg!(--index <INDEX> "Target index ID")
+                        .display_order(1)
+                        .required(true),
+                    arg!(--query <QUERY> "Query selecting the documents to delete.")
+                        .display_order(2)
+                        .required(true),
+                    arg!(--"search-fields" <FIELD_NAME> "List of fields that Quickwit will search into if the user query does not explicitly target a field in the query. It overrides the default search fields defined in the index config. Space-separated list, e.g. \"field1 field2\". ")
+                        .display_order(3)
+                        .num_args(1..)
+                        .required(false),
+                    arg!(--"start-timestamp" <TIMESTAMP> "Restricts the delete task to documents with a `timestamp >= start_timestamp`.")
+                        .display_order(4)
+                        .required(false),
+                    arg!(--"end-timestamp" <TIMESTAMP> "Restricts the delete task to documents with a `timestamp < end_timestamp`.")
+                        .display_order(5)
+                        .required(false),
+                ])
+            )
+        .subcommand(
+            Command::new("list")
+                .about("Lists the delete tasks of an index.")
+                .alias("ls")
+                .args(&[
+                    arg!(--index <INDEX> "Target index ID")
+                        .display_order(1)
+                        .required(true),
+                ])
+            )
+        .arg_required_else_help(true)
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct CreateDeleteTaskArgs {
+    pub client_args: ClientArgs,
+    pub index_id: String,
+    pub query: String,
+    pub search_fields: Vec<String>,
+    pub start_timestamp: Option<i64>,
+    pub end_timestamp: Option<i64>,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub struct ListDeleteTasksArgs {
+    pub client_args: ClientArgs,
+    pub index_id: String,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum DeleteTaskCliCommand {
+    Create(CreateDeleteTaskArgs),
+    List(ListDeleteTasksArgs),
+}
+
+impl DeleteTaskCliCommand {
+    pub fn parse_cli_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let (subcommand, submatches) = matches
+            .remove_subcommand()
+            .context("Failed to parse delete task subcommand.")?;
+        match subcommand.as_str() {
+            "create" => Self::parse_create_args(submatches),
+            "list" => Self::parse_list_args(submatches),
+            _ => bail!("Unknown delete task subcommand `{subcommand}`."),
+        }
+    }
+
+    fn parse_create_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let client_args = ClientArgs::parse(&mut matches)?;
+        let index_id = matches
+            .remove_one::<String>("index")
+            .expect("`index` should be a required arg.");
+        let query = matches
+            .remove_one::<String>("query")
+            .expect("`query` should be a required arg.");
+        let search_fields = matches
+            .remove_many::<String>("search-fields")
+            .map(|values| values.collect())
+            .unwrap_or_default();
+        let start_timestamp = matches
+            .remove_one::<String>("start-timestamp")
+            .map(|timestamp_str| parse_timestamp(&timestamp_str, "start"))
+            .transpose()?;
+        let end_timestamp = matches
+            .remove_one::<String>("end-timestamp")
+            .map(|timestamp_str| parse_timestamp(&timestamp_str, "end"))
+            .transpose()?;
+        Ok(Self::Create(CreateDeleteTaskArgs {
+            client_args,
+            index_id,
+            query,
+            search_fields,
+            start_timestamp,
+            end_timestamp,
+        }))
+    }
+
+    fn parse_list_args(mut matches: ArgMatches) -> anyhow::Result<Self> {
+        let client_args = ClientArgs::parse(&mut matches)?;
+        let index_id = matches
+            .remove_one::<String>("index")
+            .expect("`index` should be a required arg.");
+        Ok(Self::List(ListDeleteTasksArgs {
+            client_args,
+            index_id,
+        }))
+    }
+
+    pub async fn execute(self) -> anyhow::Result<()> {
+        match self {
+            Self::Create(args) => create_delete_task_cli(args).await,
+            Self::List(args) => list_delete_tasks_cli(args).await,
+        }
+    }
+}
+
+async fn create_delete_task_cli(args: CreateDeleteTaskArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "create-delete-task");
+    println!("❯ Creating delete task...");
+    let qw_client = args.client_args.client();
+    let delete_query = DeleteQueryRequest {
+        query: args.query,
+        search_fields: args.search_fields,
+        start_timestamp: args.start_timestamp,
+        end_timestamp: args.end_timestamp,
+    };
+    let delete_task = qw_client
+        .delete_tasks(&args.index_id)
+        .create(delete_query)
+        .await
+        .context("Failed to create delete task.")?;
+    println!(
+        "{} Delete task with opstamp `{}` successfully created. Matching documents will be \
+         removed in the background.",
+        "✔".color(GREEN_COLOR),
+        delete_task.opstamp
+    );
+    Ok(())
+}
+
+async fn list_delete_tasks_cli(args: ListDeleteTasksArgs) -> anyhow::Result<()> {
+    debug!(args=?args, "list-delete-tasks");
+    let qw_client = args.client_args.client();
+    let delete_tasks = qw_client
+        .delete_tasks(&args.index_id)
+        .list()
+        .await
+        .context("Failed to list delete tasks.")?;
+    let delete_tasks_table = make_delete_tasks_table(delete_tasks);
+    println!("\n{delete_tasks_table}\n");
+    Ok(())
+}
+
+fn make_delete_tasks_table(delete_tasks: Vec<DeleteTask>) -> Table {
+    let rows = delete_tasks.into_iter().map(|delete_task| {
+        let delete_query = delete_task.delete_query.unwrap_or_default();
+        DeleteTaskRow {
+            opstamp: delete_task.opstamp,
+            create_timestamp: delete_task.create_timestamp,
+            query_ast: delete_query.query_ast,
+            start_timestamp: format_timestamp_opt(delete_query.start_timestamp),
+            end_timestamp: format_timestamp_opt(delete_query.end_timestamp),
+        }
+    });
+    make_table("Delete tasks", rows, false)
+}
+
+fn format_timestamp_opt(timestamp_opt: Option<i64>) -> String {
+    timestamp_opt
+        .map(|timestamp| timestamp.to_string())
+        .unwrap_or_default()
+}
+
+fn parse_timestamp(timestamp_str: &str, option_name: &str) -> anyhow::Result<i64> {
+    timestamp_str.parse::<i64>().with_context(|| {
+        format!(
+            "Failed to parse {option_name} timestamp `{timestamp_str}`: expected a Unix timestamp \
+             in seconds."
+        )
+    })
+}
+
+#[derive(Tabled)]
+struct DeleteTaskRow {
+    #[tabled(rename = "Opstamp")]
+    opstamp: u64,
+    #[tabled(rename = "Create timestamp")]
+    create_timestamp: i64,
+    #[tabled(rename = "Query")]
+    query_ast: String,
+    #[tabled(rename = "Start timestamp")]
+    start_timestamp: String,
+    #[tabled(rename = "End timestamp")]
+    end_timestamp: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use quickwit_proto::metastore::DeleteQuery;
+
+    use super::*;
+
+    #[test]
+    fn test_make_delete_tasks_table() {
+        let delete_tasks = vec![DeleteTask {
+            create_timestamp: 1,
+            opstamp: 2,
+            delete_query: Some(DeleteQuery {
+                index_uid: "my-index:0".to_string(),
+                start_timestamp: Some(3),
+                end_timestamp: None,
+                query_ast: "body:myterm".to_string(),
+            }),
+        }];
+        let expected_rows = vec![DeleteTaskRow {
+            opstamp: 2,
+            create_timestamp: 1,
+            query_ast: "body:myterm".to_string(),
+            start_timestamp: "3".to_string(),
+            end_timestamp: String::new(),
+        }];
+        assert_eq!(
+            make_delete_tasks_table(delete_tasks).to_string(),
+            make_table("Delete tasks", expected_rows, false).to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(
+            parse_timestamp("1662529435", "start").unwrap(),
+            1_662_529_435
+        );
+        let error = parse_timestamp("yesterday", "end").unwrap_err();
+        assert!(error.to_string().contains("end timestamp `yesterday`"));
+    }
+}
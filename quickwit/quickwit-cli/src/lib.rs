@@ -47,6 +47,7 @@ use crate::checklist::run_checklist;
 
 pub mod checklist;
 pub mod cli;
+pub mod delete_task;
 pub mod index;
 #[cfg(feature = "jemalloc")]
 pub mod jemalloc;
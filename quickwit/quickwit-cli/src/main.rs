@@ -155,6 +155,8 @@ async fn main_impl() -> anyhow::Result<()> {
     #[cfg(feature = "jemalloc")]
     start_jemalloc_metrics_loop();
 
+    quickwit_actors::install_panic_hook();
+
     setup_logging_and_tracing(command.default_log_level(), ansi, build_info)?;
     let return_code: i32 = if let Err(err) = command.execute().await {
         eprintln!("{} Command failed: {:?}\n", "✘".color(RED_COLOR), err);
@@ -186,6 +188,7 @@ mod tests {
 
     use byte_unit::Byte;
     use quickwit_cli::cli::{build_cli, CliCommand};
+    use quickwit_cli::delete_task::{CreateDeleteTaskArgs, DeleteTaskCliCommand};
     use quickwit_cli::index::{
         ClearIndexArgs, CreateIndexArgs, DeleteIndexArgs, DescribeIndexArgs, IndexCliCommand,
         IngestDocsArgs, SearchIndexArgs,
@@ -593,6 +596,37 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_parse_delete_task_create_args() -> anyhow::Result<()> {
+        let app = build_cli().no_binary_name(true);
+        let matches = app.try_get_matches_from([
+            "delete-task",
+            "create",
+            "--index",
+            "wikipedia",
+            "--query",
+            "title:Barack",
+            "--search-fields",
+            "title",
+            "body",
+            "--start-timestamp",
+            "1662529435",
+        ])?;
+        let command = CliCommand::parse_cli_args(matches)?;
+        assert!(matches!(
+            command,
+            CliCommand::DeleteTask(DeleteTaskCliCommand::Create(CreateDeleteTaskArgs {
+                index_id,
+                query,
+                search_fields,
+                start_timestamp: Some(1_662_529_435),
+                end_timestamp: None,
+                ..
+            })) if &index_id == "wikipedia" && &query == "title:Barack" && search_fields == vec!["title".to_string(), "body".to_string()]
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_parse_split_describe_args() -> anyhow::Result<()> {
         let app = build_cli().no_binary_name(true);
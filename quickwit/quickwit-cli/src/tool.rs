@@ -22,6 +22,7 @@ use std::io::{stdout, IsTerminal, Stdout, Write};
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use std::{env, fmt, io};
 
@@ -31,7 +32,7 @@ use chitchat::FailureDetectorConfig;
 use clap::{arg, ArgMatches, Command};
 use colored::{ColoredString, Colorize};
 use humantime::format_duration;
-use quickwit_actors::{ActorExitStatus, ActorHandle, ObservationType, Universe};
+use quickwit_actors::{ActorExitStatus, ActorHandle, MemoryBudget, ObservationType, Universe};
 use quickwit_cluster::{Cluster, ClusterMember};
 use quickwit_common::runtimes::RuntimesConfig;
 use quickwit_common::uri::Uri;
@@ -98,7 +99,7 @@ pub fn build_tool_command() -> Command {
         .subcommand(
             Command::new("gc")
                 .display_order(10)
-                .about("Garbage collects stale staged splits and splits marked for deletion.")
+                .about("Garbage collects stale staged splits, splits marked for deletion, and orphan split files.")
                 .args(&[
                     arg!(--index <INDEX> "ID of the target index")
                         .display_order(1)
@@ -335,6 +336,8 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
     let indexer_config = IndexerConfig {
         ..Default::default()
     };
+    let memory_budget =
+        MemoryBudget::new(indexer_config.max_in_flight_docs_memory.get_bytes() as usize);
     let runtimes_config = RuntimesConfig::default();
     start_actor_runtimes(
         runtimes_config,
@@ -351,7 +354,7 @@ pub async fn local_ingest_docs_cli(args: LocalIngestDocsArgs) -> anyhow::Result<
         storage_resolver,
     )
     .await?;
-    let universe = Universe::new();
+    let universe = Universe::new().with_memory_budget(Arc::new(memory_budget));
     let (indexing_server_mailbox, indexing_server_handle) =
         universe.spawn_builder().spawn(indexing_server);
     let pipeline_id = indexing_server_mailbox
@@ -509,7 +512,10 @@ pub async fn garbage_collect_index_cli(args: GarbageCollectIndexArgs) -> anyhow:
     let removal_info = index_service
         .garbage_collect_index(&args.index_id, args.grace_period, args.dry_run)
         .await?;
-    if removal_info.removed_split_entries.is_empty() && removal_info.failed_splits.is_empty() {
+    if removal_info.removed_split_entries.is_empty()
+        && removal_info.failed_splits.is_empty()
+        && removal_info.removed_orphan_files.is_empty()
+    {
         println!("No dangling files to garbage collect.");
         return Ok(());
     }
@@ -519,9 +525,19 @@ pub async fn garbage_collect_index_cli(args: GarbageCollectIndexArgs) -> anyhow:
         for split_info in removal_info.removed_split_entries {
             println!(" - {}", split_info.file_name.display());
         }
+        for orphan_file in removal_info.removed_orphan_files {
+            println!(" - {}", orphan_file.display());
+        }
         return Ok(());
     }
 
+    if !removal_info.removed_orphan_files.is_empty() {
+        println!(
+            "{} orphan split files with no matching split in the metastore were removed.",
+            removal_info.removed_orphan_files.len()
+        );
+    }
+
     if !removal_info.failed_splits.is_empty() {
         println!("The following splits were attempted to be removed, but failed.");
         for split_info in &removal_info.failed_splits {
@@ -17,11 +17,83 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::future::Future;
+use std::pin::Pin;
+#[cfg(not(loom))]
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex, Weak};
+#[cfg(not(loom))]
+use std::sync::Mutex;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
 
+#[cfg(loom)]
+use loom::sync::atomic::{AtomicBool, Ordering};
+#[cfg(loom)]
+use loom::sync::Mutex;
+use tokio::runtime::Handle;
+use tokio::sync::Notify;
 use tracing::debug;
 
+type SoftKillCallback = dyn Fn() + Send + Sync;
+
+type CleanupFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct SoftKillHook {
+    callback: Box<SoftKillCallback>,
+    // Notified when the registration is dropped.
+    unregistered: Arc<Notify>,
+}
+
+/// Keeps a soft kill callback registered on a kill switch.
+///
+/// Dropping it unregisters the callback, and signals that its owner is done shutting down.
+pub struct SoftKillRegistration {
+    hook: Arc<SoftKillHook>,
+}
+
+impl Drop for SoftKillRegistration {
+    fn drop(&mut self) {
+        // `notify_one` stores a permit if `soft_kill` is not waiting yet.
+        self.hook.unregistered.notify_one();
+    }
+}
+
+/// Keeps a cleanup future registered on a kill switch.
+///
+/// Dropping it unregisters the cleanup, e.g. once the resource it reclaims has been
+/// released normally.
+pub struct CleanupRegistration {
+    inner: Weak<Inner>,
+    cleanup_id: u64,
+}
+
+impl Drop for CleanupRegistration {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            inner
+                .cleanups
+                .lock()
+                .unwrap()
+                .cleanups
+                .retain(|cleanup| cleanup.cleanup_id != self.cleanup_id);
+        }
+    }
+}
+
+struct Cleanup {
+    cleanup_id: u64,
+    // The runtime the cleanup was registered from, as the kill switch may be killed
+    // outside of any runtime.
+    runtime_handle: Handle,
+    cleanup_fut: CleanupFuture,
+}
+
+#[derive(Default)]
+struct Cleanups {
+    next_cleanup_id: u64,
+    cleanups: Vec<Cleanup>,
+}
+
 #[derive(Clone, Default)]
 pub struct KillSwitch {
     inner: Arc<Inner>,
@@ -29,14 +101,22 @@ pub struct KillSwitch {
 
 struct Inner {
     alive: AtomicBool,
+    kill_reason_opt: Mutex<Option<Arc<str>>>,
     children: Mutex<Vec<Weak<Inner>>>,
+    soft_kill_hooks: Mutex<Vec<Weak<SoftKillHook>>>,
+    cleanups: Mutex<Cleanups>,
+    killed_notify: Notify,
 }
 
 impl Default for Inner {
     fn default() -> Self {
         Self {
             alive: AtomicBool::new(true),
-            children: Mutex::default(),
+            kill_reason_opt: Mutex::new(None),
+            children: Mutex::new(Vec::new()),
+            soft_kill_hooks: Mutex::new(Vec::new()),
+            cleanups: Mutex::new(Cleanups::default()),
+            killed_notify: Notify::new(),
         }
     }
 }
@@ -62,7 +142,114 @@ impl KillSwitch {
     }
 
     pub fn kill(&self) {
-        self.inner.kill();
+        self.inner.kill(None);
+    }
+
+    /// Kills the kill switch and its children, recording the reason why they were killed.
+    ///
+    /// If the kill switch was already dead, the original reason is kept.
+    pub fn kill_with_reason(&self, reason: impl Into<Arc<str>>) {
+        self.inner.kill(Some(reason.into()));
+    }
+
+    /// Registers a callback invoked when the kill switch, or one of its ancestors, is soft
+    /// killed. Typically, the callback asks an actor to quit gracefully.
+    ///
+    /// The callback stays registered as long as the returned registration is alive.
+    #[must_use]
+    pub fn register_soft_kill_callback(
+        &self,
+        callback: impl Fn() + Send + Sync + 'static,
+    ) -> SoftKillRegistration {
+        let hook = Arc::new(SoftKillHook {
+            callback: Box::new(callback),
+            unregistered: Arc::new(Notify::new()),
+        });
+        let mut lock = self.inner.soft_kill_hooks.lock().unwrap();
+        lock.retain(|weak| weak.strong_count() > 0);
+        lock.push(Arc::downgrade(&hook));
+        SoftKillRegistration { hook }
+    }
+
+    /// Registers a future reclaiming a resource owned outside of a specific actor (e.g.
+    /// deleting a temporary directory or aborting a multipart upload). It is spawned exactly
+    /// once when the kill switch, or one of its ancestors, is killed, or right away if the
+    /// kill switch is already dead.
+    ///
+    /// The cleanup stays registered as long as the returned registration is alive.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside of a tokio runtime, on which the cleanup is spawned.
+    #[must_use]
+    pub fn register_cleanup(
+        &self,
+        cleanup_fut: impl Future<Output = ()> + Send + 'static,
+    ) -> CleanupRegistration {
+        let runtime_handle = Handle::current();
+        let mut cleanups_guard = self.inner.cleanups.lock().unwrap();
+        let cleanup_id = cleanups_guard.next_cleanup_id;
+        cleanups_guard.next_cleanup_id += 1;
+
+        // The state is checked while holding the lock, so that a concurrent kill cannot
+        // miss the cleanup.
+        if self.is_alive() {
+            cleanups_guard.cleanups.push(Cleanup {
+                cleanup_id,
+                runtime_handle,
+                cleanup_fut: Box::pin(cleanup_fut),
+            });
+        } else {
+            runtime_handle.spawn(cleanup_fut);
+        }
+        CleanupRegistration {
+            inner: Arc::downgrade(&self.inner),
+            cleanup_id,
+        }
+    }
+
+    /// Kills the kill switch in two phases.
+    ///
+    /// The soft kill callbacks registered on the kill switch and its children are invoked
+    /// first. The kill switch is then killed as soon as all of their registrations are
+    /// dropped, or once the grace period has elapsed, whichever comes first.
+    pub async fn soft_kill(&self, grace_period: Duration) {
+        if self.is_alive() {
+            let mut pending_hooks = Vec::new();
+            self.inner.soft_kill(&mut pending_hooks);
+            let all_callbacks_unregistered = async {
+                for (hook, unregistered) in pending_hooks {
+                    if hook.strong_count() > 0 {
+                        unregistered.notified().await;
+                    }
+                }
+            };
+            let _ = tokio::time::timeout(grace_period, all_callbacks_unregistered).await;
+        }
+        self.kill();
+    }
+
+    /// Returns the reason why the kill switch was killed, if one was given.
+    pub fn kill_reason(&self) -> Option<Arc<str>> {
+        self.inner.kill_reason_opt.lock().unwrap().clone()
+    }
+
+    /// Returns a future that resolves once the kill switch is killed, or immediately if it
+    /// is already dead.
+    ///
+    /// This makes it possible to `select!` on the kill switch to cancel long awaits
+    /// promptly, instead of polling `is_alive()` in a loop.
+    pub fn notified(&self) -> impl Future<Output = ()> + Send + 'static {
+        let inner = self.inner.clone();
+        async move {
+            // The `Notified` future must be created before checking the state of the kill
+            // switch, so that a concurrent kill cannot be missed.
+            let notified = inner.killed_notify.notified();
+            if !inner.alive.load(Ordering::SeqCst) {
+                return;
+            }
+            notified.await;
+        }
     }
 
     // Creates a child killswitch.
@@ -72,6 +259,7 @@ impl KillSwitch {
         let mut lock = self.inner.children.lock().unwrap();
         let child_inner = Inner {
             alive: AtomicBool::new(self.is_alive()),
+            kill_reason_opt: Mutex::new(self.kill_reason()),
             ..Default::default()
         };
         garbage_collect(&mut lock);
@@ -84,19 +272,64 @@ impl KillSwitch {
 }
 
 impl Inner {
-    pub fn kill(&self) {
+    fn soft_kill(&self, pending_hooks: &mut Vec<(Weak<SoftKillHook>, Arc<Notify>)>) {
+        debug!("kill-switch-soft-killed");
+        let hooks: Vec<Arc<SoftKillHook>> = self
+            .soft_kill_hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        // Callbacks are invoked without holding the lock, as they may register new ones.
+        for hook in hooks {
+            (hook.callback)();
+            pending_hooks.push((Arc::downgrade(&hook), hook.unregistered.clone()));
+        }
+        let children: Vec<Arc<Inner>> = self
+            .children
+            .lock()
+            .unwrap()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect();
+        for child in children {
+            child.soft_kill(pending_hooks);
+        }
+    }
+
+    pub fn kill(&self, kill_reason_opt: Option<Arc<str>>) {
         debug!("kill-switch-activated");
-        self.alive.store(false, Ordering::Relaxed);
+        if kill_reason_opt.is_some() {
+            let mut kill_reason_guard = self.kill_reason_opt.lock().unwrap();
+            if kill_reason_guard.is_none() && self.alive.load(Ordering::Relaxed) {
+                *kill_reason_guard = kill_reason_opt.clone();
+            }
+        }
+        self.alive.store(false, Ordering::SeqCst);
+        self.killed_notify.notify_waiters();
+        let cleanups = std::mem::take(&mut self.cleanups.lock().unwrap().cleanups);
+        if !cleanups.is_empty() {
+            debug!(
+                num_cleanups = cleanups.len(),
+                "kill-switch-cleanups-spawned"
+            );
+        }
+        for cleanup in cleanups {
+            cleanup.runtime_handle.spawn(cleanup.cleanup_fut);
+        }
         let mut lock = self.children.lock().unwrap();
         for weak in lock.drain(..) {
             if let Some(inner) = weak.upgrade() {
-                inner.kill();
+                inner.kill(kill_reason_opt.clone());
             }
         }
     }
 }
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use super::KillSwitch;
 
     #[test]
@@ -124,6 +357,114 @@ mod tests {
         assert!(child_kill_switch2.is_dead());
     }
 
+    #[test]
+    fn test_kill_switch_reason() {
+        let kill_switch = KillSwitch::default();
+        let child_kill_switch = kill_switch.child();
+        assert!(kill_switch.kill_reason().is_none());
+        kill_switch.kill_with_reason("indexer failed");
+        assert_eq!(kill_switch.kill_reason().as_deref(), Some("indexer failed"));
+        assert_eq!(
+            child_kill_switch.kill_reason().as_deref(),
+            Some("indexer failed")
+        );
+        // The original reason is kept.
+        kill_switch.kill_with_reason("uploader failed");
+        assert_eq!(kill_switch.kill_reason().as_deref(), Some("indexer failed"));
+        assert_eq!(
+            kill_switch.child().kill_reason().as_deref(),
+            Some("indexer failed")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_notified() {
+        let kill_switch = KillSwitch::default();
+        let child_kill_switch = kill_switch.child();
+        let notified = kill_switch.notified();
+        let child_notified = child_kill_switch.notified();
+        let mut notified_task = tokio::spawn(notified);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut notified_task)
+                .await
+                .is_err()
+        );
+        kill_switch.kill();
+        notified_task.await.unwrap();
+        child_notified.await;
+        // The future resolves immediately once the kill switch is dead.
+        kill_switch.notified().await;
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_soft_kill() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let kill_switch = KillSwitch::default();
+        let child_kill_switch = kill_switch.child();
+        let num_calls = Arc::new(AtomicUsize::new(0));
+        let soft_killed = Arc::new(tokio::sync::Notify::new());
+        let num_calls_clone = num_calls.clone();
+        let soft_killed_clone = soft_killed.clone();
+        let registration = child_kill_switch.register_soft_kill_callback(move || {
+            num_calls_clone.fetch_add(1, Ordering::SeqCst);
+            soft_killed_clone.notify_one();
+        });
+        let soft_kill_task = tokio::spawn({
+            let kill_switch = kill_switch.clone();
+            async move { kill_switch.soft_kill(Duration::from_secs(10)).await }
+        });
+        soft_killed.notified().await;
+        assert_eq!(num_calls.load(Ordering::SeqCst), 1);
+        // The kill switch stays alive until the callback is unregistered.
+        assert!(child_kill_switch.is_alive());
+        drop(registration);
+        soft_kill_task.await.unwrap();
+        assert!(kill_switch.is_dead());
+        assert!(child_kill_switch.is_dead());
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_soft_kill_grace_period_elapsed() {
+        let kill_switch = KillSwitch::default();
+        let _registration = kill_switch.register_soft_kill_callback(|| {});
+        kill_switch.soft_kill(Duration::from_millis(50)).await;
+        assert!(kill_switch.is_dead());
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_cleanup() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let kill_switch = KillSwitch::default();
+        let child_kill_switch = kill_switch.child();
+        let num_cleanups = Arc::new(AtomicUsize::new(0));
+        let (cleanup_tx, cleanup_rx) = tokio::sync::oneshot::channel();
+        let num_cleanups_clone = num_cleanups.clone();
+        let _registration = child_kill_switch.register_cleanup(async move {
+            num_cleanups_clone.fetch_add(1, Ordering::SeqCst);
+            cleanup_tx.send(()).unwrap();
+        });
+        let num_cleanups_clone = num_cleanups.clone();
+        let cancelled_registration = kill_switch.register_cleanup(async move {
+            num_cleanups_clone.fetch_add(100, Ordering::SeqCst);
+        });
+        drop(cancelled_registration);
+        kill_switch.kill();
+        kill_switch.kill();
+        cleanup_rx.await.unwrap();
+
+        // Registering a cleanup on a dead kill switch spawns it right away.
+        let (cleanup_tx, cleanup_rx) = tokio::sync::oneshot::channel();
+        let _registration = child_kill_switch.register_cleanup(async move {
+            cleanup_tx.send(()).unwrap();
+        });
+        cleanup_rx.await.unwrap();
+        assert_eq!(num_cleanups.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_kill_switch_grandchildren() {
         let kill_switch = KillSwitch::default();
@@ -149,3 +490,20 @@ mod tests {
         assert!(child_kill_switch.is_dead());
     }
 }
+
+#[cfg(loom)]
+mod loom_tests {
+    use super::KillSwitch;
+
+    #[test]
+    fn loom_child_created_during_kill_is_killed() {
+        loom::model(|| {
+            let kill_switch = KillSwitch::default();
+            let kill_switch_clone = kill_switch.clone();
+            let kill_thread = loom::thread::spawn(move || kill_switch_clone.kill());
+            let child_kill_switch = kill_switch.child();
+            kill_thread.join().unwrap();
+            assert!(child_kill_switch.is_dead());
+        });
+    }
+}
@@ -50,7 +50,7 @@ use std::ops::{Range, RangeInclusive};
 use std::str::FromStr;
 
 pub use coolid::new_coolid;
-pub use kill_switch::KillSwitch;
+pub use kill_switch::{CleanupRegistration, KillSwitch, SoftKillRegistration};
 pub use path_hasher::PathHasher;
 pub use progress::{Progress, ProtectedZoneGuard};
 pub use stream_utils::{BoxStream, ServiceStream};
@@ -17,15 +17,26 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
 /// Progress makes it possible to register some progress.
 /// It is used in lieu of healthcheck.
 ///
 /// If no progress is observed until the next heartbeat, the actor will be killed.
 #[derive(Clone)]
-pub struct Progress(Arc<AtomicU32>);
+pub struct Progress(Arc<ProgressInner>);
+
+struct ProgressInner {
+    state: AtomicU32,
+    // Recording progress is on the hot path of the actors: we only read the monotonic clock
+    // there, and convert to a Unix timestamp on read.
+    created_at: Instant,
+    created_at_timestamp_millis: u64,
+    // Milliseconds elapsed between `created_at` and the last recorded progress.
+    last_progress_elapsed_millis: AtomicU64,
+}
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum ProgressState {
@@ -71,27 +82,62 @@ impl From<u32> for ProgressState {
     }
 }
 
+fn now_timestamp_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 impl Default for Progress {
     fn default() -> Progress {
-        Progress(Arc::new(AtomicU32::new(ProgressState::Updated.into())))
+        Progress(Arc::new(ProgressInner {
+            state: AtomicU32::new(ProgressState::Updated.into()),
+            created_at: Instant::now(),
+            created_at_timestamp_millis: now_timestamp_millis(),
+            last_progress_elapsed_millis: AtomicU64::new(0),
+        }))
     }
 }
 
 impl Progress {
     pub fn record_progress(&self) {
         self.0
+            .state
             .fetch_max(ProgressState::Updated.into(), Ordering::Relaxed);
+        let elapsed_millis = self.0.created_at.elapsed().as_millis() as u64;
+        self.0
+            .last_progress_elapsed_millis
+            .fetch_max(elapsed_millis, Ordering::Relaxed);
+    }
+
+    /// Returns the Unix timestamp in milliseconds of the last recorded progress.
+    pub fn last_progress_timestamp_millis(&self) -> u64 {
+        self.0.created_at_timestamp_millis
+            + self.0.last_progress_elapsed_millis.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of protected zone guards currently alive.
+    ///
+    /// Protected zones are re-entrant: the protection only ends when the outermost guard
+    /// is dropped.
+    pub fn protection_depth(&self) -> u32 {
+        match self.0.state.load(Ordering::SeqCst).into() {
+            ProgressState::NoUpdate | ProgressState::Updated => 0,
+            ProgressState::ProtectedZone(level) => level + 1,
+        }
     }
 
     pub fn protect_zone(&self) -> ProtectedZoneGuard {
         loop {
-            let previous_state: ProgressState = self.0.load(Ordering::SeqCst).into();
+            let previous_state: ProgressState = self.0.state.load(Ordering::SeqCst).into();
             let new_state = match previous_state {
                 ProgressState::NoUpdate | ProgressState::Updated => ProgressState::ProtectedZone(0),
                 ProgressState::ProtectedZone(level) => ProgressState::ProtectedZone(level + 1),
             };
             if self
                 .0
+                .state
                 .compare_exchange(
                     previous_state.into(),
                     new_state.into(),
@@ -113,6 +159,7 @@ impl Progress {
     pub fn registered_activity_since_last_call(&self) -> bool {
         let previous_state: ProgressState = self
             .0
+            .state
             .compare_exchange(
                 ProgressState::Updated.into(),
                 ProgressState::NoUpdate.into(),
@@ -125,11 +172,11 @@ impl Progress {
     }
 }
 
-pub struct ProtectedZoneGuard(Arc<AtomicU32>);
+pub struct ProtectedZoneGuard(Arc<ProgressInner>);
 
 impl Drop for ProtectedZoneGuard {
     fn drop(&mut self) {
-        let previous_state: ProgressState = self.0.fetch_sub(1, Ordering::SeqCst).into();
+        let previous_state: ProgressState = self.0.state.fetch_sub(1, Ordering::SeqCst).into();
         assert!(matches!(previous_state, ProgressState::ProtectedZone(_)));
     }
 }
@@ -147,6 +194,16 @@ mod tests {
         assert!(!progress.registered_activity_since_last_call());
     }
 
+    #[test]
+    fn test_progress_last_progress_timestamp() {
+        let progress = Progress::default();
+        let creation_timestamp_millis = progress.last_progress_timestamp_millis();
+        assert!(creation_timestamp_millis > 0);
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        progress.record_progress();
+        assert!(progress.last_progress_timestamp_millis() > creation_timestamp_millis);
+    }
+
     #[test]
     fn test_progress_protect_zone() {
         let progress = Progress::default();
@@ -172,10 +229,13 @@ mod tests {
         let second_protect_guard = progress.protect_zone();
         assert!(progress.registered_activity_since_last_call());
         assert!(progress.registered_activity_since_last_call());
+        assert_eq!(progress.protection_depth(), 2);
         std::mem::drop(first_protect_guard);
+        assert_eq!(progress.protection_depth(), 1);
         assert!(progress.registered_activity_since_last_call());
         assert!(progress.registered_activity_since_last_call());
         std::mem::drop(second_protect_guard);
+        assert_eq!(progress.protection_depth(), 0);
         assert!(progress.registered_activity_since_last_call());
         assert!(!progress.registered_activity_since_last_call());
     }
@@ -43,6 +43,15 @@ pub enum RuntimeType {
     ///
     /// Task are expect to yield within 500 micros.
     NonBlocking,
+
+    /// The latency-critical runtime runs non-blocking actors serving
+    /// latency-sensitive requests (e.g. the ingest API service).
+    ///
+    /// It has its own worker threads so that these actors never wait behind
+    /// throughput-oriented actors (e.g. indexing) for a worker thread.
+    ///
+    /// Task are expect to yield within 500 micros.
+    LatencyCritical,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -51,6 +60,8 @@ pub struct RuntimesConfig {
     pub num_threads_non_blocking: usize,
     /// Number of worker threads allocated to the blocking runtime.
     pub num_threads_blocking: usize,
+    /// Number of worker threads allocated to the latency-critical runtime.
+    pub num_threads_latency_critical: usize,
 }
 
 impl RuntimesConfig {
@@ -59,6 +70,7 @@ impl RuntimesConfig {
         RuntimesConfig {
             num_threads_blocking: 1,
             num_threads_non_blocking: 1,
+            num_threads_latency_critical: 1,
         }
     }
 
@@ -68,9 +80,13 @@ impl RuntimesConfig {
         // On the other hand the blocking actors are cpu intensive. We allocate
         // almost all of the threads to them.
         let num_threads_blocking = (num_cpus - num_threads_non_blocking).max(1);
+        // Latency-critical tasks are expected to be mostly waiting on io. Their threads
+        // come on top of the others, so that they can always get a worker thread.
+        let num_threads_latency_critical = num_threads_non_blocking;
         RuntimesConfig {
             num_threads_non_blocking,
             num_threads_blocking,
+            num_threads_latency_critical,
         }
     }
 }
@@ -106,6 +122,17 @@ fn start_runtimes(config: RuntimesConfig) -> HashMap<RuntimeType, Runtime> {
         .build()
         .unwrap();
     runtimes.insert(RuntimeType::NonBlocking, non_blocking_runtime);
+    let latency_critical_runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.num_threads_latency_critical)
+        .thread_name_fn(|| {
+            static ATOMIC_ID: AtomicUsize = AtomicUsize::new(0);
+            let id = ATOMIC_ID.fetch_add(1, Ordering::AcqRel);
+            format!("latency-critical-{id}")
+        })
+        .enable_all()
+        .build()
+        .unwrap();
+    runtimes.insert(RuntimeType::LatencyCritical, latency_critical_runtime);
     runtimes
 }
 
@@ -146,11 +173,24 @@ mod tests {
         assert!(runtime_default.num_threads_non_blocking <= 2);
     }
 
+    #[test]
+    fn test_latency_critical_runtime_is_isolated() {
+        let latency_critical_handle = RuntimeType::LatencyCritical.get_runtime_handle();
+        let thread_name = latency_critical_handle
+            .block_on(async {
+                tokio::spawn(async { std::thread::current().name().map(str::to_string) }).await
+            })
+            .unwrap()
+            .unwrap();
+        assert!(thread_name.starts_with("latency-critical-"));
+    }
+
     #[test]
     fn test_runtimes_with_given_num_cpus_10() {
         let runtime = RuntimesConfig::with_num_cpus(10);
         assert_eq!(runtime.num_threads_blocking, 8);
         assert_eq!(runtime.num_threads_non_blocking, 2);
+        assert_eq!(runtime.num_threads_latency_critical, 2);
     }
 
     #[test]
@@ -158,5 +198,6 @@ mod tests {
         let runtime = RuntimesConfig::with_num_cpus(3);
         assert_eq!(runtime.num_threads_blocking, 2);
         assert_eq!(runtime.num_threads_non_blocking, 1);
+        assert_eq!(runtime.num_threads_latency_critical, 1);
     }
 }
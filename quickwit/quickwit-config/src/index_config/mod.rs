@@ -75,6 +75,7 @@ pub struct DocMapping {
         fields = (
             #[serde(default)]
             mode: ModeType,
+            #[serde(default, deserialize_with = "Mode::deserialize_dynamic_mapping")]
             #[serde(skip_serializing_if = "Option::is_none")]
             dynamic_mapping: Option<QuickwitJsonOptions>
         ),
@@ -154,6 +155,17 @@ pub struct IndexingSettings {
     /// `split_num_docs_target` are considered mature and never merged.
     #[serde(default = "IndexingSettings::default_split_num_docs_target")]
     pub split_num_docs_target: usize,
+    /// Number of documents after which the indexer commits the split being built. When not set,
+    /// the indexer commits once the split reaches `split_num_docs_target` documents.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_num_docs: Option<usize>,
+    /// Estimated memory usage after which the indexer commits the split being built. When not
+    /// set, the indexer commits once its memory usage reaches `resources.heap_size`.
+    #[schema(value_type = String)]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub commit_memory_usage: Option<Byte>,
     #[serde(default)]
     pub merge_policy: MergePolicyConfig,
     #[serde(default)]
@@ -165,6 +177,38 @@ impl IndexingSettings {
         Duration::from_secs(self.commit_timeout_secs as u64)
     }
 
+    /// Returns the number of documents after which the indexer commits the split being built.
+    pub fn commit_num_docs_threshold(&self) -> usize {
+        self.commit_num_docs
+            .map_or(self.split_num_docs_target, |commit_num_docs| {
+                commit_num_docs.min(self.split_num_docs_target)
+            })
+    }
+
+    /// Returns the estimated memory usage after which the indexer commits the split being built.
+    pub fn commit_memory_usage_threshold(&self) -> Byte {
+        self.commit_memory_usage
+            .map_or(self.resources.heap_size, |commit_memory_usage| {
+                commit_memory_usage.min(self.resources.heap_size)
+            })
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.commit_num_docs == Some(0) {
+            anyhow::bail!("Indexing setting `commit_num_docs` must be strictly positive.");
+        }
+        if self
+            .commit_memory_usage
+            .map_or(false, |commit_memory_usage| {
+                commit_memory_usage.get_bytes() == 0
+            })
+        {
+            anyhow::bail!("Indexing setting `commit_memory_usage` must be strictly positive.");
+        }
+        self.merge_policy.validate()?;
+        Ok(())
+    }
+
     fn default_commit_timeout_secs() -> usize {
         60
     }
@@ -197,6 +241,8 @@ impl Default for IndexingSettings {
             docstore_blocksize: Self::default_docstore_blocksize(),
             docstore_compression_level: Self::default_docstore_compression_level(),
             split_num_docs_target: Self::default_split_num_docs_target(),
+            commit_num_docs: None,
+            commit_memory_usage: None,
             merge_policy: MergePolicyConfig::default(),
             resources: IndexingResources::default(),
         }
@@ -754,6 +800,63 @@ mod tests {
             .contains("Failed to parse human-readable duration `x`"));
     }
 
+    #[test]
+    fn test_index_config_commit_thresholds() {
+        let config_yaml = r#"
+            version: 0.6
+            index_id: hdfs-logs
+            doc_mapping: {}
+            indexing_settings:
+              split_num_docs_target: 1000
+              commit_num_docs: 100
+              commit_memory_usage: 10MB
+              resources:
+                heap_size: 1GB
+        "#;
+        let index_config = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::from_well_formed("s3://my-index"),
+        )
+        .unwrap();
+        let indexing_settings = &index_config.indexing_settings;
+        assert_eq!(indexing_settings.commit_num_docs, Some(100));
+        assert_eq!(indexing_settings.commit_num_docs_threshold(), 100);
+        assert_eq!(
+            indexing_settings.commit_memory_usage_threshold(),
+            Byte::from_bytes(10_000_000)
+        );
+
+        let indexing_settings = IndexingSettings {
+            split_num_docs_target: 1000,
+            commit_num_docs: Some(10_000),
+            ..Default::default()
+        };
+        assert_eq!(indexing_settings.commit_num_docs_threshold(), 1000);
+        assert_eq!(
+            indexing_settings.commit_memory_usage_threshold(),
+            indexing_settings.resources.heap_size
+        );
+
+        let config_yaml = r#"
+            version: 0.6
+            index_id: hdfs-logs
+            doc_mapping: {}
+            indexing_settings:
+              commit_num_docs: 0
+        "#;
+        let error = load_index_config_from_user_config(
+            ConfigFormat::Yaml,
+            config_yaml.as_bytes(),
+            &Uri::from_well_formed("s3://my-index"),
+        )
+        .unwrap_err();
+        assert!(error
+            .root_cause()
+            .to_string()
+            .contains("`commit_num_docs` must be strictly positive"));
+    }
+
     #[test]
     fn test_retention_policy_serialization() {
         let retention_policy = RetentionPolicy {
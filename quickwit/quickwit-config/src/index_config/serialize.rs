@@ -103,7 +103,7 @@ impl IndexConfigForSerialization {
         // TODO see if we should store the byproducton the IndexConfig.
         build_doc_mapper(&self.doc_mapping, &self.search_settings)?;
 
-        self.indexing_settings.merge_policy.validate()?;
+        self.indexing_settings.validate()?;
 
         Ok(IndexConfig {
             index_id: self.index_id,
@@ -165,7 +165,9 @@ impl From<IndexConfig> for IndexConfigV0_6 {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::merge_policy_config::{MergePolicyConfig, StableLogMergePolicyConfig};
+    use crate::merge_policy_config::{
+        ConstWriteAmplificationMergePolicyConfig, MergePolicyConfig, StableLogMergePolicyConfig,
+    };
 
     fn minimal_index_config_for_serialization() -> IndexConfigForSerialization {
         serde_yaml::from_str(
@@ -209,6 +211,41 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_validate_merge_policy_merge_factor_and_max_merge_ops() {
+        let mut invalid_index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        invalid_index_config.indexing_settings.merge_policy =
+            MergePolicyConfig::StableLog(StableLogMergePolicyConfig {
+                merge_factor: 1,
+                ..Default::default()
+            });
+        let validation_err = invalid_index_config
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            validation_err,
+            "Index config merge policy `merge_factor` must be at least 2."
+        );
+
+        let mut invalid_index_config: IndexConfigForSerialization =
+            minimal_index_config_for_serialization();
+        invalid_index_config.indexing_settings.merge_policy =
+            MergePolicyConfig::ConstWriteAmplification(ConstWriteAmplificationMergePolicyConfig {
+                max_merge_ops: 0,
+                ..Default::default()
+            });
+        let validation_err = invalid_index_config
+            .validate_and_build(None)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            validation_err,
+            "Index config merge policy `max_merge_ops` must be strictly positive."
+        );
+    }
+
     #[test]
     fn test_validate_retention_policy() {
         // Not yet invalid, but we modify it right after this.
@@ -152,10 +152,19 @@ impl MergePolicyConfig {
                 return Ok(());
             }
             MergePolicyConfig::ConstWriteAmplification(config) => {
+                if config.max_merge_ops == 0 {
+                    anyhow::bail!(
+                        "Index config merge policy `max_merge_ops` must be strictly positive."
+                    );
+                }
                 (config.merge_factor, config.max_merge_factor)
             }
             MergePolicyConfig::StableLog(config) => (config.merge_factor, config.max_merge_factor),
         };
+        // Merging a single split with itself would only rewrite it over and over again.
+        if merge_factor < 2 {
+            anyhow::bail!("Index config merge policy `merge_factor` must be at least 2.");
+        }
         if max_merge_factor < merge_factor {
             anyhow::bail!(
                 "Index config merge policy `max_merge_factor` must be superior or equal to \
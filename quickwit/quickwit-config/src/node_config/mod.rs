@@ -49,12 +49,24 @@ pub struct IndexerConfig {
     pub split_store_max_num_splits: usize,
     #[serde(default = "IndexerConfig::default_max_concurrent_split_uploads")]
     pub max_concurrent_split_uploads: usize,
+    /// Maximum cumulative size of the documents in flight between the sources, the doc
+    /// processors, and the indexers of the node. Sources wait while it is exceeded.
+    #[serde(default = "IndexerConfig::default_max_in_flight_docs_memory")]
+    pub max_in_flight_docs_memory: Byte,
     /// Enables the OpenTelemetry exporter endpoint to ingest logs and traces via the OpenTelemetry
     /// Protocol (OTLP).
     #[serde(default = "IndexerConfig::default_enable_otlp_endpoint")]
     pub enable_otlp_endpoint: bool,
     #[serde(default = "IndexerConfig::default_enable_cooperative_indexing")]
     pub enable_cooperative_indexing: bool,
+    /// Only one message out of `message_tracing_sample_one_in` sent or received by the actors
+    /// is logged at the debug level. 0 disables these logs.
+    #[serde(default = "IndexerConfig::default_message_tracing_sample_one_in")]
+    pub message_tracing_sample_one_in: u64,
+    /// Renderings of the messages logged at the debug level are truncated to this number of
+    /// bytes.
+    #[serde(default = "IndexerConfig::default_message_tracing_max_debug_len")]
+    pub message_tracing_max_debug_len: usize,
 }
 
 impl IndexerConfig {
@@ -77,6 +89,18 @@ impl IndexerConfig {
         12
     }
 
+    fn default_message_tracing_sample_one_in() -> u64 {
+        1
+    }
+
+    fn default_message_tracing_max_debug_len() -> usize {
+        1_024
+    }
+
+    pub fn default_max_in_flight_docs_memory() -> Byte {
+        Byte::from_bytes(2_000_000_000) // 2G
+    }
+
     pub fn default_split_store_max_num_bytes() -> Byte {
         Byte::from_bytes(100_000_000_000) // 100G
     }
@@ -93,6 +117,9 @@ impl IndexerConfig {
             split_store_max_num_bytes: Byte::from_bytes(1_000_000),
             split_store_max_num_splits: 3,
             max_concurrent_split_uploads: 4,
+            max_in_flight_docs_memory: Byte::from_bytes(10_000_000),
+            message_tracing_sample_one_in: 1,
+            message_tracing_max_debug_len: 1_024,
         };
         Ok(indexer_config)
     }
@@ -106,6 +133,9 @@ impl Default for IndexerConfig {
             split_store_max_num_bytes: Self::default_split_store_max_num_bytes(),
             split_store_max_num_splits: Self::default_split_store_max_num_splits(),
             max_concurrent_split_uploads: Self::default_max_concurrent_split_uploads(),
+            max_in_flight_docs_memory: Self::default_max_in_flight_docs_memory(),
+            message_tracing_sample_one_in: Self::default_message_tracing_sample_one_in(),
+            message_tracing_max_debug_len: Self::default_message_tracing_max_debug_len(),
         }
     }
 }
@@ -141,6 +171,18 @@ impl Default for SearcherConfig {
 pub struct IngestApiConfig {
     pub max_queue_memory_usage: Byte,
     pub max_queue_disk_usage: Byte,
+    /// The documents sent to the REST ingest endpoint are batched in memory until they reach
+    /// this size.
+    pub batch_num_bytes_threshold: Byte,
+    /// Maximum time documents sent to the REST ingest endpoint wait in memory for their batch to
+    /// fill up. `0` disables batching.
+    pub batch_timeout_millis: u64,
+}
+
+impl IngestApiConfig {
+    pub fn batch_timeout(&self) -> Duration {
+        Duration::from_millis(self.batch_timeout_millis)
+    }
 }
 
 impl Default for IngestApiConfig {
@@ -148,6 +190,8 @@ impl Default for IngestApiConfig {
         Self {
             max_queue_memory_usage: Byte::from_bytes(2 * 1024 * 1024 * 1024), /* 2 GiB // TODO maybe we want more? */
             max_queue_disk_usage: Byte::from_bytes(4 * 1024 * 1024 * 1024), /* 4 GiB // TODO maybe we want more? */
+            batch_num_bytes_threshold: Byte::from_bytes(5 * 1024 * 1024),   // 5 MiB
+            batch_timeout_millis: 0,
         }
     }
 }
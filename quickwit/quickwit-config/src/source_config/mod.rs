@@ -85,6 +85,7 @@ impl SourceConfig {
     pub fn source_type(&self) -> &str {
         match self.source_params {
             SourceParams::File(_) => "file",
+            SourceParams::FileTail(_) => "file-tail",
             SourceParams::Kafka(_) => "kafka",
             SourceParams::Kinesis(_) => "kinesis",
             SourceParams::Vec(_) => "vec",
@@ -99,6 +100,7 @@ impl SourceConfig {
     pub fn params(&self) -> JsonValue {
         match &self.source_params {
             SourceParams::File(params) => serde_json::to_value(params),
+            SourceParams::FileTail(params) => serde_json::to_value(params),
             SourceParams::Kafka(params) => serde_json::to_value(params),
             SourceParams::Kinesis(params) => serde_json::to_value(params),
             SourceParams::Vec(params) => serde_json::to_value(params),
@@ -162,6 +164,7 @@ impl TestableForRegression for SourceConfig {
                 client_log_level: None,
                 client_params: serde_json::json!({}),
                 enable_backfill_mode: false,
+                partitions: Vec::new(),
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
@@ -202,6 +205,8 @@ impl FromStr for SourceInputFormat {
 pub enum SourceParams {
     #[serde(rename = "file")]
     File(FileSourceParams),
+    #[serde(rename = "file-tail")]
+    FileTail(FileTailSourceParams),
     #[serde(rename = "kafka")]
     Kafka(KafkaSourceParams),
     #[serde(rename = "kinesis")]
@@ -267,6 +272,14 @@ impl FileSourceParams {
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct FileTailSourceParams {
+    /// Paths or glob patterns of the files to tail. Directories are expanded to the files they
+    /// contain.
+    pub paths: Vec<String>,
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct KafkaSourceParams {
@@ -285,6 +298,11 @@ pub struct KafkaSourceParams {
     #[serde(default)]
     #[serde(skip_serializing_if = "is_false")]
     pub enable_backfill_mode: bool,
+    /// Partitions of the topic that the source consumes. When empty, the partitions are
+    /// assigned to the source by the consumer group.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub partitions: Vec<i32>,
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
@@ -532,6 +550,7 @@ mod tests {
                 client_log_level: None,
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                partitions: Vec::new(),
             }),
             transform_config: Some(TransformConfig {
                 vrl_script: ".message = downcase(string!(.message))".to_string(),
@@ -543,6 +562,24 @@ mod tests {
         assert_eq!(source_config.desired_num_pipelines.get(), 2);
     }
 
+    #[test]
+    fn test_file_tail_source_params_deserialization() {
+        let yaml = r#"
+                paths:
+                    - /var/log/app/*.log
+                    - /var/log/nginx
+            "#;
+        assert_eq!(
+            serde_yaml::from_str::<FileTailSourceParams>(yaml).unwrap(),
+            FileTailSourceParams {
+                paths: vec![
+                    "/var/log/app/*.log".to_string(),
+                    "/var/log/nginx".to_string()
+                ],
+            }
+        );
+    }
+
     #[test]
     fn test_kafka_source_params_serialization() {
         {
@@ -551,6 +588,7 @@ mod tests {
                 client_log_level: None,
                 client_params: json!(null),
                 enable_backfill_mode: false,
+                partitions: Vec::new(),
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -565,6 +603,22 @@ mod tests {
                 client_log_level: Some("info".to_string()),
                 client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                 enable_backfill_mode: false,
+                partitions: Vec::new(),
+            };
+            let params_yaml = serde_yaml::to_string(&params).unwrap();
+
+            assert_eq!(
+                serde_yaml::from_str::<KafkaSourceParams>(&params_yaml).unwrap(),
+                params,
+            )
+        }
+        {
+            let params = KafkaSourceParams {
+                topic: "my-topic".to_string(),
+                client_log_level: None,
+                client_params: json!(null),
+                enable_backfill_mode: false,
+                partitions: vec![0, 2],
             };
             let params_yaml = serde_yaml::to_string(&params).unwrap();
 
@@ -588,6 +642,7 @@ mod tests {
                     client_log_level: None,
                     client_params: json!(null),
                     enable_backfill_mode: false,
+                    partitions: Vec::new(),
                 }
             );
         }
@@ -606,6 +661,23 @@ mod tests {
                     client_log_level: Some("info".to_string()),
                     client_params: json! {{"bootstrap.servers": "localhost:9092"}},
                     enable_backfill_mode: true,
+                    partitions: Vec::new(),
+                }
+            );
+        }
+        {
+            let yaml = r#"
+                    topic: my-topic
+                    partitions: [0, 2]
+                "#;
+            assert_eq!(
+                serde_yaml::from_str::<KafkaSourceParams>(yaml).unwrap(),
+                KafkaSourceParams {
+                    topic: "my-topic".to_string(),
+                    client_log_level: None,
+                    client_params: json!(null),
+                    enable_backfill_mode: false,
+                    partitions: vec![0, 2],
                 }
             );
         }
@@ -705,6 +777,75 @@ mod tests {
                 .unwrap_err();
             assert!(error.to_string().contains("supports multiple pipelines"));
         }
+        {
+            let content = r#"
+            {
+                "version": "0.6",
+                "source_id": "hdfs-logs-kafka-source",
+                "desired_num_pipelines": 2,
+                "max_num_pipelines_per_indexer": 1,
+                "source_type": "kafka",
+                "params": {
+                    "topic": "cloudera-cluster-logs",
+                    "partitions": [0, 1]
+                }
+            }
+            "#;
+            let error = load_source_config_from_user_config(ConfigFormat::Json, content.as_bytes())
+                .unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("cannot run on multiple pipelines"));
+        }
+        {
+            let content = r#"
+            {
+                "version": "0.6",
+                "source_id": "hdfs-logs-kafka-source",
+                "source_type": "kafka",
+                "params": {
+                    "topic": "cloudera-cluster-logs",
+                    "partitions": [0, 1, 0]
+                }
+            }
+            "#;
+            let error = load_source_config_from_user_config(ConfigFormat::Json, content.as_bytes())
+                .unwrap_err();
+            assert!(error.to_string().contains("partition `0` more than once"));
+        }
+        {
+            let content = r#"
+            {
+                "version": "0.6",
+                "source_id": "hdfs-logs-file-tail-source",
+                "source_type": "file-tail",
+                "params": {
+                    "paths": []
+                }
+            }
+            "#;
+            let error = load_source_config_from_user_config(ConfigFormat::Json, content.as_bytes())
+                .unwrap_err();
+            assert!(error.to_string().contains("must contain at least one path"));
+        }
+        {
+            let content = r#"
+            {
+                "version": "0.6",
+                "source_id": "hdfs-logs-file-tail-source",
+                "desired_num_pipelines": 2,
+                "source_type": "file-tail",
+                "params": {
+                    "paths": ["/var/log/app/*.log"]
+                }
+            }
+            "#;
+            let error = load_source_config_from_user_config(ConfigFormat::Json, content.as_bytes())
+                .unwrap_err();
+            assert!(error
+                .to_string()
+                .contains("cannot run on multiple pipelines"));
+        }
     }
 
     #[tokio::test]
@@ -17,6 +17,7 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
+use std::collections::HashSet;
 use std::num::NonZeroUsize;
 
 use anyhow::bail;
@@ -91,7 +92,34 @@ impl SourceConfigForSerialization {
                     )
                 }
             }
-            SourceParams::Kafka(_) | SourceParams::Kinesis(_) | SourceParams::Pulsar(_) => {
+            SourceParams::Kafka(kafka_params) => {
+                let mut partitions = HashSet::with_capacity(kafka_params.partitions.len());
+
+                for &partition in &kafka_params.partitions {
+                    if !partitions.insert(partition) {
+                        bail!(
+                            "Source `{}` lists partition `{partition}` more than once.",
+                            self.source_id
+                        )
+                    }
+                }
+                if !partitions.is_empty() && desired_num_pipelines.get() > 1 {
+                    bail!(
+                        "Source `{}` consumes an explicit list of partitions and cannot run on \
+                         multiple pipelines.",
+                        self.source_id
+                    )
+                }
+            }
+            SourceParams::FileTail(file_tail_params) => {
+                if file_tail_params.paths.is_empty() {
+                    bail!(
+                        "Source `{}` of type `file-tail` must contain at least one path.",
+                        self.source_id
+                    )
+                }
+            }
+            SourceParams::Kinesis(_) | SourceParams::Pulsar(_) => {
                 // TODO consider any validation opportunity
             }
             SourceParams::Vec(_)
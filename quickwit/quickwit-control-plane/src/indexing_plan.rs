@@ -343,6 +343,7 @@ mod tests {
                 "bootstrap.servers": "localhost:9092",
             }),
             enable_backfill_mode: true,
+            partitions: Vec::new(),
         })
     }
 
@@ -560,6 +560,7 @@ mod tests {
                     "bootstrap.servers": "localhost:9092",
                 }),
                 enable_backfill_mode: true,
+                partitions: Vec::new(),
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
@@ -150,7 +150,12 @@ pub enum DateTimeInputFormat {
     #[default]
     Rfc3339,
     Strptime(StrptimeParser),
+    /// Unix timestamp whose precision is inferred from the value range.
     Timestamp,
+    TimestampSecs,
+    TimestampMillis,
+    TimestampMicros,
+    TimestampNanos,
 }
 
 impl DateTimeInputFormat {
@@ -161,6 +166,22 @@ impl DateTimeInputFormat {
             DateTimeInputFormat::Rfc3339 => "rfc3339",
             DateTimeInputFormat::Strptime(parser) => parser.borrow_strptime_format(),
             DateTimeInputFormat::Timestamp => "unix_timestamp",
+            DateTimeInputFormat::TimestampSecs => "unix_timestamp_secs",
+            DateTimeInputFormat::TimestampMillis => "unix_timestamp_millis",
+            DateTimeInputFormat::TimestampMicros => "unix_timestamp_micros",
+            DateTimeInputFormat::TimestampNanos => "unix_timestamp_nanos",
+        }
+    }
+
+    /// Returns the number of nanoseconds in one unit of the timestamp format, or `None` if the
+    /// format is not a Unix timestamp with an explicit precision.
+    pub(crate) fn timestamp_unit_nanos(&self) -> Option<i64> {
+        match self {
+            DateTimeInputFormat::TimestampSecs => Some(1_000_000_000),
+            DateTimeInputFormat::TimestampMillis => Some(1_000_000),
+            DateTimeInputFormat::TimestampMicros => Some(1_000),
+            DateTimeInputFormat::TimestampNanos => Some(1),
+            _ => None,
         }
     }
 }
@@ -180,6 +201,10 @@ impl FromStr for DateTimeInputFormat {
             "rfc2822" => DateTimeInputFormat::Rfc2822,
             "rfc3339" => DateTimeInputFormat::Rfc3339,
             "unix_timestamp" => DateTimeInputFormat::Timestamp,
+            "unix_timestamp_secs" => DateTimeInputFormat::TimestampSecs,
+            "unix_timestamp_millis" => DateTimeInputFormat::TimestampMillis,
+            "unix_timestamp_micros" => DateTimeInputFormat::TimestampMicros,
+            "unix_timestamp_nanos" => DateTimeInputFormat::TimestampNanos,
             _ => {
                 if !is_strftime_formatting(date_time_format_str) {
                     return Err(format!(
@@ -323,11 +348,23 @@ mod tests {
             DateTimeInputFormat::Rfc2822,
             DateTimeInputFormat::Rfc3339,
             DateTimeInputFormat::Timestamp,
+            DateTimeInputFormat::TimestampSecs,
+            DateTimeInputFormat::TimestampMillis,
+            DateTimeInputFormat::TimestampMicros,
+            DateTimeInputFormat::TimestampNanos,
         ])
         .unwrap();
 
-        let expected_date_time_formats =
-            serde_json::json!(["iso8601", "rfc2822", "rfc3339", "unix_timestamp",]);
+        let expected_date_time_formats = serde_json::json!([
+            "iso8601",
+            "rfc2822",
+            "rfc3339",
+            "unix_timestamp",
+            "unix_timestamp_secs",
+            "unix_timestamp_millis",
+            "unix_timestamp_micros",
+            "unix_timestamp_nanos",
+        ]);
         assert_eq!(date_time_formats_json, expected_date_time_formats);
     }
 
@@ -338,7 +375,11 @@ mod tests {
                 "iso8601",
                 "rfc2822",
                 "rfc3339",
-                "unix_timestamp"
+                "unix_timestamp",
+                "unix_timestamp_secs",
+                "unix_timestamp_millis",
+                "unix_timestamp_micros",
+                "unix_timestamp_nanos"
             ]
             "#;
         let date_time_formats: Vec<DateTimeInputFormat> =
@@ -348,6 +389,10 @@ mod tests {
             DateTimeInputFormat::Rfc2822,
             DateTimeInputFormat::Rfc3339,
             DateTimeInputFormat::Timestamp,
+            DateTimeInputFormat::TimestampSecs,
+            DateTimeInputFormat::TimestampMillis,
+            DateTimeInputFormat::TimestampMicros,
+            DateTimeInputFormat::TimestampNanos,
         ];
         assert_eq!(date_time_formats, &expected_date_time_formats);
     }
@@ -52,6 +52,12 @@ pub fn parse_date_time_str(
                 .map(TantivyDateTime::from_utc)
                 .ok(),
             DateTimeInputFormat::Timestamp => parse_timestamp_str(date_time_str),
+            DateTimeInputFormat::TimestampSecs
+            | DateTimeInputFormat::TimestampMillis
+            | DateTimeInputFormat::TimestampMicros
+            | DateTimeInputFormat::TimestampNanos => date_time_format
+                .timestamp_unit_nanos()
+                .and_then(|unit_nanos| parse_timestamp_str_with_unit(date_time_str, unit_nanos)),
         };
         if let Some(date_time) = date_time_opt {
             return Ok(date_time);
@@ -66,39 +72,89 @@ pub fn parse_date_time_str(
     ))
 }
 
+/// Parses a float timestamp using the first Unix timestamp format of `date_time_formats`.
+/// With the `unix_timestamp` format, the value is interpreted in seconds.
 pub fn parse_timestamp_float(
     timestamp: f64,
     date_time_formats: &[DateTimeInputFormat],
 ) -> Result<TantivyDateTime, String> {
-    if !date_time_formats.contains(&DateTimeInputFormat::Timestamp) {
-        return Err(format!(
-            "Failed to parse datetime `{timestamp}` using the following formats: `{}`.",
-            date_time_formats
-                .iter()
-                .map(|date_time_format| date_time_format.as_str())
-                .join("`, `")
-        ));
+    for date_time_format in date_time_formats {
+        if *date_time_format == DateTimeInputFormat::Timestamp {
+            let duration_since_epoch = Duration::try_from_secs_f64(timestamp)
+                .map_err(|error| format!("Failed to parse datetime `{timestamp}`: {error}"))?;
+            let timestamp_nanos = duration_since_epoch.as_nanos() as i64;
+            return Ok(TantivyDateTime::from_timestamp_nanos(timestamp_nanos));
+        }
+        if let Some(unit_nanos) = date_time_format.timestamp_unit_nanos() {
+            return parse_timestamp_float_with_unit(timestamp, unit_nanos);
+        }
     }
-    let duration_since_epoch = Duration::try_from_secs_f64(timestamp)
-        .map_err(|error| format!("Failed to parse datetime `{timestamp}`: {error}"))?;
-    let timestamp_nanos = duration_since_epoch.as_nanos() as i64;
-    Ok(TantivyDateTime::from_timestamp_nanos(timestamp_nanos))
+    Err(format!(
+        "Failed to parse datetime `{timestamp}` using the following formats: `{}`.",
+        date_time_formats
+            .iter()
+            .map(|date_time_format| date_time_format.as_str())
+            .join("`, `")
+    ))
 }
 
+/// Parses an integer timestamp using the first Unix timestamp format of `date_time_formats`.
+/// With the `unix_timestamp` format, the precision is inferred from the value range.
 pub fn parse_timestamp_int(
     timestamp: i64,
     date_time_formats: &[DateTimeInputFormat],
 ) -> Result<TantivyDateTime, String> {
-    if !date_time_formats.contains(&DateTimeInputFormat::Timestamp) {
+    for date_time_format in date_time_formats {
+        if *date_time_format == DateTimeInputFormat::Timestamp {
+            return parse_timestamp(timestamp);
+        }
+        if let Some(unit_nanos) = date_time_format.timestamp_unit_nanos() {
+            return parse_timestamp_int_with_unit(timestamp, unit_nanos);
+        }
+    }
+    Err(format!(
+        "Failed to parse datetime `{timestamp}` using the following formats: `{}`.",
+        date_time_formats
+            .iter()
+            .map(|date_time_format| date_time_format.as_str())
+            .join("`, `")
+    ))
+}
+
+fn parse_timestamp_float_with_unit(
+    timestamp: f64,
+    unit_nanos: i64,
+) -> Result<TantivyDateTime, String> {
+    let timestamp_nanos = timestamp * unit_nanos as f64;
+    if !timestamp_nanos.is_finite()
+        || timestamp_nanos < i64::MIN as f64
+        || timestamp_nanos > i64::MAX as f64
+    {
         return Err(format!(
-            "Failed to parse datetime `{timestamp}` using the following formats: `{}`.",
-            date_time_formats
-                .iter()
-                .map(|date_time_format| date_time_format.as_str())
-                .join("`, `")
+            "Failed to parse datetime `{timestamp}`: value is out of range."
         ));
     }
-    parse_timestamp(timestamp)
+    Ok(TantivyDateTime::from_timestamp_nanos(
+        timestamp_nanos as i64,
+    ))
+}
+
+fn parse_timestamp_int_with_unit(
+    timestamp: i64,
+    unit_nanos: i64,
+) -> Result<TantivyDateTime, String> {
+    timestamp
+        .checked_mul(unit_nanos)
+        .map(TantivyDateTime::from_timestamp_nanos)
+        .ok_or_else(|| format!("Failed to parse datetime `{timestamp}`: value is out of range."))
+}
+
+fn parse_timestamp_str_with_unit(timestamp_str: &str, unit_nanos: i64) -> Option<TantivyDateTime> {
+    if let Ok(timestamp) = timestamp_str.parse::<i64>() {
+        return parse_timestamp_int_with_unit(timestamp, unit_nanos).ok();
+    }
+    let timestamp = timestamp_str.parse::<f64>().ok()?;
+    parse_timestamp_float_with_unit(timestamp, unit_nanos).ok()
 }
 
 pub fn parse_timestamp_str(timestamp_str: &str) -> Option<TantivyDateTime> {
@@ -387,6 +443,57 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_timestamp_with_explicit_precision() {
+        let date_time = parse_timestamp_int(1_000, &[DateTimeInputFormat::TimestampSecs]).unwrap();
+        assert_eq!(date_time.into_timestamp_secs(), 1_000);
+
+        let date_time =
+            parse_timestamp_int(1_668_730_394, &[DateTimeInputFormat::TimestampMillis]).unwrap();
+        assert_eq!(date_time.into_timestamp_millis(), 1_668_730_394);
+
+        let date_time =
+            parse_timestamp_int(-1_000, &[DateTimeInputFormat::TimestampMicros]).unwrap();
+        assert_eq!(date_time.into_timestamp_micros(), -1_000);
+
+        let date_time = parse_timestamp_int(
+            1_668_730_394_917_000_001,
+            &[
+                DateTimeInputFormat::Rfc3339,
+                DateTimeInputFormat::TimestampNanos,
+                DateTimeInputFormat::Timestamp,
+            ],
+        )
+        .unwrap();
+        assert_eq!(date_time.into_timestamp_nanos(), 1_668_730_394_917_000_001);
+
+        let date_time =
+            parse_timestamp_float(1_668_730_394.5, &[DateTimeInputFormat::TimestampMillis])
+                .unwrap();
+        assert_eq!(date_time.into_timestamp_micros(), 1_668_730_394_500);
+
+        let error =
+            parse_timestamp_int(i64::MAX, &[DateTimeInputFormat::TimestampSecs]).unwrap_err();
+        assert_eq!(
+            error,
+            format!(
+                "Failed to parse datetime `{}`: value is out of range.",
+                i64::MAX
+            )
+        );
+        parse_timestamp_float(f64::INFINITY, &[DateTimeInputFormat::TimestampSecs]).unwrap_err();
+
+        let date_time =
+            parse_date_time_str("1668730394917", &[DateTimeInputFormat::TimestampMillis]).unwrap();
+        assert_eq!(date_time.into_timestamp_millis(), 1_668_730_394_917);
+
+        let date_time =
+            parse_date_time_str("1668730394.5", &[DateTimeInputFormat::TimestampSecs]).unwrap();
+        assert_eq!(date_time.into_timestamp_millis(), 1_668_730_394_500);
+
+        parse_date_time_str("foo", &[DateTimeInputFormat::TimestampSecs]).unwrap_err();
+    }
+
     #[test]
     fn test_parse_timestamp_str() {
         let date_time = parse_timestamp_str("123456789").unwrap();
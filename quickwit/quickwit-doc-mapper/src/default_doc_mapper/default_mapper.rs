@@ -211,6 +211,27 @@ impl TryFrom<DefaultDocMapperBuilder> for DefaultDocMapper {
                     default_search_field_name
                 )
             }
+            // Object fields are expanded into their indexed sub-fields.
+            if let Some(object_node) = field_mappings.find_object_node(default_search_field_name) {
+                let indexed_field_names: Vec<String> = object_node
+                    .leaf_fields()
+                    .into_iter()
+                    .filter(|field| schema.get_field_entry(*field).is_indexed())
+                    .map(|field| schema.get_field_name(field).to_string())
+                    .collect();
+                if indexed_field_names.is_empty() {
+                    bail!(
+                        "Default search field `{default_search_field_name}` does not contain any \
+                         indexed field."
+                    );
+                }
+                for indexed_field_name in indexed_field_names {
+                    if !default_search_field_names.contains(&indexed_field_name) {
+                        default_search_field_names.push(indexed_field_name);
+                    }
+                }
+                continue;
+            }
             let dynamic_field = schema.get_field(DYNAMIC_FIELD_NAME).ok();
             let (default_search_field, _json_path) = schema
                 .find_field_with_default(default_search_field_name, dynamic_field)
@@ -1525,6 +1546,179 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_array_of_objects() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "attributes",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "key",
+                            "type": "array<text>"
+                        },
+                        {
+                            "name": "value",
+                            "type": "array<u64>"
+                        }
+                    ]
+                }
+            ],
+            "mode": "dynamic"
+        }"#,
+        )
+        .unwrap();
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(
+                r#"{
+                    "attributes": [
+                        {"key": "cpu", "value": 12, "unit": "percent"},
+                        null,
+                        {"key": "mem", "value": 42, "unit": "bytes"}
+                    ]
+                }"#,
+            )
+            .unwrap();
+        let schema = default_doc_mapper.schema();
+        let key_field = schema.get_field("attributes.key").unwrap();
+        let keys: Vec<&str> = doc
+            .get_all(key_field)
+            .map(|value| value.as_text().unwrap())
+            .collect();
+        assert_eq!(keys, ["cpu", "mem"]);
+
+        let value_field = schema.get_field("attributes.value").unwrap();
+        let values: Vec<u64> = doc
+            .get_all(value_field)
+            .map(|value| value.as_u64().unwrap())
+            .collect();
+        assert_eq!(values, [12, 42]);
+
+        let dynamic_field = schema.get_field(DYNAMIC_FIELD_NAME).unwrap();
+        let TantivyValue::JsonObject(dynamic_json_obj) = doc.get_first(dynamic_field).unwrap()
+        else {
+            panic!("Expected json");
+        };
+        assert_eq!(
+            serde_json::to_value(dynamic_json_obj).unwrap(),
+            serde_json::json!({
+                "attributes": {
+                    "unit": ["percent", "bytes"]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_array_of_objects_with_single_valued_sub_field() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "field_mappings": [
+                {
+                    "name": "attributes",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "key",
+                            "type": "text"
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        default_doc_mapper
+            .doc_from_json_str(r#"{"attributes": [{"key": "cpu"}]}"#)
+            .unwrap();
+        // Like `null` leaves, `null` objects are ignored.
+        let (_, doc) = default_doc_mapper
+            .doc_from_json_str(r#"{"attributes": null}"#)
+            .unwrap();
+        let key_field = default_doc_mapper
+            .schema()
+            .get_field("attributes.key")
+            .unwrap();
+        assert!(doc.get_first(key_field).is_none());
+        let error = default_doc_mapper
+            .doc_from_json_str(r#"{"attributes": [{"key": "cpu"}, {"key": "mem"}]}"#)
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DocParsingError::MultiValuesNotSupported("attributes.key".to_string())
+        );
+        let error = default_doc_mapper
+            .doc_from_json_str(r#"{"attributes": ["cpu"]}"#)
+            .unwrap_err();
+        assert!(
+            matches!(error, DocParsingError::ValueError(field_name, _) if field_name == "attributes")
+        );
+    }
+
+    #[test]
+    fn test_default_search_field_on_object_is_expanded() {
+        let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
+            r#"{
+            "default_search_fields": ["resource", "body"],
+            "field_mappings": [
+                {
+                    "name": "resource",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "service",
+                            "type": "text"
+                        },
+                        {
+                            "name": "host.name",
+                            "type": "text"
+                        },
+                        {
+                            "name": "pid",
+                            "type": "u64",
+                            "indexed": false
+                        }
+                    ]
+                },
+                {
+                    "name": "body",
+                    "type": "text"
+                }
+            ]
+        }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            default_doc_mapper.default_search_fields(),
+            ["resource.service", "resource.host\\.name", "body"]
+        );
+
+        let error = serde_json::from_str::<DefaultDocMapper>(
+            r#"{
+            "default_search_fields": ["resource"],
+            "field_mappings": [
+                {
+                    "name": "resource",
+                    "type": "object",
+                    "field_mappings": [
+                        {
+                            "name": "pid",
+                            "type": "u64",
+                            "indexed": false
+                        }
+                    ]
+                }
+            ]
+        }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("Default search field `resource` does not contain any indexed field."));
+    }
+
     #[test]
     fn test_json_object_in_mapping() {
         let default_doc_mapper: DefaultDocMapper = serde_json::from_str(
@@ -19,7 +19,9 @@
 
 use std::num::NonZeroU32;
 
-use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value as JsonValue;
 
 use super::tokenizer_entry::TokenizerEntry;
 use super::FieldMappingEntry;
@@ -71,7 +73,7 @@ pub struct DefaultDocMapperBuilder {
             mode: ModeType,
             /// If mode is set to dynamic, `dynamic_mapping` defines
             /// how the unmapped fields should be handled.
-            #[serde(default)]
+            #[serde(default, deserialize_with = "Mode::deserialize_dynamic_mapping")]
             dynamic_mapping: Option<QuickwitJsonOptions>,
         ),
     )]
@@ -121,6 +123,27 @@ impl Mode {
         })
     }
 
+    /// Deserializes the `dynamic_mapping` options.
+    ///
+    /// Unlike JSON fields, dynamic fields are fast by default, so a `dynamic_mapping`
+    /// that does not specify `fast` keeps fast fields enabled.
+    pub fn deserialize_dynamic_mapping<'de, D>(
+        deserializer: D,
+    ) -> Result<Option<QuickwitJsonOptions>, D::Error>
+    where D: Deserializer<'de> {
+        let Some(mut dynamic_mapping_json) = Option::<JsonValue>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        if let JsonValue::Object(dynamic_mapping_obj) = &mut dynamic_mapping_json {
+            dynamic_mapping_obj
+                .entry("fast")
+                .or_insert(JsonValue::Bool(true));
+        }
+        serde_json::from_value(dynamic_mapping_json)
+            .map(Some)
+            .map_err(D::Error::custom)
+    }
+
     /// Obtain the mode type and dynamic options from a Mode
     pub fn into_parts(self) -> (ModeType, Option<QuickwitJsonOptions>) {
         match self {
@@ -169,6 +192,7 @@ impl DefaultDocMapperBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::default_doc_mapper::FastFieldOptions;
 
     #[test]
     fn test_default_mapper_builder_deserialize_from_empty_object() {
@@ -182,6 +206,54 @@ mod tests {
         assert!(default_mapper_builder.timestamp_field.is_none());
     }
 
+    #[test]
+    fn test_default_mapper_builder_partial_dynamic_mapping_keeps_fast_default() {
+        let default_mapper_builder: DefaultDocMapperBuilder = serde_json::from_str(
+            r#"{
+                "mode": "dynamic",
+                "dynamic_mapping": {
+                    "stored": false
+                }
+            }"#,
+        )
+        .unwrap();
+        let Mode::Dynamic(dynamic_mapping) = default_mapper_builder.mode else {
+            panic!("Expected dynamic mode.");
+        };
+        assert!(!dynamic_mapping.stored);
+        assert_eq!(dynamic_mapping.fast, FastFieldOptions::default_enabled());
+
+        let default_mapper_builder: DefaultDocMapperBuilder = serde_json::from_str(
+            r#"{
+                "mode": "dynamic",
+                "dynamic_mapping": {
+                    "fast": false
+                }
+            }"#,
+        )
+        .unwrap();
+        let Mode::Dynamic(dynamic_mapping) = default_mapper_builder.mode else {
+            panic!("Expected dynamic mode.");
+        };
+        assert_eq!(dynamic_mapping.fast, FastFieldOptions::Disabled);
+    }
+
+    #[test]
+    fn test_default_mapper_builder_dynamic_mapping_only_allowed_in_dynamic_mode() {
+        let error = serde_json::from_str::<DefaultDocMapperBuilder>(
+            r#"{
+                "mode": "strict",
+                "dynamic_mapping": {
+                    "stored": false
+                }
+            }"#,
+        )
+        .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("`dynamic_mapping` is only allowed with mode=dynamic"));
+    }
+
     #[test]
     fn test_default_mapper_builder_extra_field() {
         assert!(
@@ -24,6 +24,7 @@ use std::str::FromStr;
 
 use anyhow::bail;
 use itertools::Itertools;
+use serde_json::map::Entry;
 use serde_json::Value as JsonValue;
 use tantivy::schema::{
     BytesOptions, Field, IntoIpv6Addr, IpAddrOptions, JsonObjectOptions, NumericOptions,
@@ -347,16 +348,58 @@ fn get_or_insert_path<'a>(
     dynamic_json_obj
 }
 
+/// Inserts an unmapped value in the dynamic JSON object.
+///
+/// The same unmapped field can be encountered several times when it belongs to
+/// an array of objects. In that case, the values are gathered in an array
+/// rather than overwriting each other.
+fn insert_dynamic_json_val(
+    dynamic_json_obj: &mut serde_json::Map<String, JsonValue>,
+    field_name: String,
+    json_val: JsonValue,
+) {
+    match dynamic_json_obj.entry(field_name) {
+        Entry::Vacant(vacant_entry) => {
+            vacant_entry.insert(json_val);
+        }
+        Entry::Occupied(mut occupied_entry) => {
+            let existing_json_val = occupied_entry.get_mut();
+            if !existing_json_val.is_array() {
+                let first_json_val = existing_json_val.take();
+                *existing_json_val = JsonValue::Array(vec![first_json_val]);
+            }
+            let JsonValue::Array(json_vals) = existing_json_val else {
+                unreachable!();
+            };
+            match json_val {
+                JsonValue::Array(new_json_vals) => json_vals.extend(new_json_vals),
+                new_json_val => json_vals.push(new_json_val),
+            }
+        }
+    }
+}
+
 impl MappingNode {
     /// Finds the field mapping type for a given field path in the mapping tree.
     /// Dots in `field_path_as_str` define the boundaries between field names.
     /// If a dot is part of a field name, it must be escaped with '\'.
     pub fn find_field_mapping_type(&self, field_path_as_str: &str) -> Option<FieldMappingType> {
         let field_path = build_field_path_from_str(field_path_as_str);
-        self.internal_find_field_mapping_type(&field_path)
+        self.internal_find_field_mapping_tree(&field_path)
+            .map(|mapping_tree| mapping_tree.clone().into())
+    }
+
+    /// Finds the object node for a given field path in the mapping tree.
+    /// Returns `None` if the path does not exist or leads to a leaf.
+    pub fn find_object_node(&self, field_path_as_str: &str) -> Option<&MappingNode> {
+        let field_path = build_field_path_from_str(field_path_as_str);
+        match self.internal_find_field_mapping_tree(&field_path)? {
+            MappingTree::Node(mapping_node) => Some(mapping_node),
+            MappingTree::Leaf(_) => None,
+        }
     }
 
-    fn internal_find_field_mapping_type(&self, field_path: &[String]) -> Option<FieldMappingType> {
+    fn internal_find_field_mapping_tree(&self, field_path: &[String]) -> Option<&MappingTree> {
         let (first_path_fragment, sub_field_path) = field_path.split_first()?;
         let field_name = self
             .branches_order
@@ -364,12 +407,51 @@ impl MappingNode {
             .find(|name| name == &first_path_fragment)?;
         let child_tree = self.branches.get(field_name).expect("Missing field");
         match (child_tree, sub_field_path.is_empty()) {
-            (_, true) => Some(child_tree.clone().into()),
+            (_, true) => Some(child_tree),
             (MappingTree::Leaf(_), false) => None,
             (MappingTree::Node(child_node), false) => {
-                child_node.internal_find_field_mapping_type(sub_field_path)
+                child_node.internal_find_field_mapping_tree(sub_field_path)
+            }
+        }
+    }
+
+    /// Returns an error if a single-valued leaf below this node holds several values in
+    /// `document`, which happens when the node is fed an array of objects.
+    fn check_single_valued_leaves(
+        &self,
+        document: &Document,
+        path: &mut Vec<String>,
+    ) -> Result<(), DocParsingError> {
+        for field_name in &self.branches_order {
+            path.push(field_name.clone());
+            let check_result = match self.branches.get(field_name).expect("Missing field") {
+                MappingTree::Leaf(mapping_leaf)
+                    if mapping_leaf.cardinality == Cardinality::SingleValue
+                        && document.get_all(mapping_leaf.field).nth(1).is_some() =>
+                {
+                    Err(DocParsingError::MultiValuesNotSupported(path.join(".")))
+                }
+                MappingTree::Leaf(_) => Ok(()),
+                MappingTree::Node(child_node) => {
+                    child_node.check_single_valued_leaves(document, path)
+                }
+            };
+            path.pop();
+            check_result?;
+        }
+        Ok(())
+    }
+
+    /// Returns the fields of all the leaves below this node, in mapping order.
+    pub fn leaf_fields(&self) -> Vec<Field> {
+        let mut leaf_fields = Vec::new();
+        for field_name in &self.branches_order {
+            match self.branches.get(field_name).expect("Missing field") {
+                MappingTree::Leaf(mapping_leaf) => leaf_fields.push(mapping_leaf.field),
+                MappingTree::Node(child_node) => leaf_fields.extend(child_node.leaf_fields()),
             }
         }
+        leaf_fields
     }
 
     #[cfg(test)]
@@ -417,7 +499,7 @@ impl MappingNode {
                     ModeType::Dynamic => {
                         let dynamic_json_obj_after_path =
                             get_or_insert_path(path, dynamic_json_obj);
-                        dynamic_json_obj_after_path.insert(field_name, val);
+                        insert_dynamic_json_val(dynamic_json_obj_after_path, field_name, val);
                     }
                     ModeType::Strict => {
                         path.push(field_name);
@@ -496,16 +578,47 @@ impl MappingTree {
             MappingTree::Leaf(mapping_leaf) => {
                 mapping_leaf.doc_from_json(json_value, document, path)
             }
-            MappingTree::Node(mapping_node) => {
-                if let JsonValue::Object(json_obj) = json_value {
+            MappingTree::Node(mapping_node) => match json_value {
+                JsonValue::Object(json_obj) => {
                     mapping_node.doc_from_json(json_obj, mode, document, path, dynamic_json_obj)
-                } else {
-                    Err(DocParsingError::ValueError(
-                        path.join("."),
-                        format!("Expected an JSON Object, got {json_value}"),
-                    ))
                 }
-            }
+                // Arrays of objects are flattened: the values of each sub-field are
+                // gathered across all of the objects of the array.
+                JsonValue::Array(json_values) => {
+                    let num_objects = json_values
+                        .iter()
+                        .filter(|json_value| json_value.is_object())
+                        .count();
+                    for json_value in json_values {
+                        match json_value {
+                            JsonValue::Null => {}
+                            JsonValue::Object(json_obj) => mapping_node.doc_from_json(
+                                json_obj,
+                                mode,
+                                document,
+                                path,
+                                dynamic_json_obj,
+                            )?,
+                            _ => {
+                                return Err(DocParsingError::ValueError(
+                                    path.join("."),
+                                    format!("Expected an array of JSON Objects, got {json_value}"),
+                                ));
+                            }
+                        }
+                    }
+                    if num_objects > 1 {
+                        mapping_node.check_single_valued_leaves(document, path)?;
+                    }
+                    Ok(())
+                }
+                // Like for leaves, `null` is ignored.
+                JsonValue::Null => Ok(()),
+                _ => Err(DocParsingError::ValueError(
+                    path.join("."),
+                    format!("Expected an JSON Object, got {json_value}"),
+                )),
+            },
         }
     }
 
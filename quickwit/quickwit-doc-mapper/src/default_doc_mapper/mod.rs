@@ -41,7 +41,7 @@ pub(crate) use self::field_mapping_entry::{
 pub(crate) use self::field_mapping_type::FieldMappingType;
 pub use self::tokenizer_entry::{analyze_text, TokenizerConfig, TokenizerEntry};
 pub(crate) use self::tokenizer_entry::{
-    NgramTokenizerOption, RegexTokenizerOption, TokenFilterType, TokenizerType,
+    NgramTokenizerOption, RegexTokenizerOption, StemmerLanguage, TokenFilterType, TokenizerType,
 };
 use crate::QW_RESERVED_FIELD_NAMES;
 
@@ -23,8 +23,8 @@ use quickwit_query::MultiLangTokenizer;
 use quickwit_query::{CodeTokenizer, DEFAULT_REMOVE_TOKEN_LENGTH};
 use serde::{Deserialize, Serialize};
 use tantivy::tokenizer::{
-    AsciiFoldingFilter, LowerCaser, NgramTokenizer, RegexTokenizer, RemoveLongFilter,
-    SimpleTokenizer, TextAnalyzer, Token,
+    AsciiFoldingFilter, Language, LowerCaser, NgramTokenizer, RegexTokenizer, RemoveLongFilter,
+    SimpleTokenizer, Stemmer, TextAnalyzer, Token,
 };
 
 /// A `TokenizerEntry` defines a custom tokenizer with its name and configuration.
@@ -79,6 +79,9 @@ impl TokenizerConfig {
                 TantivyTokenFilterEnum::AsciiFolding(token_filter) => {
                     text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
                 }
+                TantivyTokenFilterEnum::Stemmer(token_filter) => {
+                    text_analyzer_builder = text_analyzer_builder.filter_dynamic(token_filter);
+                }
             }
         }
         Ok(text_analyzer_builder.build())
@@ -102,6 +105,58 @@ pub enum TokenFilterType {
     RemoveLong,
     LowerCaser,
     AsciiFolding,
+    /// Reduces tokens to their stem in the given language.
+    /// Tokens are expected to be lowercased beforehand.
+    Stemmer(StemmerLanguage),
+}
+
+/// Languages supported by the `stemmer` token filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StemmerLanguage {
+    Arabic,
+    Danish,
+    Dutch,
+    English,
+    Finnish,
+    French,
+    German,
+    Greek,
+    Hungarian,
+    Italian,
+    Norwegian,
+    Portuguese,
+    Romanian,
+    Russian,
+    Spanish,
+    Swedish,
+    Tamil,
+    Turkish,
+}
+
+impl StemmerLanguage {
+    fn tantivy_language(&self) -> Language {
+        match self {
+            Self::Arabic => Language::Arabic,
+            Self::Danish => Language::Danish,
+            Self::Dutch => Language::Dutch,
+            Self::English => Language::English,
+            Self::Finnish => Language::Finnish,
+            Self::French => Language::French,
+            Self::German => Language::German,
+            Self::Greek => Language::Greek,
+            Self::Hungarian => Language::Hungarian,
+            Self::Italian => Language::Italian,
+            Self::Norwegian => Language::Norwegian,
+            Self::Portuguese => Language::Portuguese,
+            Self::Romanian => Language::Romanian,
+            Self::Russian => Language::Russian,
+            Self::Spanish => Language::Spanish,
+            Self::Swedish => Language::Swedish,
+            Self::Tamil => Language::Tamil,
+            Self::Turkish => Language::Turkish,
+        }
+    }
 }
 
 /// Tantivy token filter enum to build
@@ -110,6 +165,7 @@ enum TantivyTokenFilterEnum {
     RemoveLong(RemoveLongFilter),
     LowerCaser(LowerCaser),
     AsciiFolding(AsciiFoldingFilter),
+    Stemmer(Stemmer),
 }
 
 impl TokenFilterType {
@@ -120,6 +176,9 @@ impl TokenFilterType {
             )),
             Self::LowerCaser => TantivyTokenFilterEnum::LowerCaser(LowerCaser),
             Self::AsciiFolding => TantivyTokenFilterEnum::AsciiFolding(AsciiFoldingFilter),
+            Self::Stemmer(language) => {
+                TantivyTokenFilterEnum::Stemmer(Stemmer::new(language.tantivy_language()))
+            }
         }
     }
 }
@@ -152,7 +211,9 @@ pub struct RegexTokenizerOption {
 
 #[cfg(test)]
 mod tests {
-    use super::{NgramTokenizerOption, TokenizerType};
+    use super::{
+        analyze_text, NgramTokenizerOption, StemmerLanguage, TokenFilterType, TokenizerType,
+    };
     use crate::default_doc_mapper::RegexTokenizerOption;
     use crate::TokenizerEntry;
 
@@ -245,4 +306,47 @@ mod tests {
             _ => panic!("Unexpected tokenizer type"),
         }
     }
+
+    #[test]
+    fn test_tokenizer_entry_stemmer() {
+        let tokenizer_config_entry = serde_json::from_str::<TokenizerEntry>(
+            r#"
+            {
+                "name": "my_english_tokenizer",
+                "type": "simple",
+                "filters": [
+                    "lower_caser",
+                    {"stemmer": "english"}
+                ]
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            tokenizer_config_entry.config.filters,
+            [
+                TokenFilterType::LowerCaser,
+                TokenFilterType::Stemmer(StemmerLanguage::English)
+            ]
+        );
+        let tokens: Vec<String> =
+            analyze_text("Running cats jumped", &tokenizer_config_entry.config)
+                .unwrap()
+                .into_iter()
+                .map(|token| token.text)
+                .collect();
+        assert_eq!(tokens, ["run", "cat", "jump"]);
+
+        let error = serde_json::from_str::<TokenizerEntry>(
+            r#"
+            {
+                "name": "my_tokenizer",
+                "type": "simple",
+                "filters": [{"stemmer": "klingon"}]
+            }
+            "#,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("unknown variant `klingon`"));
+    }
 }
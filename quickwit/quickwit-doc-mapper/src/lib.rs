@@ -41,7 +41,7 @@ pub use default_doc_mapper::{
 use default_doc_mapper::{
     FastFieldOptions, FieldMappingEntryForSerialization, IndexRecordOptionSchema,
     NgramTokenizerOption, QuickwitTextNormalizer, QuickwitTextTokenizer, RegexTokenizerOption,
-    TokenFilterType, TokenizerType,
+    StemmerLanguage, TokenFilterType, TokenizerType,
 };
 pub use doc_mapper::{DocMapper, JsonObject, NamedField, TermRange, WarmupInfo};
 pub use error::{DocParsingError, QueryParserError};
@@ -77,6 +77,7 @@ pub(crate) enum Cardinality {
     QuickwitTextNormalizer,
     QuickwitTextTokenizer,
     RegexTokenizerOption,
+    StemmerLanguage,
     TokenFilterType,
     TokenizerConfig,
     TokenizerEntry,
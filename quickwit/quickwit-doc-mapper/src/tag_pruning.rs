@@ -340,9 +340,13 @@ fn collect_tag_filters_for_clause(
 /// using De Morgan's law
 /// - NOT( A AND B )=> NOT(A) OR NOT(B)
 /// - NOT( A OR B )=> NOT(A) AND NOT(B)
-/// - NOT( Tag ) => NotTag
-/// - NOT( NotTag ) => Tag
+/// - NOT( Tag ) => Uninformative
 /// - NOT( Uninformative ) => Uninformative.
+///
+/// The tags of a split record the values present in at least one of its documents.
+/// A split containing the tag `lang:fr` may also contain documents in another language
+/// (or without any language), so the absence of a tag is the only thing we could test for
+/// `NOT lang:fr`, which would wrongly prune such splits.
 fn negate_ast(clause: UnsimplifiedTagFilterAst) -> UnsimplifiedTagFilterAst {
     match clause {
         UnsimplifiedTagFilterAst::And(leaves) => {
@@ -351,16 +355,9 @@ fn negate_ast(clause: UnsimplifiedTagFilterAst) -> UnsimplifiedTagFilterAst {
         UnsimplifiedTagFilterAst::Or(leaves) => {
             UnsimplifiedTagFilterAst::And(leaves.into_iter().map(negate_ast).collect())
         }
-        UnsimplifiedTagFilterAst::Tag {
-            is_present,
-            field,
-            value,
-        } => UnsimplifiedTagFilterAst::Tag {
-            is_present: !is_present,
-            field,
-            value,
-        },
-        UnsimplifiedTagFilterAst::Uninformative => UnsimplifiedTagFilterAst::Uninformative,
+        UnsimplifiedTagFilterAst::Tag { .. } | UnsimplifiedTagFilterAst::Uninformative => {
+            UnsimplifiedTagFilterAst::Uninformative
+        }
     }
 }
 
@@ -381,6 +378,8 @@ pub fn no_tag(tag: impl ToString) -> TagFilterAst {
 }
 #[cfg(test)]
 mod test {
+    use std::collections::BTreeSet;
+
     use quickwit_query::query_ast::{QueryAst, UserInputQuery};
     use quickwit_query::BooleanOperand;
 
@@ -462,12 +461,7 @@ mod test {
 
     #[test]
     fn test_disjunction_of_tag_disjunction_with_not_clause() {
-        assert_eq!(
-            extract_tags_from_query_helper("(user:bart -lang:fr)")
-                .unwrap()
-                .to_string(),
-            "((¬user! ∨ user:bart) ∨ (¬lang! ∨ ¬lang:fr))"
-        );
+        assert_eq!(extract_tags_from_query_helper("(user:bart -lang:fr)"), None);
     }
 
     #[test]
@@ -476,10 +470,30 @@ mod test {
             &extract_tags_from_query_helper("user:bart AND NOT lang:fr")
                 .unwrap()
                 .to_string(),
-            "(¬user! ∨ user:bart) ∧ (¬lang! ∨ ¬lang:fr)"
+            "(¬user! ∨ user:bart)"
         );
     }
 
+    #[test]
+    fn test_negated_tags_do_not_prune_splits_with_other_values() {
+        assert_eq!(extract_tags_from_query_helper("NOT lang:fr"), None);
+        assert_eq!(
+            extract_tags_from_query_helper("NOT (lang:fr OR lang:en)"),
+            None
+        );
+        assert_eq!(
+            extract_tags_from_query_helper("NOT (lang:fr AND NOT user:bart)"),
+            None
+        );
+
+        let split_tags: BTreeSet<String> = ["lang!", "lang:en", "lang:fr", "user!", "user:bart"]
+            .into_iter()
+            .map(ToString::to_string)
+            .collect();
+        let tags_filter = extract_tags_from_query_helper("user:bart AND NOT lang:fr").unwrap();
+        assert!(tags_filter.evaluate(&split_tags));
+    }
+
     #[test]
     fn test_disjunction_of_tag_must_should() {
         assert_eq!(
@@ -18,20 +18,21 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
 use futures::Future;
-use quickwit_common::{PrettySample, Progress};
+use quickwit_common::{split_file, PrettySample, Progress};
 use quickwit_metastore::{
     ListSplitsQuery, Metastore, MetastoreError, SplitInfo, SplitMetadata, SplitState,
 };
 use quickwit_proto::IndexUid;
-use quickwit_storage::{BulkDeleteError, Storage};
+use quickwit_storage::{BulkDeleteError, Storage, StorageErrorKind};
 use thiserror::Error;
 use time::OffsetDateTime;
-use tracing::{error, instrument};
+use tracing::{error, info, instrument, warn};
 
 /// The maximum number of splits that the GC should delete per attempt.
 const DELETE_SPLITS_BATCH_SIZE: usize = 1000;
@@ -65,6 +66,8 @@ pub struct SplitRemovalInfo {
     pub removed_split_entries: Vec<SplitInfo>,
     /// The set of split ids that were attempted to be removed, but were unsuccessful.
     pub failed_splits: Vec<SplitInfo>,
+    /// The split files that had no matching split in the metastore and have been removed.
+    pub removed_orphan_files: Vec<PathBuf>,
 }
 
 /// Detect all dangling splits and associated files from the index and removes them.
@@ -121,6 +124,7 @@ pub async fn run_garbage_collect(
         return Ok(SplitRemovalInfo {
             removed_split_entries: candidate_entries,
             failed_splits: Vec::new(),
+            removed_orphan_files: Vec::new(),
         });
     }
 
@@ -218,9 +222,79 @@ async fn delete_splits_marked_for_deletion(
     SplitRemovalInfo {
         removed_split_entries: removed_splits,
         failed_splits,
+        removed_orphan_files: Vec::new(),
     }
 }
 
+/// Detects the split files of the index storage that have no matching split in the metastore and
+/// removes them.
+///
+/// Such orphan files are left behind when a split is removed from the metastore while the
+/// deletion of its file fails, for instance.
+///
+/// * `index_uid` - The target index UID.
+/// * `storage - The storage managing the target index.
+/// * `metastore` - The metastore managing the target index.
+/// * `dry_run` - Should this only return the list of orphan files without deleting them.
+/// * `progress` - For reporting progress (useful when called from within a quickwit actor).
+pub async fn delete_orphan_split_files(
+    index_uid: IndexUid,
+    storage: Arc<dyn Storage>,
+    metastore: Arc<dyn Metastore>,
+    dry_run: bool,
+    progress_opt: Option<&Progress>,
+) -> anyhow::Result<Vec<PathBuf>> {
+    // The storage must be listed before the metastore: split files are uploaded after their
+    // split is staged, so a file listed here with no split in the metastore is truly orphan.
+    let files = match protect_future(progress_opt, storage.list_files()).await {
+        Ok(files) => files,
+        Err(storage_error) if storage_error.kind() == StorageErrorKind::Unsupported => {
+            warn!(
+                index_id = index_uid.index_id(),
+                storage_uri = %storage.uri(),
+                "Skipping orphan split files collection: the storage does not support listing \
+                 files."
+            );
+            return Ok(Vec::new());
+        }
+        Err(storage_error) => return Err(storage_error.into()),
+    };
+    let split_files: Vec<PathBuf> = files
+        .into_iter()
+        .filter(|path| {
+            path.parent() == Some(Path::new("")) && path.extension() == Some(OsStr::new("split"))
+        })
+        .collect();
+
+    if split_files.is_empty() {
+        return Ok(Vec::new());
+    }
+    let query = ListSplitsQuery::for_index(index_uid.clone());
+    let known_split_files: HashSet<PathBuf> =
+        protect_future(progress_opt, metastore.list_splits(query))
+            .await?
+            .into_iter()
+            .map(|split| PathBuf::from(split_file(split.split_id())))
+            .collect();
+    let orphan_files: Vec<PathBuf> = split_files
+        .into_iter()
+        .filter(|path| !known_split_files.contains(path))
+        .collect();
+
+    if dry_run || orphan_files.is_empty() {
+        return Ok(orphan_files);
+    }
+    let orphan_paths: Vec<&Path> = orphan_files.iter().map(PathBuf::as_path).collect();
+    protect_future(progress_opt, storage.bulk_delete(&orphan_paths)).await?;
+
+    info!(
+        index_id = index_uid.index_id(),
+        "Deleted orphan split file(s) {:?}.",
+        PrettySample::new(&orphan_paths, 5),
+    );
+    Ok(orphan_files)
+}
+
 /// Delete a list of splits from the storage and the metastore.
 /// It should leave the index and the metastore in good state.
 ///
@@ -326,6 +400,7 @@ mod tests {
     use std::time::Duration;
 
     use itertools::Itertools;
+    use quickwit_common::uri::Uri;
     use quickwit_config::IndexConfig;
     use quickwit_metastore::{
         metastore_for_test, ListSplitsQuery, MockMetastore, SplitMetadata, SplitState,
@@ -483,6 +558,109 @@ mod tests {
         .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_delete_orphan_split_files() {
+        let storage = storage_for_test();
+        let metastore = metastore_for_test();
+
+        let index_id = "test-delete-orphan-split-files--index";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(index_id, &index_uri);
+        let index_uid = metastore.create_index(index_config).await.unwrap();
+
+        let split_id = "test-delete-orphan-split-files--split";
+        let split_metadata = SplitMetadata {
+            split_id: split_id.to_string(),
+            index_uid: index_uid.clone(),
+            ..Default::default()
+        };
+        metastore
+            .stage_splits(index_uid.clone(), vec![split_metadata])
+            .await
+            .unwrap();
+
+        for path in [
+            "test-delete-orphan-split-files--split.split",
+            "test-delete-orphan-split-files--orphan.split",
+            "metastore.json",
+            "subdir/nested.split",
+        ] {
+            storage
+                .put(Path::new(path), Box::new(b"split".to_vec()))
+                .await
+                .unwrap();
+        }
+        let orphan_files = delete_orphan_split_files(
+            index_uid.clone(),
+            storage.clone(),
+            metastore.clone(),
+            true,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            orphan_files,
+            [PathBuf::from(
+                "test-delete-orphan-split-files--orphan.split"
+            )]
+        );
+        assert!(storage
+            .exists(Path::new("test-delete-orphan-split-files--orphan.split"))
+            .await
+            .unwrap());
+
+        let orphan_files = delete_orphan_split_files(
+            index_uid.clone(),
+            storage.clone(),
+            metastore.clone(),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            orphan_files,
+            [PathBuf::from(
+                "test-delete-orphan-split-files--orphan.split"
+            )]
+        );
+        let mut files = storage.list_files().await.unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("metastore.json"),
+                PathBuf::from("subdir/nested.split"),
+                PathBuf::from("test-delete-orphan-split-files--split.split"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_orphan_split_files_skips_storage_without_listing() {
+        let mut mock_storage = MockStorage::new();
+        mock_storage.expect_list_files().return_once(|| {
+            Err(StorageErrorKind::Unsupported
+                .with_error(anyhow::anyhow!("Listing files is not supported.")))
+        });
+        mock_storage
+            .expect_uri()
+            .return_const(Uri::for_test("ram:///indexes"));
+        let storage = Arc::new(mock_storage);
+        let metastore = metastore_for_test();
+
+        let index_id = "test-delete-orphan-split-files-no-listing--index";
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(index_id, &index_uri);
+        let index_uid = metastore.create_index(index_config).await.unwrap();
+
+        let orphan_files = delete_orphan_split_files(index_uid, storage, metastore, false, None)
+            .await
+            .unwrap();
+        assert!(orphan_files.is_empty());
+    }
+
     #[tokio::test]
     async fn test_delete_splits_from_storage_and_metastore_happy_path() {
         let storage = storage_for_test();
@@ -33,8 +33,8 @@ use thiserror::Error;
 use tracing::{error, info};
 
 use crate::garbage_collection::{
-    delete_splits_from_storage_and_metastore, run_garbage_collect, DeleteSplitsError,
-    SplitRemovalInfo,
+    delete_orphan_split_files, delete_splits_from_storage_and_metastore, run_garbage_collect,
+    DeleteSplitsError, SplitRemovalInfo,
 };
 
 #[derive(Error, Debug)]
@@ -188,7 +188,8 @@ impl IndexService {
         Ok(deleted_splits)
     }
 
-    /// Detect all dangling splits and associated files from the index and removes them.
+    /// Detect all dangling splits and associated files from the index and removes them. Split files
+    /// with no matching split in the metastore are removed as well.
     ///
     /// * `index_id` - The target index Id.
     /// * `grace_period` -  Threshold period after which a staged split can be garbage collected.
@@ -207,9 +208,9 @@ impl IndexService {
             .resolve(&index_config.index_uri)
             .await?;
 
-        let deleted_entries = run_garbage_collect(
-            index_uid,
-            storage,
+        let mut deleted_entries = run_garbage_collect(
+            index_uid.clone(),
+            storage.clone(),
             self.metastore.clone(),
             grace_period,
             // deletion_grace_period of zero, so that a cli call directly deletes splits after
@@ -220,6 +221,9 @@ impl IndexService {
         )
         .await?;
 
+        deleted_entries.removed_orphan_files =
+            delete_orphan_split_files(index_uid, storage, self.metastore.clone(), dry_run, None)
+                .await?;
         Ok(deleted_entries)
     }
 
@@ -20,5 +20,5 @@
 mod garbage_collection;
 mod index;
 
-pub use garbage_collection::run_garbage_collect;
+pub use garbage_collection::{delete_orphan_split_files, run_garbage_collect};
 pub use index::{clear_cache_directory, validate_storage_uri, IndexService, IndexServiceError};
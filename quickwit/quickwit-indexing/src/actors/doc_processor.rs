@@ -23,7 +23,9 @@ use std::sync::Arc;
 use anyhow::Context;
 use async_trait::async_trait;
 use bytes::Bytes;
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Handler, Mailbox, QueueCapacity,
+};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_config::{SourceInputFormat, TransformConfig};
 use quickwit_doc_mapper::{DocMapper, DocParsingError, JsonObject};
@@ -360,6 +362,11 @@ impl Actor for DocProcessor {
         QueueCapacity::Bounded(10)
     }
 
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once the source has, after processing everything it was sent.
+        false
+    }
+
     fn runtime_handle(&self) -> Handle {
         RuntimeType::Blocking.get_runtime_handle()
     }
@@ -385,6 +392,16 @@ impl Actor for DocProcessor {
         }
         Ok(())
     }
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_message(&self.indexer_mailbox, checkpoint_barrier.clone())
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -426,7 +443,7 @@ impl Handler<RawDocBatch> for DocProcessor {
             checkpoint_delta: raw_doc_batch.checkpoint_delta,
             force_commit: raw_doc_batch.force_commit,
         };
-        ctx.send_message(&self.indexer_mailbox, processed_doc_batch)
+        ctx.forward_sized_message(&self.indexer_mailbox, processed_doc_batch)
             .await?;
         Ok(())
     }
@@ -18,7 +18,9 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use async_trait::async_trait;
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Handler, Mailbox, QueueCapacity,
+};
 use quickwit_common::io::IoControls;
 use quickwit_common::runtimes::RuntimeType;
 use tokio::runtime::Handle;
@@ -55,9 +57,24 @@ impl Actor for IndexSerializer {
         QueueCapacity::Bounded(0)
     }
 
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once the indexer has, after processing everything it was sent.
+        false
+    }
+
     fn runtime_handle(&self) -> Handle {
         RuntimeType::Blocking.get_runtime_handle()
     }
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_message(&self.packager_mailbox, checkpoint_barrier.clone())
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
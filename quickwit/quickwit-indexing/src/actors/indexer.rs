@@ -30,7 +30,8 @@ use fail::fail_point;
 use fnv::FnvHashMap;
 use itertools::Itertools;
 use quickwit_actors::{
-    Actor, ActorContext, ActorExitStatus, Command, Handler, Mailbox, QueueCapacity,
+    Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Command, Handler, Mailbox,
+    QueueCapacity,
 };
 use quickwit_common::io::IoControls;
 use quickwit_common::runtimes::RuntimeType;
@@ -344,6 +345,11 @@ impl Actor for Indexer {
         QueueCapacity::Bounded(10)
     }
 
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once the doc processor has, after processing everything it was sent.
+        false
+    }
+
     fn name(&self) -> String {
         "Indexer".to_string()
     }
@@ -381,6 +387,12 @@ impl Actor for Indexer {
         Ok(())
     }
 
+    async fn flush(&mut self, ctx: &ActorContext<Self>) -> Result<(), ActorExitStatus> {
+        self.send_to_serializer(CommitTrigger::ForceCommit, ctx)
+            .await?;
+        Ok(())
+    }
+
     async fn finalize(
         &mut self,
         exit_status: &ActorExitStatus,
@@ -399,6 +411,17 @@ impl Actor for Indexer {
         }
         Ok(())
     }
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        // `flush` sent the split in progress to the serializer right before the barrier.
+        ctx.send_message(&self.index_serializer_mailbox, checkpoint_barrier.clone())
+            .await?;
+        Ok(())
+    }
 }
 
 fn record_timestamp(timestamp: DateTime, time_range: &mut Option<RangeInclusive<DateTime>>) {
@@ -521,13 +544,15 @@ impl Indexer {
                 ctx,
             )
             .await?;
-        if self.memory_usage() >= self.indexer_state.indexing_settings.resources.heap_size {
+        let indexing_settings = &self.indexer_state.indexing_settings;
+        let commit_memory_usage_threshold = indexing_settings.commit_memory_usage_threshold();
+        let commit_num_docs_threshold = indexing_settings.commit_num_docs_threshold() as u64;
+
+        if self.memory_usage() >= commit_memory_usage_threshold {
             self.send_to_serializer(CommitTrigger::MemoryLimit, ctx)
                 .await?;
         }
-        if self.counters.num_docs_in_workbench
-            >= self.indexer_state.indexing_settings.split_num_docs_target as u64
-        {
+        if self.counters.num_docs_in_workbench >= commit_num_docs_threshold {
             self.send_to_serializer(CommitTrigger::NumDocsLimit, ctx)
                 .await?;
         }
@@ -783,6 +808,85 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_indexer_triggers_commit_on_commit_num_docs() -> anyhow::Result<()> {
+        let pipeline_id = IndexingPipelineId {
+            index_uid: IndexUid::new("test-index"),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper = Arc::new(default_doc_mapper_for_test());
+        let last_delete_opstamp = 10;
+        let schema = doc_mapper.schema();
+        let body_field = schema.get_field("body").unwrap();
+        let timestamp_field = schema.get_field("timestamp").unwrap();
+        let indexing_directory = TempDirectory::for_test();
+        let mut indexing_settings = IndexingSettings::for_test();
+        indexing_settings.commit_num_docs = Some(2);
+        let universe = Universe::with_accelerated_time();
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .times(2)
+            .returning(move |index_uid| {
+                assert_eq!(index_uid.index_id(), "test-index");
+                Ok(last_delete_opstamp)
+            });
+        metastore.expect_publish_splits().never();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            None,
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        for (doc_ord, checkpoint_ord) in (0..3).zip(4u64..) {
+            indexer_mailbox
+                .send_message(ProcessedDocBatch {
+                    docs: vec![ProcessedDoc {
+                        doc: doc!(
+                            body_field=>format!("this is a test document {doc_ord}"),
+                            timestamp_field=>DateTime::from_timestamp_secs(1_662_529_435)
+                        ),
+                        timestamp_opt: Some(DateTime::from_timestamp_secs(1_662_529_435)),
+                        partition: 1,
+                        num_bytes: 30,
+                    }],
+                    checkpoint_delta: SourceCheckpointDelta::from_range(
+                        checkpoint_ord..checkpoint_ord + 1,
+                    ),
+                    force_commit: false,
+                })
+                .await?;
+        }
+        let indexer_counters = indexer_handle.process_pending_and_observe().await.state;
+        assert_eq!(
+            indexer_counters,
+            IndexerCounters {
+                num_splits_emitted: 1,
+                num_split_batches_emitted: 1,
+                num_docs_in_workbench: 1,
+            }
+        );
+        let messages: Vec<IndexedSplitBatchBuilder> = index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(messages.len(), 1);
+        let batch = messages.into_iter().next().unwrap();
+        assert_eq!(batch.commit_trigger, CommitTrigger::NumDocsLimit);
+        assert_eq!(batch.splits[0].split_attrs.num_docs, 2);
+        let index_checkpoint = batch.checkpoint_delta.unwrap();
+        assert_eq!(
+            index_checkpoint.source_delta,
+            SourceCheckpointDelta::from_range(4..6)
+        );
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_indexer_triggers_commit_on_memory_limit() -> anyhow::Result<()> {
         let universe = Universe::new();
@@ -1479,6 +1583,135 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_indexer_flush() {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_uid: IndexUid::new("test-index"),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> =
+            Arc::new(serde_json::from_str::<DefaultDocMapper>(DOCMAPPER_SIMPLE_JSON).unwrap());
+        let body_field = doc_mapper.schema().get_field("body").unwrap();
+        let indexing_directory = TempDirectory::for_test();
+        let indexing_settings = IndexingSettings::for_test();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(move |index_uid| {
+                assert_eq!(index_uid.index_id(), "test-index");
+                Ok(10)
+            });
+        metastore.expect_publish_splits().never();
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            None,
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        indexer_mailbox
+            .send_message(ProcessedDocBatch {
+                docs: vec![ProcessedDoc {
+                    doc: doc!(body_field=>"doc 1"),
+                    timestamp_opt: None,
+                    partition: 0,
+                    num_bytes: 30,
+                }],
+                checkpoint_delta: SourceCheckpointDelta::from_range(0..1),
+                force_commit: false,
+            })
+            .await
+            .unwrap();
+        indexer_handle.flush().await.unwrap();
+
+        let output_messages: Vec<IndexedSplitBatchBuilder> =
+            index_serializer_inbox.drain_for_test_typed();
+        assert_eq!(output_messages.len(), 1);
+        assert_eq!(
+            output_messages[0].commit_trigger,
+            CommitTrigger::ForceCommit
+        );
+        assert_eq!(output_messages[0].splits[0].split_attrs.num_docs, 1);
+        universe.assert_quit().await;
+    }
+
+    #[tokio::test]
+    async fn test_indexer_forwards_checkpoint_barrier_after_committing() {
+        let universe = Universe::with_accelerated_time();
+        let pipeline_id = IndexingPipelineId {
+            index_uid: IndexUid::new("test-index"),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let doc_mapper: Arc<dyn DocMapper> =
+            Arc::new(serde_json::from_str::<DefaultDocMapper>(DOCMAPPER_SIMPLE_JSON).unwrap());
+        let body_field = doc_mapper.schema().get_field("body").unwrap();
+        let indexing_directory = TempDirectory::for_test();
+        let indexing_settings = IndexingSettings::for_test();
+        let mut metastore = MockMetastore::default();
+        metastore
+            .expect_last_delete_opstamp()
+            .times(1)
+            .returning(move |index_uid| {
+                assert_eq!(index_uid.index_id(), "test-index");
+                Ok(10)
+            });
+        metastore.expect_publish_splits().never();
+        let (index_serializer_mailbox, index_serializer_inbox) = universe.create_test_mailbox();
+        let indexer = Indexer::new(
+            pipeline_id,
+            doc_mapper,
+            Arc::new(metastore),
+            indexing_directory,
+            indexing_settings,
+            None,
+            index_serializer_mailbox,
+        );
+        let (indexer_mailbox, indexer_handle) = universe.spawn_builder().spawn(indexer);
+        indexer_mailbox
+            .send_message(ProcessedDocBatch {
+                docs: vec![ProcessedDoc {
+                    doc: doc!(body_field=>"doc 1"),
+                    timestamp_opt: None,
+                    partition: 0,
+                    num_bytes: 30,
+                }],
+                checkpoint_delta: SourceCheckpointDelta::from_range(0..1),
+                force_commit: false,
+            })
+            .await
+            .unwrap();
+        let _checkpoint_completion = universe
+            .inject_checkpoint_barrier(1, &[indexer_mailbox])
+            .await;
+        indexer_handle.process_pending_and_observe().await;
+
+        // The split in progress is committed, then the barrier is forwarded to the serializer.
+        let output_messages = index_serializer_inbox.drain_for_test();
+        assert_eq!(output_messages.len(), 2);
+        let indexed_split_batch = output_messages[0]
+            .downcast_ref::<IndexedSplitBatchBuilder>()
+            .unwrap();
+        assert_eq!(
+            indexed_split_batch.commit_trigger,
+            CommitTrigger::ForceCommit
+        );
+        let checkpoint_barrier = output_messages[1]
+            .downcast_ref::<CheckpointBarrier>()
+            .unwrap();
+        assert_eq!(checkpoint_barrier.checkpoint_id(), 1);
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_indexer_checkpoint_on_all_failed_docs() -> anyhow::Result<()> {
         let pipeline_id = IndexingPipelineId {
@@ -21,10 +21,11 @@ use std::collections::{HashMap, HashSet};
 use std::fmt::Debug;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use anyhow::Context;
 use async_trait::async_trait;
-use futures::future::try_join_all;
+use futures::future::{join_all, try_join_all};
 use itertools::Itertools;
 use quickwit_actors::{
     Actor, ActorContext, ActorExitStatus, ActorHandle, ActorState, Handler, Healthz, Mailbox,
@@ -59,6 +60,10 @@ use crate::{IndexingPipeline, IndexingPipelineParams, IndexingSplitStore, Indexi
 /// Name of the indexing directory, usually located at `<data_dir_path>/indexing`.
 pub const INDEXING_DIR_NAME: &str = "indexing";
 
+/// Time given to a pipeline that is shut down to publish the documents it already read,
+/// before it is killed.
+const PIPELINE_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
 #[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
 pub struct IndexingServiceCounters {
     pub num_running_pipelines: usize,
@@ -449,6 +454,7 @@ impl IndexingService {
 
         // Shut down currently running pipelines that are missing in the new plan.
         self.shutdown_pipelines(
+            ctx,
             running_pipeline_ids
                 .difference(&updated_pipeline_ids)
                 .collect(),
@@ -524,12 +530,19 @@ impl IndexingService {
     }
 
     /// Shuts down the pipelines with supplied ids and performs necessary cleanup.
-    async fn shutdown_pipelines(&mut self, pipeline_ids: Vec<&IndexingPipelineId>) {
+    ///
+    /// The pipelines are soft killed: their sources stop, and the documents already read are
+    /// indexed and published, unless it takes longer than `PIPELINE_SHUTDOWN_GRACE_PERIOD`.
+    async fn shutdown_pipelines(
+        &mut self,
+        ctx: &ActorContext<Self>,
+        pipeline_ids: Vec<&IndexingPipelineId>,
+    ) {
+        let mut pipeline_handles = Vec::new();
         for pipeline_id_to_remove in pipeline_ids.clone() {
             match self.detach_pipeline(pipeline_id_to_remove).await {
                 Ok(pipeline_handle) => {
-                    // Killing the pipeline ensure that all pipeline actors will stop.
-                    pipeline_handle.kill().await;
+                    pipeline_handles.push(pipeline_handle);
                 }
                 Err(error) => {
                     // Just log the detach error, it can only come from a missing pipeline in the
@@ -542,6 +555,10 @@ impl IndexingService {
                 }
             }
         }
+        let soft_kill_futures = pipeline_handles
+            .into_iter()
+            .map(|pipeline_handle| pipeline_handle.soft_kill(PIPELINE_SHUTDOWN_GRACE_PERIOD));
+        ctx.protect_future(join_all(soft_kill_futures)).await;
 
         // If at least one ingest source has been removed, the related index has possibly been
         // deleted. Thus we run a garbage collect to remove queues of potentially deleted
@@ -63,8 +63,10 @@ impl Actor for MergePlanner {
             .iter()
             .map(|tracked_operation| tracked_operation.as_ref().clone())
             .collect_vec();
+        let num_young_splits = self.partitioned_young_splits.values().map(Vec::len).sum();
         MergePlannerState {
             ongoing_merge_operations,
+            num_young_splits,
         }
     }
 
@@ -186,10 +188,14 @@ impl MergePlanner {
         ctx: &ActorContext<Self>,
     ) -> Result<Vec<MergeOperation>, ActorExitStatus> {
         let mut merge_operations = Vec::new();
+        let now_utc = OffsetDateTime::now_utc();
         for young_splits in self.partitioned_young_splits.values_mut() {
             if !young_splits.is_empty() {
                 merge_operations.extend(self.merge_policy.operations(young_splits));
             }
+            // Splits may have reached maturity since they were recorded. They will never be
+            // candidates to a merge operation again, so we stop tracking them.
+            young_splits.retain(|split| !split.is_mature(now_utc));
             ctx.record_progress();
             ctx.yield_now().await;
         }
@@ -294,6 +300,8 @@ impl Handler<RefreshMetric> for MergePlanner {
 #[derive(Clone, Debug, Serialize)]
 pub struct MergePlannerState {
     pub(crate) ongoing_merge_operations: Vec<MergeOperation>,
+    /// Number of splits that are still candidates to future merge operations.
+    pub(crate) num_young_splits: usize,
 }
 
 #[cfg(test)]
@@ -426,6 +434,64 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_merge_planner_evicts_splits_that_became_mature() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let (merge_split_downloader_mailbox, merge_split_downloader_inbox) =
+            universe.create_test_mailbox();
+        let pipeline_id = IndexingPipelineId {
+            index_uid: IndexUid::new("test-index"),
+            source_id: "test-source".to_string(),
+            node_id: "test-node".to_string(),
+            pipeline_ord: 0,
+        };
+        let merge_policy = Arc::new(StableLogMergePolicy::new(
+            StableLogMergePolicyConfig {
+                min_level_num_docs: 10_000,
+                merge_factor: 3,
+                max_merge_factor: 5,
+                maturation_period: Duration::from_secs(3600),
+            },
+            50_000,
+        ));
+        let mut merge_planner = MergePlanner::new(
+            pipeline_id,
+            vec![
+                split_metadata_for_test("1_1", 1, 1000, 0),
+                split_metadata_for_test("1_2", 1, 1000, 0),
+                split_metadata_for_test("2_1", 2, 1000, 0),
+            ],
+            merge_policy,
+            merge_split_downloader_mailbox,
+        );
+        // The splits of partition 1 reach maturity while being tracked by the merge planner.
+        for split in merge_planner
+            .partitioned_young_splits
+            .get_mut(&1)
+            .unwrap()
+            .iter_mut()
+        {
+            split.maturity = SplitMaturity::Mature;
+        }
+        let (merge_planner_mailbox, merge_planner_handle) =
+            universe.spawn_builder().spawn(merge_planner);
+        let merge_planner_state = merge_planner_handle.process_pending_and_observe().await;
+        assert_eq!(merge_planner_state.num_young_splits, 1);
+
+        merge_planner_mailbox
+            .send_message(NewSplits {
+                new_splits: vec![split_metadata_for_test("2_2", 2, 1000, 0)],
+            })
+            .await?;
+        let merge_planner_state = merge_planner_handle.process_pending_and_observe().await;
+        assert_eq!(merge_planner_state.num_young_splits, 2);
+
+        let merge_ops = merge_split_downloader_inbox.drain_for_test();
+        assert!(merge_ops.is_empty());
+        universe.assert_quit().await;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_merge_planner_priority() -> anyhow::Result<()> {
         let universe = Universe::with_accelerated_time();
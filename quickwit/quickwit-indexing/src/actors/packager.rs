@@ -26,7 +26,9 @@ use anyhow::{bail, Context};
 use async_trait::async_trait;
 use fail::fail_point;
 use itertools::Itertools;
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Handler, Mailbox, QueueCapacity,
+};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_common::temp_dir::TempDirectory;
 use quickwit_directories::write_hotcache;
@@ -107,6 +109,11 @@ impl Actor for Packager {
         QueueCapacity::Bounded(1)
     }
 
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once its upstream actor has, after processing everything it was sent.
+        false
+    }
+
     fn name(&self) -> String {
         self.actor_name.to_string()
     }
@@ -114,6 +121,16 @@ impl Actor for Packager {
     fn runtime_handle(&self) -> Handle {
         RuntimeType::Blocking.get_runtime_handle()
     }
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_message(&self.uploader_mailbox, checkpoint_barrier.clone())
+            .await?;
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -97,6 +97,11 @@ impl Actor for Publisher {
             PublisherType::MergePublisher => QueueCapacity::Unbounded,
         }
     }
+
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once its upstream actor has, after processing everything it was sent.
+        false
+    }
 }
 
 #[async_trait]
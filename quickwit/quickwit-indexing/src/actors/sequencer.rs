@@ -21,7 +21,9 @@ use std::fmt::Debug;
 
 use anyhow::Context;
 use async_trait::async_trait;
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Handler, Mailbox, QueueCapacity,
+};
 use tokio::sync::oneshot;
 
 /// The sequencer serves as a proxy to another actor,
@@ -53,7 +55,24 @@ impl<A: Actor> Actor for Sequencer<A> {
         QueueCapacity::Bounded(2)
     }
 
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once the uploader has, after processing everything it was sent.
+        false
+    }
+
     fn observable_state(&self) {}
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        // The barrier is queued after the pending messages, so it reaches the targeted actor
+        // after all of the messages sequenced before it.
+        ctx.send_message(&self.mailbox, checkpoint_barrier.clone())
+            .await?;
+        Ok(())
+    }
 }
 
 #[derive(Debug)]
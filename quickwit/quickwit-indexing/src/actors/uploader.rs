@@ -28,7 +28,9 @@ use async_trait::async_trait;
 use fail::fail_point;
 use itertools::Itertools;
 use once_cell::sync::OnceCell;
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox, QueueCapacity};
+use quickwit_actors::{
+    Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Handler, Mailbox, QueueCapacity,
+};
 use quickwit_metastore::checkpoint::IndexCheckpointDelta;
 use quickwit_metastore::{Metastore, SplitMetadata};
 use quickwit_proto::IndexUid;
@@ -251,9 +253,36 @@ impl Actor for Uploader {
         QueueCapacity::Bounded(0)
     }
 
+    fn quit_on_soft_kill(&self) -> bool {
+        // Exits once the packager has, after processing everything it was sent.
+        false
+    }
+
     fn name(&self) -> String {
         format!("{:?}", self.uploader_type)
     }
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        match &self.split_update_mailbox {
+            // The sequencer forwards the barrier to the publisher once the splits uploaded
+            // before it are published.
+            SplitsUpdateMailbox::Sequencer(sequencer_mailbox) => {
+                ctx.send_message(sequencer_mailbox, checkpoint_barrier.clone())
+                    .await?;
+            }
+            // Without a sequencer, the barrier may reach the publisher before the splits
+            // whose upload is still in progress.
+            SplitsUpdateMailbox::Publisher(publisher_mailbox) => {
+                ctx.send_message(publisher_mailbox, checkpoint_barrier.clone())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[async_trait]
@@ -330,13 +359,18 @@ impl Handler<PackagedSplitBatch> for Uploader {
 
                 let mut packaged_splits_and_metadata = Vec::with_capacity(batch.splits.len());
                 for (packaged_split, metadata) in batch.splits.into_iter().zip(split_metadata_list) {
-                    let upload_result = upload_split(
-                        &packaged_split,
-                        &metadata,
-                        &split_store,
-                        counters.clone(),
-                    )
-                    .await;
+                    let upload_result = tokio::select! {
+                        upload_result = upload_split(
+                            &packaged_split,
+                            &metadata,
+                            &split_store,
+                            counters.clone(),
+                        ) => upload_result,
+                        _ = kill_switch.notified() => {
+                            warn!(split_id=packaged_split.split_id(), "Kill switch was activated. Cancelling upload.");
+                            bail!("Upload of split `{}` was cancelled.", packaged_split.split_id());
+                        }
+                    };
 
                     if let Err(cause) = upload_result {
                         warn!(cause=?cause, split_id=packaged_split.split_id(), "Failed to upload split. Killing!");
@@ -466,7 +500,7 @@ mod tests {
     use quickwit_metastore::checkpoint::{IndexCheckpointDelta, SourceCheckpointDelta};
     use quickwit_metastore::MockMetastore;
     use quickwit_proto::indexing::IndexingPipelineId;
-    use quickwit_storage::RamStorage;
+    use quickwit_storage::{RamStorage, Storage};
     use tantivy::DateTime;
     use tokio::sync::oneshot;
 
@@ -576,7 +610,7 @@ mod tests {
             SourceCheckpointDelta::from_range(3..15)
         );
         assert!(replaced_split_ids.is_empty());
-        let mut files = ram_storage.list_files().await;
+        let mut files = ram_storage.list_files().await.unwrap();
         files.sort();
         assert_eq!(&files, &[PathBuf::from("test-split.split")]);
         universe.assert_quit().await;
@@ -714,7 +748,7 @@ mod tests {
         );
         assert!(checkpoint_delta_opt.is_none());
 
-        let mut files = ram_storage.list_files().await;
+        let mut files = ram_storage.list_files().await.unwrap();
         files.sort();
         assert_eq!(
             &files,
@@ -869,7 +903,7 @@ mod tests {
             SourceCheckpointDelta::from_range(3..15)
         );
         assert!(replaced_split_ids.is_empty());
-        let files = ram_storage.list_files().await;
+        let files = ram_storage.list_files().await.unwrap();
         assert!(files.is_empty());
         universe.assert_quit().await;
         Ok(())
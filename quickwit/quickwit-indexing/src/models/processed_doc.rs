@@ -19,6 +19,7 @@
 
 use std::fmt;
 
+use quickwit_actors::MessageSize;
 use quickwit_metastore::checkpoint::SourceCheckpointDelta;
 use tantivy::{DateTime, Document};
 
@@ -45,6 +46,15 @@ pub struct ProcessedDocBatch {
     pub force_commit: bool,
 }
 
+impl MessageSize for ProcessedDocBatch {
+    fn size_in_bytes(&self) -> usize {
+        self.docs
+            .iter()
+            .map(|processed_doc| processed_doc.num_bytes)
+            .sum()
+    }
+}
+
 impl fmt::Debug for ProcessedDocBatch {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ProcessedDocBatch")
@@ -20,6 +20,7 @@
 use std::fmt;
 
 use bytes::Bytes;
+use quickwit_actors::MessageSize;
 use quickwit_metastore::checkpoint::SourceCheckpointDelta;
 
 #[derive(Default)]
@@ -80,3 +81,9 @@ impl fmt::Debug for RawDocBatch {
             .finish()
     }
 }
+
+impl MessageSize for RawDocBatch {
+    fn size_in_bytes(&self) -> usize {
+        self.docs.size_in_bytes()
+    }
+}
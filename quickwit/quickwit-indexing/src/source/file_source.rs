@@ -104,7 +104,8 @@ impl Source for FileSource {
                     .unwrap();
             }
             self.counters.previous_offset = self.counters.current_offset;
-            ctx.send_message(doc_processor_mailbox, doc_batch).await?;
+            ctx.send_sized_message(doc_processor_mailbox, doc_batch)
+                .await?;
         }
         if reached_eof {
             info!("EOF");
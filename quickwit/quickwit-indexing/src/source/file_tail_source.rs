@@ -0,0 +1,874 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::{BTreeMap, HashSet};
+use std::hash::Hasher;
+use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use std::{fmt, mem};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use bytes::Bytes;
+use fnv::FnvHasher;
+use quickwit_actors::{ActorExitStatus, Mailbox};
+use quickwit_config::FileTailSourceParams;
+use quickwit_metastore::checkpoint::{PartitionId, Position, SourceCheckpoint};
+use serde_json::{json, Value as JsonValue};
+use tokio::fs::File;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
+use tracing::{info, warn};
+
+use crate::actors::DocProcessor;
+use crate::models::RawDocBatch;
+use crate::source::file_source::BATCH_NUM_BYTES_LIMIT;
+use crate::source::{Source, SourceContext, SourceExecutionContext, TypedSourceFactory};
+
+/// Interval at which the paths are listed again to discover new and rotated files.
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time to wait before reading the files again once all of them have been read to the end.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Files rotated out of the paths of the source are closed once they have not been written to
+/// for this duration. Writers usually keep appending to a rotated file until they reopen it.
+const CLOSE_ROTATED_FILE_AFTER: Duration = Duration::from_secs(30);
+
+/// Number of bytes at the beginning of a file used to compute its fingerprint.
+const FINGERPRINT_NUM_BYTES: u64 = 1024;
+
+/// Lines longer than this, newline included, are skipped, so that a file without newlines
+/// cannot make the source buffer it whole.
+const MAX_LINE_NUM_BYTES: usize = if cfg!(test) { 16 } else { 10_000_000 };
+
+/// Identifies a file independently of its path, so that a file keeps its partition when it is
+/// renamed by a log rotation.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, Ord, PartialOrd)]
+struct FileId {
+    device: u64,
+    inode: u64,
+}
+
+impl FileId {
+    fn from_metadata(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+        }
+    }
+
+    fn partition_id(&self) -> PartitionId {
+        PartitionId::from(format!("{}:{}", self.device, self.inode))
+    }
+}
+
+/// Position of a tailed file, as recorded in the checkpoint.
+#[derive(Debug, Default, Eq, PartialEq)]
+struct FilePosition {
+    /// Number of times the file was truncated or replaced.
+    num_truncations: u64,
+    /// Byte offset within the current content of the file.
+    offset: u64,
+    /// Fingerprint of the first `min(offset, FINGERPRINT_NUM_BYTES)` bytes of the file. It
+    /// detects files that were deleted and replaced by a file reusing the same inode. `None`
+    /// for positions recorded before fingerprints were introduced.
+    fingerprint_opt: Option<u64>,
+}
+
+/// Builds the position of a tailed file: the number of times the file was truncated, followed
+/// by a byte offset within the current content of the file, and the fingerprint of the file.
+/// The numbers are zero-padded so that positions keep increasing across truncations.
+fn position(num_truncations: u64, offset: u64, fingerprint: u64) -> Position {
+    Position::from(format!(
+        "{num_truncations:0>20}:{offset:0>20}:{fingerprint:0>16x}"
+    ))
+}
+
+/// Parses a position built with `position`.
+fn parse_position(position: &Position) -> anyhow::Result<FilePosition> {
+    let Position::Offset(position_str) = position else {
+        return Ok(FilePosition::default());
+    };
+    let parse_error = || format!("Failed to parse file tail position `{position_str}`.");
+    let mut parts = position_str.split(':');
+    let (Some(num_truncations_str), Some(offset_str)) = (parts.next(), parts.next()) else {
+        anyhow::bail!(parse_error());
+    };
+    let num_truncations = num_truncations_str
+        .parse::<u64>()
+        .with_context(parse_error)?;
+    let offset = offset_str.parse::<u64>().with_context(parse_error)?;
+    let fingerprint_opt = parts
+        .next()
+        .map(|fingerprint_str| u64::from_str_radix(fingerprint_str, 16))
+        .transpose()
+        .with_context(parse_error)?;
+    if parts.next().is_some() {
+        anyhow::bail!(parse_error());
+    }
+    Ok(FilePosition {
+        num_truncations,
+        offset,
+        fingerprint_opt,
+    })
+}
+
+/// Computes the fingerprint of the first bytes of a file incrementally, as its lines are read.
+#[derive(Default)]
+struct Fingerprinter {
+    hasher: FnvHasher,
+    num_bytes: u64,
+}
+
+impl Fingerprinter {
+    fn update(&mut self, bytes: &[u8]) {
+        let num_bytes_left = FINGERPRINT_NUM_BYTES.saturating_sub(self.num_bytes) as usize;
+        let bytes = &bytes[..bytes.len().min(num_bytes_left)];
+        self.hasher.write(bytes);
+        self.num_bytes += bytes.len() as u64;
+    }
+
+    fn fingerprint(&self) -> u64 {
+        self.hasher.finish()
+    }
+}
+
+struct TailedFile {
+    file_id: FileId,
+    path: PathBuf,
+    reader: BufReader<File>,
+    num_truncations: u64,
+    /// Offset of the end of the last complete line read from the file.
+    offset: u64,
+    /// Fingerprint of the bytes of the file up to `offset`.
+    fingerprinter: Fingerprinter,
+    /// Beginning of a line that is still being written.
+    partial_line: Vec<u8>,
+    /// Whether the bytes read are the end of a line longer than `MAX_LINE_NUM_BYTES`.
+    skipping_oversized_line: bool,
+    /// Position up to which the lines of the file have been emitted.
+    emitted_position: Position,
+    /// Whether the file still matches the paths of the source. Files that were rotated out of
+    /// the paths are read to the end, then closed.
+    is_matched: bool,
+    last_read_at: Instant,
+}
+
+impl TailedFile {
+    async fn open(path: PathBuf, emitted_position: Position) -> anyhow::Result<(TailedFile, bool)> {
+        let mut file = File::open(&path)
+            .await
+            .with_context(|| format!("Failed to open source file `{}`.", path.display()))?;
+        let metadata = file.metadata().await?;
+        let FilePosition {
+            mut num_truncations,
+            mut offset,
+            fingerprint_opt,
+        } = parse_position(&emitted_position)?;
+        // The file is shorter than the position recorded in the checkpoint: it was truncated
+        // while the source was not running.
+        let mut truncated = metadata.len() < offset;
+        let mut fingerprinter = Fingerprinter::default();
+
+        if !truncated {
+            let mut first_bytes = vec![0u8; offset.min(FINGERPRINT_NUM_BYTES) as usize];
+            file.read_exact(&mut first_bytes).await?;
+            fingerprinter.update(&first_bytes);
+            // The file does not start with the bytes that were read: it was deleted and
+            // replaced by a new file reusing the same inode.
+            truncated = fingerprint_opt
+                .map(|fingerprint| fingerprint != fingerprinter.fingerprint())
+                .unwrap_or(false);
+        }
+        if truncated {
+            num_truncations += 1;
+            offset = 0;
+            fingerprinter = Fingerprinter::default();
+        }
+        file.seek(SeekFrom::Start(offset)).await?;
+
+        let tailed_file = TailedFile {
+            file_id: FileId::from_metadata(&metadata),
+            path,
+            reader: BufReader::new(file),
+            num_truncations,
+            offset,
+            fingerprinter,
+            partial_line: Vec::new(),
+            skipping_oversized_line: false,
+            emitted_position,
+            is_matched: true,
+            last_read_at: Instant::now(),
+        };
+        Ok((tailed_file, truncated))
+    }
+
+    fn position(&self) -> Position {
+        position(
+            self.num_truncations,
+            self.offset,
+            self.fingerprinter.fingerprint(),
+        )
+    }
+
+    /// Starts reading the file from the beginning again if it was truncated.
+    async fn detect_truncation(&mut self) -> anyhow::Result<bool> {
+        let file_len = self.reader.get_ref().metadata().await?.len();
+        let read_len = self.offset + self.partial_line.len() as u64;
+
+        if file_len >= read_len {
+            return Ok(false);
+        }
+        self.reader.seek(SeekFrom::Start(0)).await?;
+        self.num_truncations += 1;
+        self.offset = 0;
+        self.fingerprinter = Fingerprinter::default();
+        self.partial_line.clear();
+        self.skipping_oversized_line = false;
+        Ok(true)
+    }
+
+    /// Reads complete lines until `num_bytes_limit` is reached or the end of the file, and
+    /// returns the number of bytes read. Lines longer than `MAX_LINE_NUM_BYTES` are skipped.
+    async fn read_lines(
+        &mut self,
+        docs: &mut Vec<Bytes>,
+        num_bytes_limit: u64,
+    ) -> anyhow::Result<u64> {
+        let mut num_bytes_read = 0;
+
+        while num_bytes_read < num_bytes_limit {
+            let max_num_bytes = (MAX_LINE_NUM_BYTES - self.partial_line.len()) as u64;
+            let num_bytes = (&mut self.reader)
+                .take(max_num_bytes)
+                .read_until(b'\n', &mut self.partial_line)
+                .await?;
+            let is_complete_line = self.partial_line.ends_with(b"\n");
+            // A line without a trailing newline is still being written: we keep it aside until
+            // its end is written.
+            if num_bytes == 0 || (!is_complete_line && self.partial_line.len() < MAX_LINE_NUM_BYTES)
+            {
+                break;
+            }
+            let line = mem::take(&mut self.partial_line);
+            num_bytes_read += line.len() as u64;
+            self.offset += line.len() as u64;
+            self.fingerprinter.update(&line);
+            self.last_read_at = Instant::now();
+
+            if self.skipping_oversized_line || !is_complete_line {
+                if !self.skipping_oversized_line {
+                    warn!(
+                        path=%self.path.display(),
+                        offset=self.offset - line.len() as u64,
+                        "Skipping line longer than {MAX_LINE_NUM_BYTES} bytes."
+                    );
+                }
+                self.skipping_oversized_line = !is_complete_line;
+                continue;
+            }
+            docs.push(Bytes::from(line));
+        }
+        Ok(num_bytes_read)
+    }
+
+    fn should_close(&self) -> bool {
+        !self.is_matched && self.last_read_at.elapsed() >= CLOSE_ROTATED_FILE_AFTER
+    }
+}
+
+/// A `FileTailSource` tails the local files matching a list of paths or glob patterns, and
+/// forwards their lines to the doc processor.
+///
+/// Each file is a partition identified by its device and inode numbers, so that a rotated file
+/// is read to the end without being indexed again under its new name. Truncated files, and files
+/// replaced by a new file reusing the same inode, are read again from the beginning.
+///
+/// The source reads files local to the node it runs on, so it runs on a single pipeline.
+pub struct FileTailSource {
+    source_id: String,
+    patterns: Vec<String>,
+    tailed_files: Vec<TailedFile>,
+    /// Round-robin cursor over the tailed files, so that a busy file cannot starve the others.
+    next_file_ord: usize,
+    /// Positions of the files that were emitted or recorded in the checkpoint.
+    positions: BTreeMap<PartitionId, Position>,
+    last_scan_opt: Option<Instant>,
+    num_bytes_processed: u64,
+    num_lines_processed: u64,
+    num_rotations: u64,
+    num_truncations: u64,
+}
+
+impl fmt::Debug for FileTailSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FileTailSource")
+            .field("source_id", &self.source_id)
+            .field("patterns", &self.patterns)
+            .finish()
+    }
+}
+
+impl FileTailSource {
+    fn new(source_id: String, params: FileTailSourceParams, checkpoint: SourceCheckpoint) -> Self {
+        Self {
+            source_id,
+            patterns: params.paths,
+            tailed_files: Vec::new(),
+            next_file_ord: 0,
+            positions: checkpoint.iter().collect(),
+            last_scan_opt: None,
+            num_bytes_processed: 0,
+            num_lines_processed: 0,
+            num_rotations: 0,
+            num_truncations: 0,
+        }
+    }
+
+    /// Lists the files matching the paths of the source, opens the new ones, and tracks the
+    /// ones that were renamed.
+    async fn scan(&mut self) -> anyhow::Result<()> {
+        let patterns = self.patterns.clone();
+        let matched_files = tokio::task::spawn_blocking(move || list_files(&patterns)).await??;
+
+        let previously_matched_file_ids: HashSet<FileId> = self
+            .tailed_files
+            .iter()
+            .filter(|tailed_file| tailed_file.is_matched)
+            .map(|tailed_file| tailed_file.file_id)
+            .collect();
+        for tailed_file in &mut self.tailed_files {
+            tailed_file.is_matched = false;
+        }
+        for (file_id, path) in matched_files {
+            if let Some(tailed_file) = self
+                .tailed_files
+                .iter_mut()
+                .find(|tailed_file| tailed_file.file_id == file_id)
+            {
+                if tailed_file.path != path {
+                    info!(
+                        source_id=%self.source_id,
+                        from=%tailed_file.path.display(),
+                        to=%path.display(),
+                        "File was rotated."
+                    );
+                    tailed_file.path = path;
+                    self.num_rotations += 1;
+                }
+                tailed_file.is_matched = true;
+                continue;
+            }
+            let emitted_position = self
+                .positions
+                .get(&file_id.partition_id())
+                .cloned()
+                .unwrap_or_default();
+            let (tailed_file, truncated) = match TailedFile::open(path, emitted_position).await {
+                Ok(tailed_file_and_truncated) => tailed_file_and_truncated,
+                Err(error) => {
+                    // The file may have been removed since it was listed.
+                    warn!(source_id=%self.source_id, error=?error, "Failed to open file.");
+                    continue;
+                }
+            };
+            if truncated {
+                self.num_truncations += 1;
+            }
+            info!(
+                source_id=%self.source_id,
+                path=%tailed_file.path.display(),
+                offset=%tailed_file.offset,
+                "Start tailing file."
+            );
+            self.tailed_files.push(tailed_file);
+        }
+        for tailed_file in &self.tailed_files {
+            if !tailed_file.is_matched && previously_matched_file_ids.contains(&tailed_file.file_id)
+            {
+                info!(
+                    source_id=%self.source_id,
+                    path=%tailed_file.path.display(),
+                    "File was rotated out of the paths."
+                );
+                self.num_rotations += 1;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lists the regular files matching the patterns. Directories are expanded to the files they
+/// contain.
+fn list_files(patterns: &[String]) -> anyhow::Result<Vec<(FileId, PathBuf)>> {
+    let mut file_ids = HashSet::new();
+    let mut files = Vec::new();
+
+    let mut add_file = |path: PathBuf| {
+        // The file may have been removed since it was listed.
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            return;
+        };
+        if metadata.is_file() && file_ids.insert(FileId::from_metadata(&metadata)) {
+            files.push((FileId::from_metadata(&metadata), path));
+        }
+    };
+    for pattern in patterns {
+        let paths = glob::glob(pattern)
+            .with_context(|| format!("Failed to parse glob pattern `{pattern}`."))?;
+
+        for path_res in paths {
+            let path = match path_res {
+                Ok(path) => path,
+                Err(error) => {
+                    warn!(pattern=%pattern, error=?error, "Failed to list path.");
+                    continue;
+                }
+            };
+            if !path.is_dir() {
+                add_file(path);
+                continue;
+            }
+            let Ok(entries) = std::fs::read_dir(&path) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                add_file(entry.path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+#[async_trait]
+impl Source for FileTailSource {
+    async fn emit_batches(
+        &mut self,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        ctx: &SourceContext,
+    ) -> Result<Duration, ActorExitStatus> {
+        let scan_is_due = self
+            .last_scan_opt
+            .map(|last_scan| last_scan.elapsed() >= SCAN_INTERVAL)
+            .unwrap_or(true);
+        if scan_is_due {
+            ctx.protect_future(self.scan()).await?;
+            self.last_scan_opt = Some(Instant::now());
+        }
+        let mut doc_batch = RawDocBatch::default();
+        let mut num_bytes_read = 0;
+        let num_files = self.tailed_files.len();
+        let first_file_ord = self.next_file_ord;
+
+        for file_ord in (0..num_files).map(|offset| (first_file_ord + offset) % num_files) {
+            if num_bytes_read >= BATCH_NUM_BYTES_LIMIT {
+                self.next_file_ord = file_ord;
+                break;
+            }
+            let tailed_file = &mut self.tailed_files[file_ord];
+
+            if ctx.protect_future(tailed_file.detect_truncation()).await? {
+                info!(
+                    source_id=%self.source_id,
+                    path=%tailed_file.path.display(),
+                    "File was truncated."
+                );
+                self.num_truncations += 1;
+            }
+            let num_bytes = ctx
+                .protect_future(
+                    tailed_file
+                        .read_lines(&mut doc_batch.docs, BATCH_NUM_BYTES_LIMIT - num_bytes_read),
+                )
+                .await?;
+            num_bytes_read += num_bytes;
+
+            if num_bytes > 0 {
+                let position = tailed_file.position();
+                let partition_id = tailed_file.file_id.partition_id();
+                doc_batch
+                    .checkpoint_delta
+                    .record_partition_delta(
+                        partition_id.clone(),
+                        tailed_file.emitted_position.clone(),
+                        position.clone(),
+                    )
+                    .context("Failed to record partition delta.")?;
+                tailed_file.emitted_position = position.clone();
+                self.positions.insert(partition_id, position);
+            }
+        }
+        if self.tailed_files.iter().any(TailedFile::should_close) {
+            let source_id = &self.source_id;
+            self.tailed_files.retain(|tailed_file| {
+                let should_close = tailed_file.should_close();
+                if should_close {
+                    info!(
+                        source_id=%source_id,
+                        path=%tailed_file.path.display(),
+                        "Stop tailing rotated file."
+                    );
+                }
+                !should_close
+            });
+            self.next_file_ord = 0;
+        }
+        if doc_batch.docs.is_empty() {
+            return Ok(POLL_INTERVAL);
+        }
+        self.num_bytes_processed += num_bytes_read;
+        self.num_lines_processed += doc_batch.docs.len() as u64;
+        ctx.send_sized_message(doc_processor_mailbox, doc_batch)
+            .await?;
+        Ok(Duration::default())
+    }
+
+    fn name(&self) -> String {
+        format!("FileTailSource{{source_id={}}}", self.source_id)
+    }
+
+    fn observable_state(&self) -> JsonValue {
+        let tailed_files: Vec<&Path> = self
+            .tailed_files
+            .iter()
+            .map(|tailed_file| tailed_file.path.as_path())
+            .collect();
+        json!({
+            "tailed_files": tailed_files,
+            "num_bytes_processed": self.num_bytes_processed,
+            "num_lines_processed": self.num_lines_processed,
+            "num_rotations": self.num_rotations,
+            "num_truncations": self.num_truncations,
+        })
+    }
+}
+
+pub struct FileTailSourceFactory;
+
+#[async_trait]
+impl TypedSourceFactory for FileTailSourceFactory {
+    type Source = FileTailSource;
+    type Params = FileTailSourceParams;
+
+    async fn typed_create_source(
+        ctx: Arc<SourceExecutionContext>,
+        params: FileTailSourceParams,
+        checkpoint: SourceCheckpoint,
+    ) -> anyhow::Result<FileTailSource> {
+        let file_tail_source =
+            FileTailSource::new(ctx.source_config.source_id.clone(), params, checkpoint);
+        Ok(file_tail_source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    use quickwit_actors::{ActorContext, Inbox, Universe};
+    use tokio::sync::watch;
+
+    use super::*;
+    use crate::source::SourceActor;
+
+    fn append(path: &Path, content: &str) {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+    }
+
+    /// Scans the paths of the source, emits the new lines, and applies the checkpoint deltas
+    /// of the emitted batches to `checkpoint`.
+    async fn emit_batch(
+        source: &mut FileTailSource,
+        ctx: &SourceContext,
+        doc_processor_mailbox: &Mailbox<DocProcessor>,
+        doc_processor_inbox: &Inbox<DocProcessor>,
+        checkpoint: &mut SourceCheckpoint,
+        expected_docs: &[&str],
+    ) {
+        source.last_scan_opt = None;
+        source
+            .emit_batches(doc_processor_mailbox, ctx)
+            .await
+            .unwrap();
+        let batches: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+        let docs: Vec<&str> = batches
+            .iter()
+            .flat_map(|batch| batch.docs.iter())
+            .map(|doc| std::str::from_utf8(doc).unwrap())
+            .collect();
+        assert_eq!(docs, expected_docs);
+
+        for batch in batches {
+            checkpoint.try_apply_delta(batch.checkpoint_delta).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_file_tail_position() {
+        assert_eq!(
+            parse_position(&Position::Beginning).unwrap(),
+            FilePosition::default()
+        );
+        assert_eq!(
+            parse_position(&position(3, 42, 0xabc)).unwrap(),
+            FilePosition {
+                num_truncations: 3,
+                offset: 42,
+                fingerprint_opt: Some(0xabc),
+            }
+        );
+        assert_eq!(
+            parse_position(&Position::from(format!("{:0>20}:{:0>20}", 3, 42))).unwrap(),
+            FilePosition {
+                num_truncations: 3,
+                offset: 42,
+                fingerprint_opt: None,
+            }
+        );
+        assert!(position(0, 1000, u64::MAX) < position(1, 0, 0));
+        assert!(parse_position(&Position::from(42u64)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_tail_source() {
+        let universe = Universe::with_accelerated_time();
+        let (source_mailbox, _source_inbox) = universe.create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(json!({}));
+        let ctx: ActorContext<SourceActor> =
+            ActorContext::for_test(&universe, source_mailbox, observable_state_tx);
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("app.log");
+        let rotated_log_path = temp_dir.path().join("app.log.1");
+        let params = FileTailSourceParams {
+            paths: vec![format!("{}/*.log", temp_dir.path().display())],
+        };
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut source = FileTailSource::new(
+            "test-file-tail-source".to_string(),
+            params.clone(),
+            SourceCheckpoint::default(),
+        );
+        // The last line is still being written.
+        append(&log_path, "a\nb\nc");
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["a\n", "b\n"],
+        )
+        .await;
+
+        append(&log_path, "\nd\n");
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["c\n", "d\n"],
+        )
+        .await;
+
+        // The file is rotated, and its writer appends a last line before reopening it.
+        std::fs::rename(&log_path, &rotated_log_path).unwrap();
+        append(&rotated_log_path, "e\n");
+        append(&log_path, "f\n");
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["e\n", "f\n"],
+        )
+        .await;
+        assert_eq!(source.num_rotations, 1);
+
+        // The new file is truncated.
+        OpenOptions::new()
+            .write(true)
+            .open(&log_path)
+            .unwrap()
+            .set_len(0)
+            .unwrap();
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &[],
+        )
+        .await;
+        assert_eq!(source.num_truncations, 1);
+
+        append(&log_path, "g\n");
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["g\n"],
+        )
+        .await;
+        assert_eq!(checkpoint.num_partitions(), 2);
+
+        // After a restart, the source resumes from the checkpoint.
+        append(&log_path, "h\n");
+        let mut source = FileTailSource::new(
+            "test-file-tail-source".to_string(),
+            params,
+            checkpoint.clone(),
+        );
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["h\n"],
+        )
+        .await;
+
+        let observable_state = source.observable_state();
+        assert_eq!(observable_state["tailed_files"], json!([log_path]));
+        assert_eq!(observable_state["num_lines_processed"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_tail_source_detects_replaced_file() {
+        let universe = Universe::with_accelerated_time();
+        let (source_mailbox, _source_inbox) = universe.create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(json!({}));
+        let ctx: ActorContext<SourceActor> =
+            ActorContext::for_test(&universe, source_mailbox, observable_state_tx);
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("app.log");
+        let params = FileTailSourceParams {
+            paths: vec![log_path.display().to_string()],
+        };
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut source = FileTailSource::new(
+            "test-file-tail-source".to_string(),
+            params.clone(),
+            SourceCheckpoint::default(),
+        );
+        append(&log_path, "a\nb\n");
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["a\n", "b\n"],
+        )
+        .await;
+
+        // While the source is stopped, the file is replaced by a longer file with the same
+        // inode, as when an inode is reused.
+        std::fs::write(&log_path, "x\ny\nz\n").unwrap();
+        let mut source = FileTailSource::new(
+            "test-file-tail-source".to_string(),
+            params,
+            checkpoint.clone(),
+        );
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["x\n", "y\n", "z\n"],
+        )
+        .await;
+        assert_eq!(source.num_truncations, 1);
+    }
+
+    #[tokio::test]
+    async fn test_file_tail_source_skips_oversized_lines() {
+        let universe = Universe::with_accelerated_time();
+        let (source_mailbox, _source_inbox) = universe.create_test_mailbox();
+        let (observable_state_tx, _observable_state_rx) = watch::channel(json!({}));
+        let ctx: ActorContext<SourceActor> =
+            ActorContext::for_test(&universe, source_mailbox, observable_state_tx);
+        let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("app.log");
+        let params = FileTailSourceParams {
+            paths: vec![log_path.display().to_string()],
+        };
+        let mut checkpoint = SourceCheckpoint::default();
+        let mut source = FileTailSource::new(
+            "test-file-tail-source".to_string(),
+            params,
+            checkpoint.clone(),
+        );
+        let oversized_line = "x".repeat(MAX_LINE_NUM_BYTES + 4);
+        append(&log_path, &format!("a\n{oversized_line}\nb\n"));
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["a\n", "b\n"],
+        )
+        .await;
+
+        // The oversized line is still being written.
+        append(&log_path, &oversized_line);
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &[],
+        )
+        .await;
+
+        append(&log_path, "x\nc\n");
+        emit_batch(
+            &mut source,
+            &ctx,
+            &doc_processor_mailbox,
+            &doc_processor_inbox,
+            &mut checkpoint,
+            &["c\n"],
+        )
+        .await;
+    }
+}
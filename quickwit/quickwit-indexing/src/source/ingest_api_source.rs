@@ -166,7 +166,7 @@ impl Source for IngestApiSource {
             .map_err(anyhow::Error::from)?;
 
         self.update_counters(current_offset, raw_doc_batch.docs.len() as u64);
-        ctx.send_message(batch_sink, raw_doc_batch).await?;
+        ctx.send_sized_message(batch_sink, raw_doc_batch).await?;
         Ok(Duration::default())
     }
 
@@ -244,6 +244,7 @@ impl KafkaSource {
         _ignored_checkpoint: SourceCheckpoint,
     ) -> anyhow::Result<Self> {
         let topic = params.topic.clone();
+        let partitions = params.partitions.clone();
         let backfill_mode_enabled = params.enable_backfill_mode;
 
         let (events_tx, events_rx) = mpsc::channel(100);
@@ -262,13 +263,15 @@ impl KafkaSource {
             .get("max.poll.interval.ms")?
             .parse::<u64>()?;
 
-        let poll_loop_jh = spawn_consumer_poll_loop(consumer, topic.clone(), events_tx);
+        let poll_loop_jh =
+            spawn_consumer_poll_loop(consumer, topic.clone(), partitions.clone(), events_tx);
         let publish_lock = PublishLock::default();
 
         info!(
             index_id=%ctx.index_uid.index_id(),
             source_id=%ctx.source_config.source_id,
             topic=%topic,
+            partitions=?partitions,
             group_id=%group_id,
             max_poll_interval_ms=%max_poll_interval_ms,
             session_timeout_ms=%session_timeout_ms,
@@ -525,7 +528,8 @@ impl Source for KafkaSource {
                 num_millis=%now.elapsed().as_millis(),
                 "Sending doc batch to indexer.");
             let message = batch.build();
-            ctx.send_message(doc_processor_mailbox, message).await?;
+            ctx.send_sized_message(doc_processor_mailbox, message)
+                .await?;
         }
         if self.should_exit() {
             info!(topic = %self.topic, "Reached end of topic.");
@@ -581,22 +585,48 @@ impl Source for KafkaSource {
 // callback is sync. Until `rust-rdkafka` offers a fully asynchronous API, we poll the consumer in a
 // blocking tokio task and handle the rebalance events via message passing between the rebalance
 // callback and the source.
+//
+// When the source is configured with an explicit list of partitions, the consumer does not join
+// the consumer group: the partitions are assigned once, at startup, from the positions recorded
+// in the checkpoint.
 fn spawn_consumer_poll_loop(
     consumer: RdKafkaConsumer,
     topic: String,
+    partitions: Vec<i32>,
     events_tx: mpsc::Sender<KafkaEvent>,
 ) -> JoinHandle<()> {
     spawn_blocking(move || {
-        // `subscribe()` returns immediately but triggers the execution of synchronous code (e.g.
-        // rebalance callback) so it must be called in a blocking task.
-        //
-        // From the librdkafka docs:
-        // `subscribe()` is an asynchronous method which returns immediately: background threads
-        // will (re)join the group, wait for group rebalance, issue any registered rebalance_cb,
-        // assign() the assigned partitions, and then start fetching messages.
-        if let Err(error) = consumer.subscribe(&[&topic]) {
-            let _ = events_tx.blocking_send(KafkaEvent::Error(anyhow!(error)));
-            return;
+        let consumer_group_enabled = partitions.is_empty();
+
+        if consumer_group_enabled {
+            // `subscribe()` returns immediately but triggers the execution of synchronous code
+            // (e.g. rebalance callback) so it must be called in a blocking task.
+            //
+            // From the librdkafka docs:
+            // `subscribe()` is an asynchronous method which returns immediately: background
+            // threads will (re)join the group, wait for group rebalance, issue any registered
+            // rebalance_cb, assign() the assigned partitions, and then start fetching messages.
+            if let Err(error) = consumer.subscribe(&[&topic]) {
+                let _ = events_tx.blocking_send(KafkaEvent::Error(anyhow!(error)));
+                return;
+            }
+        } else {
+            let (assignment_tx, assignment_rx) = oneshot::channel();
+            return_if_err!(
+                events_tx.blocking_send(KafkaEvent::AssignPartitions {
+                    partitions,
+                    assignment_tx,
+                }),
+                "Failed to send assign message to source."
+            );
+            let assignment = return_if_err!(
+                assignment_rx.recv(),
+                "Failed to receive assignment from source."
+            );
+            if let Err(error) = assign_partitions(&consumer, &topic, assignment) {
+                let _ = events_tx.blocking_send(KafkaEvent::Error(error));
+                return;
+            }
         }
         while !events_tx.is_closed() {
             if let Some(message_res) = consumer.poll(Some(Duration::from_secs(1))) {
@@ -617,10 +647,33 @@ fn spawn_consumer_poll_loop(
             }
         }
         debug!("Exiting consumer poll loop.");
-        consumer.unsubscribe();
+
+        if consumer_group_enabled {
+            consumer.unsubscribe();
+        } else if let Err(error) = consumer.unassign() {
+            warn!(topic=%topic, error=?error, "Failed to unassign partitions.");
+        }
     })
 }
 
+/// Assigns the partitions to the consumer, starting at the given offsets.
+fn assign_partitions(
+    consumer: &RdKafkaConsumer,
+    topic: &str,
+    assignment: Vec<(i32, Offset)>,
+) -> anyhow::Result<()> {
+    let mut tpl = TopicPartitionList::with_capacity(assignment.len());
+
+    for (partition, offset) in assignment {
+        tpl.add_partition_offset(topic, partition, offset)
+            .with_context(|| format!("Failed to set offset for partition `{partition}`."))?;
+    }
+    consumer
+        .assign(&tpl)
+        .with_context(|| format!("Failed to assign partitions of topic `{topic}`."))?;
+    Ok(())
+}
+
 /// Returns the preceding `Position` for the offset.
 fn previous_position_for_offset(offset: i64) -> Position {
     if offset == 0 {
@@ -657,6 +710,18 @@ pub(super) async fn check_connectivity(params: KafkaSourceParams) -> anyhow::Res
     if topic_metadata.partitions().is_empty() {
         bail!("Topic `{}` has no partitions.", params.topic);
     }
+    for partition in &params.partitions {
+        if !topic_metadata
+            .partitions()
+            .iter()
+            .any(|partition_metadata| partition_metadata.id() == *partition)
+        {
+            bail!(
+                "Partition `{partition}` does not exist in topic `{}`.",
+                params.topic
+            );
+        }
+    }
     Ok(())
 }
 
@@ -884,6 +949,7 @@ mod kafka_broker_tests {
                     "bootstrap.servers": "localhost:9092",
                 }),
                 enable_backfill_mode: true,
+                partitions: Vec::new(),
             }),
             transform_config: None,
             input_format: SourceInputFormat::Json,
@@ -1428,6 +1494,71 @@ mod kafka_broker_tests {
             });
             assert_eq!(exit_state, expected_exit_state);
         }
+        {
+            // The source consumes an explicit list of partitions.
+            let metastore = metastore_for_test();
+            let index_id = append_random_suffix("test-kafka-source--index");
+            let (source_id, mut source_config) = get_source_config(&topic);
+            if let SourceParams::Kafka(params) = &mut source_config.source_params {
+                params.partitions = vec![1, 2];
+            }
+            let index_uid =
+                setup_index(metastore.clone(), &index_id, &source_id, &[(1, -1, 0)]).await;
+            let source = source_loader
+                .load_source(
+                    Arc::new(SourceExecutionContext {
+                        metastore,
+                        index_uid,
+                        queues_dir_path: PathBuf::from("./queues"),
+                        source_config,
+                    }),
+                    SourceCheckpoint::default(),
+                )
+                .await?;
+
+            let (doc_processor_mailbox, doc_processor_inbox) = universe.create_test_mailbox();
+            let source_actor = SourceActor {
+                source,
+                doc_processor_mailbox: doc_processor_mailbox.clone(),
+            };
+            let (_source_mailbox, source_handle) = universe.spawn_builder().spawn(source_actor);
+            let (exit_status, exit_state) = source_handle.join().await;
+            assert!(exit_status.is_success());
+
+            let messages: Vec<RawDocBatch> = doc_processor_inbox.drain_for_test_typed();
+            assert!(!messages.is_empty());
+
+            let batch = merge_doc_batches(messages)?;
+            let expected_docs = vec!["Message #102", "Message #200", "Message #202"];
+            assert_eq!(batch.docs, expected_docs);
+
+            let mut expected_checkpoint_delta = SourceCheckpointDelta::default();
+            expected_checkpoint_delta.record_partition_delta(
+                PartitionId::from(1u64),
+                Position::from(0u64),
+                Position::from(2u64),
+            )?;
+            expected_checkpoint_delta.record_partition_delta(
+                PartitionId::from(2u64),
+                Position::Beginning,
+                Position::from(2u64),
+            )?;
+            assert_eq!(batch.checkpoint_delta, expected_checkpoint_delta);
+
+            let expected_exit_state = json!({
+                "index_id": index_id,
+                "source_id": source_id,
+                "topic":  topic,
+                "assigned_partitions": vec![1, 2],
+                "current_positions":  vec![(1, "00000000000000000002"), (2, "00000000000000000002")],
+                "num_inactive_partitions": 2,
+                "num_bytes_processed": 36,
+                "num_messages_processed": 5,
+                "num_invalid_messages": 2,
+                "num_rebalances": 0,
+            });
+            assert_eq!(exit_state, expected_exit_state);
+        }
         Ok(())
     }
 
@@ -1445,6 +1576,7 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            partitions: Vec::new(),
         })
         .await
         .unwrap();
@@ -1456,6 +1588,18 @@ mod kafka_broker_tests {
             client_log_level: None,
             client_params: json!({ "bootstrap.servers": bootstrap_servers }),
             enable_backfill_mode: true,
+            partitions: Vec::new(),
+        })
+        .await
+        .unwrap_err();
+
+        // Non existent partition should throw an error.
+        check_connectivity(KafkaSourceParams {
+            topic: topic.clone(),
+            client_log_level: None,
+            client_params: json!({ "bootstrap.servers": bootstrap_servers }),
+            enable_backfill_mode: true,
+            partitions: vec![1],
         })
         .await
         .unwrap_err();
@@ -1468,6 +1612,7 @@ mod kafka_broker_tests {
                 "bootstrap.servers": "192.0.2.10:9092"
             }),
             enable_backfill_mode: true,
+            partitions: Vec::new(),
         })
         .await
         .unwrap_err();
@@ -55,9 +55,12 @@
 //! Right now two sources are implemented in quickwit.
 //! - the file source: there partition here is a filepath, and the position is a byte-offset within
 //!   that file.
+//! - the file tail source: the partition is a file identified by its inode, and the position is a
+//!   byte-offset within that file, prefixed by the number of times the file was truncated.
 //! - the kafka source: the partition id is a kafka topic partition id, and the position is a kafka
 //!   offset.
 mod file_source;
+mod file_tail_source;
 mod ingest_api_source;
 #[cfg(feature = "kafka")]
 mod kafka_source;
@@ -73,9 +76,10 @@ use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::bail;
+use anyhow::{bail, Context};
 use async_trait::async_trait;
 pub use file_source::{FileSource, FileSourceFactory};
+pub use file_tail_source::{FileTailSource, FileTailSourceFactory};
 #[cfg(feature = "kafka")]
 pub use kafka_source::{KafkaSource, KafkaSourceFactory};
 #[cfg(feature = "kinesis")]
@@ -83,7 +87,7 @@ pub use kinesis::kinesis_source::{KinesisSource, KinesisSourceFactory};
 use once_cell::sync::OnceCell;
 #[cfg(feature = "pulsar")]
 pub use pulsar_source::{PulsarSource, PulsarSourceFactory};
-use quickwit_actors::{Actor, ActorContext, ActorExitStatus, Handler, Mailbox};
+use quickwit_actors::{Actor, ActorContext, ActorExitStatus, CheckpointBarrier, Handler, Mailbox};
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_config::{SourceConfig, SourceParams};
 use quickwit_metastore::checkpoint::SourceCheckpoint;
@@ -260,6 +264,23 @@ impl Actor for SourceActor {
         ctx: &SourceContext,
     ) -> anyhow::Result<()> {
         self.source.finalize(exit_status, ctx).await?;
+        // When quitting gracefully, e.g. on a soft kill, the downstream actors process the
+        // batches already emitted before exiting in turn.
+        if matches!(exit_status, ActorExitStatus::Quit) {
+            let _ = ctx
+                .send_exit_with_success(&self.doc_processor_mailbox)
+                .await;
+        }
+        Ok(())
+    }
+
+    async fn forward_checkpoint_barrier(
+        &mut self,
+        checkpoint_barrier: &CheckpointBarrier,
+        ctx: &SourceContext,
+    ) -> Result<(), ActorExitStatus> {
+        ctx.send_message(&self.doc_processor_mailbox, checkpoint_barrier.clone())
+            .await?;
         Ok(())
     }
 }
@@ -287,6 +308,7 @@ pub fn quickwit_supported_sources() -> &'static SourceLoader {
     SOURCE_LOADER.get_or_init(|| {
         let mut source_factory = SourceLoader::default();
         source_factory.add_source("file", FileSourceFactory);
+        source_factory.add_source("file-tail", FileTailSourceFactory);
         #[cfg(feature = "kafka")]
         source_factory.add_source("kafka", KafkaSourceFactory);
         #[cfg(feature = "kinesis")]
@@ -310,6 +332,13 @@ pub async fn check_source_connectivity(source_config: &SourceConfig) -> anyhow::
             }
             Ok(())
         }
+        SourceParams::FileTail(params) => {
+            for path in &params.paths {
+                glob::Pattern::new(path)
+                    .with_context(|| format!("Failed to parse glob pattern `{path}`."))?;
+            }
+            Ok(())
+        }
         #[allow(unused_variables)]
         SourceParams::Kafka(params) => {
             #[cfg(not(feature = "kafka"))]
@@ -374,7 +403,7 @@ mod tests {
 
     use std::num::NonZeroUsize;
 
-    use quickwit_config::{SourceInputFormat, VecSourceParams};
+    use quickwit_config::{FileTailSourceParams, SourceInputFormat, VecSourceParams};
 
     use super::*;
 
@@ -428,6 +457,20 @@ mod tests {
             };
             assert!(check_source_connectivity(&source_config).await.is_ok());
         }
+        {
+            let source_config = SourceConfig {
+                source_id: "file-tail".to_string(),
+                desired_num_pipelines: NonZeroUsize::new(1).unwrap(),
+                max_num_pipelines_per_indexer: NonZeroUsize::new(1).unwrap(),
+                enabled: true,
+                source_params: SourceParams::FileTail(FileTailSourceParams {
+                    paths: vec!["data/[test_corpus.json".to_string()],
+                }),
+                transform_config: None,
+                input_format: SourceInputFormat::Json,
+            };
+            assert!(check_source_connectivity(&source_config).await.is_err());
+        }
         Ok(())
     }
 }
@@ -261,7 +261,8 @@ impl Source for PulsarSource {
                 num_millis=%now.elapsed().as_millis(),
                 "Sending doc batch to indexer.");
             let message = batch.build();
-            ctx.send_message(doc_processor_mailbox, message).await?;
+            ctx.send_sized_message(doc_processor_mailbox, message)
+                .await?;
         }
 
         Ok(Duration::default())
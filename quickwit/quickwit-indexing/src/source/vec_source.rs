@@ -108,7 +108,7 @@ impl Source for VecSource {
             position_from_offset(to_item_idx),
         )
         .unwrap();
-        ctx.send_message(batch_sink, doc_batch).await?;
+        ctx.send_sized_message(batch_sink, doc_batch).await?;
         Ok(Duration::default())
     }
 
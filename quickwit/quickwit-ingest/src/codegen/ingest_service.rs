@@ -156,6 +156,10 @@ use tower::{Layer, Service, ServiceExt};
 #[async_trait::async_trait]
 pub trait IngestService: std::fmt::Debug + dyn_clone::DynClone + Send + Sync + 'static {
     async fn ingest(&mut self, request: IngestRequest) -> crate::Result<IngestResponse>;
+    async fn ingest_stream(
+        &mut self,
+        request: quickwit_common::ServiceStream<IngestRequest>,
+    ) -> crate::Result<IngestResponse>;
     async fn fetch(&mut self, request: FetchRequest) -> crate::Result<FetchResponse>;
     async fn tail(&mut self, request: TailRequest) -> crate::Result<FetchResponse>;
 }
@@ -219,6 +223,12 @@ impl IngestService for IngestServiceClient {
     async fn ingest(&mut self, request: IngestRequest) -> crate::Result<IngestResponse> {
         self.inner.ingest(request).await
     }
+    async fn ingest_stream(
+        &mut self,
+        request: quickwit_common::ServiceStream<IngestRequest>,
+    ) -> crate::Result<IngestResponse> {
+        self.inner.ingest_stream(request).await
+    }
     async fn fetch(&mut self, request: FetchRequest) -> crate::Result<FetchResponse> {
         self.inner.fetch(request).await
     }
@@ -241,6 +251,12 @@ pub mod mock {
         ) -> crate::Result<IngestResponse> {
             self.inner.lock().await.ingest(request).await
         }
+        async fn ingest_stream(
+            &mut self,
+            request: quickwit_common::ServiceStream<IngestRequest>,
+        ) -> crate::Result<IngestResponse> {
+            self.inner.lock().await.ingest_stream(request).await
+        }
         async fn fetch(
             &mut self,
             request: FetchRequest,
@@ -279,6 +295,26 @@ impl tower::Service<IngestRequest> for Box<dyn IngestService> {
         Box::pin(fut)
     }
 }
+impl tower::Service<quickwit_common::ServiceStream<IngestRequest>>
+for Box<dyn IngestService> {
+    type Response = IngestResponse;
+    type Error = crate::IngestServiceError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+    fn poll_ready(
+        &mut self,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+    fn call(
+        &mut self,
+        request: quickwit_common::ServiceStream<IngestRequest>,
+    ) -> Self::Future {
+        let mut svc = self.clone();
+        let fut = async move { svc.ingest_stream(request).await };
+        Box::pin(fut)
+    }
+}
 impl tower::Service<FetchRequest> for Box<dyn IngestService> {
     type Response = FetchResponse;
     type Error = crate::IngestServiceError;
@@ -319,6 +355,11 @@ struct IngestServiceTowerBlock {
         IngestResponse,
         crate::IngestServiceError,
     >,
+    ingest_stream_svc: quickwit_common::tower::BoxService<
+        quickwit_common::ServiceStream<IngestRequest>,
+        IngestResponse,
+        crate::IngestServiceError,
+    >,
     fetch_svc: quickwit_common::tower::BoxService<
         FetchRequest,
         FetchResponse,
@@ -334,6 +375,7 @@ impl Clone for IngestServiceTowerBlock {
     fn clone(&self) -> Self {
         Self {
             ingest_svc: self.ingest_svc.clone(),
+            ingest_stream_svc: self.ingest_stream_svc.clone(),
             fetch_svc: self.fetch_svc.clone(),
             tail_svc: self.tail_svc.clone(),
         }
@@ -344,6 +386,12 @@ impl IngestService for IngestServiceTowerBlock {
     async fn ingest(&mut self, request: IngestRequest) -> crate::Result<IngestResponse> {
         self.ingest_svc.ready().await?.call(request).await
     }
+    async fn ingest_stream(
+        &mut self,
+        request: quickwit_common::ServiceStream<IngestRequest>,
+    ) -> crate::Result<IngestResponse> {
+        self.ingest_stream_svc.ready().await?.call(request).await
+    }
     async fn fetch(&mut self, request: FetchRequest) -> crate::Result<FetchResponse> {
         self.fetch_svc.ready().await?.call(request).await
     }
@@ -363,6 +411,15 @@ pub struct IngestServiceTowerBlockBuilder {
         >,
     >,
     #[allow(clippy::type_complexity)]
+    ingest_stream_layer: Option<
+        quickwit_common::tower::BoxLayer<
+            Box<dyn IngestService>,
+            quickwit_common::ServiceStream<IngestRequest>,
+            IngestResponse,
+            crate::IngestServiceError,
+        >,
+    >,
+    #[allow(clippy::type_complexity)]
     fetch_layer: Option<
         quickwit_common::tower::BoxLayer<
             Box<dyn IngestService>,
@@ -391,6 +448,14 @@ impl IngestServiceTowerBlockBuilder {
                 Error = crate::IngestServiceError,
             > + Clone + Send + Sync + 'static,
         <L::Service as tower::Service<IngestRequest>>::Future: Send + 'static,
+        L::Service: tower::Service<
+                quickwit_common::ServiceStream<IngestRequest>,
+                Response = IngestResponse,
+                Error = crate::IngestServiceError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<
+            quickwit_common::ServiceStream<IngestRequest>,
+        >>::Future: Send + 'static,
         L::Service: tower::Service<
                 FetchRequest,
                 Response = FetchResponse,
@@ -405,6 +470,9 @@ impl IngestServiceTowerBlockBuilder {
         <L::Service as tower::Service<TailRequest>>::Future: Send + 'static,
     {
         self.ingest_layer = Some(quickwit_common::tower::BoxLayer::new(layer.clone()));
+        self.ingest_stream_layer = Some(
+            quickwit_common::tower::BoxLayer::new(layer.clone()),
+        );
         self.fetch_layer = Some(quickwit_common::tower::BoxLayer::new(layer.clone()));
         self.tail_layer = Some(quickwit_common::tower::BoxLayer::new(layer));
         self
@@ -422,6 +490,21 @@ impl IngestServiceTowerBlockBuilder {
         self.ingest_layer = Some(quickwit_common::tower::BoxLayer::new(layer));
         self
     }
+    pub fn ingest_stream_layer<L>(mut self, layer: L) -> Self
+    where
+        L: tower::Layer<Box<dyn IngestService>> + Send + Sync + 'static,
+        L::Service: tower::Service<
+                quickwit_common::ServiceStream<IngestRequest>,
+                Response = IngestResponse,
+                Error = crate::IngestServiceError,
+            > + Clone + Send + Sync + 'static,
+        <L::Service as tower::Service<
+            quickwit_common::ServiceStream<IngestRequest>,
+        >>::Future: Send + 'static,
+    {
+        self.ingest_stream_layer = Some(quickwit_common::tower::BoxLayer::new(layer));
+        self
+    }
     pub fn fetch_layer<L>(mut self, layer: L) -> Self
     where
         L: tower::Layer<Box<dyn IngestService>> + Send + Sync + 'static,
@@ -491,6 +574,11 @@ impl IngestServiceTowerBlockBuilder {
         } else {
             quickwit_common::tower::BoxService::new(boxed_instance.clone())
         };
+        let ingest_stream_svc = if let Some(layer) = self.ingest_stream_layer {
+            layer.layer(boxed_instance.clone())
+        } else {
+            quickwit_common::tower::BoxService::new(boxed_instance.clone())
+        };
         let fetch_svc = if let Some(layer) = self.fetch_layer {
             layer.layer(boxed_instance.clone())
         } else {
@@ -503,6 +591,7 @@ impl IngestServiceTowerBlockBuilder {
         };
         let tower_block = IngestServiceTowerBlock {
             ingest_svc,
+            ingest_stream_svc,
             fetch_svc,
             tail_svc,
         };
@@ -587,6 +676,12 @@ where
             Error = crate::IngestServiceError,
             Future = BoxFuture<IngestResponse, crate::IngestServiceError>,
         >
+        + tower::Service<
+            quickwit_common::ServiceStream<IngestRequest>,
+            Response = IngestResponse,
+            Error = crate::IngestServiceError,
+            Future = BoxFuture<IngestResponse, crate::IngestServiceError>,
+        >
         + tower::Service<
             FetchRequest,
             Response = FetchResponse,
@@ -603,6 +698,12 @@ where
     async fn ingest(&mut self, request: IngestRequest) -> crate::Result<IngestResponse> {
         self.call(request).await
     }
+    async fn ingest_stream(
+        &mut self,
+        request: quickwit_common::ServiceStream<IngestRequest>,
+    ) -> crate::Result<IngestResponse> {
+        self.call(request).await
+    }
     async fn fetch(&mut self, request: FetchRequest) -> crate::Result<FetchResponse> {
         self.call(request).await
     }
@@ -639,6 +740,16 @@ where
             .map(|response| response.into_inner())
             .map_err(|error| error.into())
     }
+    async fn ingest_stream(
+        &mut self,
+        request: quickwit_common::ServiceStream<IngestRequest>,
+    ) -> crate::Result<IngestResponse> {
+        self.inner
+            .ingest_stream(request)
+            .await
+            .map(|response| response.into_inner())
+            .map_err(|error| error.into())
+    }
     async fn fetch(&mut self, request: FetchRequest) -> crate::Result<FetchResponse> {
         self.inner
             .fetch(request)
@@ -679,6 +790,20 @@ impl ingest_service_grpc_server::IngestServiceGrpc for IngestServiceGrpcServerAd
             .map(tonic::Response::new)
             .map_err(|error| error.into())
     }
+    async fn ingest_stream(
+        &self,
+        request: tonic::Request<tonic::Streaming<IngestRequest>>,
+    ) -> Result<tonic::Response<IngestResponse>, tonic::Status> {
+        self.inner
+            .clone()
+            .ingest_stream({
+                let streaming: tonic::Streaming<_> = request.into_inner();
+                quickwit_common::ServiceStream::from(streaming)
+            })
+            .await
+            .map(tonic::Response::new)
+            .map_err(|error| error.into())
+    }
     async fn fetch(
         &self,
         request: tonic::Request<FetchRequest>,
@@ -816,6 +941,36 @@ pub mod ingest_service_grpc_client {
                 .insert(GrpcMethod::new("ingest_service.IngestService", "Ingest"));
             self.inner.unary(req, path, codec).await
         }
+        /// Ingests a stream of document batches.
+        ///
+        /// The requests of the stream are ingested one after the other: the next
+        /// request is only read once the previous one has been appended to the queue.
+        /// When the queue is full, the stream stops being consumed until some memory
+        /// is released, slowing down the client instead of rejecting its requests.
+        ///
+        /// The response holds the total number of documents ingested.
+        pub async fn ingest_stream(
+            &mut self,
+            request: impl tonic::IntoStreamingRequest<Message = super::IngestRequest>,
+        ) -> std::result::Result<tonic::Response<super::IngestResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/ingest_service.IngestService/IngestStream",
+            );
+            let mut req = request.into_streaming_request();
+            req.extensions_mut()
+                .insert(GrpcMethod::new("ingest_service.IngestService", "IngestStream"));
+            self.inner.client_streaming(req, path, codec).await
+        }
         /// Fetches record from a given queue.
         ///
         /// Records are returned in order.
@@ -895,6 +1050,18 @@ pub mod ingest_service_grpc_server {
             &self,
             request: tonic::Request<super::IngestRequest>,
         ) -> std::result::Result<tonic::Response<super::IngestResponse>, tonic::Status>;
+        /// Ingests a stream of document batches.
+        ///
+        /// The requests of the stream are ingested one after the other: the next
+        /// request is only read once the previous one has been appended to the queue.
+        /// When the queue is full, the stream stops being consumed until some memory
+        /// is released, slowing down the client instead of rejecting its requests.
+        ///
+        /// The response holds the total number of documents ingested.
+        async fn ingest_stream(
+            &self,
+            request: tonic::Request<tonic::Streaming<super::IngestRequest>>,
+        ) -> std::result::Result<tonic::Response<super::IngestResponse>, tonic::Status>;
         /// Fetches record from a given queue.
         ///
         /// Records are returned in order.
@@ -1042,6 +1209,54 @@ pub mod ingest_service_grpc_server {
                     };
                     Box::pin(fut)
                 }
+                "/ingest_service.IngestService/IngestStream" => {
+                    #[allow(non_camel_case_types)]
+                    struct IngestStreamSvc<T: IngestServiceGrpc>(pub Arc<T>);
+                    impl<
+                        T: IngestServiceGrpc,
+                    > tonic::server::ClientStreamingService<super::IngestRequest>
+                    for IngestStreamSvc<T> {
+                        type Response = super::IngestResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<
+                                tonic::Streaming<super::IngestRequest>,
+                            >,
+                        ) -> Self::Future {
+                            let inner = Arc::clone(&self.0);
+                            let fut = async move {
+                                (*inner).ingest_stream(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let max_decoding_message_size = self.max_decoding_message_size;
+                    let max_encoding_message_size = self.max_encoding_message_size;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = IngestStreamSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            )
+                            .apply_max_message_size_config(
+                                max_decoding_message_size,
+                                max_encoding_message_size,
+                            );
+                        let res = grpc.client_streaming(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 "/ingest_service.IngestService/Fetch" => {
                     #[allow(non_camel_case_types)]
                     struct FetchSvc<T: IngestServiceGrpc>(pub Arc<T>);
@@ -18,15 +18,22 @@
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
 use std::{fmt, iter};
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use futures::StreamExt;
 use quickwit_actors::{
-    Actor, ActorContext, ActorExitStatus, DeferableReplyHandler, Handler, QueueCapacity,
+    Actor, ActorContext, ActorExitStatus, AskError, DeferableReplyHandler, Handler, QueueCapacity,
+    WeakMailbox,
 };
 use quickwit_common::runtimes::RuntimeType;
 use quickwit_common::tower::Cost;
+use quickwit_common::ServiceStream;
+use tokio::sync::Notify;
+use tokio::time::Instant;
 use tracing::info;
 use ulid::Ulid;
 
@@ -55,6 +62,8 @@ pub struct IngestApiService {
     disk_limit: usize,
     memory_capacity: MemoryCapacity,
     notifications: Notifications,
+    // Notified whenever truncating the queues frees some capacity.
+    capacity_freed: Arc<Notify>,
 }
 
 impl fmt::Debug for IngestApiService {
@@ -77,6 +86,10 @@ impl fmt::Debug for IngestApiService {
 /// (See #2310)
 const PARTITION_ID_PATH: &str = "partition_id";
 
+/// Maximum duration a request of an ingest stream waits for the queues to have enough capacity
+/// before the whole stream is rejected.
+const INGEST_STREAM_MAX_STALL_DURATION: Duration = Duration::from_secs(30);
+
 async fn get_or_initialize_partition_id(dir_path: &Path) -> crate::Result<String> {
     let partition_id_path = dir_path.join(PARTITION_ID_PATH);
     if let Ok(partition_id_bytes) = tokio::fs::read(&partition_id_path).await {
@@ -101,8 +114,18 @@ impl IngestApiService {
         let queues = Queues::open(queues_dir_path).await?;
         let partition_id = get_or_initialize_partition_id(queues_dir_path).await?;
         let memory_capacity = MemoryCapacity::new(memory_limit);
+        // The records that were not truncated before the node stopped are reloaded from the record
+        // log so that the ingest API sources can replay them. They count towards the memory limit.
+        let memory_usage = queues.memory_usage();
+        memory_capacity.reset_capacity(memory_limit.saturating_sub(memory_usage));
         let notifications = Notifications::new();
         info!(ingest_partition_id=%partition_id, "Ingest API partition id");
+        if memory_usage > 0 {
+            info!(
+                num_bytes = memory_usage,
+                "Reloaded records pending indexing from the ingest API record log."
+            );
+        }
         Ok(Self {
             partition_id,
             queues,
@@ -110,6 +133,7 @@ impl IngestApiService {
             disk_limit,
             memory_capacity,
             notifications,
+            capacity_freed: Arc::default(),
         })
     }
 
@@ -119,18 +143,10 @@ impl IngestApiService {
         reply: impl FnOnce(crate::Result<IngestResponse>) + Send + Sync + 'static,
         ctx: &ActorContext<Self>,
     ) -> Result<(), ActorExitStatus> {
-        let notification = self.ingest_inner(request, ctx).await;
+        let notification = self.ingest_inner(&request, ctx).await;
         match notification {
-            Ok((response, index_positions)) => {
-                if index_positions.is_empty() {
-                    reply(Ok(response));
-                } else {
-                    self.notifications
-                        .register(index_positions, move || {
-                            reply(Ok(response));
-                        })
-                        .await;
-                }
+            Ok(ingest_output) => {
+                self.reply_once_committed(ingest_output, reply).await;
                 Ok(())
             }
             Err(err) => {
@@ -140,9 +156,26 @@ impl IngestApiService {
         }
     }
 
+    /// Replies right away, or once the requested commits are done.
+    async fn reply_once_committed(
+        &mut self,
+        (response, index_positions): (IngestResponse, Vec<(String, u64)>),
+        reply: impl FnOnce(crate::Result<IngestResponse>) + Send + Sync + 'static,
+    ) {
+        if index_positions.is_empty() {
+            reply(Ok(response));
+        } else {
+            self.notifications
+                .register(index_positions, move || {
+                    reply(Ok(response));
+                })
+                .await;
+        }
+    }
+
     async fn ingest_inner(
         &mut self,
-        request: IngestRequest,
+        request: &IngestRequest,
         ctx: &ActorContext<Self>,
     ) -> crate::Result<(IngestResponse, Vec<(String, u64)>)> {
         // Check all indexes exist assuming existing queues always have a corresponding index.
@@ -241,8 +274,9 @@ impl IngestApiService {
             .await?;
 
         let memory_usage = self.queues.memory_usage();
-        let new_capacity = self.memory_limit - memory_usage;
+        let new_capacity = self.memory_limit.saturating_sub(memory_usage);
         self.memory_capacity.reset_capacity(new_capacity);
+        self.capacity_freed.notify_waiters();
 
         Ok(())
     }
@@ -255,7 +289,8 @@ impl Actor for IngestApiService {
     fn observable_state(&self) -> Self::ObservableState {}
 
     fn runtime_handle(&self) -> tokio::runtime::Handle {
-        RuntimeType::NonBlocking.get_runtime_handle()
+        // Clients wait on the ingest requests: they must not queue behind the indexing actors.
+        RuntimeType::LatencyCritical.get_runtime_handle()
     }
 
     /// The Actor's incoming mailbox queue capacity. It is set when the actor is spawned.
@@ -355,6 +390,117 @@ impl DeferableReplyHandler<IngestRequest> for IngestApiService {
     }
 }
 
+/// Request of an ingest stream. Unlike a plain `IngestRequest`, it is handed back when the queues
+/// are full, so that it can be retried without being cloned.
+#[derive(Debug)]
+pub struct StreamedIngestRequest(IngestRequest);
+
+pub enum StreamedIngestReply {
+    Ingested(crate::Result<IngestResponse>),
+    RateLimited(IngestRequest),
+}
+
+#[async_trait]
+impl DeferableReplyHandler<StreamedIngestRequest> for IngestApiService {
+    type Reply = StreamedIngestReply;
+
+    async fn handle_message(
+        &mut self,
+        streamed_ingest_request: StreamedIngestRequest,
+        reply: impl FnOnce(Self::Reply) + Send + Sync + 'static,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        let StreamedIngestRequest(ingest_request) = streamed_ingest_request;
+        match self.ingest_inner(&ingest_request, ctx).await {
+            Ok(ingest_output) => {
+                self.reply_once_committed(ingest_output, move |ingest_result| {
+                    reply(StreamedIngestReply::Ingested(ingest_result))
+                })
+                .await;
+            }
+            Err(IngestServiceError::RateLimited) => {
+                reply(StreamedIngestReply::RateLimited(ingest_request));
+            }
+            Err(error) => {
+                reply(StreamedIngestReply::Ingested(Err(error)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Ingests the requests of the stream one at a time, going through the mailbox of the ingest API
+/// service. The stream is only polled once the previous request has been ingested, so the queue
+/// filling up slows down the stream instead of failing it: a request rejected because the queues
+/// are full is retried as soon as truncating the queues frees some capacity.
+///
+/// The mailbox is only held while a request is in flight, so a stalled stream does not keep the
+/// ingest API service alive.
+async fn ingest_stream(
+    weak_mailbox: WeakMailbox<IngestApiService>,
+    capacity_freed: Arc<Notify>,
+    mut ingest_req_stream: ServiceStream<IngestRequest>,
+) -> crate::Result<IngestResponse> {
+    let mut num_docs_for_processing = 0;
+
+    while let Some(mut ingest_request) = ingest_req_stream.next().await {
+        let stall_deadline = Instant::now() + INGEST_STREAM_MAX_STALL_DURATION;
+
+        let ingest_response = loop {
+            let Some(mailbox) = weak_mailbox.upgrade() else {
+                return Err(IngestServiceError::Unavailable);
+            };
+            // The future is created before sending the request so that no notification is
+            // missed in between.
+            let capacity_freed_notified = capacity_freed.notified();
+            let streamed_ingest_reply = mailbox
+                .ask(StreamedIngestRequest(ingest_request))
+                .await
+                .map_err(|ask_error| match ask_error {
+                    AskError::MessageNotDelivered => IngestServiceError::Unavailable,
+                    _ => IngestServiceError::Internal(ask_error.to_string()),
+                })?;
+            drop(mailbox);
+
+            match streamed_ingest_reply {
+                StreamedIngestReply::Ingested(ingest_result) => break ingest_result?,
+                StreamedIngestReply::RateLimited(rejected_ingest_request) => {
+                    ingest_request = rejected_ingest_request;
+                    tokio::time::timeout_at(stall_deadline, capacity_freed_notified)
+                        .await
+                        .map_err(|_| IngestServiceError::RateLimited)?;
+                }
+            }
+        };
+        num_docs_for_processing += ingest_response.num_docs_for_processing;
+    }
+    Ok(IngestResponse {
+        num_docs_for_processing,
+    })
+}
+
+#[async_trait]
+impl DeferableReplyHandler<ServiceStream<IngestRequest>> for IngestApiService {
+    type Reply = crate::Result<IngestResponse>;
+    async fn handle_message(
+        &mut self,
+        ingest_req_stream: ServiceStream<IngestRequest>,
+        reply: impl FnOnce(Self::Reply) + Send + Sync + 'static,
+        ctx: &ActorContext<Self>,
+    ) -> Result<(), ActorExitStatus> {
+        // The stream is consumed in a separate task so that the actor keeps processing the other
+        // requests, including the ones of the stream itself. The task ends with the stream.
+        let weak_mailbox = ctx.mailbox().downgrade();
+        let capacity_freed = self.capacity_freed.clone();
+        tokio::spawn(async move {
+            let ingest_result =
+                ingest_stream(weak_mailbox, capacity_freed, ingest_req_stream).await;
+            reply(ingest_result);
+        });
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl Handler<FetchRequest> for IngestApiService {
     type Reply = crate::Result<FetchResponse>;
@@ -412,7 +558,7 @@ mod tests {
     use quickwit_config::IngestApiConfig;
 
     use super::*;
-    use crate::{init_ingest_api, DocBatch, DocBatchBuilder};
+    use crate::{init_ingest_api, DocBatch, DocBatchBuilder, IngestService, IngestServiceClient};
 
     #[test]
     fn test_ingest_request_cost() {
@@ -547,4 +693,198 @@ mod tests {
         universe.assert_quit().await;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_ingest_api_service_ingest_stream() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let temp_dir = tempfile::tempdir()?;
+        let queues_dir_path = temp_dir.path();
+
+        let ingest_api_service =
+            init_ingest_api(&universe, queues_dir_path, &IngestApiConfig::default()).await?;
+
+        let create_queue_req = CreateQueueIfNotExistsRequest {
+            queue_id: "index-1".to_string(),
+        };
+        ingest_api_service.ask_for_res(create_queue_req).await?;
+
+        let mut ingest_service = IngestServiceClient::from_mailbox(ingest_api_service.clone());
+        let (ingest_req_tx, ingest_req_stream) = ServiceStream::new_bounded(1);
+
+        let ingest_stream_handle =
+            tokio::spawn(async move { ingest_service.ingest_stream(ingest_req_stream).await });
+
+        for docs in [&[b"Test1", b"Test2"][..], &[b"Test3"][..]] {
+            let mut batch = DocBatchBuilder::new("index-1".to_string());
+            for doc in docs {
+                batch.ingest_doc(Bytes::from_static(*doc));
+            }
+            let ingest_request = IngestRequest {
+                doc_batches: vec![batch.build()],
+                commit: CommitType::Auto.into(),
+            };
+            ingest_req_tx.send(ingest_request).await.unwrap();
+        }
+        drop(ingest_req_tx);
+
+        let ingest_response = ingest_stream_handle.await.unwrap().unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 3);
+
+        let fetch_request = FetchRequest {
+            index_id: "index-1".to_string(),
+            start_after: None,
+            num_bytes_limit: None,
+        };
+        let fetch_response = ingest_api_service.ask_for_res(fetch_request).await.unwrap();
+        assert_eq!(fetch_response.doc_batch.unwrap().num_docs(), 3);
+
+        // Requests targeting a missing queue fail the whole stream.
+        let mut ingest_service = IngestServiceClient::from_mailbox(ingest_api_service.clone());
+        let (ingest_req_tx, ingest_req_stream) = ServiceStream::new_bounded(1);
+        ingest_req_tx
+            .send(IngestRequest {
+                doc_batches: vec![DocBatchBuilder::new("index-2".to_string()).build()],
+                commit: CommitType::Auto.into(),
+            })
+            .await
+            .unwrap();
+        drop(ingest_req_tx);
+
+        let ingest_error = ingest_service
+            .ingest_stream(ingest_req_stream)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            ingest_error,
+            IngestServiceError::IndexNotFound { index_id } if index_id == "index-2"
+        ));
+
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_service_runs_on_latency_critical_runtime() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let ingest_api_service =
+            IngestApiService::with_queues_dir(temp_dir.path(), 1024, usize::MAX).await?;
+        let thread_name = ingest_api_service
+            .runtime_handle()
+            .spawn(async { std::thread::current().name().map(str::to_string) })
+            .await?
+            .unwrap();
+        assert!(thread_name.starts_with("latency-critical-"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_service_ingest_stream_waits_for_capacity() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let temp_dir = tempfile::tempdir()?;
+        // Each request below costs 12 bytes: the queues only fit one at a time.
+        let ingest_api_service =
+            IngestApiService::with_queues_dir(temp_dir.path(), 20, usize::MAX).await?;
+        let (ingest_api_mailbox, _ingest_api_handle) =
+            universe.spawn_builder().spawn(ingest_api_service);
+
+        let create_queue_req = CreateQueueRequest {
+            queue_id: "index-1".to_string(),
+        };
+        ingest_api_mailbox.ask_for_res(create_queue_req).await?;
+
+        let mut ingest_service = IngestServiceClient::from_mailbox(ingest_api_mailbox.clone());
+        let (ingest_req_tx, ingest_req_stream) = ServiceStream::new_bounded(2);
+        let ingest_stream_handle =
+            tokio::spawn(async move { ingest_service.ingest_stream(ingest_req_stream).await });
+
+        for _ in 0..2 {
+            let mut batch = DocBatchBuilder::new("index-1".to_string());
+            batch.ingest_doc(Bytes::from_static(b"Test1"));
+            batch.ingest_doc(Bytes::from_static(b"Test2"));
+            let ingest_request = IngestRequest {
+                doc_batches: vec![batch.build()],
+                commit: CommitType::Auto.into(),
+            };
+            ingest_req_tx.send(ingest_request).await.unwrap();
+        }
+        drop(ingest_req_tx);
+        universe.sleep(Duration::from_secs(1)).await;
+
+        // The second request waits for the first one to be truncated.
+        assert!(!ingest_stream_handle.is_finished());
+        let fetch_request = FetchRequest {
+            index_id: "index-1".to_string(),
+            start_after: None,
+            num_bytes_limit: None,
+        };
+        let fetch_response = ingest_api_mailbox.ask_for_res(fetch_request).await?;
+        assert_eq!(fetch_response.doc_batch.unwrap().num_docs(), 2);
+
+        ingest_api_mailbox
+            .ask_for_res(SuggestTruncateRequest {
+                index_id: "index-1".to_string(),
+                up_to_position_included: 1,
+            })
+            .await?;
+        let ingest_response = ingest_stream_handle.await.unwrap().unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 4);
+
+        universe.assert_quit().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ingest_api_service_reloads_pending_records() -> anyhow::Result<()> {
+        let universe = Universe::with_accelerated_time();
+        let temp_dir = tempfile::tempdir()?;
+        let queues_dir_path = temp_dir.path();
+        let memory_limit = 1024 * 1024;
+
+        let ingest_api_service =
+            IngestApiService::with_queues_dir(queues_dir_path, memory_limit, usize::MAX).await?;
+        let (ingest_api_mailbox, ingest_api_handle) =
+            universe.spawn_builder().spawn(ingest_api_service);
+
+        let create_queue_req = CreateQueueRequest {
+            queue_id: "index-1".to_string(),
+        };
+        ingest_api_mailbox.ask_for_res(create_queue_req).await?;
+
+        let mut batch = DocBatchBuilder::new("index-1".to_string());
+        batch.ingest_doc(Bytes::from_static(b"Test1"));
+        batch.ingest_doc(Bytes::from_static(b"Test2"));
+
+        let ingest_request = IngestRequest {
+            doc_batches: vec![batch.build()],
+            commit: CommitType::Auto.into(),
+        };
+        ingest_api_mailbox.ask_for_res(ingest_request).await?;
+        ingest_api_handle.quit().await;
+
+        // The acknowledged records survive the restart of the service.
+        let mut ingest_api_service =
+            IngestApiService::with_queues_dir(queues_dir_path, memory_limit, usize::MAX).await?;
+        assert!(ingest_api_service.memory_capacity.capacity() < memory_limit);
+
+        let fetch_request = FetchRequest {
+            index_id: "index-1".to_string(),
+            start_after: None,
+            num_bytes_limit: None,
+        };
+        let fetch_response = ingest_api_service.fetch(fetch_request).unwrap();
+        assert_eq!(fetch_response.first_position, Some(0));
+
+        let doc_batch = fetch_response.doc_batch.unwrap();
+        let docs: Vec<Bytes> = doc_batch
+            .iter()
+            .filter_map(|doc_command| match doc_command {
+                DocCommand::Ingest { payload } => Some(payload),
+                DocCommand::Commit => None,
+            })
+            .collect();
+        assert_eq!(docs, [&b"Test1"[..], &b"Test2"[..]]);
+
+        universe.assert_quit().await;
+        Ok(())
+    }
 }
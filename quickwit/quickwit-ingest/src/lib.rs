@@ -221,6 +221,7 @@ mod tests {
             &IngestApiConfig {
                 max_queue_memory_usage: Byte::from_bytes(1200),
                 max_queue_disk_usage: Byte::from_bytes(1024 * 1024 * 256),
+                ..Default::default()
             },
         )
         .await
@@ -26,13 +26,12 @@ use async_trait::async_trait;
 use futures::{stream, StreamExt};
 use itertools::Itertools;
 use quickwit_actors::{Actor, ActorContext, Handler};
-// use quickwit_index_management::run_garbage_collect;
 use quickwit_common::shared_consts::DELETION_GRACE_PERIOD;
-use quickwit_index_management::run_garbage_collect;
+use quickwit_index_management::{delete_orphan_split_files, run_garbage_collect};
 use quickwit_metastore::Metastore;
 use quickwit_storage::StorageResolver;
 use serde::Serialize;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 const RUN_INTERVAL: Duration = Duration::from_secs(10 * 60); // 10 minutes
 
@@ -51,6 +50,8 @@ pub struct GarbageCollectorCounters {
     pub num_deleted_files: usize,
     /// The number of bytes deleted.
     pub num_deleted_bytes: usize,
+    /// The number of deleted split files that had no matching split in the metastore.
+    pub num_deleted_orphan_files: usize,
     /// The number of failed garbage collection run on an index.
     pub num_failed_gc_run_on_index: usize,
     /// The number of successful garbage collection run on an index.
@@ -110,21 +111,34 @@ impl GarbageCollector {
             let index_uid = index.index_uid;
             let gc_res = run_garbage_collect(
                 index_uid.clone(),
-                storage,
-                metastore,
+                storage.clone(),
+                metastore.clone(),
                 STAGED_GRACE_PERIOD,
                 DELETION_GRACE_PERIOD,
                 false,
                 Some(ctx.progress()),
             ).await;
-            Some((index_uid, gc_res))
+            let orphan_files_res = delete_orphan_split_files(
+                index_uid.clone(),
+                storage,
+                metastore,
+                false,
+                Some(ctx.progress()),
+            ).await;
+            Some((index_uid, gc_res, orphan_files_res))
         }}).buffer_unordered(MAX_CONCURRENT_GC_TASKS);
 
         while let Some(gc_future_res) = gc_futures.next().await {
-            let Some((index_uid, gc_res)) = gc_future_res else {
+            let Some((index_uid, gc_res, orphan_files_res)) = gc_future_res else {
                 self.counters.num_failed_storage_resolution += 1;
                 continue;
             };
+            match orphan_files_res {
+                Ok(orphan_files) => self.counters.num_deleted_orphan_files += orphan_files.len(),
+                Err(error) => {
+                    warn!(index_id=%index_uid.index_id(), error=?error, "Failed to delete orphan split files.");
+                }
+            }
             let deleted_file_entries = match gc_res {
                 Ok(removal_info) => {
                     self.counters.num_successful_gc_run_on_index += 1;
@@ -204,11 +218,12 @@ mod tests {
 
     use quickwit_actors::Universe;
     use quickwit_common::shared_consts::DELETION_GRACE_PERIOD;
+    use quickwit_common::uri::Uri;
     use quickwit_metastore::{
         IndexMetadata, ListSplitsQuery, MetastoreError, MockMetastore, Split, SplitMetadata,
         SplitState,
     };
-    use quickwit_storage::MockStorage;
+    use quickwit_storage::{MockStorage, PutPayload};
     use time::OffsetDateTime;
 
     use super::*;
@@ -381,6 +396,55 @@ mod tests {
         universe.assert_quit().await;
     }
 
+    #[tokio::test]
+    async fn test_garbage_collect_deletes_orphan_split_files() {
+        let storage_resolver = StorageResolver::ram_for_test();
+        let storage = storage_resolver
+            .resolve(&Uri::for_test("ram:///indexes/test-index"))
+            .await
+            .unwrap();
+        for split_file in ["known.split", "orphan.split"] {
+            let payload: Box<dyn PutPayload> = Box::new(vec![0]);
+            storage.put(Path::new(split_file), payload).await.unwrap();
+        }
+        let mut mock_metastore = MockMetastore::default();
+        mock_metastore
+            .expect_list_indexes_metadatas()
+            .times(1)
+            .returning(|| {
+                Ok(vec![IndexMetadata::for_test(
+                    "test-index",
+                    "ram:///indexes/test-index",
+                )])
+            });
+        mock_metastore
+            .expect_list_splits()
+            .times(3)
+            .returning(|query| {
+                assert_eq!(query.index_uid.index_id(), "test-index");
+                let splits = match query.split_states.first() {
+                    Some(SplitState::Staged) | Some(SplitState::MarkedForDeletion) => Vec::new(),
+                    None => make_splits(&["known"], SplitState::Published),
+                    _ => panic!("only Staged, MarkedForDeletion or all splits expected."),
+                };
+                Ok(splits)
+            });
+
+        let garbage_collect_actor =
+            GarbageCollector::new(Arc::new(mock_metastore), storage_resolver);
+        let universe = Universe::with_accelerated_time();
+        let (_mailbox, handle) = universe.spawn_builder().spawn(garbage_collect_actor);
+
+        let counters = handle.process_pending_and_observe().await.state;
+        assert_eq!(counters.num_passes, 1);
+        assert_eq!(counters.num_deleted_files, 0);
+        assert_eq!(counters.num_deleted_orphan_files, 1);
+        assert_eq!(counters.num_successful_gc_run_on_index, 1);
+        assert!(storage.exists(Path::new("known.split")).await.unwrap());
+        assert!(!storage.exists(Path::new("orphan.split")).await.unwrap());
+        universe.assert_quit().await;
+    }
+
     #[tokio::test]
     async fn test_garbage_collect_get_calls_repeatedly() {
         let storage_resolver = StorageResolver::unconfigured();
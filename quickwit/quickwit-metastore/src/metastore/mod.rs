@@ -176,6 +176,11 @@ pub trait Metastore: Send + Sync + 'static {
     ///
     /// This method can be used to advance the checkpoint, by supplying an empty array for
     /// `staged_split_ids`.
+    ///
+    /// The publication of the splits and the application of the checkpoint delta form a single
+    /// transaction: if either of them fails, neither the splits nor the checkpoint are modified.
+    /// This is what guarantees exactly-once indexing from sources that can replay their
+    /// documents from a given position.
     async fn publish_splits<'a>(
         &self,
         index_uid: IndexUid,
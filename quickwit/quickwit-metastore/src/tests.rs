@@ -1102,6 +1102,118 @@ pub mod test_suite {
         cleanup_index(metastore.as_ref(), index_uid).await
     }
 
+    pub async fn test_metastore_publish_splits_with_checkpoint_is_atomic<
+        MetastoreToTest: Metastore + DefaultForTest,
+    >() {
+        let metastore = MetastoreToTest::default_for_test().await;
+
+        let current_timestamp = OffsetDateTime::now_utc().unix_timestamp();
+
+        let index_id = append_random_suffix("test-publish-splits-atomic");
+        let index_uri = format!("ram:///indexes/{index_id}");
+        let index_config = IndexConfig::for_test(&index_id, &index_uri);
+
+        let source_id = format!("{index_id}--source");
+
+        let index_uid = metastore.create_index(index_config).await.unwrap();
+
+        let split_id_1 = format!("{index_id}--split-1");
+        let split_metadata_1 = SplitMetadata {
+            split_id: split_id_1.clone(),
+            index_uid: index_uid.clone(),
+            create_timestamp: current_timestamp,
+            ..Default::default()
+        };
+
+        let split_id_2 = format!("{index_id}--split-2");
+        let split_metadata_2 = SplitMetadata {
+            split_id: split_id_2.clone(),
+            index_uid: index_uid.clone(),
+            create_timestamp: current_timestamp,
+            ..Default::default()
+        };
+
+        metastore
+            .stage_splits(index_uid.clone(), vec![split_metadata_1, split_metadata_2])
+            .await
+            .unwrap();
+
+        metastore
+            .publish_splits(
+                index_uid.clone(),
+                &[&split_id_1],
+                &[],
+                IndexCheckpointDelta::for_test(&source_id, 0..10).into(),
+            )
+            .await
+            .unwrap();
+
+        // The checkpoint delta overlaps with the checkpoint: the split must remain staged.
+        let error = metastore
+            .publish_splits(
+                index_uid.clone(),
+                &[&split_id_2],
+                &[],
+                IndexCheckpointDelta::for_test(&source_id, 5..15).into(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            MetastoreError::IncompatibleCheckpointDelta(_)
+        ));
+
+        let query =
+            ListSplitsQuery::for_index(index_uid.clone()).with_split_state(SplitState::Staged);
+        let staged_splits = metastore.list_splits(query).await.unwrap();
+        assert_eq!(collect_split_ids(&staged_splits), &[split_id_2.as_str()]);
+
+        // The split is already published: the checkpoint must not advance.
+        let error = metastore
+            .publish_splits(
+                index_uid.clone(),
+                &[&split_id_1],
+                &[],
+                IndexCheckpointDelta::for_test(&source_id, 10..20).into(),
+            )
+            .await
+            .unwrap_err();
+        assert!(matches!(error, MetastoreError::SplitsNotStaged { .. }));
+
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        let source_checkpoint = index_metadata
+            .checkpoint
+            .source_checkpoint(&source_id)
+            .unwrap();
+        assert_eq!(
+            source_checkpoint,
+            &SourceCheckpointDelta::from_range(0..10).get_source_checkpoint()
+        );
+
+        // The checkpoint delta following the checkpoint is accepted along with the staged split.
+        metastore
+            .publish_splits(
+                index_uid.clone(),
+                &[&split_id_2],
+                &[],
+                IndexCheckpointDelta::for_test(&source_id, 10..20).into(),
+            )
+            .await
+            .unwrap();
+
+        let index_metadata = metastore.index_metadata(&index_id).await.unwrap();
+        let source_checkpoint = index_metadata
+            .checkpoint
+            .source_checkpoint(&source_id)
+            .unwrap();
+        assert_eq!(
+            source_checkpoint,
+            &SourceCheckpointDelta::from_range(0..20).get_source_checkpoint()
+        );
+
+        cleanup_index(&metastore, index_uid).await;
+    }
+
     pub async fn test_metastore_replace_splits<MetastoreToTest: Metastore + DefaultForTest>() {
         let metastore = MetastoreToTest::default_for_test().await;
 
@@ -2781,6 +2893,12 @@ macro_rules! metastore_test_suite {
                 crate::tests::test_suite::test_metastore_publish_splits_concurrency::<$metastore_type>().await;
             }
 
+            #[tokio::test]
+            async fn test_metastore_publish_splits_with_checkpoint_is_atomic() {
+                let _ = tracing_subscriber::fmt::try_init();
+                crate::tests::test_suite::test_metastore_publish_splits_with_checkpoint_is_atomic::<$metastore_type>().await;
+            }
+
             #[tokio::test]
             async fn test_metastore_publish_splits_empty_splits_array_is_allowed() {
                 crate::tests::test_suite::test_metastore_publish_splits_empty_splits_array_is_allowed::<$metastore_type>().await;
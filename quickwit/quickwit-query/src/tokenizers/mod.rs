@@ -94,6 +94,7 @@ mod tests {
         let tokenizer_manager = super::create_default_quickwit_tokenizer_manager();
         tokenizer_manager.get("chinese_compatible").unwrap();
         tokenizer_manager.get("default").unwrap();
+        tokenizer_manager.get("en_stem").unwrap();
         tokenizer_manager.get("raw").unwrap();
     }
 
@@ -25,8 +25,9 @@ use quickwit_config::{ConfigFormat, SourceConfig};
 use quickwit_indexing::actors::IndexingServiceCounters;
 pub use quickwit_ingest::CommitType;
 use quickwit_metastore::{IndexMetadata, Split, SplitInfo};
+use quickwit_proto::metastore::DeleteTask;
 use quickwit_search::SearchResponseRest;
-use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString};
+use quickwit_serve::{DeleteQueryRequest, ListSplitsQueryParams, SearchRequestQueryString};
 use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
 use reqwest::{Client, ClientBuilder, Method, StatusCode, Url};
 use serde::Serialize;
@@ -238,6 +239,10 @@ impl QuickwitClient {
         SourceClient::new(&self.transport, self.timeout, index_id)
     }
 
+    pub fn delete_tasks<'a, 'b: 'a>(&'a self, index_id: &'b str) -> DeleteTaskClient {
+        DeleteTaskClient::new(&self.transport, self.timeout, index_id)
+    }
+
     pub fn cluster(&self) -> ClusterClient {
         ClusterClient::new(&self.transport, self.timeout)
     }
@@ -550,6 +555,60 @@ impl<'a, 'b> SourceClient<'a, 'b> {
     }
 }
 
+/// Client for delete task APIs.
+pub struct DeleteTaskClient<'a, 'b> {
+    transport: &'a Transport,
+    timeout: Timeout,
+    index_id: &'b str,
+}
+
+impl<'a, 'b> DeleteTaskClient<'a, 'b> {
+    fn new(transport: &'a Transport, timeout: Timeout, index_id: &'b str) -> Self {
+        Self {
+            transport,
+            timeout,
+            index_id,
+        }
+    }
+
+    fn delete_tasks_root_url(&self) -> String {
+        format!("{}/delete-tasks", self.index_id)
+    }
+
+    pub async fn create(&self, delete_query: DeleteQueryRequest) -> Result<DeleteTask, Error> {
+        let body = Bytes::from(serde_json::to_vec(&delete_query)?);
+        let response = self
+            .transport
+            .send::<()>(
+                Method::POST,
+                &self.delete_tasks_root_url(),
+                None,
+                None,
+                Some(body),
+                self.timeout,
+            )
+            .await?;
+        let delete_task = response.deserialize().await?;
+        Ok(delete_task)
+    }
+
+    pub async fn list(&self) -> Result<Vec<DeleteTask>, Error> {
+        let response = self
+            .transport
+            .send::<()>(
+                Method::GET,
+                &self.delete_tasks_root_url(),
+                None,
+                None,
+                None,
+                self.timeout,
+            )
+            .await?;
+        let delete_tasks = response.deserialize().await?;
+        Ok(delete_tasks)
+    }
+}
+
 /// Client for Cluster APIs.
 pub struct ClusterClient<'a> {
     transport: &'a Transport,
@@ -651,8 +710,9 @@ mod test {
     use quickwit_indexing::mock_split;
     use quickwit_ingest::CommitType;
     use quickwit_metastore::IndexMetadata;
+    use quickwit_proto::metastore::{DeleteQuery, DeleteTask};
     use quickwit_search::SearchResponseRest;
-    use quickwit_serve::{ListSplitsQueryParams, SearchRequestQueryString};
+    use quickwit_serve::{DeleteQueryRequest, ListSplitsQueryParams, SearchRequestQueryString};
     use reqwest::header::CONTENT_TYPE;
     use reqwest::{StatusCode, Url};
     use serde_json::json;
@@ -1015,6 +1075,62 @@ mod test {
             .unwrap_err();
     }
 
+    #[tokio::test]
+    async fn test_delete_tasks_endpoints() {
+        let mock_server = MockServer::start().await;
+        let server_url = Url::parse(&mock_server.uri()).unwrap();
+        let qw_client = QuickwitClientBuilder::new(server_url).build();
+        let delete_task = DeleteTask {
+            create_timestamp: 1,
+            opstamp: 1,
+            delete_query: Some(DeleteQuery {
+                index_uid: "my-index:0".to_string(),
+                start_timestamp: Some(1),
+                end_timestamp: None,
+                query_ast: r#"{"type":"match_all"}"#.to_string(),
+            }),
+        };
+        // POST delete task
+        Mock::given(method("POST"))
+            .and(path("/api/v1/my-index/delete-tasks"))
+            .and(body_json(json!({
+                "query": "body:myterm",
+                "search_fields": [],
+                "start_timestamp": 1,
+            })))
+            .respond_with(ResponseTemplate::new(StatusCode::OK).set_body_json(&delete_task))
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        let delete_query = DeleteQueryRequest {
+            query: "body:myterm".to_string(),
+            start_timestamp: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(
+            qw_client
+                .delete_tasks("my-index")
+                .create(delete_query)
+                .await
+                .unwrap(),
+            delete_task
+        );
+
+        // GET delete tasks
+        Mock::given(method("GET"))
+            .and(path("/api/v1/my-index/delete-tasks"))
+            .respond_with(
+                ResponseTemplate::new(StatusCode::OK).set_body_json(vec![delete_task.clone()]),
+            )
+            .up_to_n_times(1)
+            .mount(&mock_server)
+            .await;
+        assert_eq!(
+            qw_client.delete_tasks("my-index").list().await.unwrap(),
+            vec![delete_task]
+        );
+    }
+
     #[tokio::test]
     async fn test_sources_endpoints() {
         let mock_server = MockServer::start().await;
@@ -89,6 +89,7 @@ pub struct RuntimeInfo {
     pub num_cpus_physical: usize,
     pub num_threads_blocking: usize,
     pub num_threads_non_blocking: usize,
+    pub num_threads_latency_critical: usize,
 }
 
 impl RuntimeInfo {
@@ -105,6 +106,7 @@ impl RuntimeInfo {
                 num_cpus_physical: num_cpus::get_physical(),
                 num_threads_blocking: runtimes_config.num_threads_blocking,
                 num_threads_non_blocking: runtimes_config.num_threads_non_blocking,
+                num_threads_latency_critical: runtimes_config.num_threads_latency_critical,
             }
         })
     }
@@ -25,7 +25,7 @@ use quickwit_metastore::{Metastore, MetastoreError};
 use quickwit_proto::metastore::{DeleteQuery, DeleteTask};
 use quickwit_proto::{IndexUid, SearchRequest};
 use quickwit_query::query_ast::{query_ast_from_user_text, QueryAst};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use warp::{Filter, Rejection};
 
 use crate::format::extract_format_from_qs;
@@ -41,7 +41,7 @@ pub struct DeleteTaskApi;
 
 /// This struct represents the delete query passed to
 /// the rest API.
-#[derive(Deserialize, Debug, Eq, PartialEq, Default, utoipa::ToSchema)]
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq, Default, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct DeleteQueryRequest {
     /// Query text. The query language is that of tantivy.
@@ -50,8 +50,10 @@ pub struct DeleteQueryRequest {
     #[serde(default)]
     pub search_fields: Vec<String>,
     /// If set, restrict delete to documents with a `timestamp >= start_timestamp`.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub start_timestamp: Option<i64>,
     /// If set, restrict delete to documents with a `timestamp < end_timestamp``.
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub end_timestamp: Option<i64>,
 }
 
@@ -135,9 +137,10 @@ pub async fn post_delete_request(
 ) -> Result<DeleteTask, JanitorError> {
     let metadata = metastore.index_metadata(&index_id).await?;
     let index_uid: IndexUid = metadata.index_uid.clone();
-    let query_ast = query_ast_from_user_text(&delete_request.query, Some(Vec::new()))
-        .parse_user_query(&[])
-        .map_err(|err| JanitorError::InvalidDeleteQuery(err.to_string()))?;
+    let query_ast =
+        query_ast_from_user_text(&delete_request.query, Some(delete_request.search_fields))
+            .parse_user_query(&[])
+            .map_err(|err| JanitorError::InvalidDeleteQuery(err.to_string()))?;
     let query_ast_json = serde_json::to_string(&query_ast).map_err(|_err| {
         JanitorError::InternalError("Failed to serialized delete query ast".to_string())
     })?;
@@ -168,6 +171,9 @@ pub async fn post_delete_request(
 mod tests {
     use quickwit_indexing::TestSandbox;
     use quickwit_proto::metastore::DeleteTask;
+    use quickwit_query::query_ast::QueryAst;
+    use tantivy::collector::TopDocs;
+    use tantivy::Index;
     use warp::Filter;
 
     use crate::rest::recover_fn;
@@ -234,4 +240,62 @@ mod tests {
         assert_eq!(delete_tasks.len(), 1);
         test_sandbox.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_delete_task_api_search_fields() {
+        let index_id = "test-delete-task-rest-search-fields";
+        let doc_mapping_yaml = r#"
+            field_mappings:
+              - name: title
+                type: text
+              - name: body
+                type: text
+            mode: lenient
+        "#;
+        let test_sandbox = TestSandbox::create(index_id, doc_mapping_yaml, "{}", &["body"])
+            .await
+            .unwrap();
+        let delete_query_api_handlers =
+            super::delete_task_api_handlers(test_sandbox.metastore()).recover(recover_fn);
+        let resp = warp::test::request()
+            .path("/test-delete-task-rest-search-fields/delete-tasks")
+            .method("POST")
+            .json(&true)
+            .body(r#"{"query": "myterm", "search_fields": ["title"]}"#)
+            .reply(&delete_query_api_handlers)
+            .await;
+        assert_eq!(resp.status(), 200);
+        let created_delete_task: DeleteTask = serde_json::from_slice(resp.body()).unwrap();
+        let query_ast: QueryAst =
+            serde_json::from_str(&created_delete_task.delete_query.unwrap().query_ast).unwrap();
+
+        // The delete query matches the term in the search fields only, not in the default
+        // search field of the index.
+        let doc_mapper = test_sandbox.doc_mapper();
+        let index = Index::create_in_ram(doc_mapper.schema());
+        let mut index_writer = index.writer_with_num_threads(1, 15_000_000).unwrap();
+        for json_doc in [
+            r#"{"title": "myterm", "body": "other"}"#,
+            r#"{"title": "other", "body": "myterm"}"#,
+        ] {
+            let (_partition, doc) = doc_mapper.doc_from_json_str(json_doc).unwrap();
+            index_writer.add_document(doc).unwrap();
+        }
+        index_writer.commit().unwrap();
+        let searcher = index.reader().unwrap().searcher();
+        let (query, _warmup_info) = doc_mapper
+            .query(doc_mapper.schema(), &query_ast, true)
+            .unwrap();
+        let matching_docs = searcher
+            .search(query.as_ref(), &TopDocs::with_limit(2))
+            .unwrap();
+        assert_eq!(matching_docs.len(), 1);
+        let title_field = doc_mapper.schema().get_field("title").unwrap();
+        let matching_doc = searcher.doc(matching_docs[0].1).unwrap();
+        assert_eq!(
+            matching_doc.get_first(title_field).unwrap().as_text(),
+            Some("myterm")
+        );
+        test_sandbox.assert_quit().await;
+    }
 }
@@ -0,0 +1,187 @@
+// Copyright (C) 2023 Quickwit, Inc.
+//
+// Quickwit is offered under the AGPL v3.0 and as commercial software.
+// For commercial licensing, contact us at hello@quickwit.io.
+//
+// AGPL:
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as
+// published by the Free Software Foundation, either version 3 of the
+// License, or (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program. If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use quickwit_config::IngestApiConfig;
+use quickwit_ingest::{
+    CommitType, DocBatchBuilder, IngestRequest, IngestResponse, IngestService, IngestServiceClient,
+    IngestServiceError,
+};
+use tokio::sync::oneshot;
+
+type IngestResult = Result<IngestResponse, IngestServiceError>;
+
+/// Documents of one or several ingest requests targeting the same index with the same commit
+/// type.
+struct PendingBatch {
+    batch_id: u64,
+    doc_batch_builder: DocBatchBuilder,
+    num_bytes: usize,
+    /// Number of documents of each request, along with the channel used to acknowledge it.
+    requests: Vec<(u64, oneshot::Sender<IngestResult>)>,
+}
+
+#[derive(Default)]
+struct PendingBatches {
+    next_batch_id: u64,
+    batches: HashMap<(String, CommitType), PendingBatch>,
+}
+
+/// Coalesces the documents sent to the REST ingest endpoint into larger ingest requests.
+///
+/// Documents are batched per index and commit type, and sent to the ingest service once their
+/// batch reaches `batch_num_bytes_threshold`, or after `batch_timeout`. Each request is only
+/// acknowledged once its batch has been ingested, so that the commit type requested by the
+/// client is honored.
+#[derive(Clone)]
+pub(crate) struct IngestBatcher {
+    ingest_service: IngestServiceClient,
+    batch_num_bytes_threshold: usize,
+    batch_timeout: Duration,
+    pending_batches: Arc<Mutex<PendingBatches>>,
+}
+
+impl IngestBatcher {
+    pub fn new(ingest_service: IngestServiceClient, ingest_api_config: &IngestApiConfig) -> Self {
+        Self {
+            ingest_service,
+            batch_num_bytes_threshold: ingest_api_config.batch_num_bytes_threshold.get_bytes()
+                as usize,
+            batch_timeout: ingest_api_config.batch_timeout(),
+            pending_batches: Arc::default(),
+        }
+    }
+
+    /// Ingests the documents, and returns once the batch they were added to has been ingested.
+    pub async fn ingest<'a>(
+        &self,
+        index_id: String,
+        docs: impl Iterator<Item = &'a [u8]>,
+        commit_type: CommitType,
+    ) -> IngestResult {
+        if self.batch_timeout.is_zero() {
+            let mut doc_batch_builder = DocBatchBuilder::new(index_id);
+            for doc in docs {
+                doc_batch_builder.ingest_doc(doc);
+            }
+            let ingest_request = IngestRequest {
+                doc_batches: vec![doc_batch_builder.build()],
+                commit: commit_type.into(),
+            };
+            return self.ingest_service.clone().ingest(ingest_request).await;
+        }
+        let response_rx = self.enqueue(index_id, docs, commit_type);
+        response_rx
+            .await
+            .unwrap_or(Err(IngestServiceError::Unavailable))
+    }
+
+    /// Adds the documents to the pending batch of the index, and flushes the batch if it is full.
+    fn enqueue<'a>(
+        &self,
+        index_id: String,
+        docs: impl Iterator<Item = &'a [u8]>,
+        commit_type: CommitType,
+    ) -> oneshot::Receiver<IngestResult> {
+        let (response_tx, response_rx) = oneshot::channel();
+        let mut pending_batches = self.pending_batches.lock().unwrap();
+        let next_batch_id = pending_batches.next_batch_id;
+        let batch_key = (index_id, commit_type);
+        let pending_batch = pending_batches
+            .batches
+            .entry(batch_key.clone())
+            .or_insert_with(|| PendingBatch {
+                batch_id: next_batch_id,
+                doc_batch_builder: DocBatchBuilder::new(batch_key.0.clone()),
+                num_bytes: 0,
+                requests: Vec::new(),
+            });
+        let is_new_batch = pending_batch.batch_id == next_batch_id;
+        let mut num_docs = 0;
+
+        for doc in docs {
+            pending_batch.num_bytes += pending_batch.doc_batch_builder.ingest_doc(doc);
+            num_docs += 1;
+        }
+        pending_batch.requests.push((num_docs, response_tx));
+
+        if pending_batch.num_bytes >= self.batch_num_bytes_threshold {
+            let pending_batch = pending_batches
+                .batches
+                .remove(&batch_key)
+                .expect("The batch should be pending.");
+            tokio::spawn(self.clone().flush(pending_batch, commit_type));
+        } else if is_new_batch {
+            pending_batches.next_batch_id += 1;
+            let batcher = self.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(batcher.batch_timeout).await;
+                batcher.flush_on_timeout(batch_key, next_batch_id).await;
+            });
+        }
+        response_rx
+    }
+
+    async fn flush_on_timeout(self, batch_key: (String, CommitType), batch_id: u64) {
+        let pending_batch_opt = {
+            let mut pending_batches = self.pending_batches.lock().unwrap();
+            // The batch may have been flushed already because it reached the size threshold.
+            let is_same_batch = pending_batches
+                .batches
+                .get(&batch_key)
+                .map(|pending_batch| pending_batch.batch_id == batch_id)
+                .unwrap_or(false);
+            if is_same_batch {
+                pending_batches.batches.remove(&batch_key)
+            } else {
+                None
+            }
+        };
+        if let Some(pending_batch) = pending_batch_opt {
+            self.flush(pending_batch, batch_key.1).await;
+        }
+    }
+
+    async fn flush(mut self, pending_batch: PendingBatch, commit_type: CommitType) {
+        let PendingBatch {
+            doc_batch_builder,
+            requests,
+            ..
+        } = pending_batch;
+        let ingest_request = IngestRequest {
+            doc_batches: vec![doc_batch_builder.build()],
+            commit: commit_type.into(),
+        };
+        let ingest_result = self.ingest_service.ingest(ingest_request).await;
+
+        for (num_docs, response_tx) in requests {
+            let response = match &ingest_result {
+                Ok(_) => Ok(IngestResponse {
+                    num_docs_for_processing: num_docs,
+                }),
+                Err(error) => Err(error.clone()),
+            };
+            // The client may have disconnected in the meantime.
+            let _ = response_tx.send(response);
+        }
+    }
+}
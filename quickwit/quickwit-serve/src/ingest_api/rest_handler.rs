@@ -17,15 +17,17 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program. If not, see <http://www.gnu.org/licenses/>.
 
-use bytes::{Buf, Bytes};
+use bytes::Bytes;
+use quickwit_config::IngestApiConfig;
 use quickwit_ingest::{
-    CommitType, DocBatchBuilder, FetchResponse, IngestRequest, IngestResponse, IngestService,
-    IngestServiceClient, IngestServiceError, TailRequest,
+    CommitType, FetchResponse, IngestResponse, IngestService, IngestServiceClient,
+    IngestServiceError, TailRequest,
 };
 use serde::Deserialize;
 use thiserror::Error;
 use warp::{Filter, Rejection};
 
+use super::batcher::IngestBatcher;
 use crate::format::extract_format_from_qs;
 use crate::json_api_response::make_json_api_response;
 use crate::{with_arg, BodyFormat};
@@ -60,8 +62,10 @@ struct IngestOptions {
 
 pub(crate) fn ingest_api_handlers(
     ingest_service: IngestServiceClient,
+    ingest_api_config: &IngestApiConfig,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
-    ingest_handler(ingest_service.clone()).or(tail_handler(ingest_service))
+    let ingest_batcher = IngestBatcher::new(ingest_service.clone(), ingest_api_config);
+    ingest_handler(ingest_batcher).or(tail_handler(ingest_service))
 }
 
 fn ingest_filter(
@@ -76,10 +80,10 @@ fn ingest_filter(
 }
 
 fn ingest_handler(
-    ingest_service: IngestServiceClient,
+    ingest_batcher: IngestBatcher,
 ) -> impl Filter<Extract = (impl warp::Reply,), Error = Rejection> + Clone {
     ingest_filter()
-        .and(with_arg(ingest_service))
+        .and(with_arg(ingest_batcher))
         .then(ingest)
         .map(|result| make_json_api_response(result, BodyFormat::default()))
 }
@@ -102,20 +106,11 @@ async fn ingest(
     index_id: String,
     body: Bytes,
     ingest_options: IngestOptions,
-    mut ingest_service: IngestServiceClient,
+    ingest_batcher: IngestBatcher,
 ) -> Result<IngestResponse, IngestServiceError> {
-    // The size of the body should be an upper bound of the size of the batch. The removal of the
-    // end of line character for each doc compensates the addition of the `DocCommand` header.
-    let mut doc_batch_builder = DocBatchBuilder::with_capacity(index_id, body.remaining());
-    for line in lines(&body) {
-        doc_batch_builder.ingest_doc(line);
-    }
-    let ingest_req = IngestRequest {
-        doc_batches: vec![doc_batch_builder.build()],
-        commit: ingest_options.commit_type.into(),
-    };
-    let ingest_response = ingest_service.ingest(ingest_req).await?;
-    Ok(ingest_response)
+    ingest_batcher
+        .ingest(index_id, lines(&body), ingest_options.commit_type)
+        .await
 }
 
 pub fn tail_handler(
@@ -166,8 +161,8 @@ pub(crate) mod tests {
     use quickwit_config::IngestApiConfig;
     use quickwit_ingest::{
         init_ingest_api, CreateQueueIfNotExistsRequest, FetchRequest, FetchResponse,
-        IngestApiService, IngestResponse, IngestServiceClient, SuggestTruncateRequest,
-        QUEUES_DIR_NAME,
+        IngestApiService, IngestResponse, IngestServiceClient, MockIngestService,
+        SuggestTruncateRequest, QUEUES_DIR_NAME,
     };
 
     use super::ingest_api_handlers;
@@ -204,7 +199,7 @@ pub(crate) mod tests {
     async fn test_ingest_api_returns_200_when_ingest_json_and_fetch() {
         let (universe, _temp_dir, ingest_service, _) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers(ingest_service, &IngestApiConfig::default());
         let resp = warp::test::request()
             .path("/my-index/ingest")
             .method("POST")
@@ -238,7 +233,7 @@ pub(crate) mod tests {
     async fn test_ingest_api_returns_200_when_ingest_ndjson_and_fetch() {
         let (universe, _temp_dir, ingest_service, _) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers(ingest_service, &IngestApiConfig::default());
         let payload = r#"
             {"id": 1, "message": "push"}
             {"id": 2, "message": "push"}
@@ -264,7 +259,7 @@ pub(crate) mod tests {
         };
         let (universe, _temp_dir, ingest_service, _) =
             setup_ingest_service(&["my-index"], &config).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service);
+        let ingest_api_handlers = ingest_api_handlers(ingest_service, &config);
         let resp = warp::test::request()
             .path("/my-index/ingest")
             .method("POST")
@@ -280,7 +275,8 @@ pub(crate) mod tests {
     async fn test_ingest_api_blocks_when_wait_is_specified() {
         let (universe, _temp_dir, ingest_service_client, ingest_service_mailbox) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service_client);
+        let ingest_api_handlers =
+            ingest_api_handlers(ingest_service_client, &IngestApiConfig::default());
         let handle = tokio::spawn(async move {
             let resp = warp::test::request()
                 .path("/my-index/ingest?commit=wait_for")
@@ -324,7 +320,8 @@ pub(crate) mod tests {
     async fn test_ingest_api_blocks_when_force_is_specified() {
         let (universe, _temp_dir, ingest_service_client, ingest_service_mailbox) =
             setup_ingest_service(&["my-index"], &IngestApiConfig::default()).await;
-        let ingest_api_handlers = ingest_api_handlers(ingest_service_client);
+        let ingest_api_handlers =
+            ingest_api_handlers(ingest_service_client, &IngestApiConfig::default());
         let handle = tokio::spawn(async move {
             let resp = warp::test::request()
                 .path("/my-index/ingest?commit=force")
@@ -363,4 +360,48 @@ pub(crate) mod tests {
         handle.await.unwrap();
         universe.assert_quit().await;
     }
+
+    #[tokio::test]
+    async fn test_ingest_api_batches_concurrent_requests() {
+        tokio::time::pause();
+        let config = IngestApiConfig {
+            batch_timeout_millis: 100,
+            ..Default::default()
+        };
+        let mut mock_ingest_service = MockIngestService::new();
+        // Both requests are coalesced into a single ingest request.
+        mock_ingest_service
+            .expect_ingest()
+            .times(1)
+            .returning(|ingest_request| {
+                assert_eq!(ingest_request.doc_batches.len(), 1);
+                let doc_batch = &ingest_request.doc_batches[0];
+                assert_eq!(doc_batch.index_id, "my-index");
+                assert_eq!(doc_batch.num_docs(), 3);
+                Ok(IngestResponse {
+                    num_docs_for_processing: 3,
+                })
+            });
+        let ingest_api_handlers =
+            ingest_api_handlers(IngestServiceClient::from(mock_ingest_service), &config);
+        let (resp_1, resp_2) = tokio::join!(
+            warp::test::request()
+                .path("/my-index/ingest")
+                .method("POST")
+                .body(r#"{"id": 1, "message": "push"}"#)
+                .reply(&ingest_api_handlers),
+            warp::test::request()
+                .path("/my-index/ingest")
+                .method("POST")
+                .body("{\"id\": 2, \"message\": \"push\"}\n{\"id\": 3, \"message\": \"push\"}")
+                .reply(&ingest_api_handlers)
+        );
+        assert_eq!(resp_1.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp_1.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 1);
+
+        assert_eq!(resp_2.status(), 200);
+        let ingest_response: IngestResponse = serde_json::from_slice(resp_2.body()).unwrap();
+        assert_eq!(ingest_response.num_docs_for_processing, 2);
+    }
 }
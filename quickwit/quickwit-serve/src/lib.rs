@@ -52,7 +52,10 @@ use byte_unit::n_mib_bytes;
 use format::BodyFormat;
 use futures::{Stream, StreamExt};
 use itertools::Itertools;
-use quickwit_actors::{ActorExitStatus, Mailbox, Universe};
+use quickwit_actors::{
+    set_message_tracing_config, ActorExitStatus, Mailbox, MemoryBudget, MessageTracingConfig,
+    Universe,
+};
 use quickwit_cluster::{Cluster, ClusterChange, ClusterMember};
 use quickwit_common::pubsub::{EventBroker, EventSubscriptionHandle};
 use quickwit_common::runtimes::RuntimesConfig;
@@ -93,6 +96,7 @@ use tracing::{debug, error, info, warn};
 use warp::{Filter, Rejection};
 
 pub use crate::build_info::{BuildInfo, RuntimeInfo};
+pub use crate::delete_task_api::DeleteQueryRequest;
 pub use crate::index_api::ListSplitsQueryParams;
 pub use crate::metrics::SERVE_METRICS;
 #[cfg(test)]
@@ -159,7 +163,19 @@ pub async fn serve_quickwit(
     metastore_resolver: MetastoreResolver,
     shutdown_signal: BoxFutureInfaillible<()>,
 ) -> anyhow::Result<HashMap<String, ActorExitStatus>> {
-    let universe = Universe::new();
+    set_message_tracing_config(MessageTracingConfig {
+        sample_one_in: config.indexer_config.message_tracing_sample_one_in,
+        max_message_debug_len: config.indexer_config.message_tracing_max_debug_len,
+    });
+    let memory_budget =
+        MemoryBudget::new(config.indexer_config.max_in_flight_docs_memory.get_bytes() as usize);
+    let universe = Universe::new().with_memory_budget(Arc::new(memory_budget));
+    // Sending SIGUSR1 to the process logs the state of all of the actors, which helps
+    // diagnosing a stuck pipeline.
+    #[cfg(unix)]
+    if let Err(signal_error) = universe.log_observations_on_sigusr1() {
+        warn!(error=?signal_error, "Failed to install the SIGUSR1 handler.");
+    }
     let event_broker = EventBroker::default();
     let cluster =
         quickwit_cluster::start_cluster_service(&config, &config.enabled_services).await?;
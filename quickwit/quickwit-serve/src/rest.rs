@@ -107,7 +107,10 @@ pub(crate) async fn start_rest_server(
         .or(search_stream_handler(
             quickwit_services.search_service.clone(),
         ))
-        .or(ingest_api_handlers(ingest_service.clone()))
+        .or(ingest_api_handlers(
+            ingest_service.clone(),
+            &quickwit_services.config.ingest_api_config,
+        ))
         .or(index_management_handlers(
             quickwit_services.index_service.clone(),
             quickwit_services.config.clone(),
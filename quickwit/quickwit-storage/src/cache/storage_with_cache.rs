@@ -19,7 +19,7 @@
 
 use std::fmt;
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use async_trait::async_trait;
@@ -97,6 +97,10 @@ impl Storage for StorageWithCache {
         self.storage.file_num_bytes(path).await
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<PathBuf>> {
+        self.storage.list_files().await
+    }
+
     fn uri(&self) -> &Uri {
         self.storage.uri()
     }
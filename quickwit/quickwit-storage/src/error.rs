@@ -41,6 +41,8 @@ pub enum StorageErrorKind {
     Timeout,
     /// Io error.
     Io,
+    /// The storage does not support this operation.
+    Unsupported,
 }
 
 /// Generic Storage Resolver Error.
@@ -80,7 +80,7 @@ pub use self::storage_resolver::StorageResolver;
 #[cfg(feature = "testsuite")]
 pub use self::test_suite::{
     storage_test_multi_part_upload, storage_test_single_part_upload, storage_test_suite,
-    test_write_and_bulk_delete,
+    test_write_and_bulk_delete, test_write_and_list_files,
 };
 pub use crate::error::{
     BulkDeleteError, DeleteFailure, StorageError, StorageErrorKind, StorageResolverError,
@@ -233,6 +233,30 @@ pub(crate) mod test_suite {
         Ok(())
     }
 
+    /// Tests `Storage::list_files`.
+    ///
+    /// This test is not part of the generic test suite because not all storages support listing
+    /// their files.
+    pub async fn test_write_and_list_files(storage: &mut dyn Storage) -> anyhow::Result<()> {
+        let test_paths = [Path::new("list_files/foo"), Path::new("list_files/bar/baz")];
+        for test_path in test_paths {
+            storage.put(test_path, Box::new(b"123".to_vec())).await?;
+        }
+        let files = storage.list_files().await?;
+
+        for test_path in test_paths {
+            assert!(files.iter().any(|file| file == test_path));
+        }
+        storage.bulk_delete(&test_paths).await?;
+
+        let files = storage.list_files().await?;
+
+        for test_path in test_paths {
+            assert!(!files.iter().any(|file| file == test_path));
+        }
+        Ok(())
+    }
+
     async fn test_file_size(storage: &mut dyn Storage) -> anyhow::Result<()> {
         let test_path = Path::new("write_for_filesize");
         let payload_bytes = b"abcdefghijklmnopqrstuvwxyz";
@@ -166,6 +166,35 @@ fn delete_all_dirs_if_empty<'a>(
     .boxed()
 }
 
+/// Lists the files located under `root` recursively, returning their paths relative to `root`.
+fn list_files_recursively(root: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let read_dir = match std::fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(io_error) if io_error.kind() == ErrorKind::NotFound => continue,
+            Err(io_error) => return Err(io_error),
+        };
+        for dir_entry_res in read_dir {
+            let dir_entry = dir_entry_res?;
+            let path = dir_entry.path();
+
+            if dir_entry.file_type()?.is_dir() {
+                dirs.push(path);
+            } else {
+                let relative_path = path
+                    .strip_prefix(root)
+                    .expect("The path should be located under the storage root.")
+                    .to_path_buf();
+                files.push(relative_path);
+            }
+        }
+    }
+    Ok(files)
+}
+
 #[async_trait]
 impl Storage for LocalFileStorage {
     async fn check_connectivity(&self) -> anyhow::Result<()> {
@@ -311,6 +340,17 @@ impl Storage for LocalFileStorage {
         &self.uri
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<PathBuf>> {
+        let root = self.root.clone();
+        tokio::task::spawn_blocking(move || list_files_recursively(&root))
+            .await
+            .map_err(|_| {
+                StorageErrorKind::InternalError
+                    .with_error(anyhow::anyhow!("listing files panicked"))
+            })?
+            .map_err(StorageError::from)
+    }
+
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64> {
         let full_path = self.full_path(path)?;
         match fs::metadata(full_path).await {
@@ -357,7 +397,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
-    use crate::test_suite::storage_test_suite;
+    use crate::test_suite::{storage_test_suite, test_write_and_list_files};
 
     #[tokio::test]
     async fn test_local_file_storage() -> anyhow::Result<()> {
@@ -365,9 +405,18 @@ mod tests {
         let uri = Uri::from_str(&format!("{}", temp_dir.path().display())).unwrap();
         let mut local_file_storage = LocalFileStorage::from_uri(&uri)?;
         storage_test_suite(&mut local_file_storage).await?;
+        test_write_and_list_files(&mut local_file_storage).await?;
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_local_file_storage_list_files_missing_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let uri = Uri::from_str(&format!("{}/missing", temp_dir.path().display())).unwrap();
+        let local_file_storage = LocalFileStorage::from_uri(&uri).unwrap();
+        assert!(local_file_storage.list_files().await.unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_local_file_storage_forbids_double_dot() {
         let temp_dir = tempfile::tempdir().unwrap();
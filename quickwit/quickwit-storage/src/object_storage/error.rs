@@ -25,6 +25,7 @@ use aws_sdk_s3::operation::delete_object::DeleteObjectError;
 use aws_sdk_s3::operation::delete_objects::DeleteObjectsError;
 use aws_sdk_s3::operation::get_object::GetObjectError;
 use aws_sdk_s3::operation::head_object::HeadObjectError;
+use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Error;
 use aws_sdk_s3::operation::put_object::PutObjectError;
 use aws_sdk_s3::operation::upload_part::UploadPartError;
 use hyper::http::StatusCode;
@@ -125,6 +126,16 @@ impl ToStorageErrorKind for PutObjectError {
     }
 }
 
+impl ToStorageErrorKind for ListObjectsV2Error {
+    fn to_storage_error_kind(&self) -> StorageErrorKind {
+        match self {
+            ListObjectsV2Error::NoSuchBucket(_) => StorageErrorKind::NotFound,
+            ListObjectsV2Error::Unhandled(_) => StorageErrorKind::Service,
+            _ => StorageErrorKind::Service,
+        }
+    }
+}
+
 impl ToStorageErrorKind for HeadObjectError {
     fn to_storage_error_kind(&self) -> StorageErrorKind {
         match self {
@@ -782,6 +782,41 @@ impl Storage for S3CompatibleObjectStorage {
         Ok(head_object_output.content_length() as u64)
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<PathBuf>> {
+        let bucket = self.bucket.clone();
+        let prefix = self.key(Path::new(""));
+        let mut files = Vec::new();
+        let mut continuation_token_opt: Option<String> = None;
+
+        loop {
+            let _permit = REQUEST_SEMAPHORE.acquire().await;
+            let list_objects_output = retry(&self.retry_params, || async {
+                self.s3_client
+                    .list_objects_v2()
+                    .bucket(&bucket)
+                    .prefix(&prefix)
+                    .set_continuation_token(continuation_token_opt.clone())
+                    .send()
+                    .await
+            })
+            .await?;
+
+            for object in list_objects_output.contents().unwrap_or_default() {
+                if let Some(key) = object.key() {
+                    files.push(self.relative_path(key));
+                }
+            }
+            continuation_token_opt = list_objects_output
+                .next_continuation_token()
+                .map(|continuation_token| continuation_token.to_string());
+
+            if continuation_token_opt.is_none() {
+                break;
+            }
+        }
+        Ok(files)
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
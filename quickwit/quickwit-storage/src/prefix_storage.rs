@@ -108,6 +108,21 @@ impl Storage for PrefixStorage {
     async fn file_num_bytes(&self, path: &Path) -> crate::StorageResult<u64> {
         self.storage.file_num_bytes(&self.prefix.join(path)).await
     }
+
+    async fn list_files(&self) -> crate::StorageResult<Vec<PathBuf>> {
+        let files = self
+            .storage
+            .list_files()
+            .await?
+            .into_iter()
+            .filter_map(|path| {
+                path.strip_prefix(&self.prefix)
+                    .ok()
+                    .map(|relative_path| relative_path.to_path_buf())
+            })
+            .collect();
+        Ok(files)
+    }
 }
 
 /// Creates a [`PrefixStorage`] using an underlying storage and a prefix.
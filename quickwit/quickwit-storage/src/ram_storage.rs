@@ -76,11 +76,6 @@ impl RamStorage {
     async fn get_data(&self, path: &Path) -> Option<OwnedBytes> {
         self.files.read().await.get(path).cloned()
     }
-
-    /// Returns the list of files that are present in the RamStorage.
-    pub async fn list_files(&self) -> Vec<PathBuf> {
-        self.files.read().await.keys().cloned().collect()
-    }
 }
 
 #[async_trait]
@@ -138,6 +133,10 @@ impl Storage for RamStorage {
         Ok(payload_bytes)
     }
 
+    async fn list_files(&self) -> StorageResult<Vec<PathBuf>> {
+        Ok(self.files.read().await.keys().cloned().collect())
+    }
+
     fn uri(&self) -> &Uri {
         &self.uri
     }
@@ -213,12 +212,13 @@ impl StorageFactory for RamStorageFactory {
 mod tests {
 
     use super::*;
-    use crate::test_suite::storage_test_suite;
+    use crate::test_suite::{storage_test_suite, test_write_and_list_files};
 
     #[tokio::test]
     async fn test_storage() -> anyhow::Result<()> {
         let mut ram_storage = RamStorage::default();
         storage_test_suite(&mut ram_storage).await?;
+        test_write_and_list_files(&mut ram_storage).await?;
         Ok(())
     }
 
@@ -19,7 +19,7 @@
 
 use std::fmt;
 use std::ops::Range;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use async_trait::async_trait;
 use quickwit_common::uri::Uri;
@@ -122,6 +122,17 @@ pub trait Storage: fmt::Debug + Send + Sync + 'static {
     /// Returns a file size.
     async fn file_num_bytes(&self, path: &Path) -> StorageResult<u64>;
 
+    /// Lists all the files of the storage. The returned paths are relative to the storage root.
+    ///
+    /// Storages that do not support listing their files return an error of kind
+    /// `StorageErrorKind::Unsupported`.
+    async fn list_files(&self) -> StorageResult<Vec<PathBuf>> {
+        Err(StorageErrorKind::Unsupported.with_error(anyhow::anyhow!(
+            "Listing files is not supported by storage `{}`.",
+            self.uri()
+        )))
+    }
+
     /// Returns an URI identifying the storage
     fn uri(&self) -> &Uri;
 }
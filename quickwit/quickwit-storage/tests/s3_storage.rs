@@ -61,6 +61,11 @@ async fn run_s3_storage_test_suite(s3_storage_config: S3StorageConfig, bucket_ur
         .context("S3 storage test suite failed.")
         .unwrap();
 
+    quickwit_storage::test_write_and_list_files(&mut object_storage)
+        .await
+        .context("S3 list files test failed.")
+        .unwrap();
+
     let mut object_storage = S3CompatibleObjectStorage::from_uri(&s3_storage_config, &storage_uri)
         .await
         .unwrap()